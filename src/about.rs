@@ -1,5 +1,6 @@
 use soulog::*;
-use crate::{archive::Archive, unwrap_opt};
+use serde_json::json;
+use crate::{archive::Archive, archived, entry::format_date, json, moc::{walk_collections, Collection}, provenance::Provenance, signature::Signature, sort::sort_uids, unwrap_opt};
 
 macro_rules! log_attr {
     ([$entry:ident, $logger:ident] $($name:ident$(($multi:expr))?),* $(,)?) => {$(
@@ -14,21 +15,63 @@ macro_rules! log_attr {
     )*}
 }
 
-pub fn about(is_moc: bool, uid: String, logger: impl Logger) {
+pub fn about(is_moc: bool, uid: String, force_unlock: bool, provenance: bool, logger: impl Logger) {
     let archive = Archive::load(logger.hollow());
+    let uid = archive.resolve_uid(uid, is_moc, logger.hollow());
 
     if is_moc {
-        about_moc(archive, uid, logger)
+        about_moc(archive, uid, provenance, logger)
     } else {
-        about_entry(archive, uid, logger)
+        about_entry(archive, uid, force_unlock, provenance, logger)
     }
 }
 
-fn about_entry(archive: Archive, uid: String, mut logger: impl Logger) {
+fn about_entry(archive: Archive, uid: String, force_unlock: bool, provenance: bool, mut logger: impl Logger) {
     let error_msg = format!("Entry of uid '{uid}' not found in archive");
     let mut entry = unwrap_opt!((archive.get_entry(uid, logger.hollow())) with logger, format: About("{error_msg}"));
     std::mem::drop(error_msg);
 
+    // Time capsules only show their title and date until they unlock
+    if !force_unlock && entry.is_locked(logger.hollow()) {
+        let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+        if json::enabled() {
+            return json::print(json!({
+                "uid": entry.uid,
+                "date": format_date(entry.date(logger.hollow())),
+                "title": entry.title(logger.hollow()),
+                "locked": true,
+                "unlock_date": unlock_date,
+            }));
+        }
+
+        log!((logger) About(""));
+        log!((logger.vital) About("{}", colour_format![blue("# "), green("About Entry of uid `"), none(&entry.uid), green("`")]) as Log);
+        log_attr! {
+            [entry, logger]
+            date(false),
+            title(false),
+        }
+        log!((logger.vital) About("Entry is a time capsule that unlocks on {unlock_date}; use `--force-unlock` to see the rest") as Warning);
+        return;
+    }
+
+    if json::enabled() {
+        return json::print(json!({
+            "uid": entry.uid,
+            "date": format_date(entry.date(logger.hollow())),
+            "title": entry.title(logger.hollow()),
+            "description": entry.description(logger.hollow()),
+            "notes": entry.notes(logger.hollow()),
+            "tags": entry.tags(logger.hollow()),
+            "references": entry.references(logger.hollow()),
+            "links": entry.links(logger.hollow()),
+            "unlock_date": entry.unlock_date(logger.hollow()).map(|x| format_date(&x)),
+            "archived": archived::is_archived(&entry.container, logger.hollow()),
+            "committed_by": signature_json(&entry.container, logger.hollow()),
+            "provenance": if provenance { provenance_json(&entry.container, logger.hollow()) } else { serde_json::Value::Null },
+        }));
+    }
+
     // Print the stuff
     log!((logger) About(""));
     log!((logger.vital) About("{}", colour_format![blue("# "), green("About Entry of uid `"), none(&entry.uid), green("`")]) as Log);
@@ -40,13 +83,32 @@ fn about_entry(archive: Archive, uid: String, mut logger: impl Logger) {
         notes,
         tags,
     }
+    if !entry.references(logger.hollow()).is_empty() { log_attr!([entry, logger] references); }
+    if !entry.links(logger.hollow()).is_empty() { log_attr!([entry, logger] links); }
+    if archived::is_archived(&entry.container, logger.hollow()) { log!((logger.vital) archived("true") as Result) }
+    log_signature(&entry.container, logger.hollow());
+    if provenance { log_provenance(&entry.container, logger) }
 }
 
-fn about_moc(archive: Archive, uid: String, mut logger: impl Logger) {
+fn about_moc(archive: Archive, uid: String, provenance: bool, mut logger: impl Logger) {
     let error_msg = format!("MOC of uid '{uid}' not found in archive");
-    let mut moc = unwrap_opt!((archive.get_moc(uid, logger.hollow())) with logger, format: About("{error_msg}"));
+    let mut moc = unwrap_opt!((archive.get_moc(uid.clone(), logger.hollow())) with logger, format: About("{error_msg}"));
     std::mem::drop(error_msg);
 
+    if json::enabled() {
+        return json::print(json!({
+            "uid": moc.uid,
+            "title": moc.title(logger.hollow()),
+            "description": moc.description(logger.hollow()),
+            "notes": moc.notes(logger.hollow()),
+            "tags": moc.tags(logger.hollow()),
+            "collections": moc.collections(logger.hollow()).iter_mut().map(|x| collection_json(x, &archive, &uid, logger.hollow())).collect::<Vec<_>>(),
+            "archived": archived::is_archived(&moc.container, logger.hollow()),
+            "committed_by": signature_json(&moc.container, logger.hollow()),
+            "provenance": if provenance { provenance_json(&moc.container, logger.hollow()) } else { serde_json::Value::Null },
+        }));
+    }
+
     // Print the stuff
     log!((logger) About(""));
     log!((logger.vital) About("{}", colour_format![blue("# "), green("About MOC of uid `"), none(&moc.uid), green("`")]) as Log);
@@ -57,4 +119,73 @@ fn about_moc(archive: Archive, uid: String, mut logger: impl Logger) {
         description(false),
         notes,
     }
-}
\ No newline at end of file
+
+    // Collections are searched for their items now, so they are always up to date with the archive
+    let hollow = logger.hollow();
+    walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+        let indent = "  ".repeat(at.len() - 1);
+        let title = collection.title(logger.hollow()).clone();
+        if collection.groups_only(logger.hollow()) {
+            log!((logger.vital) collection("{indent}{title}") as Result);
+            return collection.clear_cache();
+        }
+        let (entries, mocs) = members(collection, &archive, &uid, logger.hollow());
+        log!((logger.vital) collection("{indent}{title} ({} entries, {} mocs)", entries.len(), mocs.len()) as Result);
+        collection.clear_cache();
+    }, hollow);
+    if archived::is_archived(&moc.container, logger.hollow()) { log!((logger.vital) archived("true") as Result) }
+    log_signature(&moc.container, logger.hollow());
+    if provenance { log_provenance(&moc.container, logger) }
+}
+
+/// The uids of the entries (oldest first, up to its `limit`) and mocs (other than its own) in a moc's collection
+fn members(collection: &mut Collection, archive: &Archive, moc: &str, logger: impl Logger) -> (Vec<String>, Vec<String>) {
+    if collection.groups_only(logger.hollow()) { return (Vec::new(), Vec::new()) }
+    let entries = collection.search_entries(archived::visible(archive.list_entries(logger.hollow()), false, logger.hollow()), logger.hollow());
+    let entries = collection.newest(&sort_uids(&entries, false, logger.hollow()), logger.hollow()).to_vec();
+    let mocs = collection.search_mocs(archived::visible(archive.list_mocs(logger.hollow()), false, logger.hollow()), logger.hollow())
+        .into_iter().filter(|x| x != moc)
+        .collect();
+    (collection.arrange(entries, |x| x, logger.hollow()), collection.arrange(mocs, |x| x, logger.hollow()))
+}
+
+fn collection_json(collection: &mut Collection, archive: &Archive, moc: &str, logger: impl Logger) -> serde_json::Value {
+    let (entries, mocs) = members(collection, archive, moc, logger.hollow());
+    let value = json!({
+        "title": collection.title(logger.hollow()),
+        "entries": entries,
+        "mocs": mocs,
+        "collections": collection.collections(logger.hollow()).iter_mut().map(|x| collection_json(x, archive, moc, logger.hollow())).collect::<Vec<_>>(),
+    });
+    collection.clear_cache();
+    value
+}
+
+fn log_signature(container: &lazy_db::LazyContainer, mut logger: impl Logger) {
+    if let Some(signature) = Signature::load(container, logger.hollow()) {
+        log!((logger.vital) committed_by("{signature}") as Result);
+    }
+}
+
+fn signature_json(container: &lazy_db::LazyContainer, logger: impl Logger) -> serde_json::Value {
+    match Signature::load(container, logger) {
+        Some(x) => json!({ "author": x.author, "device": x.device }),
+        None => serde_json::Value::Null,
+    }
+}
+fn log_provenance(container: &lazy_db::LazyContainer, mut logger: impl Logger) {
+    match Provenance::load(container, logger.hollow()) {
+        Some(x) => {
+            log!((logger.vital) committed_from("{}", x.source) as Result);
+            log!((logger.vital) committed_at("{}", x.committed_at) as Result);
+        },
+        None => log!((logger.vital) About("No provenance was recorded; it was committed before provenance existed") as Inconvenience),
+    }
+}
+
+fn provenance_json(container: &lazy_db::LazyContainer, logger: impl Logger) -> serde_json::Value {
+    match Provenance::load(container, logger) {
+        Some(x) => json!({ "source": x.source, "committed_at": x.committed_at }),
+        None => serde_json::Value::Null,
+    }
+}
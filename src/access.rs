@@ -0,0 +1,81 @@
+use clap::ValueEnum;
+use soulog::*;
+use crate::{search::{query_matches, Searchable}, unwrap_opt};
+
+/// Who an entry or moc can be published to, from the most to the least open
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Access {
+    /// Anyone
+    Public,
+    /// Only readers that are signed in
+    Authenticated,
+    /// Only you
+    #[default]
+    Private,
+}
+
+/// The per-tag access rules of the config (`[access]`) deciding which items can be published to whom
+#[derive(Default)]
+pub struct AccessRules {
+    /// The access of items that none of the rules match (private unless set)
+    pub default: Access,
+    /// Tag queries (hierarchical tags or globs) and the access of the items that have them
+    pub rules: Vec<(String, Access)>,
+}
+
+impl AccessRules {
+    /// Parses the `[access]` table of the config
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let default = match table.get("default") {
+            Some(x) => {
+                let x = unwrap_opt!((x.as_str()) with logger, format: Config("Config's access 'default' attribute must be a string"));
+                match Access::from_str(x, true) {
+                    Ok(x) => x,
+                    Err(_) => {
+                        log!((logger.error) Config("Config's access 'default' attribute '{x}' must be one of [\"public\", \"authenticated\", \"private\"]") as Fatal);
+                        return logger.crash();
+                    },
+                }
+            },
+            None => Access::default(),
+        };
+
+        let mut rules = Vec::new();
+        for access in Access::value_variants() {
+            let name = access.to_possible_value().unwrap().get_name().to_string();
+            let Some(tags) = table.get(&name) else { continue };
+            let tags = unwrap_opt!((tags.as_array()) with logger, format: Config("Config's access '{name}' attribute must be a list of tags"));
+            for tag in tags {
+                let tag = unwrap_opt!((tag.as_str()) with logger, format: Config("Config's access '{name}' tags must only be strings"));
+                rules.push((tag.to_string(), *access));
+            }
+        }
+
+        Self { default, rules }
+    }
+
+    /// The access of an item with these tags; when several rules match it, the most restrictive one wins
+    pub fn access(&self, tags: &[String]) -> Access {
+        self.strictest(|query| tags.iter().any(|x| query_matches(x, query)))
+    }
+
+    /// The access of an entry or moc
+    pub fn access_of(&self, item: &mut impl Searchable, logger: impl Logger) -> Access {
+        self.strictest(|query| item.contains_tag(query, logger.hollow()))
+    }
+
+    fn strictest(&self, mut matches: impl FnMut(&String) -> bool) -> Access {
+        self.rules.iter()
+            .filter(|(query, _)| matches(query))
+            .map(|(_, x)| *x)
+            .max()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Filters out the items that readers with `reader` access can't see (none without a reader)
+pub fn visible<T: Searchable>(items: Vec<T>, reader: Option<Access>, logger: impl Logger) -> Vec<T> {
+    let Some(reader) = reader else { return items };
+    let rules = &crate::config::Config::get().access;
+    items.into_iter().filter_map(|mut x| (rules.access_of(&mut x, logger.hollow()) <= reader).then_some(x)).collect()
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+use soulog::*;
+use serde_json::json;
+use crate::{config::Config, home_dir, json, lock, unwrap_opt};
+
+/// What the background agent does and how often (`[agent]`); it does nothing unless told to
+pub struct AgentConfig {
+    /// How often the archive is backed up
+    pub backup_every: Option<Duration>,
+    /// Where scheduled backups go; defaults to `backups/` in the cli's home
+    pub backup_dir: Option<PathBuf>,
+    /// How many of the scheduled backups are kept
+    pub keep_backups: usize,
+    /// A folder whose entry configs (`.toml`) and markdown entries (`.md`) are committed as they appear in it
+    pub watch: Option<PathBuf>,
+    /// How long the agent sleeps between checking for work
+    pub interval: Duration,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            backup_every: None,
+            backup_dir: None,
+            keep_backups: 7,
+            watch: None,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AgentConfig {
+    /// Parses the `[agent]` table of the config (`backup_every` in hours and `interval` in seconds)
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let default = Self::default();
+        let mut positive = |key: &str| table.get(key).map(|x| {
+            let x = unwrap_opt!((x.as_integer().filter(|x| *x > 0)) with logger, format: Config("Config's agent '{key}' must be a positive number"));
+            x as u64
+        });
+        let backup_every = positive("backup_every").map(|x| Duration::from_secs(x * 60 * 60));
+        let keep_backups = positive("keep_backups").map(|x| x as usize).unwrap_or(default.keep_backups);
+        let interval = positive("interval").map(Duration::from_secs).unwrap_or(default.interval);
+        let mut path = |key: &str| table.get(key).map(|x| {
+            let x = unwrap_opt!((x.as_str()) with logger, format: Config("Config's agent '{key}' must be a path"));
+            PathBuf::from(x)
+        });
+        Self {
+            backup_every,
+            backup_dir: path("backup_dir"),
+            keep_backups,
+            watch: path("watch"),
+            interval,
+        }
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.backup_dir.clone().unwrap_or_else(|| home_dir().join("backups"))
+    }
+}
+
+fn pid_path() -> PathBuf {
+    home_dir().join("agent.pid")
+}
+
+/// Asks a running agent to stop the next time it wakes up; a file rather than a signal so it works everywhere
+fn stop_path() -> PathBuf {
+    home_dir().join("agent.stop")
+}
+
+/// The pid of the running agent, if there is one
+pub fn running() -> Option<u32> {
+    let path = pid_path();
+    if lock::is_stale(&path) { return None }
+    fs::read_to_string(&path).ok().and_then(|x| x.trim().parse().ok())
+}
+
+/// The scheduled backups in the backup folder, oldest first
+pub fn list_backups(dir: &Path) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir).into_iter().flatten().flatten()
+        .map(|x| x.path())
+        .filter(|x| x.file_name().and_then(|x| x.to_str()).is_some_and(|x| x.starts_with("backup-") && x.ends_with(".tar.zst")))
+        .collect();
+    backups.sort(); // the timestamps in their names sort chronologically
+    backups
+}
+
+/// Runs this same executable with `args` (and `--wait`, as the archive may be locked), so the agent itself never holds
+/// the archive lock; returns if it succeeded
+fn run_self(args: &[&str], mut logger: impl Logger) -> bool {
+    let exe = if_err!((logger) [Agent, err => ("While finding the diary-cli executable: {err:?}")] retry std::env::current_exe());
+    match Command::new(exe).arg("--wait").args(args).status() {
+        Ok(status) => status.success(),
+        Err(err) => {
+            log!((logger.vital) Agent("While running `diary-cli {}`: {err:?}", args.join(" ")) as Warning);
+            false
+        },
+    }
+}
+
+/// Backs up the archive if the newest scheduled backup is older than `backup_every`, then deletes the oldest backups
+/// beyond `keep_backups`
+fn scheduled_backup(config: &AgentConfig, mut logger: impl Logger) {
+    let Some(every) = config.backup_every else { return };
+    let dir = config.backup_dir();
+    let newest = list_backups(&dir).last().and_then(|x| x.metadata().ok()).and_then(|x| x.modified().ok());
+    if newest.is_some_and(|x| SystemTime::now().duration_since(x).unwrap_or_default() < every) { return }
+
+    if_err!((logger) [Agent, err => ("While creating backup folder '{}': {err:?}", dir.to_string_lossy())] retry fs::create_dir_all(&dir));
+    let path = dir.join(format!("backup-{}.tar.zst", chrono::Local::now().format("%Y-%m-%d_%H%M%S")));
+    log!((logger.vital) Agent("Backing up the archive into '{}'...", path.to_string_lossy()) as Log);
+    if !run_self(&["backup", &path.to_string_lossy(), "--format", "tar.zst"], logger.hollow()) {
+        log!((logger.vital) Agent("Scheduled backup failed; trying again in {}s", config.interval.as_secs()) as Warning);
+        return;
+    }
+
+    let backups = list_backups(&dir);
+    for old in backups.iter().take(backups.len().saturating_sub(config.keep_backups)) {
+        log!((logger) Agent("Deleting old backup '{}'...", old.to_string_lossy()));
+        let _ = fs::remove_file(old);
+    }
+}
+
+/// Commits the entry configs and markdown entries in the watch folder, moving them into its `committed/` or `failed/`
+/// folder afterwards; files changed in the last few seconds are left alone as they may still be being written
+fn commit_watched(config: &AgentConfig, mut logger: impl Logger) {
+    let Some(watch) = &config.watch else { return };
+    let mut files: Vec<PathBuf> = fs::read_dir(watch).into_iter().flatten().flatten()
+        .map(|x| x.path())
+        .filter(|x| x.is_file() && matches!(x.extension().and_then(|x| x.to_str()), Some("toml" | "md")))
+        .filter(|x| x.metadata().and_then(|x| x.modified()).is_ok_and(|x| SystemTime::now().duration_since(x).unwrap_or_default() > Duration::from_secs(5)))
+        .collect();
+    files.sort();
+
+    for file in files {
+        let name = file.file_name().unwrap().to_string_lossy().to_string();
+        log!((logger.vital) Agent("Committing '{name}' from the watch folder...") as Log);
+        let path = file.to_string_lossy();
+        let committed = match file.extension().and_then(|x| x.to_str()) {
+            Some("md") => run_self(&["commit", "--markdown", &path], logger.hollow()),
+            _ => run_self(&["commit", &path], logger.hollow()),
+        };
+
+        let dest = watch.join(if committed { "committed" } else { "failed" });
+        if !committed { log!((logger.vital) Agent("Failed to commit '{name}'; moving it into '{}'", dest.to_string_lossy()) as Warning) }
+        if_err!((logger) [Agent, err => ("While creating '{}': {err:?}", dest.to_string_lossy())] retry fs::create_dir_all(&dest));
+        if_err!((logger) [Agent, err => ("While moving '{name}' out of the watch folder: {err:?}")] retry fs::rename(&file, dest.join(&name)));
+    }
+}
+
+/// Runs the agent in the foreground until `agent stop` (meant to be run by a systemd user service or similar)
+pub fn run_command(mut logger: impl Logger) {
+    if let Some(pid) = running() {
+        log!((logger.error) Agent("An agent is already running (pid {pid}); stop it with `diary-cli agent stop`") as Fatal);
+        return logger.crash();
+    }
+
+    let config = &Config::get().agent;
+    if config.backup_every.is_none() && config.watch.is_none() {
+        log!((logger.vital) Agent("Neither `backup_every` nor `watch` are set in config.toml's `[agent]`, so the agent has nothing to do") as Inconvenience);
+    }
+
+    let _ = fs::remove_file(stop_path());
+    if_err!((logger) [Agent, err => ("While writing agent pid file: {err:?}")] retry fs::write(pid_path(), std::process::id().to_string()));
+    log!((logger.vital) Agent("Agent started (pid {}); checking for work every {}s", std::process::id(), config.interval.as_secs()) as Log);
+
+    loop {
+        scheduled_backup(config, logger.hollow());
+        commit_watched(config, logger.hollow());
+
+        // Sleeps in short steps so stopping doesn't take a whole interval
+        let woken = SystemTime::now();
+        while SystemTime::now().duration_since(woken).unwrap_or_default() < config.interval {
+            if stop_path().exists() {
+                let _ = fs::remove_file(stop_path());
+                let _ = fs::remove_file(pid_path());
+                return log!((logger.vital) Agent("Agent stopped") as Log);
+            } std::thread::sleep(Duration::from_millis(500));
+        }
+    }
+}
+
+/// Shows if the agent is running and what it is keeping track of
+pub fn status_command(mut logger: impl Logger) {
+    let config = &Config::get().agent;
+    let pid = running();
+    let last_backup = list_backups(&config.backup_dir()).last().map(|x| x.to_string_lossy().to_string());
+
+    if json::enabled() {
+        return json::print(json!({
+            "running": pid.is_some(),
+            "pid": pid,
+            "last_backup": last_backup,
+            "backup_every_hours": config.backup_every.map(|x| x.as_secs() / 60 / 60),
+            "watch": config.watch.as_ref().map(|x| x.to_string_lossy()),
+        }));
+    }
+
+    match pid {
+        Some(pid) => log!((logger.vital) Agent("{}", colour_format![green("Running"), none(" (pid "), cyan(&pid.to_string()), none(")")]) as Log),
+        None => log!((logger.vital) Agent("{}", colour_format![yellow("Not running"), none("; start it with `diary-cli agent run`")]) as Log),
+    }
+    if let Some(every) = config.backup_every {
+        log!((logger.vital) Agent("Backs up every {}h into '{}' (last: {})", every.as_secs() / 60 / 60, config.backup_dir().to_string_lossy(), last_backup.as_deref().unwrap_or("never")) as Log);
+    }
+    if let Some(watch) = &config.watch {
+        log!((logger.vital) Agent("Commits what appears in '{}'", watch.to_string_lossy()) as Log);
+    }
+}
+
+/// Asks the running agent to stop and waits a little for it to
+pub fn stop_command(mut logger: impl Logger) {
+    let Some(pid) = running() else {
+        let _ = fs::remove_file(pid_path()); // cleanup of a stale pid file
+        log!((logger.vital) Agent("The agent isn't running") as Inconvenience);
+        return;
+    };
+
+    if_err!((logger) [Agent, err => ("While asking the agent to stop: {err:?}")] retry fs::write(stop_path(), ""));
+    log!((logger) Agent("Waiting for the agent (pid {pid}) to stop..."));
+    for _ in 0..20 {
+        if running().is_none() || !pid_path().exists() {
+            return log!((logger.vital) Agent("Stopped the agent (pid {pid})") as Log);
+        } std::thread::sleep(Duration::from_millis(500));
+    }
+    log!((logger.vital) Agent("The agent (pid {pid}) hasn't stopped yet; it will the next time it wakes up") as Inconvenience);
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+/// How many aliases can expand into other aliases before giving up (to stop alias loops)
+const MAX_DEPTH: usize = 8;
+
+/// Splits an alias into arguments like a shell would, honouring quotes and expanding a leading `~`
+pub fn split_args(alias: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+
+    for c in alias.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => { quote = Some(c); current.get_or_insert_with(String::new); },
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => if let Some(x) = current.take() { args.push(x) },
+            (c, _) => current.get_or_insert_with(String::new).push(c),
+        }
+    } args.extend(current);
+
+    let home = std::env::var("HOME").unwrap_or_default();
+    args.into_iter()
+        .map(|x| if x == "~" || x.starts_with("~/") { format!("{home}{}", &x[1..]) } else { x })
+        .collect()
+}
+
+/// Expands the subcommand of the cli arguments if it is a user-defined alias (and not a built-in command)
+pub fn expand(mut args: Vec<String>, aliases: &HashMap<String, String>, builtins: &[String]) -> Vec<String> {
+    for _ in 0..MAX_DEPTH {
+        // The subcommand is the first argument (after the binary) that isn't a flag
+        let Some(idx) = args.iter().skip(1).position(|x| !x.starts_with('-')).map(|x| x + 1) else { break };
+        if builtins.contains(&args[idx]) { break }
+        let Some(alias) = aliases.get(&args[idx]) else { break };
+        args.splice(idx..=idx, split_args(alias));
+    } args
+}
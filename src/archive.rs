@@ -1,23 +1,60 @@
 use lazy_db::*;
 use crate::home_dir;
 use crate::list;
+use crate::tarball;
+use crate::check;
+use crate::journal;
+use crate::history;
+use crate::lock;
+use crate::diff;
+use crate::signal;
+use crate::validate;
 use crate::unwrap_opt;
+use crate::with_context;
+use crate::uid;
 use soulog::*;
 use std::fs;
 use std::path::PathBuf;
 use std::path::Path;
 use crate::entry::Entry;
 use crate::moc::MOC;
+use crate::signature::Signature;
+use crate::provenance::Provenance;
+use crate::search::filter_matches;
 
 pub struct Archive {
     database: LazyDB,
     uid: u64,
     pub itver: u16,
+    /// Monotonically increasing count of mutations (unlike itver it never goes backwards)
+    pub sequence: u64,
+    /// Random id of the latest mutation; `None` for archives made before commit ids existed
+    pub commit_id: Option<u64>,
+}
+
+/// How a backup relates to the active archive
+#[derive(Debug, PartialEq)]
+pub enum Relation {
+    Same,
+    Older,
+    Newer,
+    /// Both have mutations the other doesn't
+    Diverged,
+    /// One of them predates commit ids so only itvers can be compared
+    Unknown,
+}
+
+/// Generates a random 64-bit id
+pub fn random_id() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    RandomState::new().build_hasher().finish()
 }
 
 impl Archive {
     /// Initialises a new archive, will throw error if one already exists
     pub fn init(mut logger: impl Logger) -> Self {
+        lock::acquire(logger.hollow());
         let path = home_dir().join("archive");
         let path_string = path.to_string_lossy();
         // Check if archive already exists
@@ -27,34 +64,41 @@ impl Archive {
         }
 
         log!((logger) Init("Initialising a new archive at '{path_string}'..."));
-        let database = if_err!((logger) [Init, err => ("While initialising database: {err:?}")] retry LazyDB::init(&path));
+        let database = with_context!((logger) [Init, "archive-init" => ("While initialising database")] retry LazyDB::init(&path));
         
-        let uid = {
-            use std::collections::hash_map::RandomState;
-            use std::hash::{BuildHasher, Hasher};
-            RandomState::new().build_hasher().finish()
-        };
+        let uid = random_id();
         let itver = 0u16;
+        let commit_id = random_id();
 
         log!((logger) Init("Writing uid and itver to archive..."));
-        if_err!((logger) [Init, err => ("While writing uid: {err:?}")] retry write_database!((&database) uid = new_u64(uid)));
-        if_err!((logger) [Init, err => ("While writing itver: {err:?}")] retry write_database!((&database) itver = new_u16(itver)));
+        with_context!((logger) [Init, "archive-init" => ("While writing uid")] retry write_database!((&database) uid = new_u64(uid)));
+        with_context!((logger) [Init, "archive-init" => ("While writing itver")] retry write_database!((&database) itver = new_u16(itver)));
+        with_context!((logger) [Init, "archive-init" => ("While writing commit sequence")] retry write_database!((&database) sequence = new_u64(0)));
+        with_context!((logger) [Init, "archive-init" => ("While writing commit id")] retry write_database!((&database) commit_id = new_u64(commit_id)));
+        with_context!((logger) [Init, "archive-init" => ("While writing commit lineage")] retry write_database!((&database) lineage = new_u64_array(&[commit_id])));
 
         log!((logger) Init("Initialising sorted and unsorted entry containers..."));
-        if_err!((logger) [Init, err => ("While writing stack length: {err:?}")] retry write_database!((&database) /order/sorted::length = new_u16(0)));
-        if_err!((logger) [Init, err => ("While writing stack length: {err:?}")] retry write_database!((&database) /order/unsorted::length = new_u16(0)));
+        with_context!((logger) [Init, "archive-init" => ("While writing stack length")] retry write_database!((&database) /order/sorted::length = new_u16(0)));
+        with_context!((logger) [Init, "archive-init" => ("While writing stack length")] retry write_database!((&database) /order/unsorted::length = new_u16(0)));
 
-        log!((logger.vital) Init("Successfully initialised archive '{path_string}'") as Log);
-        Self {
+        let this = Self {
             database,
             uid,
             itver,
-        }
+            sequence: 0,
+            commit_id: Some(commit_id),
+        };
+        history::record(&this, "init", None, logger.hollow());
+
+        log!((logger.vital) Init("Successfully initialised archive '{path_string}'") as Log);
+        this
     }
 
     /// Loads an archive at the cli's home
     #[inline]
     pub fn load(logger: impl Logger) -> Self {
+        lock::acquire(logger.hollow());
+        crate::trash::expire_on_load(logger.hollow());
         let path = home_dir().join("archive");
         Self::load_dir(path, logger)
     }
@@ -70,10 +114,12 @@ impl Archive {
             return Self::init(logger)
         };
 
-        let database = if_err!((logger) [Archive, err => ("While loading archive '{path_string}': {err:?}")] retry LazyDB::load_dir(&path));
+        let database = with_context!((logger) [Archive, "archive-load" => ("While loading archive '{path_string}'")] retry LazyDB::load_dir(&path));
         log!((logger) Archive("Loading uid and itver of archive..."));
-        let uid = if_err!((logger) [Archive, err => ("While loading archive uid: {err:?}")] retry (|| search_database!((&database) uid)?.collect_u64())());
-        let itver = if_err!((logger) [Archive, err => ("While loading archive itver: {err:?}")] retry (|| search_database!((&database) itver)?.collect_u16())());
+        let uid = with_context!((logger) [Archive, "archive-load" => ("While loading archive uid")] retry (|| search_database!((&database) uid)?.collect_u64())());
+        let itver = with_context!((logger) [Archive, "archive-load" => ("While loading archive itver")] retry (|| search_database!((&database) itver)?.collect_u16())());
+        let sequence = Self::read_optional_u64(&database, "sequence", logger.hollow()).unwrap_or(0);
+        let commit_id = Self::read_optional_u64(&database, "commit_id", logger.hollow());
 
         log!((logger.verbose) Archive("Successfully loaded archive at '{path_string}'") as Log);
         log!((logger) Archive(""));
@@ -82,9 +128,46 @@ impl Archive {
             database,
             uid,
             itver,
+            sequence,
+            commit_id,
         }
     }
 
+    /// Reads a u64 that older archives may not have
+    fn read_optional_u64(database: &LazyDB, key: &str, mut logger: impl Logger) -> Option<u64> {
+        let container = with_context!((logger) [Archive, "archive-load" => ("While loading archive as container")] retry database.as_container());
+        match container.read_data(key) {
+            Ok(x) => Some(with_context!((logger) [Archive, "archive-load" => ("While loading archive {key}")] {x.collect_u64()} crash)),
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Archive("While loading archive {key}: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    }
+
+    /// The ids of every mutation the archive has been through, oldest first
+    pub fn lineage(&self, mut logger: impl Logger) -> Vec<u64> {
+        let container = with_context!((logger) [Archive, "archive-load" => ("While loading archive as container")] retry self.database.as_container());
+        match container.read_data("lineage") {
+            Ok(x) => with_context!((logger) [Archive, "archive-load" => ("While loading archive lineage")] {x.collect_u64_array()} crash).into_vec(),
+            Err(LDBError::FileNotFound(_)) => Vec::new(),
+            Err(err) => {
+                log!((logger.error) Archive("While loading archive lineage: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    }
+
+    /// Works out if a backup is older, newer or has diverged from this archive using their commit ids
+    pub fn relation(&self, backup: &Archive, logger: impl Logger) -> Relation {
+        let (Some(this_id), Some(backup_id)) = (self.commit_id, backup.commit_id) else { return Relation::Unknown };
+        if this_id == backup_id { return Relation::Same }
+        if self.lineage(logger.hollow()).contains(&backup_id) { return Relation::Older }
+        if backup.lineage(logger.hollow()).contains(&this_id) { return Relation::Newer }
+        Relation::Diverged
+    }
+
     /// Rolls back to last backup
     pub fn rollback(force: bool, mut logger: impl Logger) {
         log!((logger) RollBack("Rolling back to last backup..."));
@@ -99,6 +182,7 @@ impl Archive {
 
     /// Backs up home archive to specified path
     pub fn backup(out_path: impl AsRef<Path>, mut logger: impl Logger) {
+        lock::acquire(logger.hollow());
         let out_path = out_path.as_ref();
         let path = home_dir().join("archive");
         let path_string = path.to_string_lossy();
@@ -111,14 +195,91 @@ impl Archive {
             return logger.crash();
         }
 
-        let database = if_err!((logger) [Backup, err => ("While backing up archive: {err:?}")] retry LazyDB::load_dir(&path));
-        if_err!((logger) [Backup, err => ("While backing up archive: {err:?}")] retry database.compile(out_path));
+        let database = with_context!((logger) [Backup, "backup" => ("While backing up archive")] retry LazyDB::load_dir(&path));
+        signal::remove_on_interrupt(out_path); // A half written backup is worse than none
+        with_context!((logger) [Backup, "backup" => ("While backing up archive")] retry database.compile(out_path));
+        signal::keep(out_path);
         log!((logger.vital) Backup("Successfully backed up archive '{path_string}' as '{out_string}'") as Log);
         log!((logger) Backup(""));
     }
 
+    /// Backs up home archive as a standard tarball (optionally zstd compressed) with a manifest
+    pub fn backup_tar(out_path: impl AsRef<Path>, compress: bool, mut logger: impl Logger) {
+        let out_path = out_path.as_ref();
+        let path = home_dir().join("archive");
+        let path_string = path.to_string_lossy();
+        let out_string = out_path.to_string_lossy();
+
+        log!((logger) Backup("Backing up archive '{path_string}' as tarball '{out_string}'..."));
+
+        if !path.is_dir() {
+            log!((logger.error) Backup("Archive does not exist, run `diary-cli init` to create a new one before you can back it up.") as Fatal);
+            return logger.crash();
+        }
+
+        let archive = Self::load(logger.hollow());
+        let mut manifest = toml::Table::new();
+        manifest.insert("diary-cli".into(), env!("CARGO_PKG_VERSION").into());
+        manifest.insert("uid".into(), archive.uid.to_string().into());
+        manifest.insert("itver".into(), (archive.itver as i64).into());
+        manifest.insert("sequence".into(), (archive.sequence as i64).into());
+        if let Some(x) = archive.commit_id { manifest.insert("commit_id".into(), format!("{x:016x}").into()); }
+        manifest.insert("created".into(), chrono::Local::now().to_rfc3339().into());
+        manifest.insert("entries".into(), (archive.list_entries(logger.hollow()).len() as i64).into());
+        manifest.insert("mocs".into(), (archive.list_mocs(logger.hollow()).len() as i64).into());
+        let manifest = with_context!((logger) [Backup, "backup" => ("While encoding backup manifest")] retry toml::to_string_pretty(&manifest));
+
+        signal::remove_on_interrupt(out_path);
+        with_context!((logger) [Backup, "backup" => ("While backing up archive")] retry tarball::write(&path, out_path, compress, &manifest));
+        signal::keep(out_path);
+        log!((logger.vital) Backup("Successfully backed up archive '{path_string}' as tarball '{out_string}'") as Log);
+        log!((logger) Backup(""));
+    }
+
+    /// Unpacks a backup into a temporary directory and checks its integrity, crashing if it isn't restorable
+    pub fn verify_backup(path: impl AsRef<Path>, mut logger: impl Logger) {
+        let path = path.as_ref();
+        let path_string = path.to_string_lossy();
+        log!((logger) Verify("Verifying backup '{path_string}'..."));
+
+        let tmp = home_dir().join("verify");
+        let _ = fs::remove_dir_all(&tmp); // cleanup of any failed previous verifications
+        signal::remove_on_interrupt(&tmp);
+        Self::unpack_backup(path, &tmp, logger.hollow());
+
+        if !tmp.join(".meta").is_file() {
+            let _ = fs::remove_dir_all(&tmp);
+            log!((logger.error) Verify("Backup '{path_string}' doesn't contain an archive") as Fatal);
+            return logger.crash();
+        }
+
+        let backup = Self::load_dir(tmp.clone(), logger.hollow());
+        let problems = check::check_archive(&backup);
+        let _ = fs::remove_dir_all(&tmp); // cleanup
+        signal::keep(&tmp);
+
+        if !problems.is_empty() {
+            problems.iter().for_each(|x| log!((logger.vital) Verify("{x}") as Warning));
+            log!((logger.error) Verify("Backup '{path_string}' is corrupted ({} problems found); it may not be restorable", problems.len()) as Fatal);
+            return logger.crash();
+        }
+
+        log!((logger.vital) Verify("Successfully verified backup '{path_string}'") as Log);
+    }
+
+    /// Unpacks an `.ldb`, `.tar` or `.tar.zst` backup into a directory
+    fn unpack_backup(path: &Path, out_path: &Path, logger: impl Logger) {
+        let path_string = path.to_string_lossy();
+        if tarball::is_tarball(path) {
+            with_context!((logger) [Backup, "backup" => ("While unpacking backup '{path_string}'")] retry tarball::unpack(path, out_path));
+        } else {
+            with_context!((logger) [Backup, "backup" => ("While decompiling backup '{path_string}'")] retry LazyDB::decompile(path, out_path));
+        }
+    }
+
     /// Loads a backup if that backup is the same as the active archive and or newer than the active archive, otherwise errors will be thrown
     pub fn load_backup(path: impl AsRef<Path>, force: bool, mut logger: impl Logger) {
+        lock::acquire(logger.hollow());
         let path = path.as_ref();
         let archive = home_dir().join("archive");
         let archive_string = archive.to_string_lossy();
@@ -143,35 +304,140 @@ impl Archive {
 
             // Load new archive
             let new = home_dir().join("new");
-            if_err!((logger) [Backup, err => ("While decompiling backup '{path_string}': {err:?}")] retry LazyDB::decompile(path, &new));
+            signal::remove_on_interrupt(&new);
+            Self::unpack_backup(path, &new, logger.hollow());
             let new = Archive::load_dir(new, logger.hollow());
-
+            let relation = old.relation(&new, logger.hollow());
+
+            // Show what differs so the user can make an informed decision about forcing it
+            if relation == Relation::Diverged || (relation == Relation::Unknown && old.itver == new.itver) {
+                let diffs = diff::diff_archives(&old, &new);
+                if diffs.is_empty() {
+                    log!((logger.vital) Backup("The backup's entries and mocs are identical to the archive's") as Log);
+                } else {
+                    log!((logger.vital) Backup("{} entries and mocs differ between the archive and the backup:", diffs.len()) as Warning);
+                    diffs.iter().for_each(|x| log!((logger.vital) Backup("  {x}") as Log));
+                }
+            }
             let _ = std::fs::remove_dir_all(new.database.path()); // cleanup
+            signal::keep(new.database.path());
 
-            // Check if uid is the same and that the itver is higher
+            // Check if uid is the same and that the backup is newer
             if new.uid != old.uid && !force {
                 log!((logger.error) Backup("Cannot load backup as it is a backup of a different archive (uids don't match)") as Fatal);
                 log!((logger.vital) Backup("If you still want to load it (deleting your current archive in the process) then run the same command but with `-f` to force it.") as Warning);
                 return logger.crash();
             }
 
-            if old.itver == new.itver && !force {
-                log!((logger.vital) Backup("Detected that backup is the same age as the currently loaded archive (itver is the same)") as Warning);
+            if !force {
+                match relation {
+                    Relation::Same => log!((logger.vital) Backup("Detected that backup is identical to the currently loaded archive (commit ids are the same)") as Warning),
+                    Relation::Older => {
+                        log!((logger.error) Backup("Cannot load backup as it is older than the currently loaded archive") as Fatal);
+                        log!((logger.vital) Backup("If you still want to load it (losing un-backed changes in the process) then run the same command but with `-f` to force it.") as Warning);
+                        return logger.crash();
+                    },
+                    Relation::Diverged => {
+                        log!((logger.error) Backup("Cannot load backup as it has diverged from the currently loaded archive (both have changes the other doesn't)") as Fatal);
+                        log!((logger.vital) Backup("If you still want to load it (losing the archive's changes in the process) then run the same command but with `-f` to force it.") as Warning);
+                        return logger.crash();
+                    },
+                    Relation::Newer => (),
+                    // Fall back to itvers for archives without commit ids
+                    Relation::Unknown if old.itver == new.itver => log!((logger.vital) Backup("Detected that backup is the same age as the currently loaded archive (itver is the same)") as Warning),
+                    Relation::Unknown if old.itver > new.itver => {
+                        log!((logger.error) Backup("Cannot load backup as it is older than the currently loaded archive (itver is less)") as Fatal);
+                        log!((logger.vital) Backup("If you still want to load it (losing un-backed changes in the process) then run the same command but with `-f` to force it.") as Warning);
+                        return logger.crash();
+                    },
+                    Relation::Unknown => (),
+                }
             }
 
-            if old.itver > new.itver && !force {
-                log!((logger.error) Backup("Cannot load backup as it is older than the currently loaded archive (itver is less)") as Fatal);
-                log!((logger.vital) Backup("If you still want to load it (losing un-backed changes in the process) then run the same command but with `-f` to force it.") as Warning);
-                return logger.crash();
-            }
-            
-            let _ = std::fs::remove_dir_all(&archive); // cleanup
         }
 
-        if_err!((logger) [Backup, err => ("While decompiling backup '{path_string}': {err:?}")] retry LazyDB::decompile(path, &archive));
+        // The old archive is only removed once it can't be left half replaced
+        let _critical = signal::critical();
+        let _ = std::fs::remove_dir_all(&archive); // cleanup
+        Self::unpack_backup(path, &archive, logger.hollow());
+        history::record(&Self::load(logger.hollow()), "load-backup", None, logger.hollow());
         log!((logger.vital) Backup("Successfully loaded backup '{path_string}'") as Log);
     }
 
+    /// Restores only the specified entries and mocs (by uid or tag) from a backup into the active archive
+    pub fn load_selective(path: impl AsRef<Path>, only: Option<Vec<String>>, tags: Option<Vec<String>>, mut logger: impl Logger) {
+        let path = path.as_ref();
+        let path_string = path.to_string_lossy();
+        log!((logger) Restore("Selectively restoring items from backup '{path_string}'..."));
+
+        // Check if backup exists
+        if !path.is_file() {
+            log!((logger.error) Restore("Backup file '{path_string}' does not exist") as Fatal);
+            return logger.crash();
+        }
+
+        let this = Archive::load(logger.hollow());
+
+        // Load the backup into a temporary archive
+        let new = home_dir().join("new");
+        let _ = fs::remove_dir_all(&new); // cleanup of any failed previous restores
+        signal::remove_on_interrupt(&new);
+        Self::unpack_backup(path, &new, logger.hollow());
+        let backup = Archive::load_dir(new, logger.hollow());
+
+        // Select the items to restore
+        let matches = |uid: &String, item_tags: &[String]| {
+            only.as_ref().map(|x| x.contains(uid)).unwrap_or(false)
+                || tags.as_ref().map(|x| filter_matches(item_tags, x)).unwrap_or(false)
+        };
+        let entries: Vec<String> = backup.list_entries(logger.hollow()).into_iter()
+            .filter_map(|mut x| matches(&x.uid.clone(), x.tags(logger.hollow())).then_some(x.uid))
+            .collect();
+        let mocs: Vec<String> = backup.list_mocs(logger.hollow()).into_iter()
+            .filter_map(|mut x| matches(&x.uid.clone(), x.tags(logger.hollow())).then_some(x.uid))
+            .collect();
+
+        if let Some(only) = &only {
+            only.iter()
+                .filter(|x| !entries.contains(x) && !mocs.contains(x))
+                .for_each(|x| log!((logger.vital) Restore("Item of uid '{x}' not found in backup; skipping it") as Inconvenience));
+        }
+
+        if entries.is_empty() && mocs.is_empty() {
+            log!((logger.vital) Restore("No matching items found in backup; doing nothing") as Inconvenience);
+            let _ = fs::remove_dir_all(backup.database.path()); // cleanup
+            signal::keep(backup.database.path());
+            return;
+        }
+
+        Self::backup_before_modification(logger.hollow());
+
+        // Copy the items over
+        let unsorted = with_context!((logger) [Restore, "restore-read" => ("While loading unsorted stack")] retry search_database!((this.database) /order/unsorted));
+        for uid in entries.iter() {
+            log!((logger) Restore("Restoring entry of uid '{uid}'..."));
+            let to = this.database.path().join("entries").join(uid);
+            let _ = fs::remove_dir_all(&to);
+            with_context!((logger) [Restore, "restore" => ("While restoring entry '{uid}'")] retry copy_dir(backup.database.path().join("entries").join(uid), &to));
+            list::push(|file| LazyData::new_string(file, uid), &unsorted, logger.hollow());
+        }
+
+        for uid in mocs.iter() {
+            log!((logger) Restore("Restoring moc of uid '{uid}'..."));
+            let to = this.database.path().join("mocs").join(uid);
+            let _ = fs::remove_dir_all(&to);
+            with_context!((logger) [Restore, "restore" => ("While restoring moc '{uid}'")] retry copy_dir(backup.database.path().join("mocs").join(uid), &to));
+        }
+
+        let _ = fs::remove_dir_all(backup.database.path()); // cleanup
+        signal::keep(backup.database.path());
+
+        this.bump_itver(logger.hollow());
+        entries.iter().chain(mocs.iter()).for_each(|uid| history::record(&this, "restore", Some(uid), logger.hollow()));
+
+        log!((logger.vital) Restore("Successfully restored {} entries and {} mocs from backup '{path_string}'", entries.len(), mocs.len()) as Log);
+    }
+
     /// Wipes the specified archive and asks the user for confirmation
     pub fn wipe(self, mut logger: impl Logger) {
         // Confirm with the user about the action
@@ -194,69 +460,229 @@ impl Archive {
         }
 
         // Wipe archive
-        if_err!((logger) [Wipe, err => ("While wiping archive: {err:?}")] retry std::fs::remove_dir_all(&path));
+        with_context!((logger) [Wipe, "wipe" => ("While wiping archive")] retry std::fs::remove_dir_all(&path));
         log!((logger.vital) Wipe("Successfully wiped archive! Run `diary-cli init` to init a new archive\n") as Log);
     }
 
-    pub fn commit(&self, config: impl AsRef<Path>, mut logger: impl Logger) {
-        let config = config.as_ref();
-        let path = home_dir().join("archive");
-        let path_string = path.to_string_lossy();
-
-        // Checks if path exists or not
-        if !path.is_dir() {
-            log!((logger.error) Commit("Archive '{path_string}' doesn't exist! Run `diary-cli init` before you can commit") as Fatal);
-            return logger.crash();
-        }
+    /// Commits an entry or moc config file into the archive, returning its uid
+    pub fn commit(&self, config: impl AsRef<Path>, logger: impl Logger) -> String {
+        let (entry, config) = Self::read_config(config.as_ref(), logger.hollow());
+        self.commit_table(entry, &config, logger)
+    }
 
+    /// Reads and parses an entry or moc config file, returning it along with its canonical path
+    pub fn read_config(config: &Path, mut logger: impl Logger) -> (toml::Table, PathBuf) {
         // Check if entry path exists or not
         let config_string = config.to_string_lossy();
         if !config.is_file() {
             log!((logger.error) Commit("Entry config file '{config_string}' doesn't exist") as Fatal);
             return logger.crash();
         }
-        
-        // Backup archive before modification
-        let _ = std::fs::remove_file(home_dir().join("backup.ldb")); // Clean up
-        Self::backup(home_dir().join("backup.ldb"), logger.hollow());
+
+        let config = guard_source_path(config, logger.hollow());
 
         // Parse toml
         log!((logger) Commit("Parsing toml at '{}'", config.to_string_lossy()));
-        let entry = if_err!((logger) [Commit, err => ("While reading the entry config file: {err:?}")] retry std::fs::read_to_string(config));
-        let entry = if_err!((logger) [Commit, err => ("While parsing entry config toml: {err:?}")] {entry.parse::<toml::Table>()} crash {
-            log!((logger.error) Commit("{err:#?}") as Fatal);
-            logger.crash()
-        });
+        let entry = with_context!((logger) [Commit, "commit-read" => ("While reading the entry config file")] retry std::fs::read_to_string(&config));
+        let entry = with_context!((logger) [Commit, "commit-parse" => ("While parsing entry config toml")] {entry.parse::<toml::Table>()} crash);
+        (entry, config)
+    }
+
+    /// Commits a lightweight markdown entry from a file (or stdin without one) into the archive, returning its uid
+    pub fn commit_markdown(&self, file: Option<&Path>, logger: impl Logger) -> String {
+        let (entry, source) = Self::read_markdown(file, logger.hollow());
+        self.commit_table(entry, &source, logger)
+    }
+
+    /// Reads a lightweight markdown entry from a file (or stdin without one) into an entry config,
+    /// returning it along with where it came from
+    pub fn read_markdown(file: Option<&Path>, mut logger: impl Logger) -> (toml::Table, PathBuf) {
+        let (text, source) = match file {
+            Some(file) => {
+                if !file.is_file() {
+                    log!((logger.error) Commit("Markdown entry file '{}' doesn't exist", file.to_string_lossy()) as Fatal);
+                    return logger.crash();
+                }
+                let file = guard_source_path(file, logger.hollow());
+                (with_context!((logger) [Commit, "commit-read" => ("While reading markdown entry file")] retry std::fs::read_to_string(&file)), file)
+            },
+            None => {
+                log!((logger) Commit("Reading markdown entry from stdin..."));
+                let mut text = String::new();
+                with_context!((logger) [Commit, "commit-read" => ("While reading markdown entry from stdin")] {std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)} crash);
+                (text, PathBuf::from("<stdin>"))
+            },
+        };
+
+        (crate::markdown::parse(&text, &source.to_string_lossy(), logger), source)
+    }
+
+    /// Commits an already parsed entry or moc config into the archive, returning its uid;
+    /// `config` is where it came from (relative section paths are relative to it)
+    pub fn commit_table(&self, entry: toml::Table, config: &Path, mut logger: impl Logger) -> String {
+        let entry = crate::suggest::review(entry, config, self, logger.hollow());
+        let _critical = signal::critical(); // Commits the item and bumps the itver together
+        let uid = self.commit_item(entry, config, logger.hollow());
+        crate::limits::check_archive(self.database.path(), logger.hollow());
+
+        // Update itver as the final step
+        self.bump_itver(logger.hollow());
+        history::record(self, "commit", Some(&uid), logger.hollow());
+
+        log!((logger.vital) Commit("Successfully commited config to archive") as Log);
+        uid
+    }
+
+    /// Writes a parsed entry or moc config into the archive without bumping its itver, returning its uid
+    pub fn commit_item(&self, entry: toml::Table, config: &Path, mut logger: impl Logger) -> String {
+        // Checks if the archive exists or not
+        let path = home_dir().join("archive");
+        let path_string = path.to_string_lossy();
+        if !path.is_dir() {
+            log!((logger.error) Commit("Archive '{path_string}' doesn't exist! Run `diary-cli init` before you can commit") as Fatal);
+            return logger.crash();
+        }
+        let config_string = config.to_string_lossy();
+        let entry = with_default_tags(entry, &crate::config::Config::get().default_tags);
+        validate::check_commit(&entry, config, self, logger.hollow());
 
-        
         // Checks if it is a moc
         let is_moc = entry.get("is-moc")
             .map(|x| unwrap_opt!((x.as_bool()) with logger, format: Commit("`is-moc` attribute of config file '{config_string}' must be boolean")))
             .unwrap_or(false);
-        
-        if is_moc {
-            let container = if_err!((logger) [Commit, err => ("While loading archive as container: {err:?}")] retry search_database!((self.database) /mocs/));
+
+        // Re-committing a moc keeps the orders its collections were given by hand
+        let mut entry = entry;
+        if is_moc { crate::moc::keep_old_orders(&mut entry, self, logger.hollow()) }
+
+        // Write the entry or moc into the staging area first so a failure midway never touches the archive
+        let staging_path = self.database.path().join("staging");
+        if staging_path.exists() {
+            log!((logger) Commit("Cleaning up staging area of an interrupted commit..."));
+            with_context!((logger) [Commit, "commit" => ("While cleaning up staging area")] retry std::fs::remove_dir_all(&staging_path));
+        }
+        signal::remove_on_interrupt(&staging_path);
+        let staging = with_context!((logger) [Commit, "commit" => ("While initialising staging area")] retry search_database!((self.database) /staging));
+
+        let uid = if is_moc {
             log!((logger) Commit("Detected that config file '{config_string}' is an moc (map of contents)"));
-            MOC::new(entry, &config_string, container, logger.hollow());
+            let moc = MOC::new(entry, &config_string, staging, logger.hollow());
+            Signature::current().store(&moc.container, logger.hollow());
+            Provenance::current(config).store(&moc.container, logger.hollow());
+            moc.uid
         } else {
-            let container = if_err!((logger) [Commit, err => ("While loading archive as container: {err:?}")] retry search_database!((self.database) /entries/));
             log!((logger) Commit("Detected that config file '{config_string}' is an entry"));
-            
-            // Add to unsorted list
-            let entry = Entry::new(entry, &config_string, container, logger.hollow());
+            let mut entry = Entry::new(entry, &config_string, staging, logger.hollow());
+            Signature::current().store(&entry.container, logger.hollow());
+            Provenance::current(config).store(&entry.container, logger.hollow());
+
+            // Cross-references to items that don't exist (yet) only render as plain text
+            let uid = entry.uid.clone();
+            for reference in entry.references(logger.hollow()).iter().filter(|x| **x != uid) {
+                if !self.database_exists(format!("entries/{reference}")) && !self.database_exists(format!("mocs/{reference}")) {
+                    log!((logger.vital) Commit("Entry '{uid}' cross-references '{{{{uid:{reference}}}}}', but there's no entry or moc of uid '{reference}' in the archive") as Warning);
+                }
+            }
+            for link in entry.links(logger.hollow()).iter().filter(|x| **x != uid) {
+                if !self.database_exists(format!("entries/{link}")) && !self.database_exists(format!("mocs/{link}")) {
+                    log!((logger.vital) Commit("Entry '{uid}' links to '{link}', but there's no entry or moc of uid '{link}' in the archive") as Warning);
+                }
+            }
+            crate::nudge::check(&mut entry, self, logger.hollow());
+            uid
+        };
+
+        // Validate the staged item fully before it enters the archive
+        log!((logger) Commit("Validating staged commit..."));
+        let kind = if is_moc { "mocs" } else { "entries" };
+        let problems = check::check_item(&staging_path.join(&uid), format!("{kind}/{uid}"), is_moc);
+        if !problems.is_empty() {
+            problems.iter().for_each(|x| log!((logger.vital) Commit("{x}") as Warning));
+            let _ = std::fs::remove_dir_all(&staging_path);
+            log!((logger.error) Commit("Staged commit of '{uid}' is invalid; the archive has been left untouched") as Fatal);
+            return logger.crash();
+        }
+
+        crate::limits::check_item(&staging_path.join(&uid), &uid, is_moc, logger.hollow());
+
+        // Record the commit so it can be undone
+        let _critical = signal::critical();
+        journal::record(self, &uid, is_moc, logger.hollow());
+
+        // Move the staged item into the archive, replacing the old one
+        log!((logger) Commit("Moving staged commit into the archive..."));
+        let container = with_context!((logger) [Commit, "commit-read" => ("While loading archive as container")] retry search_database!((self.database) /(kind)/));
+        let target = container.path().join(&uid);
+        let replaced = staging_path.join(format!("{uid}.old"));
+        if target.exists() {
+            log!((logger.vital) Commit("Overwriting the {} of uid '{uid}' already in the archive", if is_moc { "moc" } else { "entry" }) as Inconvenience);
+            with_context!((logger) [Commit, "commit" => ("While moving the old '{uid}' out of the archive")] retry std::fs::rename(&target, &replaced));
+
+            // The new entry is re-sorted, as its date may have changed
+            if !is_moc {
+                crate::sort::remove_from_order(self, "sorted", &uid, logger.hollow());
+                crate::sort::remove_from_order(self, "unsorted", &uid, logger.hollow());
+            }
+        }
+        with_context!((logger) [Commit, "commit" => ("While moving staged commit into the archive")] retry std::fs::rename(staging_path.join(&uid), &target));
+
+        if !is_moc && (unsafe { crate::cli::SORT_ON_COMMIT } || crate::config::Config::get().sort_on_commit) {
+            crate::sort::insert_sorted(self, &uid, logger.hollow());
+        } else if !is_moc {
             log!((logger) Commit("Adding entry to unsorted stack..."));
             list::push(
-                |file| LazyData::new_string(file, &entry.uid),
-                &if_err!((logger) [Commit, err => ("While loaded unsorted stack: {err:?}")] retry search_database!((self.database) /order/unsorted)),
+                |file| LazyData::new_string(file, &uid),
+                &with_context!((logger) [Commit, "commit" => ("While loading unsorted stack")] retry search_database!((self.database) /order/unsorted)),
                 logger.hollow(),
             );
         }
 
-        // Update itver
-        log!((logger) Commit("Updating archive itver..."));
-        if_err!((logger) [Commit, err => ("While update archive itver: {err:?}")] retry write_database!((self.database) itver = new_u16(self.itver + 1)));
+        let _ = std::fs::remove_dir_all(&staging_path); // Clean up
+        signal::keep(&staging_path);
+        uid
+    }
 
-        log!((logger.vital) Commit("Successfully commited config to archive") as Log);
+    /// Increments the archive's itver after a modification
+    pub fn bump_itver(&self, mut logger: impl Logger) {
+        let _critical = signal::critical();
+        log!((logger) Archive("Updating archive itver..."));
+        with_context!((logger) [Archive, "archive-write" => ("While updating archive itver")] retry write_database!((self.database) itver = new_u16(self.itver + 1)));
+        self.advance_commit(logger);
+    }
+
+    /// Records a new mutation of the archive, advancing its commit sequence and giving it a new commit id
+    pub fn advance_commit(&self, mut logger: impl Logger) {
+        log!((logger) Archive("Advancing archive commit sequence..."));
+        let sequence = Self::read_optional_u64(&self.database, "sequence", logger.hollow()).unwrap_or(0) + 1;
+        let commit_id = random_id();
+        let mut lineage = self.lineage(logger.hollow());
+        lineage.push(commit_id);
+
+        with_context!((logger) [Archive, "archive-write" => ("While writing commit sequence")] retry write_database!((self.database) sequence = new_u64(sequence)));
+        with_context!((logger) [Archive, "archive-write" => ("While writing commit lineage")] retry write_database!((self.database) lineage = new_u64_array(&lineage)));
+        with_context!((logger) [Archive, "archive-write" => ("While writing commit id")] retry write_database!((self.database) commit_id = new_u64(commit_id)));
+    }
+
+    /// The commit sequence of the archive as it is on disk now, which may be ahead of `sequence` if it was modified
+    /// since it was loaded
+    pub fn current_sequence(&self, logger: impl Logger) -> u64 {
+        Self::read_optional_u64(&self.database, "sequence", logger).unwrap_or(0)
+    }
+
+    /// Backs up the home archive to `backup.ldb` before it is modified, moving the older backups kept by `keep_backups`
+    /// along to `backup.1.ldb`, `backup.2.ldb` and so on
+    pub fn backup_before_modification(mut logger: impl Logger) {
+        log!((logger) Archive("Backing up archive before modification..."));
+        let path = |i: usize| home_dir().join(if i == 0 { String::from("backup.ldb") } else { format!("backup.{i}.ldb") });
+        let keep = crate::config::Config::get().keep_backups();
+        let _ = std::fs::remove_file(path(keep - 1)); // Clean up
+        for i in (1..keep).rev() { let _ = std::fs::rename(path(i - 1), path(i)); }
+        Self::backup(path(0), logger.hollow());
+    }
+
+    #[inline]
+    pub fn uid(&self) -> u64 {
+        self.uid
     }
 
     #[inline]
@@ -269,9 +695,56 @@ impl Archive {
         self.database().path().join(path).exists()
     }
 
+    /// The uids of every entry (or moc), without loading them
+    pub fn uids(&self, is_moc: bool, logger: impl Logger) -> Vec<String> {
+        let path = self.database.path().join(if is_moc { "mocs" } else { "entries" });
+        if !path.is_dir() { return Vec::new() }
+
+        let dir = with_context!((logger) [Archive, "archive-read" => ("While reading directory {}'s contents", path.to_string_lossy())] retry fs::read_dir(&path));
+        dir.filter_map(|x| x.ok())
+            .filter(|x| x.file_type().is_ok_and(|x| x.is_dir()))
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Finds the full uid of an entry (or moc) from its uid or the start of it, crashing with the uids it could have
+    /// meant if it doesn't match exactly one
+    pub fn resolve_uid(&self, uid: String, is_moc: bool, mut logger: impl Logger) -> String {
+        let kind = if is_moc { "Moc" } else { "Entry" };
+        let uids = self.uids(is_moc, logger.hollow());
+        match uid::find(&uid, &uids) {
+            uid::Match::Found(x) if x == uid => uid,
+            uid::Match::Found(x) => {
+                log!((logger) Archive("Matched uid `{uid}` to `{x}`"));
+                x.to_string()
+            },
+            uid::Match::Ambiguous(x) if x.len() > 5 => {
+                log!((logger.error) Archive("{kind} uid `{uid}` is ambiguous; it could be {} and {} others", x[..4].iter().map(|x| format!("`{x}`")).collect::<Vec<_>>().join(", "), x.len() - 4) as Fatal);
+                logger.crash()
+            },
+            uid::Match::Ambiguous(x) => {
+                log!((logger.error) Archive("{kind} uid `{uid}` is ambiguous; it could be {}", uid::or_list(&x)) as Fatal);
+                logger.crash()
+            },
+            uid::Match::Missing(x) if x.is_empty() => {
+                log!((logger.error) Archive("{kind} of uid `{uid}` does not exist") as Fatal);
+                logger.crash()
+            },
+            uid::Match::Missing(x) => {
+                log!((logger.error) Archive("{kind} of uid `{uid}` does not exist; did you mean {}?", uid::or_list(&x)) as Fatal);
+                logger.crash()
+            },
+        }
+    }
+
     pub fn get_entry(&self, uid: String, mut logger: impl Logger) -> Option<Entry> {
         if !self.database_exists(format!("entries/{uid}")) {
-            log!((logger.error) Archive("Entry of uid `{uid}` does not exist") as Fatal);
+            let uids = self.uids(false, logger.hollow());
+            let suggestions = match uid::suggest(&uid, &uids) {
+                x if x.is_empty() => String::new(),
+                x => format!("; did you mean {}?", uid::or_list(&x)),
+            };
+            log!((logger.error) Archive("Entry of uid `{uid}` does not exist{suggestions}") as Fatal);
             return logger.crash();
         }
 
@@ -289,7 +762,12 @@ impl Archive {
 
     pub fn get_moc(&self, uid: String, mut logger: impl Logger) -> Option<MOC> {
         if !self.database_exists(format!("mocs/{uid}")) {
-            log!((logger.error) Archive("Moc of uid `{uid}` does not exist") as Fatal);
+            let uids = self.uids(true, logger.hollow());
+            let suggestions = match uid::suggest(&uid, &uids) {
+                x if x.is_empty() => String::new(),
+                x => format!("; did you mean {}?", uid::or_list(&x)),
+            };
+            log!((logger.error) Archive("Moc of uid `{uid}` does not exist{suggestions}") as Fatal);
             return logger.crash();
         }
 
@@ -313,12 +791,12 @@ impl Archive {
             return Vec::with_capacity(0);
         }
 
-        let mut logger1 = logger.hollow();
+        let logger1 = logger.hollow();
         let logger2 = logger.hollow();
-        let dir = if_err!((logger) [Entries, err => ("While reading directory {}'s contents: {err:?}", path.to_string_lossy())] retry fs::read_dir(&path));
+        let dir = with_context!((logger) [Entries, "archive-list" => ("While reading directory {}'s contents", path.to_string_lossy())] retry fs::read_dir(&path));
         dir.into_iter()
-            .map(|x| if_err!((logger) [Entries, err => ("While reading dir element: {err:?}")] {x} crash logger.crash()))
-            .filter(|x| if_err!((logger1) [Entries, err => ("While reading dir element: {err:?}")] {x.file_type()} crash logger1.crash()).is_dir())
+            .map(|x| with_context!((logger) [Entries, "archive-list" => ("While reading dir element")] {x} crash))
+            .filter(|x| with_context!((logger1) [Entries, "archive-list" => ("While reading dir element")] {x.file_type()} crash).is_dir())
             .map(|x| self.get_entry(x.file_name().to_string_lossy().to_string(), logger2.hollow()).unwrap())
             .collect()
     }
@@ -331,13 +809,53 @@ impl Archive {
             return Vec::with_capacity(0);
         }
 
-        let mut logger1 = logger.hollow();
+        let logger1 = logger.hollow();
         let logger2 = logger.hollow();
-        let dir = if_err!((logger) [MOCs, err => ("While reading directory {}'s contents: {err:?}", path.to_string_lossy())] retry fs::read_dir(&path));
+        let dir = with_context!((logger) [MOCs, "archive-list" => ("While reading directory {}'s contents", path.to_string_lossy())] retry fs::read_dir(&path));
         dir.into_iter()
-            .map(|x| if_err!((logger) [MOCs, err => ("While reading dir element: {err:?}")] {x} crash logger.crash()))
-            .filter(|x| if_err!((logger1) [MOCs, err => ("While reading dir element: {err:?}")] {x.file_type()} crash logger1.crash()).is_dir())
+            .map(|x| with_context!((logger) [MOCs, "archive-list" => ("While reading dir element")] {x} crash))
+            .filter(|x| with_context!((logger1) [MOCs, "archive-list" => ("While reading dir element")] {x.file_type()} crash).is_dir())
             .map(|x| self.get_moc(x.file_name().to_string_lossy().to_string(), logger2.hollow()).unwrap())
             .collect()
     }
-}
\ No newline at end of file
+}
+
+/// Canonicalizes a file being committed, refusing files inside the archive (which the commit may overwrite mid-read)
+/// unless `--allow-archive-paths` is set
+pub fn guard_source_path(path: &Path, mut logger: impl Logger) -> PathBuf {
+    let path_string = path.to_string_lossy();
+    let canonical = with_context!((logger) [Commit, "commit" => ("While canonicalizing path '{path_string}'")] retry path.canonicalize());
+    let archive = home_dir().join("archive");
+    let inside = archive.canonicalize().map(|x| canonical.starts_with(x)).unwrap_or(false);
+
+    if inside && !unsafe { crate::cli::ALLOW_ARCHIVE_PATHS } {
+        log!((logger.error) Commit("Path '{path_string}' is inside the archive and may be overwritten while it is read; copy it out of '{}' first (or use `--allow-archive-paths`)", archive.to_string_lossy()) as Fatal);
+        return logger.crash();
+    } canonical
+}
+
+/// Recursively copies a directory and its contents
+pub fn copy_dir(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Result<()> {
+    let to = to.as_ref();
+    fs::create_dir_all(to)?;
+    for item in fs::read_dir(from)? {
+        let item = item?;
+        if item.file_type()?.is_dir() {
+            copy_dir(item.path(), to.join(item.file_name()))?;
+        } else {
+            fs::copy(item.path(), to.join(item.file_name()))?;
+        }
+    } Ok(())
+}
+
+/// Adds the `default_tags` of the config to an entry config's tags, skipping the ones it already has (mocs are left as
+/// they are)
+pub fn with_default_tags(mut table: toml::Table, default_tags: &[String]) -> toml::Table {
+    if default_tags.is_empty() { return table }
+    let Some(entry) = table.get_mut("entry").and_then(|x| x.as_table_mut()) else { return table };
+    if let toml::Value::Array(tags) = entry.entry("tags").or_insert(toml::Value::Array(Vec::new())) {
+        for tag in default_tags {
+            if !tags.iter().any(|x| x.as_str() == Some(tag)) { tags.push(toml::Value::String(tag.clone())) }
+        }
+    } table
+}
@@ -0,0 +1,55 @@
+use lazy_db::*;
+use soulog::*;
+use crate::{archive::Archive, history, search::Searchable, unwrap_opt};
+
+/// Checks if an entry or moc is archived; archived items are hidden from lists, exports and moc collections by default
+pub fn is_archived(container: &LazyContainer, mut logger: impl Logger) -> bool {
+    match container.read_data("archived") {
+        Ok(x) => if_err!((logger) [Archived, err => ("While reading if item is archived: {err:?}")] {x.collect_bool()} crash logger.crash()),
+        Err(LDBError::FileNotFound(_)) => false,
+        Err(err) => {
+            log!((logger.error) Archived("While reading if item is archived: {err:?}") as Fatal);
+            logger.crash()
+        },
+    }
+}
+
+/// Marks an entry or moc container as archived or not
+pub fn set_archived(container: &LazyContainer, archived: bool, mut logger: impl Logger) {
+    if_err!((logger) [Archived, err => ("While marking item as archived: {err:?}")] retry write_container!((container) archived = new_bool(archived)));
+}
+
+/// Filters out the archived items unless `include_archived`
+pub fn visible<T: Searchable>(items: Vec<T>, include_archived: bool, logger: impl Logger) -> Vec<T> {
+    if include_archived { return items }
+    items.into_iter().filter(|x| !is_archived(x.container(), logger.hollow())).collect()
+}
+
+/// Marks an entry or moc as archived (or restores it with `unarchive`) without removing it from the archive
+pub fn archive_item(uid: String, is_moc: bool, unarchive: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let uid = archive.resolve_uid(uid, is_moc, logger.hollow());
+    let kind = if is_moc { "Moc" } else { "Entry" };
+    let container = if is_moc {
+        unwrap_opt!((archive.get_moc(uid.clone(), logger.hollow())) with logger, format: Archived("Moc of uid '{uid}' not found in archive")).container
+    } else {
+        unwrap_opt!((archive.get_entry(uid.clone(), logger.hollow())) with logger, format: Archived("Entry of uid '{uid}' not found in archive")).container
+    };
+
+    if is_archived(&container, logger.hollow()) != unarchive {
+        let state = if unarchive { "isn't" } else { "is already" };
+        log!((logger.vital) Archived("{kind} of uid '{uid}' {state} archived; doing nothing") as Inconvenience);
+        return;
+    }
+
+    Archive::backup_before_modification(logger.hollow());
+    set_archived(&container, !unarchive, logger.hollow());
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, if unarchive { "unarchive" } else { "archive" }, Some(&uid), logger.hollow());
+
+    if unarchive {
+        log!((logger.vital) Archived("Successfully unarchived {} of uid '{uid}'", kind.to_lowercase()) as Log);
+    } else {
+        log!((logger.vital) Archived("Successfully archived {} of uid '{uid}'; it is hidden from lists, exports and collections unless `--include-archived` is used", kind.to_lowercase()) as Log);
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, entry::{format_date, Entry}, json};
+
+/// An entry linking to an item
+pub struct Backlink {
+    pub uid: String,
+    pub title: String,
+    /// Date goes from `day` to `month` then to `year`
+    pub date: [u16; 3],
+    /// Whether it declares the link (`links = [...]`) rather than only cross-referencing it (`{{uid:...}}`)
+    pub declared: bool,
+}
+
+/// The entries that link to each item (through `links` or `{{uid:...}}` cross-references) by the uid they link to,
+/// oldest first; entries linking to themselves aren't counted
+pub fn backlink_map(entries: &mut [Entry], logger: impl Logger) -> HashMap<String, Vec<Backlink>> {
+    let mut result: HashMap<String, Vec<Backlink>> = HashMap::new();
+    for entry in entries.iter_mut() {
+        let date = *entry.date(logger.hollow());
+        let title = entry.title(logger.hollow()).clone();
+        let links = entry.links(logger.hollow()).clone();
+        let references = entry.references(logger.hollow()).clone();
+        for (target, declared) in links.iter().map(|x| (x, true)).chain(references.iter().filter(|x| !links.contains(x)).map(|x| (x, false))) {
+            if *target == entry.uid { continue }
+            result.entry(target.clone()).or_default().push(Backlink { uid: entry.uid.clone(), title: title.clone(), date, declared });
+        }
+        entry.clear_cache();
+    }
+    result.values_mut().for_each(|x| x.sort_by_key(|x| ([x.date[2], x.date[1], x.date[0]], x.uid.clone())));
+    result
+}
+
+/// Lists the entries that link to an entry or moc
+pub fn backlinks_command(uid: String, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    if !archive.database_exists(format!("entries/{uid}")) && !archive.database_exists(format!("mocs/{uid}")) {
+        log!((logger.error) Backlinks("There's no entry or moc of uid '{uid}' in the archive") as Fatal);
+        return logger.crash();
+    }
+
+    log!((logger) Backlinks("Finding the entries that link to '{uid}'..."));
+    let backlinks = backlink_map(&mut archive.list_entries(logger.hollow()), logger.hollow()).remove(&uid).unwrap_or_default();
+
+    if json::enabled() {
+        return json::print(backlinks.iter().map(|x| json!({
+            "uid": x.uid,
+            "title": x.title,
+            "date": format_date(&x.date),
+            "via": if x.declared { "link" } else { "reference" },
+        })).collect());
+    }
+
+    if backlinks.is_empty() {
+        log!((logger.vital) Backlinks("No entries link to '{uid}'") as Inconvenience);
+        return;
+    }
+    for x in backlinks.iter() {
+        let via = if x.declared { "" } else { " (cross-reference)" };
+        log!((logger.vital) Backlinks("{}", colour_format![cyan(&format_date(&x.date)), none(" "), green(&x.uid), blue(": "), none(&x.title), blue(via)]) as Log);
+    }
+}
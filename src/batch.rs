@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use soulog::*;
+use crate::{archive::Archive, history, signal, DynamicLogger};
+
+thread_local! {
+    /// The error that made the config being committed fail
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// What a config that failed to commit unwinds with
+struct Failed;
+
+/// Logs like the normal logger, but a crash only fails the config being committed (unwinding out of it)
+/// instead of exiting, so the rest of the batch can still be committed
+struct BatchLogger(DynamicLogger);
+
+impl Logger for BatchLogger {
+    fn new() -> Self { Self(DynamicLogger::new()) }
+    fn hollow(&self) -> Self { Self::new() }
+
+    fn crash<T>(&mut self) -> T {
+        resume_unwind(Box::new(Failed))
+    }
+
+    fn verbose(&mut self, log: Log) { self.0.verbose(log) }
+    fn vital(&mut self, log: Log) { self.0.vital(log) }
+
+    fn error(&mut self, log: Log) -> ErrorResponse {
+        LAST_ERROR.with(|x| *x.borrow_mut() = Some(log.message.to_string()));
+        self.0.error(log)
+    }
+}
+
+/// Finds the configs to commit: files as they are, and only the `*.toml` files in directories (recursively, skipping
+/// hidden ones)
+pub fn discover(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() { discover_dir(&path, &mut files) }
+        else { files.push(path) } // missing files fail when they are committed
+    }
+    files
+}
+
+fn discover_dir(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(read) = std::fs::read_dir(dir) else { return };
+    let mut paths: Vec<PathBuf> = read.flatten()
+        .filter(|x| !x.file_name().to_string_lossy().starts_with('.'))
+        .map(|x| x.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() { discover_dir(&path, files) }
+        else if path.extension().is_some_and(|x| x == "toml") { files.push(path) }
+    }
+}
+
+/// Commits many entry and moc configs (`.md` files given by name as markdown entries) and directories of configs in
+/// one run, with a single backup and itver bump, reporting which of them failed at the end instead of stopping at the
+/// first; markdown files inside the directories are left alone, as they are usually the content of sections
+pub fn commit_paths(paths: Vec<String>, mut logger: impl Logger) {
+    let files = discover(&paths);
+    if files.is_empty() {
+        log!((logger.vital) Commit("No configs found to commit in {paths:?}") as Inconvenience);
+        return;
+    }
+
+    let archive = Archive::load(logger.hollow());
+    Archive::backup_before_modification(logger.hollow());
+
+    // Interrupts stop it between configs, keeping the ones already committed
+    let _critical = signal::critical();
+    let mut committed = Vec::new();
+    let mut failed = Vec::new();
+    for file in files {
+        if signal::interrupted() {
+            log!((logger.vital) Commit("Interrupted; not committing the rest of the configs") as Warning);
+            break;
+        }
+        log!((logger) Commit("Committing '{}'...", file.to_string_lossy()));
+        LAST_ERROR.with(|x| *x.borrow_mut() = None);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let (entry, config) = match file.extension().is_some_and(|x| x == "md") {
+                true => Archive::read_markdown(Some(&file), BatchLogger::new()),
+                false => Archive::read_config(&file, BatchLogger::new()),
+            };
+            archive.commit_item(entry, &config, BatchLogger::new())
+        }));
+
+        match result {
+            Ok(uid) => committed.push((file, uid)),
+            Err(payload) if payload.is::<Failed>() => failed.push((file, LAST_ERROR.with(|x| x.borrow_mut().take()).unwrap_or_default())),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+    let _ = std::fs::remove_dir_all(archive.database().path().join("staging")); // Clean up after a failed one
+
+    if !committed.is_empty() {
+        crate::limits::check_archive(archive.database().path(), logger.hollow());
+        archive.bump_itver(logger.hollow());
+        committed.iter().for_each(|(_, uid)| history::record(&archive, "commit", Some(uid), logger.hollow()));
+    }
+
+    // Summary
+    for (file, uid) in committed.iter() {
+        log!((logger.vital) Commit("Committed '{}' as '{uid}'", file.to_string_lossy()) as Log);
+    }
+    for (file, error) in failed.iter() {
+        log!((logger.vital) Commit("Failed to commit '{}': {error}", file.to_string_lossy()) as Warning);
+    }
+    if !failed.is_empty() {
+        log!((logger.error) Commit("{} of {} configs failed to commit (the other {} were committed)", failed.len(), failed.len() + committed.len(), committed.len()) as Fatal);
+        return logger.crash();
+    }
+    log!((logger.vital) Commit("Successfully committed {} configs to archive", committed.len()) as Log);
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A map that holds at most `capacity` values, forgetting the least recently used one to make room for another; values
+/// are read through a shared reference, so readers behind a `RwLock` don't have to wait on each other
+pub struct Lru<K, V> {
+    capacity: usize,
+    /// Counts up with every use, so the smallest one a value was last used at is the least recent
+    tick: AtomicU64,
+    values: HashMap<K, (V, AtomicU64)>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Lru<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: AtomicU64::new(0),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Gets a value, making it the most recently used one
+    pub fn get(&self, key: &K) -> Option<V> {
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed) + 1;
+        let (value, used) = self.values.get(key)?;
+        used.fetch_max(tick, Ordering::Relaxed);
+        Some(value.clone())
+    }
+
+    /// Inserts a value unless there already is one for its key, returning the one that is kept
+    pub fn insert(&mut self, key: K, value: V) -> V {
+        if let Some(x) = self.get(&key) { return x }
+        if self.values.len() >= self.capacity {
+            let oldest = self.values.iter().min_by_key(|(_, (_, used))| used.load(Ordering::Relaxed)).map(|(key, _)| key.clone());
+            oldest.map(|x| self.values.remove(&x));
+        }
+        let tick = *self.tick.get_mut();
+        self.values.insert(key, (value.clone(), AtomicU64::new(tick)));
+        value
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.values.remove(key).map(|(x, _)| x)
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate};
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, cli::PLAIN, config::Config, entry::today, json, stats::to_naive};
+
+/// Colours of the heatmap's intensity levels (256-colour greens like GitHub's)
+const LEVELS: [u8; 5] = [237, 22, 28, 34, 40];
+
+/// Counts the entries written on each day of a year
+pub fn day_counts(dates: &[[u16; 3]], year: u16) -> BTreeMap<NaiveDate, usize> {
+    let mut counts = BTreeMap::new();
+    dates.iter()
+        .filter(|x| x[2] == year)
+        .filter_map(to_naive)
+        .for_each(|x| *counts.entry(x).or_default() += 1);
+    counts
+}
+
+/// The intensity level (`0..=4`) of a day's entry count relative to the busiest day
+pub fn level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 { return 0 }
+    1 + (count - 1) * 4 / max.max(1)
+}
+
+/// The width of a line in characters, as month names may not be ascii
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn cell(level: usize) -> String {
+    format!("\x1b[38;5;{}m■\x1b[0m ", LEVELS[level.min(4)])
+}
+
+pub fn calendar_command(year: Option<u16>, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let year = year.unwrap_or(today()[2]);
+    log!((logger) Calendar("Counting entries written in {year}..."));
+    let dates: Vec<[u16; 3]> = archive.list_entries(logger.hollow())
+        .into_iter()
+        .map(|mut x| *x.date(logger.hollow()))
+        .collect();
+    let counts = day_counts(&dates, year);
+
+    if json::enabled() {
+        return json::print(json!({
+            "year": year,
+            "days": counts.iter().map(|(day, count)| (day.to_string(), json!(count))).collect::<serde_json::Map<_, _>>(),
+        }));
+    }
+
+    let total: usize = counts.values().sum();
+    log!((logger.vital) Calendar("{total} entries on {} days in {year}", counts.len()) as Log);
+
+    // A grid can't be read by screen readers, so list the days of each month instead
+    let locale = &Config::get().calendar;
+    if unsafe { PLAIN } {
+        for (i, month) in locale.months.iter().enumerate() {
+            let days: Vec<String> = counts.iter()
+                .filter(|(x, _)| x.month0() == i as u32)
+                .map(|(x, count)| format!("{} ({count})", x.day()))
+                .collect();
+            if !days.is_empty() { log!((logger.vital) Calendar("{month}: {}", days.join(", ")) as Log) }
+        } return;
+    }
+
+    let Some(first) = NaiveDate::from_ymd_opt(year as i32, 1, 1) else {
+        log!((logger.error) Calendar("Year {year} is out of range") as Fatal);
+        return logger.crash();
+    };
+    let start = locale.week_of(first);
+    let weeks = (NaiveDate::from_ymd_opt(year as i32, 12, 31).unwrap() - start).num_days() as usize / 7 + 1;
+    let max = counts.values().copied().max().unwrap_or(0);
+
+    // Month labels above the week a month starts in
+    let mut header = String::from("    ");
+    for week in 0..weeks {
+        let day = start + chrono::Duration::days(week as i64 * 7 + 6);
+        let label = (day.year() == year as i32 && day.day() <= 7).then(|| locale.short_month(day.month()));
+        match label {
+            Some(x) if width(&header) <= 4 + week * 2 => header.push_str(&x),
+            _ => while width(&header) < 4 + (week + 1) * 2 { header.push(' ') },
+        }
+    } log!((logger.vital) Calendar("{}", header.trim_end()) as Log);
+
+    // Every other day is labelled, so the labels don't crowd each other
+    for (weekday, day) in locale.week().into_iter().enumerate() {
+        let name = if weekday % 2 == 0 { locale.short_day(day) } else { String::new() };
+        let mut row = format!("{name:<3} ");
+        for week in 0..weeks {
+            let day = start + chrono::Duration::days((week * 7 + weekday) as i64);
+            if day.year() != year as i32 { row.push_str("  "); continue }
+            row.push_str(&cell(level(counts.get(&day).copied().unwrap_or(0), max)));
+        } log!((logger.vital) Calendar("{row}") as Log);
+    }
+
+    let legend: String = (0..LEVELS.len()).map(cell).collect();
+    log!((logger.vital) Calendar("less {legend}more") as Log);
+}
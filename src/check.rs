@@ -0,0 +1,323 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{atomic::{AtomicUsize, Ordering}, mpsc};
+use lazy_db::*;
+use soulog::*;
+use crate::{archive::Archive, links, moc::walk_collections, search::Searchable};
+
+/// A problem found while checking the integrity of an archive
+pub struct Problem {
+    /// Where in the archive the problem is *eg* `entries/2023-08-21`
+    pub location: String,
+    pub message: String,
+    /// How `doctor --fix` repairs the problem, if it can
+    pub fix: Option<Fix>,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// A repair for a problem found in an archive
+#[derive(Clone, PartialEq)]
+pub enum Fix {
+    /// Rewrites the length of a list to the number of elements it actually has
+    ListLength { list: PathBuf, length: u16 },
+    /// Removes a uid that doesn't exist from an order list (`sorted` or `unsorted`)
+    RemoveFromOrder { order: String, uid: String },
+    /// Adds an entry that isn't in any order list to the unsorted list
+    AddToUnsorted(String),
+    /// Moves a broken entry or moc into the trash
+    Prune { uid: String, is_moc: bool },
+    /// Removes a directory that shouldn't be in the archive
+    RemoveDir(PathBuf),
+    /// Turns an entry's `{{uid:...}}` cross-references to an item that doesn't exist into plain text
+    UnlinkReference { uid: String, reference: String },
+    /// Removes a link to an item that doesn't exist from an entry's `links`
+    RemoveLink { uid: String, link: String },
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ListLength { length, .. } => write!(f, "set the list's length to {length}"),
+            Self::RemoveFromOrder { order, uid } => write!(f, "remove '{uid}' from the {order} list"),
+            Self::AddToUnsorted(uid) => write!(f, "add '{uid}' to the unsorted list"),
+            Self::Prune { uid, is_moc } => write!(f, "move {} '{uid}' into the trash", if *is_moc { "moc" } else { "entry" }),
+            Self::RemoveDir(_) => write!(f, "remove the directory"),
+            Self::UnlinkReference { reference, .. } => write!(f, "turn the cross-references to '{reference}' into plain text"),
+            Self::RemoveLink { link, .. } => write!(f, "remove the link to '{link}'"),
+        }
+    }
+}
+
+/// Collects problems for one location in the archive
+struct Checker<'a> {
+    problems: &'a mut Vec<Problem>,
+    location: String,
+}
+
+impl<'a> Checker<'a> {
+    /// A checker for a location nested within this one
+    fn nested(&mut self, path: impl fmt::Display) -> Checker<'_> {
+        Checker { problems: self.problems, location: format!("{}/{path}", self.location) }
+    }
+
+    fn report(&mut self, message: impl fmt::Display) {
+        self.problems.push(Problem { location: self.location.clone(), message: message.to_string(), fix: None });
+    }
+
+    fn report_fix(&mut self, message: impl fmt::Display, fix: Fix) {
+        self.problems.push(Problem { location: self.location.clone(), message: message.to_string(), fix: Some(fix) });
+    }
+
+    fn check<T>(&mut self, what: &str, result: Result<T, LDBError>) -> Option<T> {
+        match result {
+            Ok(x) => Some(x),
+            Err(err) => { self.report(format!("{what}: {err}")); None },
+        }
+    }
+
+    fn container(&mut self, container: &LazyContainer, key: &str) -> Option<LazyContainer> {
+        self.check(&format!("missing container '{key}'"), container.read_container(key))
+    }
+
+    fn string(&mut self, container: &LazyContainer, key: &str) -> Option<String> {
+        let data = self.check(&format!("missing '{key}'"), container.read_data(key))?;
+        self.check(&format!("invalid '{key}'"), data.collect_string())
+    }
+
+    /// Checks that a list's length matches the elements it actually has, returning the usable length
+    fn list_length(&mut self, container: &LazyContainer, key: &str) -> Option<u16> {
+        let actual = (0..=u16::MAX).find(|i| !container.path().join(i.to_string()).exists()).unwrap_or(u16::MAX);
+        let length = match container.read_data("length").and_then(|x| x.collect_u16()) {
+            Ok(x) => x,
+            Err(err) => {
+                self.report_fix(format!("missing or invalid '{key}' length: {err}"), Fix::ListLength { list: container.path().to_path_buf(), length: actual });
+                return Some(actual);
+            },
+        };
+        let extra = list_dir_all(container.path()).iter()
+            .filter_map(|x| x.parse::<u16>().ok())
+            .any(|x| x >= actual);
+
+        if actual != length || extra {
+            self.report_fix(format!("'{key}' has a length of {length} but {actual} contiguous elements"), Fix::ListLength { list: container.path().to_path_buf(), length: actual });
+        } Some(length.min(actual))
+    }
+
+    /// Checks a list written by `list::write`, returning its elements
+    fn string_list(&mut self, container: &LazyContainer, key: &str) -> Option<Vec<String>> {
+        let container = self.container(container, key)?;
+        let length = self.list_length(&container, key)?;
+        let mut list = Vec::with_capacity(length as usize);
+        for i in 0..length {
+            list.push(self.string(&container, &i.to_string())?);
+        } Some(list)
+    }
+
+    /// Checks a list of nested containers (sections or collections), returning them
+    fn container_list(&mut self, container: &LazyContainer, key: &str) -> Option<Vec<LazyContainer>> {
+        let container = self.container(container, key)?;
+        let length = self.list_length(&container, key)?;
+        let mut list = Vec::with_capacity(length as usize);
+        for i in 0..length {
+            list.push(self.container(&container, &i.to_string())?);
+        } Some(list)
+    }
+}
+
+/// Checks that every entry and moc of an archive can be read back, and that the order lists match the entries
+pub fn check_archive(archive: &Archive) -> Vec<Problem> {
+    check_archive_with(archive, default_jobs(), |_| ())
+}
+
+/// The number of workers items are checked with by default (one per cpu)
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map(|x| x.get()).unwrap_or(1)
+}
+
+/// Checks the entries and mocs of an archive on a pool of `jobs` workers, calling `found` with each problem as soon as
+/// it is found; the problems are returned grouped by item (in the order of their locations) once every item is checked
+pub fn check_archive_with(archive: &Archive, jobs: usize, mut found: impl FnMut(&Problem)) -> Vec<Problem> {
+    let path = archive.database().path();
+    let items: Vec<(String, bool)> = [("entries", false), ("mocs", true)].into_iter()
+        .flat_map(|(kind, is_moc)| list_dir(&path.join(kind)).into_iter().map(move |uid| (uid, is_moc)))
+        .collect();
+
+    // Workers take the next unchecked item and send back its problems
+    let next = AtomicUsize::new(0);
+    let mut groups: Vec<(usize, Vec<Problem>)> = Vec::new();
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            let (sender, next, items) = (sender.clone(), &next, &items);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((uid, is_moc)) = items.get(i) else { break };
+                let kind = if *is_moc { "mocs" } else { "entries" };
+                let mut problems = check_item(&path.join(kind).join(uid), format!("{kind}/{uid}"), *is_moc);
+
+                // Items missing required data can't be repaired, only pruned
+                problems.iter_mut()
+                    .filter(|x| x.fix.is_none())
+                    .for_each(|x| x.fix = Some(Fix::Prune { uid: uid.clone(), is_moc: *is_moc }));
+                if sender.send((i, problems)).is_err() { break }
+            });
+        } drop(sender);
+
+        for (i, problems) in receiver {
+            problems.iter().for_each(&mut found);
+            if !problems.is_empty() { groups.push((i, problems)) }
+        }
+    });
+    groups.sort_by_key(|(i, _)| *i);
+    let mut problems: Vec<Problem> = groups.into_iter().flat_map(|(_, x)| x).collect();
+    let start = problems.len();
+
+    let mut ordered = Vec::new();
+    for order in ["sorted", "unsorted"] {
+        let mut checker = Checker { problems: &mut problems, location: format!("order/{order}") };
+        let Some(order_container) = checker.check("missing order container", LazyContainer::load(path.join("order"))) else { continue };
+        let Some(uids) = checker.string_list(&order_container, order) else { continue };
+        uids.iter()
+            .filter(|uid| !path.join("entries").join(uid).is_dir())
+            .for_each(|uid| checker.report_fix(format!("entry '{uid}' doesn't exist"), Fix::RemoveFromOrder { order: order.to_string(), uid: uid.clone() }));
+        ordered.extend(uids);
+    }
+
+    // Orphaned entries that aren't in any order list
+    for uid in list_dir(&path.join("entries")).into_iter().filter(|x| !ordered.contains(x)) {
+        Checker { problems: &mut problems, location: format!("entries/{uid}") }
+            .report_fix("entry isn't in the sorted or unsorted list", Fix::AddToUnsorted(uid.clone()));
+    }
+
+    // Leftovers of an interrupted commit
+    if path.join("staging").exists() {
+        Checker { problems: &mut problems, location: String::from("staging") }
+            .report_fix("leftover staging area of an interrupted commit", Fix::RemoveDir(path.join("staging")));
+    }
+
+    problems[start..].iter().for_each(found);
+    problems
+}
+
+/// Finds the references between items of an archive whose targets no longer exist: `{{uid:...}}` cross-references
+/// in entries' sections, entries' `links` and moc collections whose tags no entry or moc has anymore
+pub fn check_references(archive: &Archive, logger: impl Logger) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let exists = |uid: &str| archive.database_exists(format!("entries/{uid}")) || archive.database_exists(format!("mocs/{uid}"));
+
+    // Section contents are scanned (rather than the entries' `references`) so entries committed before those were kept are checked too
+    for mut entry in archive.list_entries(logger.hollow()) {
+        let uid = entry.uid.clone();
+        let mut checker = Checker { problems: &mut problems, location: format!("entries/{uid}") };
+        for (i, section) in entry.sections(logger.hollow()).iter_mut().enumerate() {
+            for reference in links::cross_references(section.content(logger.hollow())).into_iter().filter(|x| !exists(x)) {
+                checker.nested(format!("sections/{i}")).report_fix(
+                    format!("cross-references '{reference}', which isn't in the archive"),
+                    Fix::UnlinkReference { uid: uid.clone(), reference },
+                );
+            } section.clear_cache();
+        }
+        for link in entry.links(logger.hollow()).iter().filter(|x| !exists(x)) {
+            checker.nested("links").report_fix(format!("links to '{link}', which isn't in the archive"), Fix::RemoveLink { uid: uid.clone(), link: link.clone() });
+        } entry.clear_cache();
+    }
+
+    let mut entries = archive.list_entries(logger.hollow());
+    let mut mocs = archive.list_mocs(logger.hollow());
+    for moc in archive.list_mocs(logger.hollow()).iter_mut() {
+        let uid = moc.uid.clone();
+        let mut checker = Checker { problems: &mut problems, location: format!("mocs/{uid}") };
+        let hollow = logger.hollow();
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+            if collection.groups_only(logger.hollow()) { return collection.clear_cache() }
+            let include = collection.include(logger.hollow()).clone();
+            let matches = |item: &mut dyn FnMut(&String) -> bool| include.iter().filter(|x| !x.starts_with('!')).all(item);
+            let found = entries.iter_mut().any(|x| matches(&mut |tag| x.contains_tag(tag, logger.hollow())))
+                || mocs.iter_mut().filter(|x| x.uid != uid).any(|x| matches(&mut |tag| x.contains_tag(tag, logger.hollow())));
+            if !found {
+                let location: Vec<String> = at.iter().map(|i| format!("collections/{i}")).collect();
+                checker.nested(location.join("/")).report(format!("collection '{}' includes {include:?}, which no entry or moc has", collection.title(logger.hollow())));
+            } collection.clear_cache();
+        }, hollow);
+        moc.clear_cache();
+    }
+    problems
+}
+
+/// Checks that a single entry or moc (*eg* a staged commit) can be read back
+pub fn check_item(path: &Path, location: String, is_moc: bool) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let checker = Checker { problems: &mut problems, location };
+    if is_moc { check_moc(path, checker) }
+    else { check_entry(path, checker) }
+    problems
+}
+
+/// Lists the names of the sub-directories of a directory (the uids of the entries or mocs)
+pub fn list_dir(path: &Path) -> Vec<String> {
+    let mut result: Vec<String> = match std::fs::read_dir(path) {
+        Ok(x) => x.filter_map(|x| x.ok())
+            .filter(|x| x.path().is_dir())
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    }; result.sort();
+    result
+}
+
+/// Lists the names of everything in a directory
+fn list_dir_all(path: &Path) -> Vec<String> {
+    match std::fs::read_dir(path) {
+        Ok(x) => x.filter_map(|x| x.ok())
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn check_entry(path: &Path, mut checker: Checker) {
+    let Some(container) = checker.check("unreadable entry", LazyContainer::load(path)) else { return };
+    checker.string(&container, "title");
+    checker.string(&container, "description");
+    checker.string_list(&container, "notes");
+    checker.string_list(&container, "tags");
+
+    if let Some(date) = checker.check("missing 'date'", container.read_data("date")) {
+        if let Some(date) = checker.check("invalid 'date'", date.collect_u16_array()) {
+            if date.len() != 3 { checker.report("'date' must have a day, month and year") }
+        }
+    }
+
+    for (i, section) in checker.container_list(&container, "sections").unwrap_or_default().iter().enumerate() {
+        let mut checker = checker.nested(format!("sections/{i}"));
+        checker.string(section, "title");
+        checker.string(section, "content");
+        checker.string_list(section, "notes");
+    }
+}
+
+fn check_moc(path: &Path, mut checker: Checker) {
+    let Some(container) = checker.check("unreadable moc", LazyContainer::load(path)) else { return };
+    checker.string(&container, "title");
+    checker.string(&container, "description");
+    checker.string_list(&container, "notes");
+    checker.string_list(&container, "tags");
+
+    check_collections(&container, &mut checker);
+}
+
+/// Checks a moc's (or a collection's) collections, and their sub-collections
+fn check_collections(container: &LazyContainer, checker: &mut Checker) {
+    for (i, collection) in checker.container_list(container, "collections").unwrap_or_default().iter().enumerate() {
+        let mut checker = checker.nested(format!("collections/{i}"));
+        checker.string(collection, "title");
+        checker.string_list(collection, "notes");
+        checker.string_list(collection, "include");
+        if collection.path().join("collections").is_dir() { check_collections(collection, &mut checker) }
+    }
+}
@@ -1,17 +1,38 @@
 use clap::*;
 use crate::archive::Archive;
+use crate::moc::generate::GroupBy;
+use crate::pick::PickAction;
 use crate::*;
 use soulog::*;
 
 pub static mut VERBOSE: bool = false;
+pub static mut JSON: bool = false;
+pub static mut PRIVATE: bool = false;
+pub static mut WAIT: bool = false;
+pub static mut PLAIN: bool = false;
+pub static mut ALLOW_ARCHIVE_PATHS: bool = false;
+/// Lets commits replace the entries and mocs already in the archive with the same uid
+pub static mut OVERWRITE: bool = false;
+/// Folds committed entries straight into the sorted list instead of leaving them for `diary-cli sort`
+pub static mut SORT_ON_COMMIT: bool = false;
+/// Overrides the folder relative section paths and attachments of committed configs are resolved from
+pub static BASE_DIR: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
 
 #[derive(Parser)]
 #[command(author, version, about)]
 pub struct Cli {
     #[arg(short, long, help="Specifies if you want it to log everything it does")]
     pub verbose: bool,
+    #[arg(long, global=true, help="Outputs results as machine-readable json instead of coloured logs")]
+    pub json: bool,
+    #[arg(long, global=true, help="Redacts diary content and absolute paths from logs (for sharing them in bug reports)")]
+    pub private: bool,
+    #[arg(long, global=true, help="Waits for other diary-cli processes to release the archive instead of failing")]
+    pub wait: bool,
+    #[arg(long, global=true, help="Plain output without colours for screen readers and dumb terminals (also set by `NO_COLOR`)")]
+    pub plain: bool,
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
@@ -24,13 +45,125 @@ pub enum Commands {
     Wipe,
     #[command(about="Commit an entry into the archive")]
     Commit {
-        #[arg(index=1, required=true, help="The path to the entry config toml file to commit.")]
-        file_path: String,
+        #[arg(index=1, num_args=1.., required_unless_present_any=["stdin", "markdown"], help="The paths to the entry config toml files (or directories of them) to commit.")]
+        file_paths: Vec<String>,
+        #[arg(long, conflicts_with_all=["file_paths", "markdown"], help="Commits a markdown entry (frontmatter and `##` sections) read from stdin")]
+        stdin: bool,
+        #[arg(long, conflicts_with="file_paths", help="Commits a markdown entry file (frontmatter and `##` sections) instead of a toml config")]
+        markdown: Option<String>,
+        #[arg(long, help="Allows committing files from inside the archive directory")]
+        allow_archive_paths: bool,
+        #[arg(long, help="Resolves relative section paths and attachments from this directory instead of each config's own folder")]
+        base_dir: Option<String>,
+        #[arg(long, help="Only validates the configs, reporting every problem with them, without committing anything")]
+        dry_run: bool,
+        #[arg(long, help="Replaces entries and mocs already in the archive with the same uid (removing everything of the old ones) instead of refusing to commit")]
+        overwrite: bool,
+        #[arg(long, help="Puts committed entries straight into their place in the sorted order (like `sort_on_commit` in config.toml)")]
+        sort: bool,
+    },
+    #[command(about="Validates entry and moc configs without committing them, reporting every problem at once")]
+    Validate {
+        #[arg(index=1, required=true, num_args=1.., help="The paths to the config toml files (or directories of them) to validate")]
+        file_paths: Vec<String>,
+        #[arg(long, help="Resolves relative section paths and attachments from this directory instead of each config's own folder")]
+        base_dir: Option<String>,
+    },
+    #[command(about="Shows the history of operations performed on the archive")]
+    History {
+        #[arg(short='n', long, help="Only shows the most recent operations")]
+        limit: Option<usize>,
+    },
+    #[command(about="Shows statistics about the archive")]
+    Stats {
+        #[arg(short, long, requires="trend", help="The tag to show the trend of (`proj*` is a glob)")]
+        tag: Option<String>,
+        #[arg(long, requires="tag", help="Shows how many entries use the tag each month")]
+        trend: bool,
+        #[arg(long, requires="trend", help="Outputs the trend as csv")]
+        csv: bool,
+        #[arg(long, num_args=0..=1, default_missing_value="10", conflicts_with="tag", help="Shows the largest entries, mocs and attachments (10 of each unless a number is given)")]
+        largest: Option<usize>,
+    },
+    #[command(about="Shows your current and longest writing streaks")]
+    Streak {
+        #[arg(short, long, help="Counts streaks of consecutive weeks instead of days")]
+        weekly: bool,
+    },
+    #[command(about="Shows a heatmap of the entries written on each day of a year")]
+    Calendar {
+        #[arg(short, long, help="The year to show (defaults to this year)")]
+        year: Option<u16>,
+    },
+    #[command(about="Shows the entries written on this day in previous years")]
+    OnThisDay {
+        #[arg(short, long, help="The day to look back on as `month-day` (defaults to today)")]
+        date: Option<String>,
+        #[arg(short, long, help="Shows the full contents of the entries")]
+        read: bool,
+        #[arg(long, help="Shows the contents of time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
+    },
+    #[command(about="Generates a demo archive of sample entries and mocs to try diary-cli out on")]
+    Demo {
+        #[arg(short, long, default_value_t=100, help="How many entries to generate")]
+        entries: usize,
+        #[arg(short, long, help="Where to generate the demo archive (defaults to a temporary directory)")]
+        path: Option<String>,
+        #[arg(short, long, help="Seeds the generator to reproduce the same archive")]
+        seed: Option<u64>,
+    },
+    #[command(about="Shows a random entry from the archive")]
+    Random {
+        #[arg(short='f', long="filter", num_args=1.., help="Only picks from entries with the specified tags (`!tag` excludes a tag, `proj*` is a glob)")]
+        tags: Option<Vec<String>>,
+        #[arg(short, long, requires="tags", help="Sets if the entry must have all of the specified tags")]
+        strict: bool,
+        #[arg(short, long, help="Shows the full entry instead of just its about-info")]
+        read: bool,
+        #[arg(long, help="Also picks from time capsules that haven't unlocked yet")]
+        force_unlock: bool,
+    },
+    #[command(about="Browses the archive in a terminal ui, where entries and mocs can be filtered, read, edited, exported and removed")]
+    Tui,
+    #[command(about="Searches the entries and mocs by uid, title and tags interactively, then shows (or reads, pulls or removes) the one picked")]
+    Pick {
+        #[arg(index=1, help="What to search for to begin with")]
+        query: Option<String>,
+        #[arg(short, long, value_enum, default_value_t, help="What to do with the entry or moc picked")]
+        action: PickAction,
     },
+    #[command(about="Reads an entry in the terminal")]
+    Read {
+        #[arg(index=1, required=true, help="The uid of the entry (or the start of it)")]
+        uid: String,
+        #[arg(long, help="Reads time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
+    },
+    #[command(about="Lists the entries that link to an entry or moc (through `links` or `{{uid:...}}` cross-references)")]
+    Backlinks {
+        #[arg(index=1, required=true, help="The uid of the entry or moc")]
+        uid: String,
+    },
+    #[command(visible_aliases=["fsck", "check"], about="Checks the integrity of the archive (and with `--references` its references) and optionally repairs it")]
+    Doctor {
+        #[arg(long, help="Repairs the problems found, moving broken entries and mocs into the trash")]
+        fix: bool,
+        #[arg(long, help="Also checks for cross-references, links and collections whose targets no longer exist (`--fix` turns broken cross-references into plain text and removes broken links)")]
+        references: bool,
+        #[arg(short, long, help="The number of workers that check entries and mocs at once (defaults to one per cpu)")]
+        jobs: Option<usize>,
+    },
+    #[command(about="Undoes the most recent commit")]
+    Undo,
     #[command(about="Backs up the archive")]
     Backup {
         #[arg(index=1, required=false, help="Specifies the path that you want the backup file to be generated.")]
         out_path: Option<String>,
+        #[arg(long, value_enum, default_value="ldb", help="Specifies the format of the backup file.")]
+        format: BackupFormat,
+        #[arg(long, help="Checks that the backup is restorable after writing it.")]
+        verify: bool,
     },
     #[command(about="Loads a backed up archive")]
     Load {
@@ -38,24 +171,26 @@ pub enum Commands {
         force: bool,
         #[arg(index=1, required=true, help="The path of the backup file you want to load.")]
         file_path: String,
+        #[arg(long, num_args=1.., help="Only restores the entries and mocs of these uids from the backup.")]
+        only: Option<Vec<String>>,
+        #[arg(short, long, num_args=1.., help="Only restores the entries and mocs with any of these tags from the backup.")]
+        tags: Option<Vec<String>>,
     },
     #[command(about="Rolls back to the last backed up archive")]
     Rollback {
         #[arg(short, long, help="Force loads a backup even if you may lose archive data.")]
         force: bool,
     },
-    #[command(about="Returns the days since 2020 from a specified date")]
-    Since {
-        #[arg(short, long, number_of_values=3, value_names=&["year", "month", "day"])]
-        date: Option<Vec<u16>>,
-        #[arg(short, long)]
-        today: bool,
+    #[command(about="Works out days between dates, days of the week, dates some days ago and ISO weeks")]
+    Date {
+        #[command(subcommand)]
+        command: DateCommands,
     },
     #[command(about="Pulls a entry or moc from the archive as toml in case you need to change something")]
     Pull {
         #[arg(short='m', long, help="Specifies if it is a moc or not (otherwise it is an entry).")]
         is_moc: bool,
-        #[arg(index=1, required=true, help="The uid of the entry or moc.")]
+        #[arg(index=1, required=true, help="The uid of the entry or moc (or the start of it).")]
         uid: String,
         #[arg(short='1', long, help="Specifies if you want it all in one file.")]
         one_file: bool,
@@ -63,10 +198,12 @@ pub enum Commands {
         path: String,
         #[arg(short, long, default_value="config.toml", help="Specifies the name of the output config file.")]
         file_name: String,
+        #[arg(long, help="Shows the contents of time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
     },
     #[command(about="Searches the archive with specified tags.")]
     List {
-        #[arg(short='f', long="filter", num_args=1.., help="Filters out the list accordding to specified tags")]
+        #[arg(short='f', long="filter", num_args=1.., help="Filters out the list accordding to specified tags (`!tag` excludes a tag, `proj*` is a glob)")]
         tags: Option<Vec<String>>,
         #[arg(short, long, requires="tags", help="Sets if the search is strict or not (if the item must implement all tags)")]
         strict: bool,
@@ -74,32 +211,291 @@ pub enum Commands {
         show_entries: bool,
         #[arg(short='m', long, help="Sets if you want to show mocs")]
         show_mocs: bool,
+        #[arg(long, help="Also lists archived entries and mocs")]
+        include_archived: bool,
+        #[arg(long, conflicts_with="show_mocs", help="Lists the entries in the order they are exported in, with their dates and titles")]
+        sorted: bool,
+        #[arg(short, long, requires="sorted", help="Lists the sorted entries newest first")]
+        reverse: bool,
+        #[arg(short, long, help="Lists every entry, even those older than the `default_list_horizon` of config.toml")]
+        all: bool,
+    },
+    #[command(about="Searches the contents of entries' sections with a regex, optionally replacing matches one by one.")]
+    Grep {
+        #[arg(index=1, required=true, help="The regex to search for")]
+        pattern: String,
+        #[arg(short, long, help="Matches the pattern case-insensitively")]
+        ignore_case: bool,
+        #[arg(short='f', long="filter", num_args=1.., help="Only searches entries with any of these tags (`!tag` excludes a tag, `proj*` is a glob)")]
+        tags: Option<Vec<String>>,
+        #[arg(long, value_name="REPLACEMENT", help="Asks whether to replace each match with this (`$1` refers to a capture group), backing up the archive first")]
+        and_replace: Option<String>,
+        #[arg(long, help="Also searches time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
+    },
+    #[command(about="Lists every tag in the archive with how often it is used.")]
+    Tags {
+        #[arg(short, long, help="Displays nested tags (`a/b/c`) as a tree")]
+        tree: bool,
+    },
+    #[command(about="Renames or merges tags across the whole archive.")]
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+    #[command(about="Adds, removes and lists the upcoming events counted down to")]
+    Event {
+        #[command(subcommand)]
+        command: EventCommands,
+    },
+    #[command(about="Sorts the committed entries by date, and entries of the same day by when they were committed.")]
+    Sort {
+        #[arg(short, long, help="Lists the sorted entries with their dates and titles")]
+        list: bool,
+        #[arg(short, long, requires="list", help="Lists the sorted entries newest first")]
+        reverse: bool,
+        #[arg(short, long, help="Sorts every entry again, even if none are waiting to be sorted (for lists sorted before same-day entries were ordered by commit time)")]
+        full: bool,
     },
-    #[command(about="Sorts the unsorted, committed, entries.")]
-    Sort,
     #[command(about="Exports the archive as an `Obsidian.md` vault.")]
     Export {
-        #[arg(short, long, num_args=1.., help="Filters out entries and mocs that don't have all these tags")]
+        #[arg(short, long, num_args=1.., help="Filters out entries and mocs that don't have all these tags (`!tag` excludes a tag, `proj*` is a glob)")]
         tags: Option<Vec<String>>,
         #[arg(short, long, requires="tags", help="Determines if the tags filter strictly or not")]
         strict: bool,
-        #[arg(index=1, required=true, help="The path the `Obsidian.md` vault is going to be placed")]
-        path: String,
+        #[arg(index=1, help="The path the `Obsidian.md` vault is going to be placed (defaults to the `default_dest` of config.toml)")]
+        path: Option<String>,
+        #[arg(short, long, help="Exports to a destination defined in config.toml (other options override its settings)")]
+        dest: Option<String>,
+        #[arg(long, help="Shows the contents of time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
+        #[arg(long, help="Exports each collection of a moc into its own file linked from the moc")]
+        split_collections: bool,
+        #[arg(long, conflicts_with="split_collections", help="Exports the entries chronologically into a single `diary.md` book with a table of contents")]
+        single_file: bool,
+        #[arg(short, long, value_enum, conflicts_with="single_file", help="The format to export the archive as (defaults to md)")]
+        format: Option<export::ExportFormat>,
+        #[arg(long, requires="format", help="The built-in theme of html exports (`light` or `dark`), customisable from `templates/html/`")]
+        theme: Option<String>,
+        #[arg(long, help="A directory of markdown templates (`entry.md`, `capsule.md`, `moc.md`, `collection.md`) to export with")]
+        template: Option<String>,
+        #[arg(long, conflicts_with="single_file", help="Only rewrites the files that changed since the last incremental export to this path")]
+        incremental: bool,
+        #[arg(long, help="Also exports archived entries and mocs and lists them in collections")]
+        include_archived: bool,
+        #[arg(long, value_enum, conflicts_with="single_file", help="How the files of the vault are arranged into folders (defaults to flat)")]
+        layout: Option<export::ExportLayout>,
+        #[arg(long, conflicts_with="single_file", help="Deletes the files of the previous export into the vault that are no longer exported")]
+        prune: bool,
+        #[arg(long, value_enum, help="How files of the vault link to each other (defaults to markdown)")]
+        links: Option<links::LinkStyle>,
+        #[arg(long, value_enum, help="Only exports the entries and mocs readers with this access can see, by the tags given access in config.toml")]
+        access: Option<access::Access>,
+        #[arg(long, conflicts_with="single_file", help="Writes a `tags/<tag>.md` page listing the entries of each tag, and a `tags.md` index of them")]
+        tag_pages: bool,
+        #[arg(long, conflicts_with="single_file", help="Lists the events coming up in the month after each entry's date at the end of its note")]
+        events: bool,
+        #[arg(long, help="Checks that the vault still matches the manifest of its last export instead of exporting")]
+        verify: bool,
+    },
+    #[command(about="Imports an export into the archive (or a new one), skipping what is already in it")]
+    Import {
+        #[arg(index=1, value_enum, help="The format of the export")]
+        format: ImportFormat,
+        #[arg(index=2, required=true, help="The path to the exported file")]
+        file: String,
     },
     #[command(about="Lists the attributes about an entry or moc.")]
     About {
         #[arg(short='m', long, help="Determines if it is a moc or not")]
         is_moc: bool,
-        #[arg(index=1, required=true, help="The uid of the entry or moc")]
+        #[arg(index=1, required=true, help="The uid of the entry or moc (or the start of it)")]
         uid: String,
+        #[arg(long, help="Shows the contents of time capsule entries that haven't unlocked yet")]
+        force_unlock: bool,
+        #[arg(long, help="Shows where and when it was committed from")]
+        provenance: bool,
     },
     #[command(about="Removes an entry or moc from the archive.")]
     Remove {
         #[arg(short='m', long, help="Determines if it is a moc or not")]
         is_moc: bool,
-        #[arg(index=1)]
+        #[arg(index=1, help="The uid of the entry or moc (or the start of it)")]
+        uid: String,
+        #[arg(long, help="Permanently deletes it instead of moving it to the trash")]
+        purge: bool,
+    },
+    #[command(about="Archives an entry or moc, hiding it from lists, exports and collections without removing it.")]
+    ArchiveItem {
+        #[arg(short='m', long, help="Determines if it is a moc or not")]
+        is_moc: bool,
+        #[arg(index=1, help="The uid of the entry or moc (or the start of it)")]
+        uid: String,
+        #[arg(long, help="Unarchives the entry or moc instead")]
+        undo: bool,
+    },
+    #[command(about="Lists or restores removed entries and mocs.")]
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+    #[command(about="Runs scheduled backups and commits from a watch folder in the background (see config.toml's `[agent]`)")]
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommands,
+    },
+    #[command(about="Generates mocs from the tags of the archive.")]
+    Moc {
+        #[command(subcommand)]
+        command: MocCommands,
+    },
+    #[command(about="Prints a completion script for a shell (bash, zsh and fish also complete uids and tags from the archive)")]
+    Completions {
+        #[arg(index=1, required=true, value_enum, help="The shell to complete in")]
+        shell: clap_complete::Shell,
+    },
+    #[command(name="__complete-uids", hide=true)]
+    CompleteUids,
+    #[command(name="__complete-tags", hide=true)]
+    CompleteTags,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum BackupFormat {
+    #[value(help="A compiled `lazy-db` archive")]
+    Ldb,
+    #[value(help="A plain tarball with a manifest")]
+    Tar,
+    #[value(name="tar.zst", help="A zstd compressed tarball with a manifest")]
+    TarZst,
+}
+
+impl BackupFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Ldb => "ldb",
+            Self::Tar => "tar",
+            Self::TarZst => "tar.zst",
+        }
+    }
+}
+
+/// The formats archives can be imported from
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// A dump made by `export --format json`
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    #[command(about="Lists the entries and mocs in the trash")]
+    List,
+    #[command(about="Restores an entry or moc from the trash")]
+    Restore {
+        #[arg(short='m', long, help="Determines if it is a moc or not")]
+        is_moc: bool,
+        #[arg(index=1, required=true, help="The uid of the entry or moc")]
         uid: String,
     },
+    #[command(about="Deletes the items in the trash that are older or larger than config.toml's `[trash]` retention allows")]
+    Prune,
+}
+
+#[derive(Subcommand)]
+pub enum AgentCommands {
+    #[command(about="Runs the agent in the foreground until it is stopped (for a systemd user service or the like)")]
+    Run,
+    #[command(about="Shows if the agent is running and what it does")]
+    Status,
+    #[command(about="Stops the running agent")]
+    Stop,
+}
+
+#[derive(Subcommand)]
+pub enum MocCommands {
+    #[command(about="Generates and commits a moc of the entries with a tag, with a collection per sub-tag or per year")]
+    Generate {
+        #[arg(long, required=true, help="The tag of the entries in the moc")]
+        tag: String,
+        #[arg(long, value_enum, default_value="subtag", help="How the entries are split into collections")]
+        by: GroupBy,
+        #[arg(long, help="The uid of the moc (defaults to `<tag>-moc`)")]
+        uid: Option<String>,
+        #[arg(long, help="The title of the moc (defaults to `All about <tag>`)")]
+        title: Option<String>,
+        #[arg(long, help="Only prints the generated moc config instead of committing it, so it can be edited first")]
+        print: bool,
+        #[arg(long, help="Replaces a moc already in the archive with the same uid instead of refusing to commit")]
+        overwrite: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DateCommands {
+    #[command(about="The number of days from one date to another")]
+    Between {
+        #[arg(index=1, required=true, help="The first date (`2024-02-29`, `today`, `yesterday` or `tomorrow`)")]
+        from: String,
+        #[arg(index=2, help="The second date (defaults to today)")]
+        to: Option<String>,
+    },
+    #[command(about="The day of the week a date is on")]
+    Weekday {
+        #[arg(index=1, required=true, help="The date (`2024-02-29`, `today`, `yesterday` or `tomorrow`)")]
+        date: String,
+    },
+    #[command(about="The date some days ago (or ahead, if negative)")]
+    Ago {
+        #[arg(index=1, required=true, allow_negative_numbers=true, help="The number of days")]
+        days: i64,
+        #[arg(long, help="The date to count back from (defaults to today)")]
+        from: Option<String>,
+    },
+    #[command(about="The ISO 8601 week (like `2024-W09`) a date is in")]
+    Week {
+        #[arg(index=1, help="The date (defaults to today)")]
+        date: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EventCommands {
+    #[command(about="Adds an event to count down to")]
+    Add {
+        #[arg(index=1, required=true, help="What the event is")]
+        title: String,
+        #[arg(index=2, required=true, help="The date of the event (like `2025-04-01`)")]
+        date: String,
+    },
+    #[command(about="Removes the events with a title")]
+    Remove {
+        #[arg(index=1, required=true, help="The title of the event")]
+        title: String,
+    },
+    #[command(about="Lists the upcoming events with how long is left until them")]
+    List {
+        #[arg(short, long, help="Also lists the events that have passed")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    #[command(about="Renames a tag in every entry, moc and collection")]
+    Rename {
+        #[arg(index=1, required=true, help="The tag to rename")]
+        old: String,
+        #[arg(index=2, required=true, help="The new name of the tag")]
+        new: String,
+    },
+    #[command(about="Merges several tags into one in every entry, moc and collection")]
+    Merge {
+        #[arg(index=1, required=true, num_args=1.., help="The tags to merge")]
+        tags: Vec<String>,
+        #[arg(long, required=true, help="The tag they are merged into")]
+        into: String,
+    },
 }
 
 impl Commands {
@@ -110,28 +506,161 @@ impl Commands {
             Test => println!("Hello, world!"),
             Init => {Archive::init(logger);},
             Wipe => Archive::load(logger.hollow()).wipe(logger),
-            Commit { file_path } => Archive::load(logger.hollow()).commit(file_path, logger),
-            Load { file_path, force } => Archive::load_backup(file_path, force, logger),
+            Commit { file_paths, stdin: _, markdown, allow_archive_paths, base_dir, dry_run, overwrite, sort } => {
+                unsafe { ALLOW_ARCHIVE_PATHS = allow_archive_paths };
+                unsafe { OVERWRITE = overwrite };
+                unsafe { SORT_ON_COMMIT = sort };
+                set_base_dir(base_dir, logger.hollow());
+                if dry_run && file_paths.is_empty() { return validate::validate_markdown(markdown.as_deref().map(std::path::Path::new), logger) }
+                if dry_run { return validate::validate_paths(file_paths, logger) }
+                if file_paths.len() > 1 || file_paths.first().is_some_and(|x| std::path::Path::new(x).is_dir()) {
+                    return batch::commit_paths(file_paths, logger);
+                }
+                let archive = Archive::load(logger.hollow());
+                let uid = match (file_paths.into_iter().next(), markdown) {
+                    (Some(file_path), _) => archive.commit(file_path, logger.hollow()),
+                    (None, Some(file)) => archive.commit_markdown(Some(std::path::Path::new(&file)), logger.hollow()),
+                    (None, None) => archive.commit_markdown(None, logger.hollow()),
+                };
+                membership::report(&archive, uid, logger);
+            },
+            Undo => journal::undo(logger),
+            Doctor { fix, references, jobs } => doctor::doctor(fix, references, jobs, logger),
+            Stats { tag: Some(tag), trend: true, csv, .. } => stats::trend_command(tag, csv, logger),
+            Stats { largest: Some(count), .. } => stats::largest_command(count, logger),
+            Stats { .. } => stats::stats_command(logger),
+            Streak { weekly } => stats::streak_command(weekly, logger),
+            Calendar { year } => calendar::calendar_command(year, logger),
+            Demo { entries, path, seed } => demo::demo(entries, path.map(std::path::PathBuf::from), seed, logger),
+            Random { tags, strict, read, force_unlock } => random::random(tags, strict, read, force_unlock, logger),
+            Pick { query, action } => pick::pick(query, action, logger),
+            Tui => tui::tui(logger),
+            Read { uid, force_unlock } => read::read(uid, force_unlock, logger),
+            Backlinks { uid } => backlinks::backlinks_command(uid, logger),
+            OnThisDay { date, read, force_unlock } => on_this_day::on_this_day(date, read, force_unlock, logger),
+            History { limit } => history::history_command(limit, logger),
+            Load { file_path, force, only: None, tags: None } => Archive::load_backup(file_path, force, logger),
+            Load { file_path, only, tags, .. } => Archive::load_selective(file_path, only, tags, logger),
             Rollback { force } => Archive::rollback(force, logger),
-            Backup { out_path } => {
-                match out_path {
-                    Some(path) => Archive::backup(path, logger),
-                    None => Archive::backup(home_dir().join("backup.ldb"), logger),
+            Backup { out_path, format, verify } => {
+                let path = match out_path {
+                    Some(path) => std::path::PathBuf::from(path),
+                    None => home_dir().join(format!("backup.{}", format.extension())),
+                };
+                match format {
+                    BackupFormat::Ldb => Archive::backup(&path, logger.hollow()),
+                    BackupFormat::Tar => Archive::backup_tar(&path, false, logger.hollow()),
+                    BackupFormat::TarZst => Archive::backup_tar(&path, true, logger.hollow()),
                 }
+                if verify { Archive::verify_backup(path, logger) }
+            },
+            Date { command: DateCommands::Between { from, to } } => date::between(from, to, logger),
+            Date { command: DateCommands::Weekday { date } } => date::weekday(date, logger),
+            Date { command: DateCommands::Ago { days, from } } => date::ago(days, from, logger),
+            Date { command: DateCommands::Week { date } } => date::week(date, logger),
+            Pull { is_moc, one_file, uid, path, file_name, force_unlock } => pull::pull(std::path::PathBuf::from(path), file_name, is_moc, uid, one_file, force_unlock, logger),
+            List { strict, tags, show_entries, show_mocs, include_archived, sorted, reverse, all } => {
+                let horizon = config::Config::get().default_list_horizon.filter(|_| !all);
+                search::list_command(show_mocs, show_entries, tags.map(|x| (x, strict)), include_archived, horizon, sorted.then_some(reverse), logger)
+            },
+            Grep { pattern, ignore_case, tags, and_replace, force_unlock } => grep::grep(pattern, ignore_case, tags, and_replace, force_unlock, logger),
+            Tags { tree } => tags::tags_command(tree, logger),
+            Tag { command: TagCommands::Rename { old, new } } => tags::rename_tags(vec![old], new, logger),
+            Tag { command: TagCommands::Merge { tags, into } } => tags::rename_tags(tags, into, logger),
+            Event { command: EventCommands::Add { title, date } } => event::add_command(title, date, logger),
+            Event { command: EventCommands::Remove { title } } => event::remove_command(title, logger),
+            Event { command: EventCommands::List { all } } => event::list_command(all, logger),
+            Sort { list, reverse, full } => sort::sort_command(list, reverse, full, logger),
+            Export { strict, tags, path, dest, force_unlock, split_collections, single_file, format, theme, template, incremental, include_archived, layout, prune, links, access, tag_pages, events, verify } => {
+                let dest = match (&path, dest.or_else(|| config::Config::get().default_dest.clone())) {
+                    (_, Some(dest)) => destination::Destination::get(&dest, logger.hollow()).clone(),
+                    (Some(_), None) => destination::Destination::default(),
+                    (None, None) => {
+                        let mut logger = logger.hollow();
+                        log!((logger.error) Export("Give export a path or `--dest`, or set a `default_dest` in config.toml") as Fatal);
+                        return logger.crash();
+                    },
+                };
+                if verify { return manifest::verify(std::path::Path::new(&path.unwrap_or(dest.path)), logger) }
+                let templates = template.or(dest.template).map(|x| template::Templates::load(std::path::Path::new(&x), logger.hollow())).unwrap_or_default();
+                let options = export::ExportOptions {
+                    format: format.or(dest.format).or(config::Config::get().default_format).unwrap_or_default(),
+                    theme: theme.or(dest.theme),
+                    force_unlock: force_unlock || dest.force_unlock,
+                    split_collections: split_collections || dest.split_collections,
+                    single_file: single_file || dest.single_file,
+                    templates,
+                    incremental: incremental || dest.incremental,
+                    include_archived: include_archived || dest.include_archived,
+                    layout: layout.or(dest.layout).unwrap_or_default(),
+                    prune: prune || dest.prune,
+                    links: links.or(dest.links).unwrap_or_default(),
+                    access: access.or(dest.access),
+                    tag_pages: tag_pages || dest.tag_pages,
+                    events: events || dest.events,
+                };
+                let tags = tags.or(dest.tags);
+                export::export_md(strict || dest.strict, tags, path.unwrap_or(dest.path), options, logger.hollow())
+            },
+            Import { format: ImportFormat::Json, file } => dump::import_json(file, logger),
+            Validate { file_paths, base_dir } => {
+                set_base_dir(base_dir, logger.hollow());
+                validate::validate_paths(file_paths, logger)
+            },
+            About { is_moc, uid, force_unlock, provenance } => about::about(is_moc, uid, force_unlock, provenance, logger),
+            ArchiveItem { is_moc, uid, undo } => archived::archive_item(uid, is_moc, undo, logger),
+            Remove { is_moc, uid, purge } => uncommit::uncommmit(uid, is_moc, purge, logger),
+            Trash { command: TrashCommands::List } => trash::list_command(logger),
+            Trash { command: TrashCommands::Restore { is_moc, uid } } => trash::restore(uid, is_moc, logger),
+            Trash { command: TrashCommands::Prune } => trash::prune_command(logger),
+            Agent { command: AgentCommands::Run } => agent::run_command(logger),
+            Agent { command: AgentCommands::Status } => agent::status_command(logger),
+            Agent { command: AgentCommands::Stop } => agent::stop_command(logger),
+            Completions { shell } => completions::completions(shell),
+            CompleteUids => completions::complete_uids(logger),
+            CompleteTags => completions::complete_tags(logger),
+            Moc { command: MocCommands::Generate { tag, by, uid, title, print, overwrite } } => {
+                unsafe { OVERWRITE = overwrite };
+                moc::generate::generate_command(tag, by, uid, title, print, logger)
             },
-            Since { date, today: _ } => since::since_2023(date, logger),
-            Pull { is_moc, one_file, uid, path, file_name } => pull::pull(std::path::PathBuf::from(path), file_name, is_moc, uid, one_file, logger),
-            List { strict, tags, show_entries, show_mocs } => search::list_command(strict, show_mocs, show_entries, tags, logger),
-            Sort => sort::sort(logger),
-            Export { strict, tags, path } => export::export_md(strict, tags, path, logger.hollow()),
-            About { is_moc, uid } => about::about(is_moc, uid, logger),
-            Remove { is_moc, uid } => uncommit::uncommmit(uid, is_moc, logger),
         }
     }
 }
 
+/// Sets the `--base-dir` relative section paths and attachments are resolved from
+fn set_base_dir(base_dir: Option<String>, mut logger: impl Logger) {
+    let Some(base_dir) = base_dir else { return };
+    if !std::path::Path::new(&base_dir).is_dir() {
+        log!((logger.error) Commit("Base directory '{base_dir}' doesn't exist") as Fatal);
+        return logger.crash();
+    } let _ = BASE_DIR.set(base_dir.into());
+}
+
 pub fn run() {
-    let args = Cli::parse();
-    unsafe { VERBOSE = args.verbose };
-    args.command.execute();
+    let command = Cli::command();
+    let builtins: Vec<String> = command.get_subcommands()
+        .flat_map(|x| std::iter::once(x.get_name()).chain(x.get_all_aliases()))
+        .chain(["help"])
+        .map(String::from)
+        .collect();
+    signal::install();
+    let args = alias::expand(std::env::args().collect(), &config::Config::get().aliases, &builtins);
+    let args = Cli::parse_from(args);
+    unsafe {
+        VERBOSE = args.verbose;
+        JSON = args.json;
+        PRIVATE = args.private || config::Config::get().private;
+        WAIT = args.wait;
+        PLAIN = args.plain || config::Config::get().plain || match config::Config::get().colour {
+            Some(colour) => !colour,
+            None => std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty())
+                || std::env::var("TERM").is_ok_and(|x| x == "dumb"),
+        };
+    }
+    match args.command {
+        Some(command) => command.execute(),
+        None if onboard::is_first_run() => onboard::wizard(DynamicLogger::new()),
+        None => { let _ = Cli::command().print_help(); },
+    }
+    lock::release();
 }
\ No newline at end of file
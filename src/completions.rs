@@ -0,0 +1,154 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+use soulog::*;
+use crate::{archive::Archive, cli::Cli, home_dir, tags::tag_usage};
+
+/// Where the arguments that take uids and tags are, so completions can fill them in from the archive
+#[derive(Default)]
+pub struct Targets {
+    /// The (sub)commands whose positional argument is the uid of an entry or moc, like `about` or `read`
+    pub uids: Vec<String>,
+    /// The (sub)commands whose positional arguments are tags, like `tag/rename`
+    pub tags: Vec<String>,
+    /// The options that take tags: the top-level command, the subcommand they are on, and their short and long flags
+    pub tag_options: Vec<(String, String, Option<char>, Option<String>)>,
+}
+
+impl Targets {
+    /// Finds the arguments of the cli that take uids (named `uid`) and tags (named `tags`, `tag`, `old` or `into`)
+    pub fn find(command: &clap::Command) -> Self {
+        let mut targets = Self::default();
+        for top in command.get_subcommands().filter(|x| !x.is_hide_set()) {
+            // The trash restores the uids of removed items, which aren't in the archive to complete from
+            if top.get_name() == "trash" { continue }
+            targets.walk(top, top.get_name(), top.get_name().to_string());
+        } targets
+    }
+
+    fn walk(&mut self, command: &clap::Command, top: &str, path: String) {
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            match (arg.is_positional(), id) {
+                (true, "uid") => self.uids.push(path.clone()),
+                (true, "tags" | "old") => self.tags.push(path.clone()),
+                (false, "tags" | "tag" | "into") => self.tag_options.push((top.to_string(), command.get_name().to_string(), arg.get_short(), arg.get_long().map(String::from))),
+                _ => (),
+            }
+        }
+        for sub in command.get_subcommands() {
+            self.walk(sub, top, format!("{path}/{}", sub.get_name()));
+        }
+    }
+
+    /// The flags of the tag options as `command:flag` pairs, like `list:-f list:--filter`
+    fn option_pairs(&self) -> String {
+        self.tag_options.iter()
+            .flat_map(|(top, _, short, long)| [short.map(|x| format!("{top}:-{x}")), long.as_ref().map(|x| format!("{top}:--{x}"))])
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Completes the uids and tags of the archive in bash, on top of clap's completion of everything else
+const BASH: &str = r#"
+_diary-cli_archive() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" sub="${COMP_WORDS[1]}" nested="${COMP_WORDS[1]}/${COMP_WORDS[2]}" option="" i
+    for (( i = COMP_CWORD - 1; i > 1; i-- )); do
+        if [[ "${COMP_WORDS[i]}" == -* ]]; then option="${COMP_WORDS[i]}"; break; fi
+    done
+    local positional=0
+    [[ "$cur" != -* ]] && (( COMP_CWORD > 1 )) && positional=1
+    if [[ " @OPTIONS@ " == *" $sub:$option "* ]] && [[ "$cur" != -* ]]; then
+        COMPREPLY=( $(compgen -W "$(diary-cli __complete-tags 2>/dev/null)" -- "$cur") )
+    elif (( positional )) && [[ " @TAGS@ " == *" $sub "* || ( " @TAGS@ " == *" $nested "* && COMP_CWORD -gt 2 ) ]]; then
+        COMPREPLY=( $(compgen -W "$(diary-cli __complete-tags 2>/dev/null)" -- "$cur") )
+    elif (( positional )) && [[ " @UIDS@ " == *" $sub "* || ( " @UIDS@ " == *" $nested "* && COMP_CWORD -gt 2 ) ]]; then
+        COMPREPLY=( $(compgen -W "$(diary-cli __complete-uids 2>/dev/null)" -- "$cur") )
+    else
+        @COMPLETE@ "$@"
+    fi
+}
+
+complete -F _diary-cli_archive -o bashdefault -o default diary-cli
+"#;
+
+/// Completes the uids and tags of the archive in zsh, on top of clap's completion of everything else
+const ZSH: &str = r#"
+_diary-cli_archive() {
+    local cur=${words[CURRENT]} sub=${words[2]} nested="${words[2]}/${words[3]}" option=${${(M)words[3,CURRENT-1]:#-*}[-1]}
+    if [[ " @OPTIONS@ " == *" $sub:$option "* && $cur != -* ]]; then
+        compadd -- ${(f)"$(diary-cli __complete-tags 2>/dev/null)"}
+    elif [[ $cur != -* ]] && (( CURRENT > 2 )) && [[ " @TAGS@ " == *" $sub "* || ( " @TAGS@ " == *" $nested "* && CURRENT -gt 3 ) ]]; then
+        compadd -- ${(f)"$(diary-cli __complete-tags 2>/dev/null)"}
+    elif [[ $cur != -* ]] && (( CURRENT > 2 )) && [[ " @UIDS@ " == *" $sub "* || ( " @UIDS@ " == *" $nested "* && CURRENT -gt 3 ) ]]; then
+        compadd -- ${(f)"$(diary-cli __complete-uids 2>/dev/null)"}
+    else
+        _diary-cli "$@"
+    fi
+}
+
+compdef _diary-cli_archive diary-cli
+"#;
+
+/// The fish completions of the uids and tags of the archive, which fish adds to clap's
+fn fish(targets: &Targets) -> String {
+    let leaf = |path: &String| path.rsplit('/').next().unwrap_or_default().to_string();
+    let mut script = String::from("\n");
+    for (path, helper) in targets.uids.iter().map(|x| (x, "uids")).chain(targets.tags.iter().map(|x| (x, "tags"))) {
+        script.push_str(&format!("complete -c diary-cli -n '__fish_seen_subcommand_from {}' -f -a '(diary-cli __complete-{helper} 2>/dev/null)'\n", leaf(path)));
+    }
+    for (_, command, short, long) in targets.tag_options.iter() {
+        let short = short.map(|x| format!(" -s {x}")).unwrap_or_default();
+        let long = long.as_ref().map(|x| format!(" -l {x}")).unwrap_or_default();
+        script.push_str(&format!("complete -c diary-cli -n '__fish_seen_subcommand_from {command}'{short}{long} -x -a '(diary-cli __complete-tags 2>/dev/null)'\n"));
+    } script
+}
+
+/// Prints the completion script of a shell; bash, zsh and fish also complete uids and tags from the archive
+pub fn completions(shell: Shell) {
+    let mut command = Cli::command();
+    let targets = Targets::find(&command);
+    // clap_complete names the bash functions of hyphenated binaries differently from how it calls them, so the bash
+    // script is generated for `diarycli` and registered for `diary-cli` afterwards
+    let name = if shell == Shell::Bash { "diarycli" } else { "diary-cli" };
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    let mut script: String = String::from_utf8_lossy(&script).lines()
+        .map(|x| if x.trim_start().starts_with("complete ") { x.replace(" diarycli", " diary-cli") } else { x.to_string() } + "\n")
+        .collect();
+
+    // clap's own completion function, which the archive's completions fall back on
+    let complete = script.split("complete -F ").nth(1).and_then(|x| x.split_whitespace().next()).unwrap_or("_diarycli").to_string();
+    let fill = |x: &str| x.replace("@COMPLETE@", &complete)
+        .replace("@OPTIONS@", &targets.option_pairs())
+        .replace("@TAGS@", &targets.tags.join(" "))
+        .replace("@UIDS@", &targets.uids.join(" "));
+    match shell {
+        Shell::Bash => script.push_str(&fill(BASH)),
+        Shell::Zsh => script.push_str(&fill(ZSH)),
+        Shell::Fish => script.push_str(&fish(&targets)),
+        _ => (),
+    }
+    print!("{script}");
+}
+
+/// Loads the archive for completions without locking it or making one if there isn't one, so completing never blocks
+/// on or changes anything
+fn completion_archive(logger: impl Logger) -> Option<Archive> {
+    let path = home_dir().join("archive");
+    path.is_dir().then(|| Archive::load_dir(path, logger))
+}
+
+/// Prints the uid of every entry and moc, one per line (`__complete-uids`)
+pub fn complete_uids(logger: impl Logger) {
+    let Some(archive) = completion_archive(logger.hollow()) else { return };
+    archive.list_entries(logger.hollow()).iter().for_each(|x| println!("{}", x.uid));
+    archive.list_mocs(logger.hollow()).iter().for_each(|x| println!("{}", x.uid));
+}
+
+/// Prints every tag, most used first, one per line (`__complete-tags`)
+pub fn complete_tags(logger: impl Logger) {
+    let Some(archive) = completion_archive(logger.hollow()) else { return };
+    tag_usage(&archive, logger).iter().for_each(|x| println!("{}", x.tag));
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use soulog::*;
+use toml::Table;
+use crate::{error::{self, Context}, access::AccessRules, agent::AgentConfig, date::Span, destination::{expand_home, Destination}, export::ExportFormat, frontmatter::Frontmatter, home_dir, limits::Limits, locale::Locale, nudge::Nudges, suggest::Suggestions, trash::Retention, transform::Transform, unwrap_opt, webhook::Webhooks, DynamicLogger};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// User configuration loaded from `config.toml` in the cli's home, on top of the defaults in the user's config file
+/// (`~/.config/diary-cli/config.toml`)
+#[derive(Default)]
+pub struct Config {
+    pub author: Option<String>,
+    pub device: Option<String>,
+    /// Redacts content and paths from logs
+    pub private: bool,
+    /// Plain output without colours for screen readers
+    pub plain: bool,
+    /// Tags injected into the frontmatter of every exported file
+    pub export_tags: Option<Vec<String>>,
+    /// Archive tags renamed at export time (`from`, `into`)
+    pub export_tag_rename: Vec<(String, String)>,
+    /// The export templates of the entries with some tags (`tag`, `template`), for kinds of entries like letters
+    pub export_templates: Vec<(String, String)>,
+    /// User-defined shortcuts for commands (`exp = "export ~/vault -t public"`)
+    pub aliases: HashMap<String, String>,
+    /// The editor entries are written in; defaults to `$EDITOR`
+    pub editor: Option<String>,
+    /// Regex replacements applied to section content at export time, in order
+    pub export_transforms: Vec<Transform>,
+    /// Named export destinations (`export --dest <name>`)
+    pub export_dests: HashMap<String, Destination>,
+    /// Date format, aliases and extra keys of the frontmatter of exported files
+    pub export_frontmatter: Frontmatter,
+    /// Which entries and mocs can be published to whom by their tags (`[access]`)
+    pub access: AccessRules,
+    /// Where mutations of the archive are posted to (`[webhooks]`)
+    pub webhooks: Webhooks,
+    /// The sizes commits warn about the archive and what is committed into it growing over (`[size_limits]`)
+    pub size_limits: Limits,
+    /// How long and how much of what is removed the trash keeps (`[trash]`)
+    pub trash: Retention,
+    /// What the background agent does (`[agent]`)
+    pub agent: AgentConfig,
+    /// The tags suggested when committing an entry (`[tag_suggestions]`)
+    pub tag_suggestions: Suggestions,
+    /// The habits committed entries are gently warned about not sticking to (`[nudges]`)
+    pub nudges: Nudges,
+    /// The day weeks start on and the names of months and days in the calendar and weekly streaks (`[calendar]`)
+    pub calendar: Locale,
+    /// Puts committed entries straight into their place in the sorted order instead of leaving them for `diary-cli sort`
+    pub sort_on_commit: bool,
+    /// How many entries long-running sessions keep read in memory; defaults to 256
+    pub entry_cache: Option<usize>,
+    /// The destination `export` exports to when it isn't given a path or `--dest`
+    pub default_dest: Option<String>,
+    /// The format `export` exports as when neither `--format` nor its destination set one
+    pub default_format: Option<ExportFormat>,
+    /// Tags added to every committed entry
+    pub default_tags: Vec<String>,
+    /// Forces colours on (`always`) or off (`never`) instead of following `NO_COLOR` and the terminal (`auto`)
+    pub colour: Option<bool>,
+    /// How many of the backups made before modifying the archive are kept; defaults to 1
+    pub keep_backups: Option<usize>,
+    /// How far back `list` goes without `--all`, like `1y`; every entry is listed if it isn't set
+    pub default_list_horizon: Option<Span>,
+}
+
+// Some ease of life macros
+macro_rules! get {
+    ($key:literal from $table:ident as $func:ident with $logger:ident) => {
+        $table.get($key)
+            .map(|x| unwrap_opt!((x.$func()) with $logger, format: Config("Config's '{}' attribute must be of the correct type", $key)))
+    };
+}
+
+impl Config {
+    /// Gets the loaded config, loading it on first use
+    pub fn get() -> &'static Self {
+        CONFIG.get_or_init(|| Self::load(DynamicLogger::new()))
+    }
+
+    /// Loads the user's config file and then the archive's, whose keys replace the user's, returning the default config
+    /// if there are neither
+    pub fn load(logger: impl Logger) -> Self {
+        let read = |path: Option<PathBuf>| path.map(|x| Self::read(&x, logger.hollow())).transpose()
+            .unwrap_or_else(|x| x.crash(logger.hollow()))
+            .flatten()
+            .unwrap_or_default();
+        let user = read(user_config_path());
+        let archive = read(Some(home_dir().join("config.toml")));
+        Self::parse(&merge(user, archive), logger)
+    }
+
+    /// Reads a config file, if it exists
+    pub fn read(path: &std::path::Path, mut logger: impl Logger) -> error::Result<Option<Table>> {
+        if !path.is_file() { return Ok(None) }
+
+        let path = path.to_string_lossy();
+        log!((logger) Config("Loading config file '{path}'..."));
+        let contents = std::fs::read_to_string(path.as_ref()).context("config-read", "Config", || format!("Couldn't read config file '{path}'"))?;
+        contents.parse::<Table>().map(Some).context("config-parse", "Config", || format!("Config file '{path}' isn't valid toml"))
+    }
+
+    /// Parses the table of a config file
+    pub fn parse(table: &Table, mut logger: impl Logger) -> Self {
+        Self {
+            author: get!("author" from table as as_str with logger).map(String::from),
+            device: get!("device" from table as as_str with logger).map(String::from),
+            private: get!("private" from table as as_bool with logger).unwrap_or(false),
+            plain: get!("plain" from table as as_bool with logger).unwrap_or(false),
+            export_tags: get!("export_tags" from table as as_array with logger)
+                .map(|x| x.iter().map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's 'export_tags' must only contain strings")).to_string()).collect()),
+            export_tag_rename: get!("export_tag_rename" from table as as_table with logger)
+                .map(|x| x.iter().map(|(k, v)| (k.clone(), unwrap_opt!((v.as_str()) with logger, format: Config("Config's 'export_tag_rename' must only map tags to strings")).to_string())).collect())
+                .unwrap_or_default(),
+            export_templates: get!("export_templates" from table as as_table with logger)
+                .map(|x| x.iter().map(|(k, v)| (k.clone(), unwrap_opt!((v.as_str()) with logger, format: Config("Config's 'export_templates' must only map tags to template names")).to_string())).collect())
+                .unwrap_or_default(),
+            editor: get!("editor" from table as as_str with logger).map(String::from),
+            aliases: get!("aliases" from table as as_table with logger)
+                .map(|x| x.iter().map(|(k, v)| (k.clone(), unwrap_opt!((v.as_str()) with logger, format: Config("Config's 'aliases' must only map aliases to strings")).to_string())).collect())
+                .unwrap_or_default(),
+            export_transforms: get!("export_transform" from table as as_array with logger)
+                .map(|x| x.iter().enumerate().map(|(i, x)| {
+                    let table = unwrap_opt!((x.as_table()) with logger, format: Config("Config's export_transform {i} must be a table"));
+                    Transform::parse(table, i, logger.hollow())
+                }).collect())
+                .unwrap_or_default(),
+            export_dests: get!("export_dest" from table as as_table with logger)
+                .map(|x| x.iter().map(|(k, v)| {
+                    let table = unwrap_opt!((v.as_table()) with logger, format: Config("Config's export_dest '{k}' must be a table"));
+                    (k.clone(), Destination::parse(k, table, logger.hollow()))
+                }).collect())
+                .unwrap_or_default(),
+            export_frontmatter: get!("export_frontmatter" from table as as_table with logger)
+                .map(|x| Frontmatter::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            access: get!("access" from table as as_table with logger)
+                .map(|x| AccessRules::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            webhooks: get!("webhooks" from table as as_table with logger)
+                .map(|x| Webhooks::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            size_limits: get!("size_limits" from table as as_table with logger)
+                .map(|x| Limits::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            trash: get!("trash" from table as as_table with logger)
+                .map(|x| Retention::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            agent: get!("agent" from table as as_table with logger)
+                .map(|x| AgentConfig::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            tag_suggestions: get!("tag_suggestions" from table as as_table with logger)
+                .map(|x| Suggestions::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            nudges: get!("nudges" from table as as_table with logger)
+                .map(|x| Nudges::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            calendar: get!("calendar" from table as as_table with logger)
+                .map(|x| Locale::parse(x, logger.hollow()))
+                .unwrap_or_default(),
+            sort_on_commit: get!("sort_on_commit" from table as as_bool with logger).unwrap_or(false),
+            entry_cache: get!("entry_cache" from table as as_integer with logger)
+                .map(|x| unwrap_opt!((Some(x).filter(|x| *x > 0)) with logger, format: Config("Config's 'entry_cache' must be a positive number")) as usize),
+            default_dest: get!("default_dest" from table as as_str with logger).map(String::from),
+            default_format: get!("default_format" from table as as_str with logger)
+                .map(|x| unwrap_opt!((<ExportFormat as clap::ValueEnum>::from_str(x, true).ok()) with logger, format: Config("Config's 'default_format' '{x}' isn't an export format"))),
+            default_tags: get!("default_tags" from table as as_array with logger)
+                .map(|x| x.iter().map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's 'default_tags' must only contain strings")).to_string()).collect())
+                .unwrap_or_default(),
+            colour: get!("colour" from table as as_str with logger)
+                .map(|x| unwrap_opt!((match x { "always" => Some(Some(true)), "never" => Some(Some(false)), "auto" => Some(None), _ => None }) with logger, format: Config("Config's 'colour' must be \"auto\", \"always\" or \"never\"")))
+                .flatten(),
+            keep_backups: get!("keep_backups" from table as as_integer with logger)
+                .map(|x| unwrap_opt!((Some(x).filter(|x| *x > 0)) with logger, format: Config("Config's 'keep_backups' must be a positive number")) as usize),
+            default_list_horizon: get!("default_list_horizon" from table as as_str with logger)
+                .map(|x| unwrap_opt!((Span::parse(x)) with logger, format: Config("Config's 'default_list_horizon' '{x}' must be a span of time like \"30d\", \"2w\", \"6m\" or \"1y\""))),
+        }
+    }
+
+    /// How many of the backups made before modifying the archive are kept
+    pub fn keep_backups(&self) -> usize {
+        self.keep_backups.unwrap_or(1)
+    }
+
+    /// The export template of the first of `export_templates`' tags an entry has
+    pub fn export_template(&self, tags: &[String]) -> Option<&String> {
+        self.export_templates.iter()
+            .find(|(tag, _)| tags.iter().any(|x| crate::search::tag_matches(x, tag)))
+            .map(|(_, template)| template)
+    }
+
+    /// How many entries long-running sessions keep read in memory
+    pub fn entry_cache(&self) -> usize {
+        self.entry_cache.unwrap_or(256)
+    }
+
+    /// The tags injected into exported frontmatter; defaults to `obsidian-md` and `diary-cli`
+    pub fn export_tags(&self) -> Vec<String> {
+        self.export_tags.clone()
+            .unwrap_or_else(|| vec![String::from("obsidian-md"), String::from("diary-cli")])
+    }
+
+    /// The author to sign commits with; defaults to the current user
+    pub fn author(&self) -> String {
+        self.author.clone()
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| String::from("unknown"))
+    }
+
+    /// The device to sign commits with; defaults to the hostname
+    pub fn device(&self) -> String {
+        self.device.clone()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok().map(|x| x.trim().to_string()))
+            .filter(|x| !x.is_empty())
+            .unwrap_or_else(|| String::from("unknown"))
+    }
+}
+
+/// The user's config file, `$XDG_CONFIG_HOME/diary-cli/config.toml` (`~/.config/diary-cli/config.toml` by default),
+/// whose settings are the defaults of every archive
+pub fn user_config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME").filter(|x| !x.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+        .map(|x| x.join("diary-cli").join("config.toml"))
+}
+
+/// The cli's home (where the archive and its config live) set by `home` in the user's config file, if it sets one
+pub fn configured_home() -> Option<PathBuf> {
+    static HOME: OnceLock<Option<PathBuf>> = OnceLock::new();
+    HOME.get_or_init(|| {
+        // Read on its own as loading the config needs the home; a broken file still crashes once the config is loaded
+        let table = std::fs::read_to_string(user_config_path()?).ok()?.parse::<Table>().ok()?;
+        table.get("home")?.as_str().map(|x| PathBuf::from(expand_home(x)))
+    }).clone()
+}
+
+/// Merges an archive's config over the user's, its keys replacing theirs
+pub fn merge(mut user: Table, archive: Table) -> Table {
+    user.extend(archive);
+    user
+}
@@ -0,0 +1,131 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use serde_json::json;
+use soulog::*;
+use crate::json;
+
+/// Parses a date like `2024-02-29`, or `today`, `yesterday` and `tomorrow`
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    let today = Local::now().date_naive();
+    match date.trim().to_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        "tomorrow" => today.succ_opt(),
+        date => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+    }
+}
+
+/// The number of days from one date to another (negative if `to` is before `from`)
+pub fn days_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    to.signed_duration_since(from).num_days()
+}
+
+/// The date some days before another (or after it, if `days` is negative)
+pub fn days_before(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    match days >= 0 {
+        true => date.checked_sub_days(Days::new(days as u64)),
+        false => date.checked_add_days(Days::new(days.unsigned_abs())),
+    }
+}
+
+/// The ISO 8601 week of a date, like `2024-W01` (its year may differ from the date's around new year)
+pub fn iso_week(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// A span of time like `30d`, `2w`, `6m` or `1y`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Span {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl Span {
+    pub fn parse(span: &str) -> Option<Self> {
+        let span = span.trim();
+        let unit = span.chars().last()?;
+        let count = span[..span.len() - unit.len_utf8()].parse().ok()?;
+        match unit.to_ascii_lowercase() {
+            'd' => Some(Self::Days(count)),
+            'w' => Some(Self::Weeks(count)),
+            'm' => Some(Self::Months(count)),
+            'y' => Some(Self::Years(count)),
+            _ => None,
+        }
+    }
+
+    /// The date the span ends on if it starts from a date and goes back in time
+    pub fn before(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Days(x) => date.checked_sub_days(Days::new(x as u64)),
+            Self::Weeks(x) => date.checked_sub_days(Days::new(x as u64 * 7)),
+            Self::Months(x) => date.checked_sub_months(Months::new(x)),
+            Self::Years(x) => date.checked_sub_months(Months::new(x.saturating_mul(12))),
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Days(x) => write!(f, "{x}d"),
+            Self::Weeks(x) => write!(f, "{x}w"),
+            Self::Months(x) => write!(f, "{x}m"),
+            Self::Years(x) => write!(f, "{x}y"),
+        }
+    }
+}
+
+/// Parses a date argument, crashing on ones that aren't dates
+fn date_arg(date: &str, mut logger: impl Logger) -> NaiveDate {
+    match parse_date(date) {
+        Some(x) => x,
+        None => {
+            log!((logger.error) Date("'{date}' isn't a date like `2024-02-29` (or `today`, `yesterday` or `tomorrow`)") as Fatal);
+            logger.crash()
+        },
+    }
+}
+
+/// Prints the days between two dates (the second defaults to today)
+pub fn between(from: String, to: Option<String>, mut logger: impl Logger) {
+    let from = date_arg(&from, logger.hollow());
+    let to = date_arg(to.as_deref().unwrap_or("today"), logger.hollow());
+    let days = days_between(from, to);
+
+    if json::enabled() { return json::print(json!({ "from": from.to_string(), "to": to.to_string(), "days": days })) }
+    log!((logger.vital) Date("{}{days}", colour_format![green("Days from "), cyan(&from.to_string()), green(" to "), cyan(&to.to_string()), blue(": ")]) as Log);
+}
+
+/// Prints the day of the week of a date
+pub fn weekday(date: String, mut logger: impl Logger) {
+    let date = date_arg(&date, logger.hollow());
+    let day = crate::config::Config::get().calendar.days[date.weekday().num_days_from_monday() as usize].clone();
+
+    if json::enabled() { return json::print(json!({ "date": date.to_string(), "weekday": day })) }
+    log!((logger.vital) Date("{}{day}", colour_format![cyan(&date.to_string()), blue(": ")]) as Log);
+}
+
+/// Prints the date some days before a date (today by default)
+pub fn ago(days: i64, from: Option<String>, mut logger: impl Logger) {
+    let from = date_arg(from.as_deref().unwrap_or("today"), logger.hollow());
+    let Some(date) = days_before(from, days) else {
+        log!((logger.error) Date("{days} days from {from} is out of range") as Fatal);
+        return logger.crash();
+    };
+
+    if json::enabled() { return json::print(json!({ "from": from.to_string(), "days": days, "date": date.to_string() })) }
+    let direction = if days < 0 { " days after " } else { " days before " };
+    log!((logger.vital) Date("{}{date}", colour_format![cyan(&days.unsigned_abs().to_string()), green(direction), cyan(&from.to_string()), blue(": ")]) as Log);
+}
+
+/// Prints the ISO week of a date (today by default)
+pub fn week(date: Option<String>, mut logger: impl Logger) {
+    let date = date_arg(date.as_deref().unwrap_or("today"), logger.hollow());
+    let week = iso_week(date);
+
+    if json::enabled() { return json::print(json!({ "date": date.to_string(), "week": week })) }
+    log!((logger.vital) Date("{}{week}", colour_format![green("ISO week of "), cyan(&date.to_string()), blue(": ")]) as Log);
+}
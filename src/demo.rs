@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use chrono::{Datelike, Duration, NaiveDate};
+use soulog::*;
+use crate::{archive::Archive, home_dir, sort};
+
+const YEARS: i64 = 3;
+const TAGS: &[&str] = &["work/meetings", "work/projects", "health/running", "health/sleep", "family", "friends", "travel", "reading", "ideas", "gratitude"];
+const TITLES: &[&str] = &["A quiet day", "Busy but good", "Long walk", "Rainy afternoon", "Catching up", "Something new", "Slow morning", "Late night thoughts", "Back on track", "Weekend plans"];
+const WORDS: &[&str] = &[
+    "today", "I", "finally", "went", "to", "the", "park", "and", "thought", "about", "work", "friends", "a", "book",
+    "we", "talked", "for", "hours", "after", "dinner", "it", "was", "quiet", "felt", "good", "tired", "but", "happy",
+    "started", "planning", "next", "week", "ran", "five", "kilometres", "slept", "badly", "coffee", "with", "family",
+];
+
+/// A tiny splitmix64 generator so demo archives can be reproduced from a seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut x = self.0;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^ (x >> 31)
+    }
+
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+fn sentence(rng: &mut Rng) -> String {
+    let words: Vec<&str> = (0..6 + rng.below(10)).map(|_| rng.pick(WORDS)).collect();
+    let sentence = words.join(" ");
+    let mut chars = sentence.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}.", first.to_uppercase(), chars.as_str()),
+        None => sentence,
+    }
+}
+
+/// Generates the toml config of a random entry written on `date`
+pub fn demo_entry(uid: &str, date: NaiveDate, seed: u64) -> String {
+    let mut rng = Rng(seed);
+    let mut tags: Vec<&str> = (0..1 + rng.below(3)).map(|_| rng.pick(TAGS)).collect();
+    tags.dedup();
+    let sections: String = (0..1 + rng.below(3)).map(|i| {
+        let contents: Vec<String> = (0..2 + rng.below(5)).map(|_| sentence(&mut rng)).collect();
+        format!("\n[[section]]\ntitle = \"Part {}\"\ncontents = \"\"\"\n{}\n\"\"\"\n", i + 1, contents.join(" "))
+    }).collect();
+
+    format!(
+        "[entry]\nuid = \"{uid}\"\ntitle = \"{}\"\ndescription = \"{}\"\ntags = {tags:?}\nnotes = [\"{}\"]\ndate = {}\n{sections}",
+        rng.pick(TITLES), sentence(&mut rng), sentence(&mut rng), date.format("%Y-%m-%d"),
+    )
+}
+
+/// Generates the toml config of a moc with a collection for each nested tag of `tag`
+fn demo_moc(tag: &str) -> String {
+    let collections: String = TAGS.iter()
+        .filter(|x| x.split('/').next() == Some(tag))
+        .map(|x| format!("\n[[collection]]\ntitle = \"{x}\"\ninclude = [\"{x}\"]\n"))
+        .collect();
+    format!("is-moc = true\n[moc]\nuid = \"moc-{tag}\"\ntitle = \"All about {tag}\"\ndescription = \"Every entry tagged {tag}\"\ntags = [\"moc\"]\n{collections}")
+}
+
+/// Populates a new archive with generated entries and mocs spanning the last few years
+pub fn demo(entries: usize, path: Option<PathBuf>, seed: Option<u64>, mut logger: impl Logger) {
+    let home = path.unwrap_or_else(|| std::env::temp_dir().join(format!("diary-cli-demo-{}", std::process::id())));
+    if home.join("archive").exists() {
+        log!((logger.error) Demo("An archive already exists at '{}'; demo archives are only generated into new locations", home.to_string_lossy()) as Fatal);
+        return logger.crash();
+    }
+    std::env::set_var("DIARY_CLI_HOME", &home);
+
+    let seed = seed.unwrap_or_else(|| std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0));
+    let mut rng = Rng(seed);
+    let archive = Archive::init(logger.hollow());
+
+    let source = home_dir().join("demo");
+    if_err!((logger) [Demo, err => ("While creating demo source directory: {err:?}")] retry std::fs::create_dir_all(&source));
+
+    // Spread the entries over the last few years, at most one per day
+    let today = chrono::Local::now().date_naive();
+    let days = (YEARS * 365) as usize;
+    let mut offsets: Vec<usize> = (0..entries.min(days)).map(|_| rng.below(days)).collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    log!((logger.vital) Demo("Generating {} entries into '{}'...", offsets.len(), home.to_string_lossy()) as Log);
+    for offset in offsets {
+        let date = today - Duration::days(offset as i64);
+        let uid = format!("{}-{:02}-{:02}", date.year(), date.month(), date.day());
+        let file = source.join(format!("{uid}.toml"));
+        if_err!((logger) [Demo, err => ("While writing demo entry: {err:?}")] retry std::fs::write(&file, demo_entry(&uid, date, rng.next())));
+        archive.commit(&file, logger.hollow());
+    }
+
+    let mut mocs: Vec<&str> = TAGS.iter().filter_map(|x| x.split('/').next()).collect();
+    mocs.dedup();
+    for tag in mocs {
+        let file = source.join(format!("moc-{tag}.toml"));
+        if_err!((logger) [Demo, err => ("While writing demo moc: {err:?}")] retry std::fs::write(&file, demo_moc(tag)));
+        archive.commit(&file, logger.hollow());
+    }
+
+    sort::sort(logger.hollow());
+    let _ = std::fs::remove_dir_all(&source); // cleanup
+    log!((logger.vital) Demo("Generated a demo archive; try it out with `DIARY_CLI_HOME=\"{}\" diary-cli list`", home.to_string_lossy()) as Log);
+}
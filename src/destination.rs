@@ -0,0 +1,95 @@
+use clap::ValueEnum;
+use soulog::*;
+use crate::{access::Access, config::Config, export::{ExportFormat, ExportLayout}, links::LinkStyle, unwrap_opt};
+
+/// A named export destination from the config with the options it is exported with (`export --dest <name>`)
+#[derive(Clone, Default)]
+pub struct Destination {
+    pub path: String,
+    pub format: Option<ExportFormat>,
+    pub layout: Option<ExportLayout>,
+    pub links: Option<LinkStyle>,
+    /// Filters out entries and mocs that don't have these tags
+    pub tags: Option<Vec<String>>,
+    pub strict: bool,
+    pub theme: Option<String>,
+    pub template: Option<String>,
+    pub force_unlock: bool,
+    pub split_collections: bool,
+    pub single_file: bool,
+    pub incremental: bool,
+    pub include_archived: bool,
+    pub prune: bool,
+    pub access: Option<Access>,
+    pub tag_pages: bool,
+    pub events: bool,
+}
+
+// Some ease of life macros
+macro_rules! get {
+    ($key:literal of $name:ident from $table:ident as $func:ident with $logger:ident) => {
+        $table.get($key)
+            .map(|x| unwrap_opt!((x.$func()) with $logger, format: Config("Config's export_dest '{}' '{}' attribute must be of the correct type", $name, $key)))
+    };
+}
+
+/// Parses a value of a clap enum (like `format = "html"`) from the config
+fn parse_enum<T: ValueEnum>(name: &str, key: &str, value: Option<&str>, mut logger: impl Logger) -> Option<T> {
+    value.map(|x| match T::from_str(x, true) {
+        Ok(x) => x,
+        Err(_) => {
+            let options: Vec<String> = T::value_variants().iter().filter_map(|x| x.to_possible_value()).map(|x| x.get_name().to_string()).collect();
+            log!((logger.error) Config("Config's export_dest '{name}' '{key}' attribute must be one of {options:?}") as Fatal);
+            logger.crash()
+        },
+    })
+}
+
+/// Expands a leading `~` to the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => format!("{home}{rest}"),
+        _ => path.to_string(),
+    }
+}
+
+impl Destination {
+    /// Parses an `[export_dest.<name>]` table of the config
+    pub fn parse(name: &str, table: &toml::Table, mut logger: impl Logger) -> Self {
+        let path = unwrap_opt!((get!("path" of name from table as as_str with logger)) with logger, format: Config("Config's export_dest '{name}' must have a 'path' attribute"));
+
+        Self {
+            path: expand_home(path),
+            format: parse_enum(name, "format", get!("format" of name from table as as_str with logger), logger.hollow()),
+            layout: parse_enum(name, "layout", get!("layout" of name from table as as_str with logger), logger.hollow()),
+            links: parse_enum(name, "links", get!("links" of name from table as as_str with logger), logger.hollow()),
+            tags: get!("tags" of name from table as as_array with logger)
+                .map(|x| x.iter().map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's export_dest '{name}' 'tags' must only contain strings")).to_string()).collect()),
+            strict: get!("strict" of name from table as as_bool with logger).unwrap_or(false),
+            theme: get!("theme" of name from table as as_str with logger).map(String::from),
+            template: get!("template" of name from table as as_str with logger).map(expand_home),
+            force_unlock: get!("force_unlock" of name from table as as_bool with logger).unwrap_or(false),
+            split_collections: get!("split_collections" of name from table as as_bool with logger).unwrap_or(false),
+            single_file: get!("single_file" of name from table as as_bool with logger).unwrap_or(false),
+            incremental: get!("incremental" of name from table as as_bool with logger).unwrap_or(false),
+            include_archived: get!("include_archived" of name from table as as_bool with logger).unwrap_or(false),
+            prune: get!("prune" of name from table as as_bool with logger).unwrap_or(false),
+            access: parse_enum(name, "access", get!("access" of name from table as as_str with logger), logger.hollow()),
+            tag_pages: get!("tag_pages" of name from table as as_bool with logger).unwrap_or(false),
+            events: get!("events" of name from table as as_bool with logger).unwrap_or(false),
+        }
+    }
+
+    /// Gets a destination defined in the config by name
+    pub fn get(name: &str, mut logger: impl Logger) -> &'static Self {
+        match Config::get().export_dests.get(name) {
+            Some(x) => x,
+            None => {
+                let mut names: Vec<&String> = Config::get().export_dests.keys().collect();
+                names.sort();
+                log!((logger.error) Config("No export destination '{name}' is defined in config.toml (defined destinations: {names:?})") as Fatal);
+                logger.crash()
+            },
+        }
+    }
+}
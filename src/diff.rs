@@ -0,0 +1,74 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::{archive::Archive, check::list_dir};
+
+/// How an entry or moc differs between two archives
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// Only in the first archive
+    Removed,
+    /// Only in the second archive
+    Added,
+    /// In both archives but with different contents
+    Modified,
+}
+
+/// An entry or moc that differs between two archives
+pub struct ItemDiff {
+    pub uid: String,
+    pub is_moc: bool,
+    pub change: Change,
+}
+
+impl fmt::Display for ItemDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.is_moc { "moc" } else { "entry" };
+        match self.change {
+            Change::Removed => write!(f, "{kind} '{}' is only in the archive", self.uid),
+            Change::Added => write!(f, "{kind} '{}' is only in the backup", self.uid),
+            Change::Modified => write!(f, "{kind} '{}' differs between the archive and the backup", self.uid),
+        }
+    }
+}
+
+/// Finds the entries and mocs that differ between an archive and a backup of it
+pub fn diff_archives(archive: &Archive, backup: &Archive) -> Vec<ItemDiff> {
+    let mut diffs = Vec::new();
+    for (kind, is_moc) in [("entries", false), ("mocs", true)] {
+        let (this, other) = (archive.database().path().join(kind), backup.database().path().join(kind));
+        let (this_uids, other_uids) = (list_dir(&this), list_dir(&other));
+
+        for uid in this_uids.iter() {
+            let change = if !other_uids.contains(uid) { Change::Removed }
+                else if !dirs_equal(&this.join(uid), &other.join(uid)) { Change::Modified }
+                else { continue };
+            diffs.push(ItemDiff { uid: uid.clone(), is_moc, change });
+        }
+
+        other_uids.into_iter()
+            .filter(|x| !this_uids.contains(x))
+            .for_each(|uid| diffs.push(ItemDiff { uid, is_moc, change: Change::Added }));
+    } diffs
+}
+
+/// Checks if two directories have the same files with the same contents
+pub fn dirs_equal(a: &Path, b: &Path) -> bool {
+    let names = |path: &Path| -> Option<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(path).ok()?
+            .filter_map(|x| x.ok())
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        Some(names)
+    };
+
+    let (Some(a_names), Some(b_names)) = (names(a), names(b)) else { return false };
+    if a_names != b_names { return false }
+
+    a_names.iter().all(|name| {
+        let (a, b) = (a.join(name), b.join(name));
+        if a.is_dir() { return b.is_dir() && dirs_equal(&a, &b) }
+        matches!((fs::read(&a), fs::read(&b)), (Ok(x), Ok(y)) if x == y)
+    })
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use lazy_db::*;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, check::{self, Fix, Problem}, history, json, links, list, sort, trash};
+
+/// Checks the archive's integrity (and with `references` for references to items that no longer exist) on `jobs`
+/// workers, repairing or pruning broken items if `fix` is set
+pub fn doctor(fix: bool, references: bool, jobs: Option<usize>, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let jobs = jobs.unwrap_or_else(check::default_jobs);
+    log!((logger) Doctor("Checking archive integrity with {jobs} workers..."));
+
+    // Problems are shown as they are found, then summarised by item once every item is checked
+    let json = json::enabled();
+    let mut stream_logger = logger.hollow();
+    let mut problems = check::check_archive_with(&archive, jobs, |x| if !json { show(x, &mut stream_logger) });
+
+    // Broken items can't be read for their references, so those are only checked in a healthy archive
+    if references && problems.is_empty() {
+        log!((logger) Doctor("Checking references between items..."));
+        problems = check::check_references(&archive, logger.hollow());
+        if !json { problems.iter().for_each(|x| show(x, &mut logger)) }
+    }
+
+    if json {
+        json::print(problems.iter()
+            .map(|x| json!({ "location": x.location, "message": x.message, "fix": x.fix.as_ref().map(|x| x.to_string()) }))
+            .collect());
+    } else {
+        summarise(&problems, logger.hollow());
+    }
+
+    if problems.is_empty() {
+        log!((logger.vital) Doctor("No problems found; the archive is healthy") as Log);
+        return;
+    }
+
+    if !fix {
+        log!((logger.vital) Doctor("Found {} problems; run `diary-cli doctor{} --fix` to repair them", problems.len(), if references { " --references" } else { "" }) as Inconvenience);
+        return;
+    }
+
+    log!((logger) Doctor("Backing up archive before repairs, if you want to revert back, run `diary-cli rollback -f`"));
+    Archive::backup_before_modification(logger.hollow());
+    let fixed = repair(&archive, &problems, logger.hollow());
+
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, "doctor", None, logger.hollow());
+    log!((logger.vital) Doctor("Successfully applied {fixed} repairs to the archive") as Log);
+}
+
+fn show(problem: &Problem, logger: &mut impl Logger) {
+    match &problem.fix {
+        Some(fix) => log!((logger.vital) Doctor("{problem} (fix: {fix})") as Warning),
+        None => log!((logger.vital) Doctor("{problem}") as Warning),
+    }
+}
+
+/// Counts the problems of each item (`entries/<uid>`, `mocs/<uid>` or an order list)
+fn summarise(problems: &[Problem], mut logger: impl Logger) {
+    let mut groups: Vec<(String, usize)> = Vec::new();
+    for problem in problems {
+        let item = problem.location.splitn(3, '/').take(2).collect::<Vec<_>>().join("/");
+        match groups.iter_mut().find(|(x, _)| *x == item) {
+            Some((_, count)) => *count += 1,
+            None => groups.push((item, 1)),
+        }
+    }
+    groups.sort();
+    groups.iter().for_each(|(item, count)| log!((logger.vital) Doctor("{item}: {count} problem{}", if *count == 1 { "" } else { "s" }) as Log));
+}
+
+/// Applies the fixes of the problems found, returning how many were applied
+fn repair(archive: &Archive, problems: &[Problem], mut logger: impl Logger) -> usize {
+    let mut fixes: Vec<&Fix> = Vec::new();
+    problems.iter()
+        .filter_map(|x| x.fix.as_ref())
+        .for_each(|x| if !fixes.contains(&x) { fixes.push(x) });
+
+    // Pruned items don't need any other repairs
+    let pruned: Vec<&String> = fixes.iter().filter_map(|x| match x { Fix::Prune { uid, .. } => Some(uid), _ => None }).collect();
+    let path = archive.database().path();
+    let mut fixed = 0;
+
+    // List lengths are repaired first so the order lists can be read
+    fixes.sort_by_key(|x| !matches!(x, Fix::ListLength { .. }));
+    for fix in fixes {
+        log!((logger) Doctor("Applying fix: {fix}..."));
+        match fix {
+            Fix::ListLength { list, length } => {
+                if pruned.iter().any(|uid| list.starts_with(path.join("entries").join(uid)) || list.starts_with(path.join("mocs").join(uid))) { continue }
+                let container = if_err!((logger) [Doctor, err => ("While loading list: {err:?}")] retry LazyContainer::load(list));
+                if_err!((logger) [Doctor, err => ("While writing list length: {err:?}")] retry write_container!((container) length = new_u16(*length)));
+            },
+            Fix::RemoveFromOrder { order, uid } => sort::remove_from_order(archive, order, uid, logger.hollow()),
+            Fix::AddToUnsorted(uid) => {
+                if pruned.contains(&uid) { continue }
+                list::push(
+                    |file| LazyData::new_string(file, uid),
+                    &if_err!((logger) [Doctor, err => ("While loading unsorted stack: {err:?}")] retry search_database!((archive.database()) /order/unsorted)),
+                    logger.hollow(),
+                );
+            },
+            Fix::Prune { uid, is_moc } => {
+                let item = path.join(if *is_moc { "mocs" } else { "entries" }).join(uid);
+                trash::store(&item, uid, *is_moc, logger.hollow());
+                if_err!((logger) [Doctor, err => ("While pruning '{uid}': {err:?}")] retry fs::remove_dir_all(&item));
+                if !is_moc {
+                    sort::remove_from_order(archive, "sorted", uid, logger.hollow());
+                    sort::remove_from_order(archive, "unsorted", uid, logger.hollow());
+                }
+            },
+            Fix::RemoveDir(dir) => if_err!((logger) [Doctor, err => ("While removing '{}': {err:?}", dir.to_string_lossy())] retry fs::remove_dir_all(dir)),
+            Fix::UnlinkReference { uid, reference } => {
+                if pruned.contains(&uid) { continue }
+                let mut entry = archive.get_entry(uid.clone(), logger.hollow()).unwrap();
+                entry.sections(logger.hollow()).iter_mut().for_each(|section| {
+                    section.content = Some(links::unlink_cross_references(section.content(logger.hollow()), reference));
+                    section.store_lazy(logger.hollow());
+                });
+                entry.references = Some(entry.references(logger.hollow()).iter().filter(|x| *x != reference).cloned().collect());
+                entry.store_lazy(logger.hollow());
+            },
+            Fix::RemoveLink { uid, link } => {
+                if pruned.contains(&uid) { continue }
+                let mut entry = archive.get_entry(uid.clone(), logger.hollow()).unwrap();
+                entry.links = Some(entry.links(logger.hollow()).iter().filter(|x| *x != link).cloned().collect());
+                entry.store_lazy(logger.hollow());
+            },
+        } fixed += 1;
+    } fixed
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::path::Path;
+use lazy_db::*;
+use soulog::*;
+use serde_json::{json, Value};
+use crate::{archive::Archive, archived, error::{self, Context}, entry::{format_date, Entry}, history, home_dir, incremental::fingerprint, list, moc::MOC, provenance::Provenance, signature::Signature, sort};
+
+/// The version of the json dump format, bumped whenever it changes incompatibly
+const DUMP_VERSION: u64 = 1;
+
+/// Converts a toml value into json; dates become `yyyy-mm-dd` strings
+pub fn toml_to_json(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(x) => json!(x),
+        toml::Value::Integer(x) => json!(x),
+        toml::Value::Float(x) => json!(x),
+        toml::Value::Boolean(x) => json!(x),
+        toml::Value::Datetime(x) => json!(x.to_string()),
+        toml::Value::Array(x) => x.iter().map(toml_to_json).collect(),
+        toml::Value::Table(x) => x.iter().map(|(k, v)| (k.clone(), toml_to_json(v))).collect::<serde_json::Map<_, _>>().into(),
+    }
+}
+
+/// Converts json back into toml, turning `date` and `unlock_date` strings back into dates; `null`s are dropped
+pub fn json_to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(x) => toml::Value::Boolean(*x),
+        Value::Number(x) => match x.as_i64() {
+            Some(x) => toml::Value::Integer(x),
+            None => toml::Value::Float(x.as_f64()?),
+        },
+        Value::String(x) => toml::Value::String(x.clone()),
+        Value::Array(x) => toml::Value::Array(x.iter().filter_map(json_to_toml).collect()),
+        Value::Object(x) => toml::Value::Table(x.iter()
+            .filter_map(|(k, v)| {
+                let value = match (k.as_str(), v) {
+                    ("date" | "unlock_date", Value::String(date)) => date.parse().map(toml::Value::Datetime).ok()?,
+                    _ => json_to_toml(v)?,
+                }; Some((k.clone(), value))
+            }).collect()),
+    })
+}
+
+fn provenance_json(container: &LazyContainer, logger: impl Logger) -> Value {
+    match Provenance::load(container, logger) {
+        Some(x) => json!({ "source": x.source, "committed_at": x.committed_at }),
+        None => Value::Null,
+    }
+}
+
+fn signature_json(container: &LazyContainer, logger: impl Logger) -> Value {
+    match Signature::load(container, logger) {
+        Some(x) => json!({ "author": x.author, "device": x.device }),
+        None => Value::Null,
+    }
+}
+
+/// Dumps entries and mocs (in the same shape as their toml configs) and the order stacks into `archive.json`
+pub fn export_json(path: &Path, entries: Vec<Entry>, mocs: Vec<MOC>, archive: &Archive, mut logger: impl Logger) {
+    let uids: Vec<&String> = entries.iter().map(|x| &x.uid).collect();
+    let mut order = serde_json::Map::new();
+    for name in ["sorted", "unsorted"] {
+        let stack = list::read(
+            |x| x.collect_string(),
+            &if_err!((logger) [Export, err => ("While reading {name} stack: {err:?}")] retry search_database!((archive.database()) /order/(name))),
+            logger.hollow(),
+        );
+        order.insert(name.to_string(), stack.iter().filter(|x| uids.contains(x)).map(|x| json!(x)).collect());
+    }
+
+    log!((logger) Export("Dumping {} entries and {} mocs as json...", entries.len(), mocs.len()));
+    let entries: Vec<Value> = entries.into_iter().map(|mut x| {
+        let mut value = toml_to_json(&toml::Value::Table(x.pull(path, true, logger.hollow())));
+        value["signature"] = signature_json(&x.container, logger.hollow());
+        value["provenance"] = provenance_json(&x.container, logger.hollow());
+        value["archived"] = json!(archived::is_archived(&x.container, logger.hollow()));
+        value
+    }).collect();
+    let mocs: Vec<Value> = mocs.into_iter().map(|mut x| {
+        let mut value = toml_to_json(&toml::Value::Table(x.pull(logger.hollow())));
+        value["signature"] = signature_json(&x.container, logger.hollow());
+        value["provenance"] = provenance_json(&x.container, logger.hollow());
+        value["archived"] = json!(archived::is_archived(&x.container, logger.hollow()));
+        value
+    }).collect();
+
+    let dump = json!({
+        "version": DUMP_VERSION,
+        "entries": entries,
+        "mocs": mocs,
+        "order": order,
+    });
+    let file = path.join("archive.json");
+    let text = if_err!((logger) [Export, err => ("While serialising json dump: {err:?}")] {serde_json::to_string_pretty(&dump)} crash logger.crash());
+    if_err!((logger) [Export, err => ("While writing '{}': {err:?}", file.to_string_lossy())] retry std::fs::write(&file, &text));
+}
+
+/// The hash of an item of a json dump by its content alone, without its uid, signature, provenance or whether it's
+/// archived, so the same item dumped twice (even under another uid) hashes the same
+pub fn content_hash(item: &Value) -> u64 {
+    let mut item = item.clone();
+    if let Some(item) = item.as_object_mut() {
+        ["signature", "provenance", "archived"].iter().for_each(|x| { item.remove(*x); });
+        for kind in ["entry", "moc"] {
+            if let Some(x) = item.get_mut(kind).and_then(|x| x.as_object_mut()) { x.remove("uid"); }
+        }
+    }
+    fingerprint(item.to_string()) // json objects are sorted by key, so this is stable
+}
+
+/// The content hash an entry or moc was imported with, if it was imported
+pub fn import_hash(container: &LazyContainer, mut logger: impl Logger) -> Option<u64> {
+    match container.read_data("import_hash") {
+        Ok(x) => Some(if_err!((logger) [Import, err => ("While reading import hash: {err:?}")] {x.collect_u64()} crash logger.crash())),
+        Err(_) => None,
+    }
+}
+
+/// Reads and parses a json dump
+pub fn read_dump(file: &str) -> error::Result<Value> {
+    let text = std::fs::read_to_string(file).context("import-read", "Import", || format!("Couldn't read '{file}'"))?;
+    serde_json::from_str(&text).context("import-parse", "Import", || format!("'{file}' isn't valid json"))
+}
+
+/// Imports a json dump made by `export --format json`, into a new archive if there isn't one; items already in the
+/// archive (with the same uid, or imported before with the same content) are skipped, so overlapping dumps can be
+/// imported one after another
+pub fn import_json(file: String, mut logger: impl Logger) {
+    log!((logger) Import("Reading json dump '{file}'..."));
+    let dump = read_dump(&file).unwrap_or_else(|x| x.crash(logger.hollow()));
+
+    let version = dump["version"].as_u64().unwrap_or(0);
+    if version != DUMP_VERSION {
+        log!((logger.error) Import("'{file}' is a version {version} json dump but only version {DUMP_VERSION} can be imported") as Fatal);
+        return logger.crash();
+    }
+
+    let archive = if home_dir().join("archive").exists() {
+        let archive = Archive::load(logger.hollow());
+        Archive::backup_before_modification(logger.hollow());
+        archive
+    } else { Archive::init(logger.hollow()) };
+    let empty = Vec::new();
+    let items = |key: &str| dump[key].as_array().unwrap_or(&empty);
+
+    // What is already in the archive: uids (with the dates of entries) and the content hashes of imported items
+    let mut present: HashMap<String, Option<String>> = HashMap::new();
+    let mut hashes: HashMap<u64, String> = HashMap::new();
+    for mut entry in archive.list_entries(logger.hollow()) {
+        if let Some(hash) = import_hash(&entry.container, logger.hollow()) { hashes.insert(hash, entry.uid.clone()); }
+        present.insert(entry.uid.clone(), Some(format_date(entry.date(logger.hollow()))));
+    }
+    for moc in archive.list_mocs(logger.hollow()) {
+        if let Some(hash) = import_hash(&moc.container, logger.hollow()) { hashes.insert(hash, moc.uid.clone()); }
+        present.insert(moc.uid, None);
+    }
+
+    let mut created: Vec<String> = Vec::new();
+    let mut skipped = 0;
+    let mut counts = [0; 2];
+    for (kind, is_moc) in [("entries", false), ("mocs", true)] {
+        for (i, item) in items(kind).iter().enumerate() {
+            let name = format!("{file} ({kind} {i})");
+            let table = match json_to_toml(item) {
+                Some(toml::Value::Table(x)) => x,
+                _ => {
+                    log!((logger.error) Import("{name} must be an object") as Fatal);
+                    return logger.crash();
+                },
+            };
+
+            let meta = &item[if is_moc { "moc" } else { "entry" }];
+            let uid = meta["uid"].as_str().unwrap_or_default().to_string();
+            let hash = content_hash(item);
+            if let Some(other) = hashes.get(&hash) {
+                match *other == uid {
+                    true => log!((logger) Import("Skipping '{uid}' as it was already imported")),
+                    false => log!((logger) Import("Skipping '{uid}' as it was already imported as '{other}'")),
+                }
+                skipped += 1;
+                continue;
+            }
+            if let Some(date) = present.get(&uid) {
+                match (date, meta["date"].as_str()) {
+                    (Some(date), Some(new)) if date != new => log!((logger.vital) Import("Skipping '{uid}' as an entry dated {date} already has its uid (the one being imported is dated {new})") as Inconvenience),
+                    _ => log!((logger) Import("Skipping '{uid}' as it's already in the archive")),
+                }
+                skipped += 1;
+                continue;
+            }
+
+            let container = if_err!((logger) [Import, err => ("While loading {kind} container: {err:?}")] retry search_database!((archive.database()) /(kind)/));
+            let item_container = if is_moc { MOC::new(table, &name, container, logger.hollow()).container }
+                else { Entry::new(table, &name, container, logger.hollow()).container };
+            if let (Some(author), Some(device)) = (item["signature"]["author"].as_str(), item["signature"]["device"].as_str()) {
+                Signature { author: author.to_string(), device: device.to_string() }.store(&item_container, logger.hollow());
+            }
+            if let (Some(source), Some(committed_at)) = (item["provenance"]["source"].as_str(), item["provenance"]["committed_at"].as_str()) {
+                Provenance { source: source.to_string(), committed_at: committed_at.to_string() }.store(&item_container, logger.hollow());
+            }
+            if item["archived"].as_bool() == Some(true) { archived::set_archived(&item_container, true, logger.hollow()) }
+            if_err!((logger) [Import, err => ("While writing import hash: {err:?}")] retry write_container!((item_container) import_hash = new_u64(hash)));
+
+            hashes.insert(hash, uid.clone());
+            present.insert(uid.clone(), None);
+            if !is_moc { created.push(uid) }
+            counts[is_moc as usize] += 1;
+        }
+    }
+
+    if counts == [0, 0] {
+        return log!((logger.vital) Import("Nothing to import from '{file}'; all of its {skipped} items are already in the archive") as Inconvenience);
+    }
+
+    // Adds the created entries to the order stacks in the order of the dump
+    let order = |key: &str| dump["order"][key].as_array().unwrap_or(&empty).iter().filter_map(|x| x.as_str().map(String::from)).filter(|x| created.contains(x)).collect::<Vec<_>>();
+    for name in ["sorted", "unsorted"] {
+        let container = if_err!((logger) [Import, err => ("While loading {name} stack: {err:?}")] retry search_database!((archive.database()) /order/(name)));
+        let mut stack = list::read(|x| x.collect_string(), &container, logger.hollow()).into_vec();
+        stack.extend(order(name));
+        list::write(&stack, |file, data| LazyData::new_string(file, data), &container, logger.hollow());
+    }
+
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, "import", None, logger.hollow());
+    sort::sort(logger.hollow());
+    log!((logger.vital) Import("Successfully imported {} entries and {} mocs from '{file}', skipping {skipped} already in the archive", counts[0], counts[1]) as Log);
+}
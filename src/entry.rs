@@ -4,7 +4,7 @@ use toml::Table;
 use soulog::*;
 use lazy_db::*;
 use std::path::Path;
-use crate::search::Searchable;
+use crate::search::{Searchable, query_matches};
 pub use crate::{
     list,
     unpack_array,
@@ -30,18 +30,15 @@ macro_rules! unwrap_opt {
 #[macro_export]
 macro_rules! read_db_container {
     ($key:ident from $name:ident($container:expr) as $func:ident with $logger:ident) => {{
-        let data = if_err!(($logger) [$name, err => ("While reading from archive: {:?}", err)] retry $container.read_data(stringify!($key)));
-        if_err!(($logger) [$name, err => ("While reading from archive: {:?}", err)] {data.$func()} crash {
-            log!(($logger.error) $name("{err:#?}") as Fatal);
-            $logger.crash()
-        })
+        let data = $crate::with_context!(($logger) [$name, "archive-read" => ("While reading '{}' from archive", stringify!($key))] retry $container.read_data(stringify!($key)));
+        $crate::with_context!(($logger) [$name, "archive-read" => ("While reading '{}' from archive", stringify!($key))] {data.$func()} crash)
     }}
 }
 
 #[macro_export]
 macro_rules! write_db_container {
     ($name:ident($container:expr) $key:ident = $func:ident($value:expr) with $logger:ident) => {
-        if_err!(($logger) [$name, err => ("While writing to archive: {err:?}")] retry write_container!(($container) $key = $func($value)));
+        $crate::with_context!(($logger) [$name, "archive-write" => ("While writing '{}' to archive", stringify!($key))] retry write_container!(($container) $key = $func($value)));
     }
 }
 
@@ -79,6 +76,22 @@ macro_rules! get {
     };
 }
 
+/// Formats an entry's date array as an RFC 3339 date string (`yyyy-mm-dd`)
+pub fn format_date(date: &[u16; 3]) -> String {
+    format!("{:04}-{:02}-{:02}",
+        date[2], // Year
+        date[1], // Month
+        date[0], // Day
+    )
+}
+
+/// Today's date in the same `[day, month, year]` layout as `Entry::date`
+pub fn today() -> [u16; 3] {
+    use chrono::Datelike;
+    let today = chrono::Local::now().date_naive();
+    [today.day() as u16, today.month() as u16, today.year() as u16]
+}
+
 pub struct Entry {
     pub container: LazyContainer,
     pub uid: String,
@@ -89,6 +102,14 @@ pub struct Entry {
     pub notes: Option<Box<[String]>>,
     /// Date goes from `day` to `month` then to `year`
     pub date: Option<[u16; 3]>,
+    /// Date the entry unlocks at if it is a time capsule
+    pub unlock_date: Option<Option<[u16; 3]>>,
+    /// The uids of the items its sections cross-reference with `{{uid:...}}`
+    pub references: Option<Box<[String]>>,
+    /// The uids of the entries and mocs it links to (`links = [...]`)
+    pub links: Option<Box<[String]>>,
+    /// The export template it is rendered with instead of the default one (`template = "letter"`)
+    pub template: Option<Option<String>>,
 }
 
 impl Entry {
@@ -101,6 +122,7 @@ impl Entry {
         let title = get!(title at entry_path from entry_table as as_str with logger).to_string();
         let description = get!(description at entry_path from entry_table as as_str with logger).to_string();
         get!(raw_notes = notes at entry_path from entry_table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
+        get!(raw_links = links at entry_path from entry_table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
         let raw_tags = get!(tags at entry_path from entry_table as as_array with logger);
         let raw_sections = get!(section at entry_path from table as as_array with logger);
 
@@ -116,6 +138,21 @@ impl Entry {
             format: Entry("Datetime 'date' must contain the date")
         ); let date = [ date.day as u16, date.month as u16, date.year ];
 
+        // Get unlock date (for time capsules)
+        let unlock_date = entry_table.get("unlock_date").map(|x| {
+            let date = match x {
+                toml::Value::Datetime(x) => x.date,
+                toml::Value::String(x) => x.parse::<toml::value::Datetime>().ok().and_then(|x| x.date),
+                _ => None,
+            };
+            let date = unwrap_opt!((date) with logger, format: Entry("Entry '{entry_path}'s 'unlock_date' attribute must be a date"));
+            [ date.day as u16, date.month as u16, date.year ]
+        });
+
+        // Get the export template (for special entries like letters)
+        let template = entry_table.get("template")
+            .map(|x| unwrap_opt!((x.as_str()) with logger, format: Entry("Entry '{entry_path}'s 'template' attribute must be the name of a template (a string)")).to_string());
+
         // Parse simple arrays
         log!((logger) Entry("Parsing notes & tags..."));
         unpack_array!(notes from raw_notes with logger by x
@@ -126,6 +163,24 @@ impl Entry {
             => unwrap_opt!((x.as_str()) with logger, format: Entry("All tags in entry '{entry_path}' must be strings")).to_string()
         );
 
+        unpack_array!(links from raw_links with logger by x
+            => unwrap_opt!((x.as_str()) with logger, format: Entry("All links in entry '{entry_path}' must be uids (strings)")).to_string()
+        );
+        let links = links.into_iter().fold(Vec::new(), |mut unique, x| { if !unique.contains(&x) { unique.push(x) } unique });
+
+        // Attachments are pulled back out into one folder so they can't share a name
+        let mut names = Vec::new();
+        for name in raw_sections.iter()
+            .filter_map(|x| x.get("attachments").and_then(|x| x.as_array()))
+            .flatten()
+            .filter_map(|x| x.as_str().and_then(|x| Path::new(x).file_name()))
+        {
+            if names.contains(&name) {
+                log!((logger.error) Entry("Entry '{entry_path}' has more than one attachment named '{}'", name.to_string_lossy()) as Fatal);
+                return logger.crash();
+            } names.push(name);
+        }
+
         // Parse sections
         log!((logger) Entry("Parsing entry's sections..."));
         let list = if_err!((logger) [Entry, err => ("While initialising sections: {err:?}")] retry container.new_container("sections"));
@@ -136,6 +191,14 @@ impl Entry {
         });
         if_err!((logger) [Entry, err => ("While writing section list length: {err:?}")] retry write_container!((list) length = new_u16(raw_sections.len() as u16)));
 
+        // Index the cross-references of the sections
+        let mut references: Vec<String> = Vec::new();
+        for section in sections.iter_mut() {
+            crate::links::cross_references(section.content(logger.hollow())).into_iter()
+                .for_each(|x| if !references.contains(&x) { references.push(x) });
+            section.clear_cache();
+        }
+
         log!((logger) Entry("Storing entry's parsed and checked data into archive..."));
 
         let mut this = Self {
@@ -144,9 +207,13 @@ impl Entry {
             title: Some(title),
             description: Some(description),
             date: Some(date),
+            unlock_date: Some(unlock_date),
             notes: Some(notes.into_boxed_slice()),
             tags: Some(tags.into_boxed_slice()),
             sections: Some(sections.into_boxed_slice()),
+            references: Some(references.into_boxed_slice()),
+            links: Some(links.into_boxed_slice()),
+            template: Some(template),
         };
         this.store_lazy(logger.hollow());
         log!((logger) Entry("Successfully written entry into archive"));
@@ -165,15 +232,25 @@ impl Entry {
         entry.insert("description".into(), self.description(logger.hollow()).clone().into());
         entry.insert("notes".into(), self.notes(logger.hollow()).to_vec().into());
         entry.insert("tags".into(), self.tags(logger.hollow()).to_vec().into());
+        if !self.links(logger.hollow()).is_empty() {
+            entry.insert("links".into(), self.links(logger.hollow()).to_vec().into());
+        }
         entry.insert("date".into(), Self::array_to_date(self.date(logger.hollow()), logger.hollow()));
+        if let Some(x) = self.unlock_date(logger.hollow()) {
+            entry.insert("unlock_date".into(), Self::array_to_date(x, logger.hollow()));
+        }
+        if let Some(x) = self.template(logger.hollow()) {
+            entry.insert("template".into(), x.clone().into());
+        }
         map.insert("entry".into(), entry.into());
 
         self.clear_cache();
 
+        let attachments = Path::new("attachments").join(&self.uid);
         map.insert("section".into(), self.sections(logger.hollow())
             .iter_mut()
             .enumerate()
-            .map(|(i, x)| x.pull(i as u8, path, one_file, logger.hollow()))
+            .map(|(i, x)| x.pull(i as u8, path, &attachments, one_file, logger.hollow()))
             .collect::<Vec<Table>>()
             .into()
         );
@@ -184,12 +261,7 @@ impl Entry {
     }
 
     fn array_to_date(arr: &[u16; 3], mut logger: impl Logger) -> toml::Value {
-        // Format the array of u16s to a string in the RFC 3339 date format
-        let date_string = format!("{:04}-{:02}-{:02}",
-            arr[2], // Year
-            arr[1], // Month
-            arr[0], // Day
-        );
+        let date_string = format_date(arr);
     
         // Parse the string to a toml::Value::Datetime
         toml::Value::Datetime(if_err!((logger) [Pull, _err => ("Invalid entry date")]
@@ -204,6 +276,8 @@ impl Entry {
         if let Some(x) = &self.title { write_db_container!(Entry(self.container) title = new_string(x) with logger); }
         if let Some(x) = &self.description { write_db_container!(Entry(self.container) description = new_string(x) with logger); }
         if let Some(x) = &self.date { write_db_container!(Entry(self.container) date = new_u16_array(x) with logger); }
+        if let Some(Some(x)) = &self.unlock_date { write_db_container!(Entry(self.container) unlock_date = new_u16_array(x) with logger); }
+        if let Some(Some(x)) = &self.template { write_db_container!(Entry(self.container) template = new_string(x) with logger); }
 
         // The bloody lists & arrays
         if let Some(x) = &self.notes {
@@ -223,6 +297,24 @@ impl Entry {
                 logger.hollow()
             );
         }
+
+        if let Some(x) = &self.references {
+            list::write(
+                x.as_ref(),
+                |file, data| LazyData::new_string(file, data),
+                &if_err!((logger) [Entry, err => ("While writing references to archive: {:?}", err)] retry self.container.new_container("references")),
+                logger.hollow()
+            );
+        }
+
+        if let Some(x) = &self.links {
+            list::write(
+                x.as_ref(),
+                |file, data| LazyData::new_string(file, data),
+                &if_err!((logger) [Entry, err => ("While writing links to archive: {:?}", err)] retry self.container.new_container("links")),
+                logger.hollow()
+            );
+        }
     }
 
     pub fn load_lazy(uid: String, container: LazyContainer) -> Self {
@@ -235,6 +327,10 @@ impl Entry {
             tags: None,
             notes: None,
             date: None,
+            unlock_date: None,
+            references: None,
+            links: None,
+            template: None,
         }
     }
 
@@ -245,6 +341,10 @@ impl Entry {
         self.tags = None;
         self.notes = None;
         self.date = None;
+        self.unlock_date = None;
+        self.references = None;
+        self.links = None;
+        self.template = None;
     }
 
     pub fn fill_cache(&mut self, logger: impl Logger) {
@@ -253,6 +353,16 @@ impl Entry {
         self.tags(logger.hollow());
         self.notes(logger.hollow());
         self.date(logger.hollow());
+        self.unlock_date(logger.hollow());
+        self.template(logger.hollow());
+    }
+
+    /// Checks if the entry is a time capsule that hasn't reached its unlock date yet
+    pub fn is_locked(&mut self, logger: impl Logger) -> bool {
+        match self.unlock_date(logger) {
+            Some(x) => crate::sort::younger(x, &today()),
+            None => false,
+        }
     }
 
     cache_field!(title(this, logger) -> String {
@@ -284,6 +394,61 @@ impl Entry {
         [array[0], array[1], array[2]]
     });
 
+    cache_field!(unlock_date(this, logger) -> Option<[u16; 3]> {
+        match this.container.read_data("unlock_date") {
+            Ok(data) => {
+                let array = if_err!((logger) [Entry, err => ("While reading entry's unlock date: {err:?}")] {data.collect_u16_array()} crash {
+                    log!((logger.error) Entry("{err:#?}") as Fatal);
+                    logger.crash()
+                });
+                Some([array[0], array[1], array[2]])
+            },
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Entry("While reading entry's unlock date: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    });
+
+    cache_field!(template(this, logger) -> Option<String> {
+        match this.container.read_data("template") {
+            Ok(data) => Some(if_err!((logger) [Entry, err => ("While reading entry's template: {err:?}")] {data.collect_string()} crash {
+                log!((logger.error) Entry("{err:#?}") as Fatal);
+                logger.crash()
+            })),
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Entry("While reading entry's template: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    });
+
+    cache_field!(references(this, logger) -> Box<[String]> {
+        // Entries committed before cross-references were indexed don't have any
+        if !this.container.path().join("references").is_dir() { Box::new([]) }
+        else {
+            list::read(
+                |data| data.collect_string(),
+                &if_err!((logger) [Entry, err => ("While reading from entry's references: {err:?}")] retry this.container.child_container("references")),
+                logger
+            )
+        }
+    });
+
+    cache_field!(links(this, logger) -> Box<[String]> {
+        // Entries committed before links were supported don't have any
+        if !this.container.path().join("links").is_dir() { Box::new([]) }
+        else {
+            list::read(
+                |data| data.collect_string(),
+                &if_err!((logger) [Entry, err => ("While reading from entry's links: {err:?}")] retry this.container.child_container("links")),
+                logger
+            )
+        }
+    });
+
     cache_field!(sections(this, logger) -> Box<[Section]> {
         let container = if_err!((logger) [Entry, err => ("While reading from entry's sections: {err:?}")] retry this.container.child_container("sections"));
         let length = if_err!((logger) [Entry, err => ("While reading from entry's sections' length: {err:?}")] retry container.read_data("length"));
@@ -308,8 +473,12 @@ impl Searchable for Entry {
         self.uid.clone()
     }
 
+    fn container(&self) -> &LazyContainer {
+        &self.container
+    }
+
     fn contains_tag(&mut self, tag: &String, logger: impl Logger) -> bool {
-        let result = self.tags(logger).contains(tag);
+        let result = self.tags(logger).iter().any(|x| query_matches(x, tag));
         self.tags = None;
         result
     }
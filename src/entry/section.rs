@@ -29,6 +29,10 @@ pub struct Section {
     pub title: Option<String>,
     pub notes: Option<Box<[String]>>,
     pub content: Option<String>,
+    /// The canonical path the section's content was read from, if it had a `path`
+    pub source: Option<Option<String>>,
+    /// The file names of the section's attachments (their contents are only read when needed)
+    pub attachments: Option<Box<[String]>>,
 }
 
 impl Section {
@@ -39,21 +43,38 @@ impl Section {
         log!((logger) Section("Reading section's data..."));
         let title = get!(title at (entry, idx) from table as as_str with logger).to_string();
         get!(raw_notes = notes at (entry, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
+        get!(raw_attachments = attachments at (entry, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
 
         // Get contents
-        let content = table.get("path")
+        let source = table.get("path")
             .map(|x| {
                 let path = unwrap_opt!((x.as_str()) with logger, format: Entry("Entry '{entry}', section {idx}'s 'path' attribute must be of the correct type"));
                 log!((logger) Section("Checking if path specified in the section is valid..."));
+                let resolved = resolve_path(Path::new(entry), Path::new(path));
                 // Check if path exists
-                if !Path::new(path).exists() {
-                    log!((logger.error) Section("Path '{path}' specified in entry '{entry}', section {idx} does not exist") as Fatal);
+                if !resolved.exists() {
+                    log!((logger.error) Section("Path '{path}' specified in entry '{entry}', section {idx} does not exist (relative paths are relative to the entry's config file or `--base-dir`)") as Fatal);
                     return logger.crash();
                 };
-                if_err!((logger) [Section, err => ("While reading entry '{entry}', section {idx}'s path contents: {err:?}")] retry fs::read_to_string(path))
-            }).unwrap_or_else(|| {
-                get!(contents at (entry, idx) from table as as_str with logger).to_string()
+                crate::archive::guard_source_path(&resolved, logger.hollow())
             });
+        if table.contains_key("contents") && table.contains_key("content") {
+            log!((logger.error) Section("Entry '{entry}', section {idx} can only have one of 'contents' or 'content'") as Fatal);
+            return logger.crash();
+        }
+        let inline = table.get("contents").or(table.get("content"))
+            .map(|x| unwrap_opt!((x.as_str()) with logger, format: Section("Entry '{entry}', section {idx}'s 'contents' attribute must be of the correct type")));
+        let content = match (&source, inline) {
+            (Some(path), inline) => {
+                if inline.is_some() { log!((logger.vital) Section("Entry '{entry}', section {idx} has both a 'path' and inline contents; using the path") as Inconvenience) }
+                if_err!((logger) [Section, err => ("While reading entry '{entry}', section {idx}'s path contents: {err:?}")] retry fs::read_to_string(path))
+            },
+            (None, Some(inline)) => inline.to_string(),
+            (None, None) => {
+                log!((logger.error) Section("Entry '{entry}', section {idx} must have its contents inline ('contents') or in a file ('path')") as Fatal);
+                return logger.crash();
+            },
+        };
 
         // Parse notes
         log!((logger) Section("Parsing section's notes"));
@@ -61,28 +82,67 @@ impl Section {
             => unwrap_opt!((x.as_str()) with logger, format: Section("All notes in entry '{entry}', section '{idx}' must be strings")).to_string()
         );
 
+        // Read attachments
+        log!((logger) Section("Reading section's attachments..."));
+        unpack_array!(attachments from raw_attachments with logger by x => {
+            let path = unwrap_opt!((x.as_str()) with logger, format: Section("All attachments in entry '{entry}', section {idx} must be paths"));
+            let resolved = resolve_path(Path::new(entry), Path::new(path));
+            if !resolved.is_file() {
+                log!((logger.error) Section("Attachment '{path}' of entry '{entry}', section {idx} isn't a file (relative paths are relative to the entry's config file or `--base-dir`)") as Fatal);
+                return logger.crash();
+            }
+            let resolved = crate::archive::guard_source_path(&resolved, logger.hollow());
+            let name = resolved.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+            let data = if_err!((logger) [Section, err => ("While reading attachment '{path}' of entry '{entry}', section {idx}: {err:?}")] retry fs::read(&resolved));
+            (name, data)
+        });
+
         log!((logger) Section("Writing entry '{entry}'s section {idx} into archive..."));
         let mut this = Self {
             container,
             title: Some(title),
             content: Some(content),
             notes: Some(notes.into_boxed_slice()),
+            source: Some(source.map(|x| x.to_string_lossy().to_string())),
+            attachments: Some(attachments.iter().map(|(x, _)| x.clone()).collect()),
         };
 
         this.store_lazy(logger.hollow());
+        if !attachments.is_empty() {
+            list::write(
+                &attachments,
+                |file, (_, data)| LazyData::new_binary(file, data),
+                &if_err!((logger) [Section, err => ("While writing section's attachments to archive: {err:?}")] retry this.container.child_container("attachments").and_then(|x| x.child_container("data"))),
+                logger.hollow()
+            );
+        }
         this.clear_cache();
         log!((logger) Section("Successfully parsed and written entry's section {idx} into archive"));
         log!((logger) Section("")); // spacer
         this
     }
 
-    pub fn pull(&mut self, idx: u8, path: &Path, one_file: bool, mut logger: impl Logger) -> Table {
+    /// Pulls the section back into a toml table, writing its content (unless `one_file`) into `path`
+    /// and its attachments into the `attachments` folder of `path`
+    pub fn pull(&mut self, idx: u8, path: &Path, attachments: &Path, one_file: bool, mut logger: impl Logger) -> Table {
         let mut map = Table::new();
 
         // Insert title and notes
         map.insert("title".into(), Value::String(self.title(logger.hollow()).clone()));
         map.insert("notes".into(), self.notes(logger.hollow()).to_vec().into());
 
+        // Write out attachments
+        let names = self.attachments(logger.hollow()).clone();
+        if !names.is_empty() {
+            if_err!((logger) [Pull, err => ("While creating attachments folder: {err:?}")] retry fs::create_dir_all(path.join(attachments)));
+            let files: Vec<String> = names.iter().enumerate().map(|(i, name)| {
+                let file = attachments.join(name);
+                if_err!((logger) [Pull, err => ("While writing attachment '{name}': {err:?}")] retry fs::write(path.join(&file), self.attachment(i, logger.hollow())));
+                file.to_string_lossy().to_string()
+            }).collect();
+            map.insert("attachments".into(), files.into());
+        }
+
         if one_file {
             map.insert("contents".into(), Value::String(self.content(logger.hollow()).clone()));
         } else {
@@ -101,6 +161,15 @@ impl Section {
         // Only store them if they are accessed (maybe modified)
         if let Some(x) = &self.title { write_db_container!(Section(self.container) title = new_string(x) with logger); }
         if let Some(x) = &self.content { write_db_container!(Section(self.container) content = new_string(x) with logger); }
+        if let Some(Some(x)) = &self.source { write_db_container!(Section(self.container) source = new_string(x) with logger); }
+        if let Some(x) = &self.attachments {
+            list::write(
+                x.as_ref(),
+                |file, data| LazyData::new_string(file, data),
+                &if_err!((logger) [Section, err => ("While writing section's attachments to archive: {:?}", err)] retry self.container.child_container("attachments").and_then(|x| x.child_container("names"))),
+                logger.hollow()
+            );
+        }
         if let Some(x) = &self.notes {
             list::write(
                 x.as_ref(),
@@ -117,6 +186,8 @@ impl Section {
             title: None,
             notes: None,
             content: None,
+            source: None,
+            attachments: None,
         }
     }
 
@@ -124,12 +195,33 @@ impl Section {
         self.title = None;
         self.content = None;
         self.notes = None;
+        self.source = None;
+        self.attachments = None;
     }
 
     pub fn fill_cache(&mut self, logger: impl Logger) {
         self.title(logger.hollow());
         self.content(logger.hollow());
         self.notes(logger.hollow());
+        self.source(logger.hollow());
+        self.attachments(logger.hollow());
+    }
+
+    /// The size of one of the section's attachments as it is stored, without reading it
+    pub fn attachment_size(&self, idx: usize) -> u64 {
+        std::fs::metadata(self.container.path().join("attachments").join("data").join(idx.to_string()))
+            .map(|x| x.len())
+            .unwrap_or(0)
+    }
+
+    /// Reads the contents of one of the section's attachments
+    pub fn attachment(&self, idx: usize, mut logger: impl Logger) -> Box<[u8]> {
+        let data = if_err!((logger) [Section, err => ("While reading section's attachment {idx}: {err:?}")] retry self.container.child_container("attachments").and_then(|x| x.child_container("data"))
+            .and_then(|x| x.read_data(idx.to_string())));
+        if_err!((logger) [Section, err => ("While reading section's attachment {idx}: {err:?}")] {data.collect_binary()} crash {
+            log!((logger.error) Section("{err:#?}") as Fatal);
+            logger.crash()
+        })
     }
 
     cache_field!(notes(this, logger) -> Box<[String]> {
@@ -147,4 +239,39 @@ impl Section {
     cache_field!(content(this, logger) -> String {
         read_db_container!(content from Section(this.container) as collect_string with logger)
     });
+
+    cache_field!(attachments(this, logger) -> Box<[String]> {
+        // Sections committed before attachments existed don't have any
+        if !this.container.path().join("attachments").is_dir() { Box::new([]) }
+        else {
+            list::read(
+                |data| data.collect_string(),
+                &if_err!((logger) [Section, err => ("While reading from section's attachments: {err:?}")] retry this.container.child_container("attachments").and_then(|x| x.child_container("names"))),
+                logger
+            )
+        }
+    });
+
+    cache_field!(source(this, logger) -> Option<String> {
+        match this.container.read_data("source") {
+            Ok(data) => Some(if_err!((logger) [Section, err => ("While reading section's source path: {err:?}")] {data.collect_string()} crash {
+                log!((logger.error) Section("{err:#?}") as Fatal);
+                logger.crash()
+            })),
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Section("While reading section's source path: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    });
+}
+
+/// Resolves a section's `path` relative to the directory of the entry config file it is written in (or `--base-dir`),
+/// falling back to the working directory for configs written before paths were relative to them
+pub fn resolve_path(config: &Path, path: &Path) -> std::path::PathBuf {
+    let base = crate::cli::BASE_DIR.get().map(|x| x.as_path()).unwrap_or(config.parent().unwrap_or(Path::new("")));
+    let resolved = base.join(path);
+    if !resolved.exists() && path.exists() { return path.to_path_buf() }
+    resolved
 }
\ No newline at end of file
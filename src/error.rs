@@ -0,0 +1,136 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Mutex;
+use serde_json::{json, Value};
+use soulog::*;
+use crate::json;
+
+/// The error the process is crashing with, printed as json by the logger's crash when `--json` is used
+static FATAL: Mutex<Option<Value>> = Mutex::new(None);
+
+/// A failure that carries the errors that caused it (`LDBError`s, `io::Error`s...) instead of formatting them into its
+/// message, so `--verbose` can show every cause and `--json` a code to match on
+#[derive(Debug)]
+pub struct Error {
+    /// A stable name for the kind of failure, like `config-parse`
+    pub code: &'static str,
+    /// Where it happened, like the origin of a log
+    pub origin: &'static str,
+    pub message: String,
+    source: Option<Box<dyn StdError + Send + Sync>>,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(code: &'static str, origin: &'static str, message: impl Into<String>) -> Self {
+        Self { code, origin, message: message.into(), source: None }
+    }
+
+    /// Sets the error this one was caused by
+    pub fn with_source(mut self, source: impl StdError + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// The messages of the errors this one was caused by, the closest first
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = Vec::new();
+        let mut cause = self.source();
+        while let Some(x) = cause {
+            causes.push(x.to_string());
+            cause = x.source();
+        } causes
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "code": self.code, "origin": self.origin, "message": self.message, "causes": self.causes() })
+    }
+
+    /// The message along with the closest cause, if there is one
+    pub fn summary(&self) -> String {
+        match self.source() {
+            Some(cause) => format!("{}: {cause}", self.message),
+            None => self.message.clone(),
+        }
+    }
+
+    /// Logs the error as fatal (with all of its causes when verbose) and crashes
+    pub fn crash<T>(self, mut logger: impl Logger) -> T {
+        logger.error(Log::new(LogType::Fatal, self.origin, &self.summary(), &[]));
+        for cause in self.causes() {
+            logger.verbose(Log::new(LogType::Log, self.origin, &format!("Caused by: {cause}"), &[]));
+        }
+        record(self.to_json());
+        logger.crash()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source.as_deref().map(|x| x as &(dyn StdError + 'static))
+    }
+}
+
+/// Wraps the errors of results in an `Error` with what was being done when they happened
+pub trait Context<T> {
+    fn context(self, code: &'static str, origin: &'static str, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T, E: StdError + Send + Sync + 'static> Context<T> for std::result::Result<T, E> {
+    fn context(self, code: &'static str, origin: &'static str, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|x| Error::new(code, origin, message()).with_source(x))
+    }
+}
+
+/// Runs something until it succeeds, like `if_err!`'s `retry`: each failure is logged along with what caused it, and
+/// retried or crashed on (with every cause) as the logger decides
+pub fn retry<T>(mut attempt: impl FnMut() -> Result<T>, mut logger: impl Logger) -> T {
+    loop {
+        let error = match attempt() { Ok(x) => return x, Err(x) => x };
+        match logger.error(Log::new(LogType::Failure, error.origin, &error.summary(), &[ErrorResponse::Retry, ErrorResponse::Crash, ErrorResponse::Panic])) {
+            ErrorResponse::Retry => continue,
+            ErrorResponse::Panic => panic!("unexpected error: {error:#?}"),
+            _ => return error.crash(logger),
+        }
+    }
+}
+
+/// `if_err!` for errors that are kept as the cause of an `Error` with a code (see `Context`), instead of being
+/// formatted into the message: `retry` retries it like `if_err!` does, `crash` crashes straight away
+#[macro_export]
+macro_rules! with_context {
+    (($logger:ident) [$origin:ident, $code:literal => $msg:tt] retry $expr:expr) => {
+        $crate::error::retry(|| $crate::error::Context::context($expr, $code, stringify!($origin), || format!$msg), $logger.hollow())
+    };
+
+    (($logger:ident) [$origin:ident, $code:literal => $msg:tt] $expr:block crash) => {
+        $crate::error::Context::context($expr, $code, stringify!($origin), || format!$msg).unwrap_or_else(|x| x.crash($logger.hollow()))
+    };
+}
+
+/// Remembers the error the process is crashing with
+pub fn record(error: Value) {
+    *FATAL.lock().unwrap_or_else(|x| x.into_inner()) = Some(error);
+}
+
+/// Remembers a fatal log as the error the process is crashing with, unless a structured one is already remembered
+pub fn record_log(log: &Log) {
+    let mut fatal = FATAL.lock().unwrap_or_else(|x| x.into_inner());
+    if fatal.is_none() {
+        *fatal = Some(json!({ "code": "fatal", "origin": log.origin, "message": log.message, "causes": [] }));
+    }
+}
+
+/// Prints the error the process is crashing with as json, if `--json` is used
+pub fn print_fatal() {
+    if !json::enabled() { return }
+    let fatal = FATAL.lock().unwrap_or_else(|x| x.into_inner()).take();
+    if let Some(error) = fatal { json::print(json!({ "error": error })) }
+}
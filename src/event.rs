@@ -0,0 +1,142 @@
+use chrono::{Local, NaiveDate};
+use lazy_db::*;
+use serde_json::json;
+use soulog::*;
+use crate::{archive::Archive, date, history, json, list, signal};
+
+/// How many days after the date of an exported entry the events it lists are
+pub const HORIZON: i64 = 30;
+
+/// Something coming up on a date that is counted down to (`diary-cli event add`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub title: String,
+    pub date: NaiveDate,
+}
+
+impl Event {
+    fn encode(&self) -> String {
+        format!("{}\t{}", self.date, self.title)
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let (date, title) = line.split_once('\t')?;
+        Some(Self {
+            title: title.to_string(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+        })
+    }
+
+    /// How long there is from a date until the event, like `in 12 days`, `tomorrow` or `3 days ago`
+    pub fn countdown(&self, from: NaiveDate) -> String {
+        match date::days_between(from, self.date) {
+            0 => String::from("today"),
+            1 => String::from("tomorrow"),
+            -1 => String::from("yesterday"),
+            days if days < 0 => format!("{} days ago", -days),
+            days => format!("in {days} days"),
+        }
+    }
+
+    pub fn to_json(&self, from: NaiveDate) -> serde_json::Value {
+        json!({ "title": self.title, "date": self.date.to_string(), "days": date::days_between(from, self.date) })
+    }
+}
+
+fn events_container(archive: &Archive, mut logger: impl Logger) -> LazyContainer {
+    let container = if_err!((logger) [Event, err => ("While loading archive events: {err:?}")] retry search_database!((archive.database()) /events));
+    if container.read_data("length").is_err() {
+        if_err!((logger) [Event, err => ("While initialising archive events: {err:?}")] retry write_container!((container) length = new_u16(0)));
+    } container
+}
+
+/// Reads every event in the archive, soonest first
+pub fn read(archive: &Archive, logger: impl Logger) -> Vec<Event> {
+    let mut events: Vec<Event> = list::read(|x| x.collect_string(), &events_container(archive, logger.hollow()), logger.hollow())
+        .iter()
+        .filter_map(|x| Event::decode(x))
+        .collect();
+    events.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.title.cmp(&b.title)));
+    events
+}
+
+/// The events from a date up to `days` after it, soonest first
+pub fn upcoming(events: &[Event], from: NaiveDate, days: i64) -> Vec<&Event> {
+    events.iter()
+        .filter(|x| (0..=days).contains(&date::days_between(from, x.date)))
+        .collect()
+}
+
+/// The `## Upcoming` list of the events in the days after the date of an exported entry, empty if there are none
+pub fn upcoming_section(events: &[Event], date: NaiveDate) -> String {
+    let upcoming = upcoming(events, date, HORIZON);
+    if upcoming.is_empty() { return String::new() }
+    let mut section = String::from("\n## Upcoming\n");
+    upcoming.iter().for_each(|x| section.push_str(&format!("- {} ({}, {})\n", x.title, x.countdown(date), x.date)));
+    section
+}
+
+/// Writes the events back into the archive, recording the change in its history
+fn write(archive: &Archive, events: &[Event], operation: &str, logger: impl Logger) {
+    Archive::backup_before_modification(logger.hollow());
+    let _critical = signal::critical(); // Writes the events and bumps the itver together
+    list::write(events, |file, x| LazyData::new_string(file, &x.encode()), &events_container(archive, logger.hollow()), logger.hollow());
+    archive.bump_itver(logger.hollow());
+    history::record(archive, operation, None, logger);
+}
+
+pub fn add_command(title: String, date: String, mut logger: impl Logger) {
+    let title = title.replace(['\t', '\n'], " ").trim().to_string();
+    if title.is_empty() {
+        log!((logger.error) Event("Events must have a title") as Fatal);
+        return logger.crash();
+    }
+    let Some(date) = date::parse_date(&date) else {
+        log!((logger.error) Event("'{date}' isn't a date like `2024-02-29` (or `today`, `yesterday` or `tomorrow`)") as Fatal);
+        return logger.crash();
+    };
+
+    let archive = Archive::load(logger.hollow());
+    let mut events = read(&archive, logger.hollow());
+    if events.iter().any(|x| x.title == title && x.date == date) {
+        log!((logger.vital) Event("Event '{title}' on {date} already exists; doing nothing") as Inconvenience);
+        return;
+    }
+    let event = Event { title, date };
+    let countdown = event.countdown(Local::now().date_naive());
+    events.push(event.clone());
+    write(&archive, &events, "event-add", logger.hollow());
+    log!((logger.vital) Event("Successfully added event '{}' on {} ({countdown})", event.title, event.date) as Log);
+}
+
+pub fn remove_command(title: String, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let events = read(&archive, logger.hollow());
+    let kept: Vec<Event> = events.iter().filter(|x| x.title != title).cloned().collect();
+    if kept.len() == events.len() {
+        log!((logger.error) Event("There is no event titled '{title}'") as Fatal);
+        return logger.crash();
+    }
+
+    write(&archive, &kept, "event-remove", logger.hollow());
+    log!((logger.vital) Event("Successfully removed event '{title}'") as Log);
+}
+
+/// Lists the upcoming events (and the ones that have passed with `all`) with how long is left until them
+pub fn list_command(all: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let today = Local::now().date_naive();
+    let events: Vec<Event> = read(&archive, logger.hollow()).into_iter()
+        .filter(|x| all || x.date >= today)
+        .collect();
+
+    if json::enabled() { return json::print(json!({ "events": events.iter().map(|x| x.to_json(today)).collect::<Vec<_>>() })) }
+    if events.is_empty() { return log!((logger.vital) Event("No upcoming events; add one with `diary-cli event add <title> <date>`") as Log) }
+    events.iter().for_each(|x| log_event(x, today, logger.hollow()));
+}
+
+/// Logs an event with its countdown
+pub fn log_event(event: &Event, today: NaiveDate, mut logger: impl Logger) {
+    log!((logger.vital) Event("{}", colour_format![cyan(&event.title), blue(": "), none(&event.countdown(today)), blue(" ("), none(&event.date.to_string()), blue(")")]) as Log);
+}
+
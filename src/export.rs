@@ -1,20 +1,105 @@
-use std::path::Path;
-use crate::{entry::{Entry, Section}, Scribe, scribe_write, archive::Archive, search, moc::{MOC, Collection}, sort::sort_uids};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use crate::{access::{self, Access}, backlinks, entry::{Entry, Section, format_date}, archived, dump, event, graph, html, logseq, incremental::{self, ExportState}, links::{self, LinkStyle, Targets}, manifest, template::{self, Templates}, transform, Scribe, scribe_write, archive::Archive, search, moc::{MOC, Collection, walk_collections}, sort::sort_uids, config::Config, tags::replace_tags};
 use soulog::*;
 
-pub fn export_md(strict: bool, tags: Option<Vec<String>>, path: String, mut logger: impl Logger) {
+/// The format the archive is exported as
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// An `Obsidian.md` vault of markdown files
+    #[default]
+    Md,
+    /// A static html site
+    Html,
+    /// A Logseq graph of `journals/` pages (one per day) with a page per tag and moc
+    Logseq,
+    /// A single `archive.json` dump of every item and the order stacks, importable with `import json`
+    Json,
+    /// An `archive.dot` Graphviz graph of the entries, mocs and tags and how they relate
+    Dot,
+    /// An `archive.graphml` graph (for Gephi and the like) of the entries, mocs and tags and how they relate
+    Graphml,
+}
+
+/// How the files of a markdown vault are arranged into folders
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum ExportLayout {
+    /// Every file in the root of the vault
+    #[default]
+    Flat,
+    /// Entries in `<year>/<month>/` folders (mocs stay in the root)
+    Date,
+    /// Entries and mocs in `tags/<first tag>/` folders
+    Tag,
+}
+
+impl ExportLayout {
+    /// The folder (relative to the vault) an item with a date and tags is exported into
+    pub fn item_dir(&self, date: Option<&[u16; 3]>, tags: &[String]) -> PathBuf {
+        match (self, date) {
+            (Self::Flat, _) => PathBuf::new(),
+            (Self::Date, Some(date)) => PathBuf::from(format!("{}/{:02}", date[2], date[1])),
+            (Self::Date, None) => PathBuf::new(),
+            (Self::Tag, _) => Path::new("tags").join(tags.iter().map(|x| x.trim_matches('/')).find(|x| !x.is_empty()).unwrap_or("untagged")),
+        }
+    }
+}
+
+/// A markdown link from a file in one folder of the vault to a file (without its extension) in another
+pub fn relative_link(from: &Path, to: &Path) -> String {
+    let (from, to): (Vec<_>, Vec<_>) = (from.components().collect(), to.components().collect());
+    let common = from.iter().zip(to.iter()).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat_n(String::from(".."), from.len() - common)
+        .chain(to[common..].iter().map(|x| x.as_os_str().to_string_lossy().to_string()))
+        .collect();
+    parts.join("/")
+}
+
+/// Options that change how the archive is exported
+#[derive(Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// The built-in theme of html exports
+    pub theme: Option<String>,
+    /// Shows the contents of time capsule entries that haven't unlocked yet
+    pub force_unlock: bool,
+    /// Exports each collection of a moc into its own file linked from the moc
+    pub split_collections: bool,
+    /// Exports every entry chronologically into a single `diary.md` book instead of a vault
+    pub single_file: bool,
+    /// The templates of markdown exports
+    pub templates: Templates,
+    /// Skips writing files that are unchanged since the last incremental export to the same path
+    pub incremental: bool,
+    /// Exports archived items and lists them in collections too
+    pub include_archived: bool,
+    /// How the files of a markdown vault are arranged into folders
+    pub layout: ExportLayout,
+    /// Deletes the files of the previous export into the vault that this export didn't write
+    pub prune: bool,
+    /// How files of the vault link to each other
+    pub links: LinkStyle,
+    /// Only exports the items readers with this access can see (and lists them in collections)
+    pub access: Option<Access>,
+    /// Writes a `tags/<tag>.md` page listing the entries of each tag, and a `tags.md` index of them
+    pub tag_pages: bool,
+    /// Appends the events coming up in the month after each entry's date to its note
+    pub events: bool,
+}
+
+pub fn export_md(strict: bool, tags: Option<Vec<String>>, path: String, options: ExportOptions, mut logger: impl Logger) {
     log!((logger) Export("Exporting archive to path '{path}'..."));
     let archive = Archive::load(logger.hollow());
 
     // Get entries and mocs
-    let mut entries = match &tags {
+    let entries: Vec<Entry> = match &tags {
         Some(x) => 
             (if strict { search::search(x, archive.list_entries(logger.hollow()), logger.hollow()) }
             else { search::search_strict(x, archive.list_entries(logger.hollow()), logger.hollow()) })
                 .into_iter().map(|x| archive.get_entry(x, logger.hollow()).unwrap()).collect(),
         None => archive.list_entries(logger.hollow()),
     };
-    let mut mocs = match &tags {
+    let mocs: Vec<MOC> = match &tags {
         Some(x) => 
             (if strict { search::search(x, archive.list_mocs(logger.hollow()), logger.hollow()) }
             else { search::search_strict(x, archive.list_mocs(logger.hollow()), logger.hollow()) })
@@ -22,134 +107,555 @@ pub fn export_md(strict: bool, tags: Option<Vec<String>>, path: String, mut logg
         None => archive.list_mocs(logger.hollow()),
     };
 
+    if options.access.is_some() && Config::get().access.rules.is_empty() {
+        log!((logger.vital) Export("No tags are given access in config.toml's `[access]`, so every item has the default access") as Warning);
+    }
+    let mut entries = access::visible(archived::visible(entries, options.include_archived, logger.hollow()), options.access, logger.hollow());
+    let mut mocs = access::visible(archived::visible(mocs, options.include_archived, logger.hollow()), options.access, logger.hollow());
+
     // Export em
     let path = Path::new(&path);
-    entries.iter_mut().for_each(|x| export_entry(path, x, logger.hollow()));
-    mocs.iter_mut().for_each(|x| export_moc(path, x, &archive, logger.hollow()));
+    if options.format != ExportFormat::Md && options.incremental {
+        log!((logger.vital) Export("Incremental exports only apply to markdown vaults; exporting everything") as Inconvenience);
+    }
+    if options.format != ExportFormat::Md && options.prune {
+        log!((logger.vital) Export("Pruning only applies to markdown vaults; not pruning anything") as Inconvenience);
+    }
+    if (options.format != ExportFormat::Md || options.single_file) && options.tag_pages {
+        log!((logger.vital) Export("Tag pages only apply to markdown vaults; not writing any") as Inconvenience);
+    }
+    if (options.format != ExportFormat::Md || options.single_file) && options.events {
+        log!((logger.vital) Export("Events only apply to markdown vaults; not listing any") as Inconvenience);
+    }
+    if options.format != ExportFormat::Md && options.layout != ExportLayout::Flat {
+        log!((logger.vital) Export("Layouts only apply to markdown vaults; exporting with the format's own layout") as Inconvenience);
+    }
+    if options.format == ExportFormat::Json {
+        dump::export_json(path, entries, mocs, &archive, logger.hollow());
+        return log!((logger.vital) Export("Successfully dumped all specified items into '{}'", path.join("archive.json").to_string_lossy()) as Log);
+    }
+    if options.format == ExportFormat::Dot || options.format == ExportFormat::Graphml {
+        let file = graph::export_graph(path, entries, mocs, &archive, options.format == ExportFormat::Graphml, logger.hollow());
+        return log!((logger.vital) Export("Successfully exported the graph of all specified items into '{}'", file.to_string_lossy()) as Log);
+    }
+    if options.format == ExportFormat::Html {
+        html::export_html(path, entries, mocs, &archive, &options, logger.hollow());
+        return log!((logger.vital) Export("Successfully exported all specified items as html; open '{}' to browse them", path.join("index.html").to_string_lossy()) as Log);
+    }
+    if options.format == ExportFormat::Logseq {
+        logseq::export_logseq(path, entries, mocs, &archive, &options, logger.hollow());
+        return log!((logger.vital) Export("Successfully exported all specified items as a Logseq graph into '{}'", path.to_string_lossy()) as Log);
+    }
+    if options.single_file {
+        export_book(path, entries, &options, logger.hollow());
+        return log!((logger.vital) Export("Successfully exported all specified entries into '{}'", path.join("diary.md").to_string_lossy()) as Log);
+    }
+    let mut state = if options.incremental { ExportState::load(&archive, path, logger.hollow()) } else { ExportState::default() };
+    state.targets = link_targets(&mut entries, &mut mocs, options.layout, logger.hollow());
+    state.mocs = memberships(&mut mocs, &state.targets, &archive, &options, logger.hollow());
+    state.backlinks = backlinks::backlink_map(&mut entries, logger.hollow());
+    if options.events { state.events = event::read(&archive, logger.hollow()) }
+    let previous = manifest::Manifest::read(path, logger.hollow()).map(|x| x.files).unwrap_or_default();
+    entries.iter_mut().for_each(|x| export_entry(path, x, &options, &state, logger.hollow()));
+    mocs.iter_mut().for_each(|x| export_moc(path, x, &archive, &options, &state, logger.hollow()));
+    if options.tag_pages { export_tag_pages(path, &mut entries, &options, &state, logger.hollow()) }
 
+    let manifest = manifest::Manifest {
+        archive: archive.uid().to_string(),
+        itver: archive.itver,
+        filters: manifest::Filters { tags, strict, include_archived: options.include_archived, force_unlock: options.force_unlock, access: options.access },
+        files: state.files.take().into_iter().map(|(file, hash)| (file, Some(hash))).collect(),
+    };
+    if options.prune {
+        let pruned = manifest::prune(path, &previous, &manifest.file_names(), logger.hollow());
+        log!((logger.vital) Export("Pruned {pruned} stale files from the previous export") as Log);
+    }
+    manifest.write(path, logger.hollow());
+
+    if state.is_incremental() {
+        return log!((logger.vital) Export("Successfully exported all specified items ({} written, {} unchanged)", state.written.get(), state.skipped.get()) as Log);
+    }
     log!((logger.vital) Export("Successfully exported all specified items") as Log);
 }
 
-pub fn export_entry(path: &Path, entry: &mut Entry, mut logger: impl Logger) {
+pub fn export_entry(path: &Path, entry: &mut Entry, options: &ExportOptions, state: &ExportState, mut logger: impl Logger) {
     log!((logger) Export("Exporting entry of uid '{}'...", entry.uid));
-    let mut scribe = Scribe::new(path.join(&entry.uid).with_extension("md"), logger.hollow());
-
-    // Tags, title and description
+    // Tags, title and date
     let date = *entry.date(logger.hollow());
-    scribe_tags_n_date(entry.tags(logger.hollow()), &date, &mut scribe);
-    scribe_write!((scribe) "# ", entry.title(logger.hollow()), "\n");
-    scribe.write_line("---");
-    scribe_write!((scribe) "**Description:** ", entry.description(logger.hollow()), "\n\n");
+    let tags = entry.tags(logger.hollow()).clone();
+    let dir = options.layout.item_dir(Some(&date), &tags);
+    let file = dir.join(Path::new(&entry.uid).with_extension("md")).to_string_lossy().to_string();
+    let title = entry.title(logger.hollow()).clone();
+    let frontmatter = frontmatter(&tags, &title, Some(&date), state.mocs.get(&entry.uid).map(Vec::as_slice).unwrap_or_default());
+    let uid = entry.uid.clone();
+    let day = crate::stats::to_naive(&date);
+    let date = format_date(&date);
+
+    // Time capsules only export their title and date until they unlock
+    if !options.force_unlock && entry.is_locked(logger.hollow()) {
+        let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+        log!((logger) Export("Entry of uid '{uid}' is a time capsule that unlocks on {unlock_date}; only exporting its title"));
+        let text = template::render(&options.templates.capsule, &[
+            ("frontmatter", &frontmatter), ("uid", &uid), ("title", &title), ("date", &date), ("unlock_date", &unlock_date),
+        ]);
+        incremental::write_file(path, &file, &text, state, logger.hollow());
+        entry.clear_cache();
+        return;
+    }
+    let description = entry.description(logger.hollow()).clone();
 
     // Notes
-    let notes = entry.notes(logger.hollow());
-    let mut notes_header_written_to: bool = false;
-    if notes.len() > 0 {
-        notes_header_written_to = true;
-        scribe.write_line("## Notes");
-        notes.iter().for_each(|x| scribe_write!((scribe) "- ", x, "\n"));  
+    let mut notes = String::new();
+    let entry_notes = entry.notes(logger.hollow());
+    if !entry_notes.is_empty() {
+        notes.push_str("## Notes\n");
+        entry_notes.iter().for_each(|x| notes.push_str(&format!("- {x}\n")));
     }
 
     // Sections' notes
     entry.sections(logger.hollow()).iter_mut().for_each(|section| {
         let title = section.title(logger.hollow()).clone();
-        let notes = section.notes(logger.hollow());
-        if notes.len() > 0 {
-            if !notes_header_written_to { scribe.write_line("## Notes"); notes_header_written_to = true; }
-            scribe_write!((scribe) "- #### ", &title, "\n");
-            notes.iter().for_each(|x| scribe_write!((scribe) "\t- ", x, "\n"));
+        let section_notes = section.notes(logger.hollow());
+        if !section_notes.is_empty() {
+            if notes.is_empty() { notes.push_str("## Notes\n") }
+            notes.push_str(&format!("- #### {title}\n"));
+            section_notes.iter().for_each(|x| notes.push_str(&format!("\t- {x}\n")));
         } section.clear_cache();
     });
-    scribe.write_line("---");
 
     // Sections
-    entry.sections(logger.hollow()).iter_mut().for_each(|x| export_section_content(&mut scribe, x, logger.hollow()));
+    let references = Some((dir.as_path(), &state.targets, options.links));
+    let sections: String = entry.sections(logger.hollow()).iter_mut()
+        .map(|x| section_content(x, &tags, references, logger.hollow()) + &export_attachments(path, x, (&uid, &dir), options, state, logger.hollow()))
+        .collect();
+
+    // Links to and from other items
+    let entry_links = entry.links(logger.hollow()).clone();
+    let links = link_list("Links", entry_links.iter().map(String::as_str), &dir, options.links, state);
+    let backlinks = link_list("Backlinks", state.backlinks.get(&uid).into_iter().flatten().map(|x| x.uid.as_str()), &dir, options.links, state);
 
+    // Special entries (letters, poems, trip reports...) can be laid out by a template of their own
+    let name = entry.template(logger.hollow()).clone().or_else(|| Config::get().export_template(&tags).cloned());
+    let text = template::render(options.templates.entry(name.as_deref(), &uid, logger.hollow()), &[
+        ("frontmatter", &frontmatter), ("uid", &uid), ("title", &title), ("date", &date),
+        ("description", &description), ("notes", &notes), ("sections", &sections),
+        ("links", &links), ("backlinks", &backlinks),
+    ]);
+    let text = match day {
+        Some(x) => text + &event::upcoming_section(&state.events, x),
+        None => text,
+    };
+    incremental::write_file(path, &file, &text, state, logger.hollow());
     entry.clear_cache();
 }
 
-pub fn export_moc(path: &Path, moc: &mut MOC, archive: &Archive, mut logger: impl Logger) {
-    log!((logger) Export("Exporting moc of uid '{}'...", moc.uid));
-    let mut scribe = Scribe::new(path.join(&moc.uid).with_extension("md"), logger.hollow());
+/// Turns a heading into the anchor markdown renderers link it by (`## Hello, World!` into `hello-world`)
+pub fn heading_anchor(heading: &str) -> String {
+    heading.trim().to_lowercase()
+        .chars()
+        .filter_map(|x| match x {
+            ' ' | '-' => Some('-'),
+            x if x.is_alphanumeric() || x == '_' => Some(x),
+            _ => None,
+        }).collect()
+}
+
+/// Exports entries chronologically into a single markdown book with a table of contents
+fn export_book(path: &Path, entries: Vec<Entry>, options: &ExportOptions, mut logger: impl Logger) {
+    log!((logger) Export("Exporting {} entries into a single book...", entries.len()));
+    let uids: Vec<String> = entries.iter().map(|x| x.uid.clone()).collect();
+    let mut by_uid: std::collections::HashMap<String, Entry> = entries.into_iter().map(|x| (x.uid.clone(), x)).collect();
+    let mut entries: Vec<Entry> = sort_uids(&uids, false, logger.hollow())
+        .iter()
+        .filter_map(|x| by_uid.remove(x))
+        .collect();
 
+    let headings: Vec<String> = entries.iter_mut()
+        .map(|x| {
+            let date = format_date(x.date(logger.hollow()));
+            format!("{} ({date})", x.title(logger.hollow()))
+        })
+        .collect();
+
+    let mut scribe = Scribe::new(path.join("diary.md"), logger.hollow());
+    scribe.write_line("# Diary");
+    scribe.write_line("## Contents");
+    headings.iter().for_each(|x| scribe_write!((scribe) "- [", x, "](#", &heading_anchor(x), ")\n"));
+    let anchors: HashMap<String, (&str, String)> = entries.iter().zip(headings.iter())
+        .map(|(entry, heading)| (entry.uid.clone(), (heading.as_str(), heading_anchor(heading))))
+        .collect();
+    let cross_reference = |uid: &str, label: Option<&str>| anchors.get(uid).map(|(heading, anchor)| format!("[{}](#{anchor})", label.unwrap_or(heading)));
+
+    for (entry, heading) in entries.iter_mut().zip(headings.iter()) {
+        log!((logger) Export("Writing entry of uid '{}' into the book...", entry.uid));
+        scribe_write!((scribe) "\n---\n## ", heading, "\n");
+
+        if !options.force_unlock && entry.is_locked(logger.hollow()) {
+            let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+            scribe_write!((scribe) "> *This entry is a time capsule sealed until ", &unlock_date, "*\n");
+            entry.clear_cache();
+            continue;
+        }
+
+        scribe_write!((scribe) "*", entry.description(logger.hollow()), "*\n\n");
+        entry.notes(logger.hollow()).iter().for_each(|x| scribe_write!((scribe) "- ", x, "\n"));
+        let tags = entry.tags(logger.hollow()).clone();
+        entry.sections(logger.hollow()).iter_mut().for_each(|x| scribe.write(&links::render_cross_references(&section_content(x, &tags, None, logger.hollow()), cross_reference)));
+        entry.clear_cache();
+    }
+}
+
+pub fn export_moc(path: &Path, moc: &mut MOC, archive: &Archive, options: &ExportOptions, state: &ExportState, mut logger: impl Logger) {
+    log!((logger) Export("Exporting moc of uid '{}'...", moc.uid));
     // Tags, title and description
-    scribe_tags(moc.tags(logger.hollow()), &mut scribe);
-    scribe_write!((scribe) "# ", moc.title(logger.hollow()), "\n");
-    scribe.write_line("---");
-    scribe_write!((scribe) "**Description:** ", moc.description(logger.hollow()), "\n\n");
+    let tags = moc.tags(logger.hollow()).clone();
+    let dir = options.layout.item_dir(None, &tags);
+    let file = dir.join(Path::new(&moc.uid).with_extension("md")).to_string_lossy().to_string();
+    let title = moc.title(logger.hollow()).clone();
+    let frontmatter = frontmatter(&tags, &title, None, state.mocs.get(&moc.uid).map(Vec::as_slice).unwrap_or_default());
+    let description = moc.description(logger.hollow()).clone();
+    let uid = moc.uid.clone();
 
     // Notes
-    let notes = moc.notes(logger.hollow());
-    if notes.len() > 0 {
-        scribe.write_line("## Notes");
-        notes.iter().for_each(|x| scribe_write!((scribe) "- ", x, "\n"));  
+    let mut notes = String::new();
+    let moc_notes = moc.notes(logger.hollow());
+    if !moc_notes.is_empty() {
+        notes.push_str("## Notes\n");
+        moc_notes.iter().for_each(|x| notes.push_str(&format!("- {x}\n")));
     }
 
-    // Collections' notes
-    moc.collections(logger.hollow()).iter_mut().for_each(|collection| {
+    // Collections' notes (indented by how deeply the collection is nested)
+    walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+        let indent = "\t".repeat(at.len() - 1);
         let title = collection.title(logger.hollow()).clone();
-        let notes = collection.notes(logger.hollow());
-        if notes.len() > 0 {
-            scribe_write!((scribe) "- #### ", &title, "\n");
-            notes.iter().for_each(|x| scribe_write!((scribe) "\t- ", x, "\n"));
+        let collection_notes = collection.notes(logger.hollow());
+        if !collection_notes.is_empty() {
+            notes.push_str(&format!("{indent}- #### {title}\n"));
+            collection_notes.iter().for_each(|x| notes.push_str(&format!("{indent}\t- {x}\n")));
         } collection.clear_cache();
-    });
-    scribe.write_line("---");
+    }, logger.hollow());
 
     // Collections
-    moc.collections(logger.hollow()).iter_mut().for_each(|x| export_collection_content(&mut scribe, x, archive, logger.hollow()));
+    let mut collections = String::new();
+    if options.split_collections {
+        collections.push_str("## Collections\n");
+        moc.collections(logger.hollow()).iter_mut().enumerate().for_each(|(i, x)| {
+            let file = format!("{uid}-collection-{i}");
+            collections.push_str(&format!("{}. {}\n", i + 1, options.links.link(&dir, &dir.join(&file), x.title(logger.hollow()))));
+            export_collection((path, &dir, &file), x, (&uid, &title, &tags), archive, options, state, logger.hollow());
+        });
+    } else {
+        moc.collections(logger.hollow()).iter_mut().for_each(|x| collections.push_str(&collection_content(x, 0, &dir, archive, options, logger.hollow())));
+    }
 
+    let backlinks = link_list("Backlinks", state.backlinks.get(&uid).into_iter().flatten().map(|x| x.uid.as_str()), &dir, options.links, state);
+    let text = template::render(&options.templates.moc, &[
+        ("frontmatter", &frontmatter), ("uid", &uid), ("title", &title),
+        ("description", &description), ("notes", &notes), ("collections", &collections),
+        ("backlinks", &backlinks),
+    ]);
+    incremental::write_file(path, &file, &text, state, logger.hollow());
     moc.clear_cache();
 }
 
-fn export_collection_content(scribe: &mut Scribe<impl Logger>, collection: &mut Collection, archive: &Archive, logger: impl Logger) {
-    let tags = collection.include(logger.hollow());
+/// Exports a collection into its own file that links back to its moc (`uid`, `title` & `tags`),
+/// continuing into `<file>-<page>` files if it is paginated; `to` is the vault, the moc's folder in it and the file's name
+fn export_collection(to: (&Path, &Path, &str), collection: &mut Collection, moc: (&str, &str, &[String]), archive: &Archive, options: &ExportOptions, state: &ExportState, mut logger: impl Logger) {
+    let (path, dir, file) = to;
+    log!((logger) Export("Exporting collection '{file}'..."));
+    let (uid, moc_title, tags) = moc;
+    let items = collection_items(collection, dir, archive, options, logger.hollow());
+    let pages = paginate(&items, *collection.paginate(logger.hollow()));
+    let page_file = |page: usize| if page == 0 { file.to_string() } else { format!("{file}-{}", page + 1) };
+    let moc_link = options.links.link(dir, &dir.join(uid), moc_title);
+
+    for (page, page_items) in pages.iter().enumerate() {
+        let title = format!("{}{}", collection.title(logger.hollow()), page_suffix(page, pages.len()));
+        let frontmatter = frontmatter(tags, &title, None, &[]);
+        let mut navigation = String::new();
+        if pages.len() > 1 {
+            let previous = (page > 0).then(|| options.links.link(dir, &dir.join(page_file(page - 1)), "Previous"));
+            let next = (page + 1 < pages.len()).then(|| options.links.link(dir, &dir.join(page_file(page + 1)), "Next"));
+            navigation = format!("{}\n", previous.into_iter().chain(next).collect::<Vec<_>>().join(" | "));
+        }
+
+        let mut notes = String::new();
+        let collection_notes = collection.notes(logger.hollow());
+        if page == 0 && !collection_notes.is_empty() {
+            notes.push_str("## Notes\n");
+            collection_notes.iter().for_each(|x| notes.push_str(&format!("- {x}\n")));
+            notes.push_str("---\n");
+        }
+
+        let mut items: String = page_items.iter().map(|x| format!("{x}\n")).collect();
+        if page + 1 == pages.len() { items.push_str(&sub_collections(collection, 0, dir, archive, options, logger.hollow())) }
+        let text = template::render(&options.templates.collection, &[
+            ("frontmatter", &frontmatter), ("title", &title), ("moc_uid", uid), ("moc_title", moc_title), ("moc_link", &moc_link),
+            ("navigation", &navigation), ("notes", &notes), ("items", &items),
+            ("page", &(page + 1).to_string()), ("pages", &pages.len().to_string()),
+        ]);
+        incremental::write_file(path, &dir.join(format!("{}.md", page_file(page))).to_string_lossy(), &text, state, logger.hollow());
+    }
+
+    collection.clear_cache();
+}
+
+/// Splits a collection's items into pages of the collection's `paginate` size
+fn paginate(items: &[String], size: Option<u16>) -> Vec<&[String]> {
+    match size {
+        Some(size) if items.len() > size as usize => items.chunks(size as usize).collect(),
+        _ => vec![items],
+    }
+}
+
+/// The ` (page/pages)` suffix of a paginated collection's title
+fn page_suffix(page: usize, pages: usize) -> String {
+    if pages > 1 { format!(" ({}/{pages})", page + 1) }
+    else { String::new() }
+}
+
+/// Renders a collection's items under a heading for each of its pages, followed by its sub-collections a heading level
+/// deeper; `depth` is how deeply it is nested (`0` for a moc's own collections, which get `##` headings)
+fn collection_content(collection: &mut Collection, depth: usize, dir: &Path, archive: &Archive, options: &ExportOptions, logger: impl Logger) -> String {
+    let items = collection_items(collection, dir, archive, options, logger.hollow());
+    let sub_collections = sub_collections(collection, depth + 1, dir, archive, options, logger.hollow());
+    let heading = "#".repeat((depth + 2).min(6));
+    let title = collection.title(logger.hollow()).clone();
+    if items.is_empty() {
+        return if sub_collections.is_empty() { String::new() } else { format!("{heading} {title}\n{sub_collections}") };
+    }
 
-    let moc_uids = search::search_strict(tags, archive.list_mocs(logger.hollow()), logger.hollow());
-    let mut entry_uids = search::search_strict(tags, archive.list_entries(logger.hollow()), logger.hollow());
+    let mut result = String::new();
+    let pages = paginate(&items, *collection.paginate(logger.hollow()));
+    for (page, page_items) in pages.iter().enumerate() {
+        result.push_str(&format!("{heading} {title}{}\n", page_suffix(page, pages.len())));
+        if pages.len() > 1 {
+            let previous = (page > 0).then(|| format!("[[#{title}{}|Previous]]", page_suffix(page - 1, pages.len())));
+            let next = (page + 1 < pages.len()).then(|| format!("[[#{title}{}|Next]]", page_suffix(page + 1, pages.len())));
+            result.push_str(&format!("{}\n\n", previous.into_iter().chain(next).collect::<Vec<_>>().join(" | ")));
+        }
+        page_items.iter().for_each(|x| result.push_str(&format!("{x}\n")));
+    }
+    result.push_str(&sub_collections);
+    result
+}
 
-    if moc_uids.is_empty() && entry_uids.is_empty() { return; }
-    scribe_write!((scribe) "## ", collection.title(logger.hollow()), "\n");
+/// Renders the sub-collections of a collection `depth` levels deep
+fn sub_collections(collection: &mut Collection, depth: usize, dir: &Path, archive: &Archive, options: &ExportOptions, logger: impl Logger) -> String {
+    let result = collection.collections(logger.hollow()).iter_mut()
+        .map(|x| collection_content(x, depth, dir, archive, options, logger.hollow()))
+        .collect();
+    collection.collections = None;
+    result
+}
 
-    entry_uids = sort_uids(&entry_uids, logger.hollow()).to_vec(); // Sorting stuff
+/// Renders the list items of the mocs and entries included in a collection
+/// (linked to from files in the `from` folder of the vault)
+fn collection_items(collection: &mut Collection, from: &Path, archive: &Archive, options: &ExportOptions, logger: impl Logger) -> Vec<String> {
+    if collection.groups_only(logger.hollow()) { return Vec::new() }
+    let mocs = access::visible(archived::visible(archive.list_mocs(logger.hollow()), options.include_archived, logger.hollow()), options.access, logger.hollow());
+    let entries = access::visible(archived::visible(archive.list_entries(logger.hollow()), options.include_archived, logger.hollow()), options.access, logger.hollow());
+    let moc_uids = collection.search_mocs(mocs, logger.hollow());
+    let moc_uids = collection.arrange(moc_uids, |x| x, logger.hollow());
+    let mut entry_uids = collection.search_entries(entries, logger.hollow());
+    entry_uids = sort_uids(&entry_uids, false, logger.hollow()).to_vec(); // Sorting stuff
+    entry_uids = collection.newest(&entry_uids, logger.hollow()).to_vec();
+    entry_uids = collection.arrange(entry_uids, |x| x, logger.hollow());
 
+    let mut items = Vec::with_capacity(moc_uids.len() + entry_uids.len());
     moc_uids.into_iter()
         .map(|x| archive.get_moc(x, logger.hollow()).unwrap())
         .enumerate()
-        .for_each(|(i, mut entry)| {
-            scribe_write!((scribe) &(i + 1).to_string(), ". \\[[", entry.title(logger.hollow()), "](", &entry.uid, ")\\] ", entry.description(logger.hollow()), &format!(" `notes: {:?}`\n", entry.notes(logger.hollow())));
-            entry.clear_cache();
+        .for_each(|(i, mut moc)| {
+            let (title, description, notes) = (moc.title(logger.hollow()).clone(), moc.description(logger.hollow()).clone(), moc.notes(logger.hollow()).clone());
+            let link = options.links.link(from, &options.layout.item_dir(None, moc.tags(logger.hollow())).join(&moc.uid), &title);
+            items.push(format!("{}. \\[{link}\\] {description} `notes: {notes:?}`", i + 1));
+            moc.clear_cache();
         });
 
     entry_uids.into_iter()
         .map(|x| archive.get_entry(x, logger.hollow()).unwrap())
         .enumerate()
         .for_each(|(i, mut entry)| {
-            scribe_write!((scribe) &(i + 1).to_string(), ". \\[[", entry.title(logger.hollow()), "](", &entry.uid, ")\\] ", entry.description(logger.hollow()), &format!(" `notes: {:?}`\n", entry.notes(logger.hollow())));
+            let title = entry.title(logger.hollow()).clone();
+            let date = *entry.date(logger.hollow());
+            let link = options.links.link(from, &options.layout.item_dir(Some(&date), entry.tags(logger.hollow())).join(&entry.uid), &title);
+            if !options.force_unlock && entry.is_locked(logger.hollow()) {
+                items.push(format!("{}. \\[{link}\\] *(time capsule)*", i + 1));
+                return entry.clear_cache();
+            }
+            let (description, notes) = (entry.description(logger.hollow()).clone(), entry.notes(logger.hollow()).clone());
+            items.push(format!("{}. \\[{link}\\] {description} `notes: {notes:?}`", i + 1));
             entry.clear_cache();
         });
+
+    items
 }
 
-fn export_section_content(scribe: &mut Scribe<impl Logger>, section: &mut Section, logger: impl Logger) {
-    scribe_write!((scribe) "### ", section.title(logger.hollow()), "\n");
-    let content = section.content(logger.hollow()).trim_end_matches('\n').split('\n');
-    content.for_each(|x| {
-        scribe_write!((scribe) "> ", x, "\n");
-    });
+/// Renders a section's title and its (transformed) content as a quote, rewriting `references` to other items
+/// (from the folder of the entry) into links
+fn section_content(section: &mut Section, tags: &[String], references: Option<(&Path, &Targets, LinkStyle)>, logger: impl Logger) -> String {
+    let mut result = format!("### {}\n", section.title(logger.hollow()));
+    let mut content = transform::apply(&Config::get().export_transforms, section.content(logger.hollow()), tags);
+    if let Some((from, targets, style)) = references { content = links::rewrite_references(&content, from, targets, style) }
+    content.trim_end_matches('\n').split('\n').for_each(|x| result.push_str(&format!("> {x}\n")));
     section.clear_cache();
+    result
 }
 
-fn scribe_tags(tags: &[String], scribe: &mut Scribe<impl Logger>) {
-    scribe.write_line("---");
-    scribe.write("tags:\n  - obsidian-md\n  - diary-cli\n");
-    tags.iter().for_each(|x| scribe_write!((scribe) "  - ", x, "\n"));
-    scribe.write_line("---");
+/// Exports a section's attachments into the `assets/<uid>/` folder of the vault, returning the embeds of them
+/// for the section of the entry (in the `dir` folder) they're from
+fn export_attachments(path: &Path, section: &mut Section, (uid, dir): (&str, &Path), options: &ExportOptions, state: &ExportState, mut logger: impl Logger) -> String {
+    let names = section.attachments(logger.hollow()).clone();
+    let mut result = String::new();
+    for (i, name) in names.iter().enumerate() {
+        log!((logger) Export("Exporting attachment '{name}' of entry '{uid}'..."));
+        let file = Path::new("assets").join(uid).join(name);
+        incremental::write_file(path, &file.to_string_lossy(), section.attachment(i, logger.hollow()), state, logger.hollow());
+        result.push_str(&format!("> {}\n", options.links.embed(dir, &file, name)));
+    }
+    section.clear_cache();
+    result
 }
 
-fn scribe_tags_n_date(tags: &[String], date: &[u16; 3], scribe: &mut Scribe<impl Logger>) {
-    scribe.write_line("---");
-    scribe.write("tags:\n  - obsidian-md\n  - diary-cli\n");
-    tags.iter().for_each(|x| scribe_write!((scribe) "  - ", x, "\n"));
-    scribe.write(&format!("date: {0}-{1}-{2}\n", date[2], date[1], date[0]));
-    scribe.write_line("---");
-}
\ No newline at end of file
+/// Renders a `## heading` list linking to the exported items of `uids` from a file in the `from` folder;
+/// nothing if none of them are exported
+fn link_list<'a>(heading: &str, uids: impl Iterator<Item = &'a str>, from: &Path, style: LinkStyle, state: &ExportState) -> String {
+    let items: String = uids.filter_map(|x| state.targets.get(x))
+        .map(|(title, file)| format!("- {}\n", style.link(from, file, title)))
+        .collect();
+    if items.is_empty() { items } else { format!("## {heading}\n{items}") }
+}
+
+/// The files and titles of the exported items, which references in section content link to
+fn link_targets(entries: &mut [Entry], mocs: &mut [MOC], layout: ExportLayout, logger: impl Logger) -> Targets {
+    let mut targets = Targets::with_capacity(entries.len() + mocs.len());
+    for entry in entries.iter_mut() {
+        let date = *entry.date(logger.hollow());
+        let dir = layout.item_dir(Some(&date), entry.tags(logger.hollow()));
+        targets.insert(entry.uid.clone(), (entry.title(logger.hollow()).clone(), dir.join(&entry.uid)));
+        entry.clear_cache();
+    }
+    for moc in mocs.iter_mut() {
+        let dir = layout.item_dir(None, moc.tags(logger.hollow()));
+        targets.insert(moc.uid.clone(), (moc.title(logger.hollow()).clone(), dir.join(&moc.uid)));
+        moc.clear_cache();
+    } targets
+}
+
+/// The wikilinks to the exported mocs each item appears in (through any of their collections), by uid;
+/// frontmatter only supports wikilinks, so they are used whatever the link style
+fn memberships(mocs: &mut [MOC], targets: &Targets, archive: &Archive, options: &ExportOptions, logger: impl Logger) -> HashMap<String, Vec<String>> {
+    let mut result: HashMap<String, Vec<String>> = HashMap::new();
+    for moc in mocs.iter_mut() {
+        let uid = moc.uid.clone();
+        let (title, file) = &targets[&uid];
+        let link = LinkStyle::Wiki.link(Path::new(""), file, title);
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, _| {
+            if collection.groups_only(logger.hollow()) { return collection.clear_cache() }
+            let entries = access::visible(archived::visible(archive.list_entries(logger.hollow()), options.include_archived, logger.hollow()), options.access, logger.hollow());
+            let others = access::visible(archived::visible(archive.list_mocs(logger.hollow()), options.include_archived, logger.hollow()), options.access, logger.hollow());
+            let uids = collection.search_entries(entries, logger.hollow()).into_iter()
+                .chain(collection.search_mocs(others, logger.hollow()))
+                .filter(|x| *x != uid);
+            for uid in uids {
+                let links = result.entry(uid).or_default();
+                if !links.contains(&link) { links.push(link.clone()) }
+            }
+            collection.clear_cache();
+        }, logger.hollow());
+        moc.clear_cache();
+    } result
+}
+
+/// The entries (by uid, in the order given) of each tag, including those of its sub-tags; every parent of a nested
+/// tag (`travel` of `travel/japan`) has entries through it
+pub fn tag_index<'a>(entries: impl IntoIterator<Item = (&'a str, &'a [String])>) -> BTreeMap<String, Vec<String>> {
+    let mut index: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (uid, tags) in entries {
+        let mut seen: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|x| x.trim_matches('/')).filter(|x| !x.is_empty()) {
+            let parts: Vec<&str> = tag.split('/').collect();
+            for tag in (1..=parts.len()).map(|x| parts[..x].join("/")) {
+                if !seen.contains(&tag) { seen.push(tag) }
+            }
+        }
+        seen.into_iter().for_each(|x| index.entry(x).or_default().push(uid.to_string()));
+    } index
+}
+
+/// Writes a page per (export) tag listing its entries chronologically with their dates and descriptions, and a
+/// `tags.md` index of every tag, for browsing the vault by tag without `Obsidian.md`'s tag pane
+fn export_tag_pages(path: &Path, entries: &mut [Entry], options: &ExportOptions, state: &ExportState, mut logger: impl Logger) {
+    log!((logger) Export("Exporting tag pages..."));
+    let mut details: HashMap<String, (String, String, Vec<String>)> = HashMap::new();
+    for entry in entries.iter_mut() {
+        let date = format_date(entry.date(logger.hollow()));
+        // Time capsules don't give away what they are about
+        let description = if !options.force_unlock && entry.is_locked(logger.hollow()) { String::new() }
+            else { entry.description(logger.hollow()).clone() };
+        let tags = renamed_tags(entry.tags(logger.hollow()));
+        details.insert(entry.uid.clone(), (date, description, tags));
+        entry.clear_cache();
+    }
+    let uids: Vec<String> = details.keys().cloned().collect();
+    let sorted = sort_uids(&uids, false, logger.hollow());
+    let index = tag_index(sorted.iter().map(|x| (x.as_str(), details[x].2.as_slice())));
+    let page = |tag: &str| Path::new("tags").join(tag);
+
+    for (tag, uids) in index.iter() {
+        let file = page(tag);
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let mut text = format!("{}# {tag}
+", frontmatter(&[], tag, None, &[]));
+
+        let children: String = index.keys()
+            .filter(|x| x.strip_prefix(tag.as_str()).and_then(|x| x.strip_prefix('/')).is_some_and(|x| !x.contains('/')))
+            .map(|x| format!("- {} ({})\n", options.links.link(&dir, &page(x), x), index[x].len()))
+            .collect();
+        if !children.is_empty() { text.push_str(&format!("## Sub-tags\n{children}")) }
+
+        text.push_str("## Entries\n");
+        for uid in uids {
+            let (date, description, _) = &details[uid];
+            let Some((title, target)) = state.targets.get(uid) else { continue };
+            let link = options.links.link(&dir, target, title);
+            match description.trim() {
+                "" => text.push_str(&format!("- {date} {link}\n")),
+                description => text.push_str(&format!("- {date} {link}: {description}\n")),
+            }
+        }
+        incremental::write_file(path, &format!("{}.md", file.to_string_lossy()), &text, state, logger.hollow());
+    }
+
+    // The index nests sub-tags under their parents
+    let mut text = format!("{}# Tags\n", frontmatter(&[], "Tags", None, &[]));
+    for (tag, uids) in index.iter() {
+        let depth = tag.matches('/').count();
+        let name = tag.rsplit('/').next().unwrap_or(tag);
+        text.push_str(&format!("{}- {} ({})\n", "\t".repeat(depth), options.links.link(Path::new(""), &page(tag), name), uids.len()));
+    }
+    incremental::write_file(path, "tags.md", &text, state, logger.hollow());
+}
+
+/// Maps archive tags through the config's rename table
+fn renamed_tags(tags: &[String]) -> Vec<String> {
+    Config::get().export_tag_rename.iter()
+        .fold(tags.to_vec(), |tags, (from, into)| replace_tags(&tags, std::slice::from_ref(from), into).map(Vec::from).unwrap_or(tags))
+}
+
+/// Maps archive tags through the config's rename table and adds the injected export tags
+fn export_tags(tags: &[String]) -> Vec<String> {
+    let config = Config::get();
+    let renamed = renamed_tags(tags);
+
+    let mut result = config.export_tags();
+    for tag in renamed.iter().map(|x| x.trim_matches('/')) { // nested tags are native to obsidian
+        if !tag.is_empty() && !result.iter().any(|x| x == tag) { result.push(tag.to_string()) }
+    } result
+}
+
+/// Renders the frontmatter of an exported file with its (export) tags, title, date and mocs as configured
+fn frontmatter(tags: &[String], title: &str, date: Option<&[u16; 3]>, mocs: &[String]) -> String {
+    Config::get().export_frontmatter.render(&export_tags(tags), title, date, mocs)
+}
@@ -0,0 +1,95 @@
+use chrono::format::{Item, StrftimeItems};
+use soulog::*;
+use crate::unwrap_opt;
+
+/// Config of the frontmatter of exported files (`[export_frontmatter]`)
+#[derive(Default)]
+pub struct Frontmatter {
+    /// The strftime format of entry dates; defaults to `<year>-<month>-<day>` without padding
+    pub date_format: Option<String>,
+    /// Adds the title of an item as an `Obsidian.md` alias so it can be linked to by title
+    pub aliases: bool,
+    /// Static keys added to every exported file
+    pub extra: Vec<(String, toml::Value)>,
+}
+
+impl Frontmatter {
+    /// Parses the `[export_frontmatter]` table of the config
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let date_format = table.get("date_format")
+            .map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's export_frontmatter 'date_format' attribute must be a string")).to_string());
+        if let Some(format) = &date_format {
+            if StrftimeItems::new(format).any(|x| matches!(x, Item::Error)) {
+                log!((logger.error) Config("Config's export_frontmatter 'date_format' attribute '{format}' isn't a valid date format") as Fatal);
+                return logger.crash();
+            }
+        }
+        let aliases = table.get("aliases")
+            .map(|x| unwrap_opt!((x.as_bool()) with logger, format: Config("Config's export_frontmatter 'aliases' attribute must be a boolean")))
+            .unwrap_or(false);
+
+        let extra: Vec<(String, toml::Value)> = table.get("extra")
+            .map(|x| unwrap_opt!((x.as_table()) with logger, format: Config("Config's export_frontmatter 'extra' attribute must be a table")))
+            .map(|x| x.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        for (key, value) in extra.iter() {
+            let scalars = match value {
+                toml::Value::Array(x) => x.iter().all(|x| !matches!(x, toml::Value::Array(_) | toml::Value::Table(_))),
+                x => !matches!(x, toml::Value::Table(_)),
+            };
+            if !scalars {
+                log!((logger.error) Config("Config's export_frontmatter extra key '{key}' must be a value or a list of values") as Fatal);
+                return logger.crash();
+            }
+        }
+
+        Self { date_format, aliases, extra }
+    }
+
+    /// Renders the frontmatter of an exported file with its (export) tags, title, date and the links to the mocs it appears in
+    pub fn render(&self, tags: &[String], title: &str, date: Option<&[u16; 3]>, mocs: &[String]) -> String {
+        let mut result = String::from("---\n");
+        if !tags.is_empty() {
+            result.push_str("tags:\n");
+            tags.iter().for_each(|x| result.push_str(&format!("  - {x}\n")));
+        }
+        if self.aliases { result.push_str(&format!("aliases:\n  - {}\n", yaml_value(&toml::Value::String(title.to_string())))) }
+        if let Some(date) = date { result.push_str(&format!("date: {}\n", self.format_date(date))) }
+        if !mocs.is_empty() {
+            result.push_str("mocs:\n");
+            mocs.iter().for_each(|x| result.push_str(&format!("  - {}\n", yaml_value(&toml::Value::String(x.clone())))));
+        }
+        for (key, value) in self.extra.iter() {
+            match value {
+                toml::Value::Array(x) => {
+                    result.push_str(&format!("{key}:\n"));
+                    x.iter().for_each(|x| result.push_str(&format!("  - {}\n", yaml_value(x))));
+                },
+                x => result.push_str(&format!("{key}: {}\n", yaml_value(x))),
+            }
+        }
+        result.push_str("---\n");
+        result
+    }
+
+    /// Formats an entry's date with the configured format
+    pub fn format_date(&self, date: &[u16; 3]) -> String {
+        let format = match &self.date_format {
+            Some(x) => x,
+            None => return format!("{0}-{1}-{2}", date[2], date[1], date[0]),
+        };
+        match chrono::NaiveDate::from_ymd_opt(date[2] as i32, date[1] as u32, date[0] as u32) {
+            Some(x) => x.format(format).to_string(),
+            None => format!("{0}-{1}-{2}", date[2], date[1], date[0]),
+        }
+    }
+}
+
+/// Renders a toml value as a yaml scalar; strings are quoted (as json strings, which are valid yaml)
+fn yaml_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(x) => serde_json::to_string(x).unwrap_or_default(),
+        toml::Value::Datetime(x) => x.to_string(),
+        x => x.to_string(),
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+use soulog::*;
+use crate::{archive::Archive, entry::Entry, moc::{walk_collections, MOC}};
+
+/// The kinds of the nodes of an exported graph
+#[derive(Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Entry,
+    Moc,
+    Tag,
+}
+
+impl NodeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Entry => "entry",
+            Self::Moc => "moc",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+/// The kinds of the edges of an exported graph
+#[derive(Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    /// An entry or moc has a tag
+    Tagged,
+    /// A moc's collection includes an entry or moc
+    Includes,
+    /// An entry links to an item (`links = [...]` or a `{{uid:...}}` cross-reference)
+    Links,
+}
+
+impl EdgeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tagged => "tagged",
+            Self::Includes => "includes",
+            Self::Links => "links",
+        }
+    }
+}
+
+pub struct Node {
+    /// Unique across kinds (`entry:<uid>`, `moc:<uid>` or `tag:<tag>`)
+    pub id: String,
+    pub label: String,
+    pub kind: NodeKind,
+}
+
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// The structure of an archive: its entries, mocs and tags, and how they relate
+#[derive(Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Adds a node unless there already is one with its id, returning the id
+    pub fn node(&mut self, kind: NodeKind, name: &str, label: &str) -> String {
+        let id = format!("{}:{name}", kind.name());
+        if !self.nodes.iter().any(|x| x.id == id) {
+            self.nodes.push(Node { id: id.clone(), label: label.to_string(), kind });
+        } id
+    }
+
+    /// Adds an edge unless the same one is already there
+    pub fn edge(&mut self, from: &str, to: &str, kind: EdgeKind) {
+        if !self.edges.iter().any(|x| x.from == from && x.to == to && x.kind == kind) {
+            self.edges.push(Edge { from: from.to_string(), to: to.to_string(), kind });
+        }
+    }
+
+    /// Renders the graph in Graphviz's dot language
+    pub fn to_dot(&self) -> String {
+        let quote = |x: &str| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut result = String::from("digraph diary {\n");
+        for node in self.nodes.iter() {
+            let shape = match node.kind {
+                NodeKind::Entry => "box",
+                NodeKind::Moc => "doubleoctagon",
+                NodeKind::Tag => "ellipse",
+            };
+            result.push_str(&format!("  {} [label={}, kind={}, shape={shape}];\n", quote(&node.id), quote(&node.label), node.kind.name()));
+        }
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                EdgeKind::Tagged => "dotted",
+                EdgeKind::Includes => "solid",
+                EdgeKind::Links => "dashed",
+            };
+            result.push_str(&format!("  {} -> {} [kind={}, style={style}];\n", quote(&edge.from), quote(&edge.to), edge.kind.name()));
+        }
+        result.push_str("}\n");
+        result
+    }
+
+    /// Renders the graph as GraphML (for Gephi and the like)
+    pub fn to_graphml(&self) -> String {
+        let escape = |x: &str| x.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+        let mut result = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <key id=\"relation\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <graph id=\"diary\" edgedefault=\"directed\">\n",
+        ));
+        for node in self.nodes.iter() {
+            result.push_str(&format!(
+                "    <node id=\"{}\"><data key=\"label\">{}</data><data key=\"kind\">{}</data></node>\n",
+                escape(&node.id), escape(&node.label), node.kind.name(),
+            ));
+        }
+        for edge in self.edges.iter() {
+            result.push_str(&format!(
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"relation\">{}</data></edge>\n",
+                escape(&edge.from), escape(&edge.to), edge.kind.name(),
+            ));
+        }
+        result.push_str("  </graph>\n</graphml>\n");
+        result
+    }
+}
+
+/// Builds the graph of the exported entries and mocs; collections and links only lead to exported items
+pub fn build(entries: &mut [Entry], mocs: &mut [MOC], archive: &Archive, logger: impl Logger) -> Graph {
+    let mut graph = Graph::default();
+    let entry_uids: Vec<String> = entries.iter().map(|x| x.uid.clone()).collect();
+    let moc_uids: Vec<String> = mocs.iter().map(|x| x.uid.clone()).collect();
+    let item_id = |uid: &String| match (entry_uids.contains(uid), moc_uids.contains(uid)) {
+        (true, _) => Some(format!("entry:{uid}")),
+        (_, true) => Some(format!("moc:{uid}")),
+        _ => None,
+    };
+
+    for entry in entries.iter_mut() {
+        let title = entry.title(logger.hollow()).clone();
+        let id = graph.node(NodeKind::Entry, &entry.uid, &title);
+        for tag in entry.tags(logger.hollow()).iter() {
+            let tag = graph.node(NodeKind::Tag, tag, &format!("#{tag}"));
+            graph.edge(&id, &tag, EdgeKind::Tagged);
+        }
+        entry.clear_cache();
+    }
+    for moc in mocs.iter_mut() {
+        let title = moc.title(logger.hollow()).clone();
+        let id = graph.node(NodeKind::Moc, &moc.uid, &title);
+        for tag in moc.tags(logger.hollow()).iter() {
+            let tag = graph.node(NodeKind::Tag, tag, &format!("#{tag}"));
+            graph.edge(&id, &tag, EdgeKind::Tagged);
+        }
+        moc.clear_cache();
+    }
+
+    // Collections include the items that match them
+    for moc in mocs.iter_mut() {
+        let uid = moc.uid.clone();
+        let id = format!("moc:{uid}");
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, _| {
+            if collection.groups_only(logger.hollow()) { return collection.clear_cache() }
+            let uids = collection.search_entries(archive.list_entries(logger.hollow()), logger.hollow()).into_iter()
+                .chain(collection.search_mocs(archive.list_mocs(logger.hollow()), logger.hollow()))
+                .filter(|x| *x != uid);
+            for to in uids.filter_map(|x| item_id(&x)) {
+                graph.edge(&id, &to, EdgeKind::Includes);
+            }
+            collection.clear_cache();
+        }, logger.hollow());
+        moc.clear_cache();
+    }
+
+    // Explicit links and cross-references
+    for entry in entries.iter_mut() {
+        let id = format!("entry:{}", entry.uid);
+        let mut targets = entry.links(logger.hollow()).to_vec();
+        targets.extend(entry.references(logger.hollow()).iter().cloned());
+        for to in targets.iter().filter(|x| **x != entry.uid).filter_map(item_id) {
+            graph.edge(&id, &to, EdgeKind::Links);
+        }
+        entry.clear_cache();
+    }
+    graph
+}
+
+/// Exports the structure of the archive as a graph into `archive.dot`, or `archive.graphml` if `graphml` is set
+pub fn export_graph(path: &Path, mut entries: Vec<Entry>, mut mocs: Vec<MOC>, archive: &Archive, graphml: bool, mut logger: impl Logger) -> std::path::PathBuf {
+    log!((logger) Export("Building the graph of {} entries and {} mocs...", entries.len(), mocs.len()));
+    let graph = build(&mut entries, &mut mocs, archive, logger.hollow());
+    let (file, text) = match graphml {
+        true => (path.join("archive.graphml"), graph.to_graphml()),
+        false => (path.join("archive.dot"), graph.to_dot()),
+    };
+    if_err!((logger) [Export, err => ("While writing '{}': {err:?}", file.to_string_lossy())] retry std::fs::write(&file, &text));
+    file
+}
@@ -0,0 +1,167 @@
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, history, json, search::filter_matches};
+
+/// A match of a pattern within a section's content
+pub struct Match {
+    /// The line (from 1) the match starts on
+    pub line: usize,
+    /// The full text of the line the match starts on
+    pub text: String,
+    /// Where the match is in the content
+    pub range: Range<usize>,
+}
+
+/// Finds every match of a pattern in some content along with the line it is on
+pub fn find_matches(content: &str, regex: &Regex) -> Vec<Match> {
+    regex.find_iter(content).map(|x| {
+        let start = content[..x.start()].rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let end = content[x.start()..].find('\n').map(|e| x.start() + e).unwrap_or(content.len());
+        Match { line: content[..x.start()].matches('\n').count() + 1, text: content[start..end].to_string(), range: x.range() }
+    }).collect()
+}
+
+/// Replaces the matches of a pattern in some content that `accept` agrees to, returning the new content and how many were replaced;
+/// the replacement can refer to capture groups (`$1`)
+pub fn replace_matches(content: &str, regex: &Regex, replace: &str, mut accept: impl FnMut(&Match, &str) -> bool) -> (String, usize) {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut replaced = 0;
+    let matches = find_matches(content, regex);
+    for (captures, x) in regex.captures_iter(content).zip(matches.iter()) {
+        let mut replacement = String::new();
+        captures.expand(replace, &mut replacement);
+        if !accept(x, &replacement) { continue }
+        result.push_str(&content[last..x.range.start]);
+        result.push_str(&replacement);
+        last = x.range.end;
+        replaced += 1;
+    }
+    result.push_str(&content[last..]);
+    (result, replaced)
+}
+
+/// Highlights a match within the line it is on
+fn highlight(x: &Match, content: &str) -> String {
+    let line_start = content[..x.range.start].rfind('\n').map(|x| x + 1).unwrap_or(0);
+    let (start, end) = (x.range.start - line_start, (x.range.end - line_start).min(x.text.len()));
+    colour_format![none(&x.text[..start]), red(&x.text[start..end]), none(&x.text[end..])]
+}
+
+/// An answer to whether a match should be replaced
+enum Answer {
+    Yes,
+    No,
+    /// Replace this and every remaining match
+    All,
+    /// Skip the rest of the matches in this entry
+    Done,
+    /// Stop replacing, keeping the replacements made so far
+    Quit,
+}
+
+fn ask(logger: &mut impl Logger) -> Answer {
+    loop {
+        print!("Replace this match? [y,n,a,d,q,?] ");
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 { return Answer::Quit } // end of input
+        match line.trim() {
+            "y" => return Answer::Yes,
+            "n" => return Answer::No,
+            "a" => return Answer::All,
+            "d" => return Answer::Done,
+            "q" => return Answer::Quit,
+            _ => log!((logger.vital) Grep("y - replace this match\nn - don't replace this match\na - replace this and every remaining match\nd - don't replace any more matches in this entry\nq - quit, keeping the replacements made so far") as Log),
+        }
+    }
+}
+
+/// Searches the content of every entry's sections for a regex, optionally replacing the matches one by one after confirming each
+pub fn grep(pattern: String, ignore_case: bool, tags: Option<Vec<String>>, replace: Option<String>, force_unlock: bool, mut logger: impl Logger) {
+    let regex = if_err!((logger) [Grep, err => ("Invalid pattern '{pattern}': {err}")] {RegexBuilder::new(&pattern).case_insensitive(ignore_case).build()} crash {
+        log!((logger.error) Grep("{err}") as Fatal);
+        logger.crash()
+    });
+
+    let archive = Archive::load(logger.hollow());
+    if replace.is_some() { Archive::backup_before_modification(logger.hollow()) }
+
+    let mut found = Vec::new();
+    let mut replaced = 0usize;
+    let (mut all, mut quit) = (false, false);
+    for mut entry in archive.list_entries(logger.hollow()) {
+        if quit { break }
+        if let Some(filter) = &tags {
+            if !filter_matches(entry.tags(logger.hollow()), filter) { entry.clear_cache(); continue }
+        }
+        if !force_unlock && entry.is_locked(logger.hollow()) {
+            log!((logger) Grep("Skipping time capsule '{}'", entry.uid));
+            entry.clear_cache();
+            continue;
+        }
+
+        let uid = entry.uid.clone();
+        let mut done = false;
+        for (i, section) in entry.sections(logger.hollow()).iter_mut().enumerate() {
+            let content = section.content(logger.hollow()).clone();
+            let title = section.title(logger.hollow()).clone();
+            let Some(replace) = &replace else {
+                find_matches(&content, &regex).into_iter().for_each(|x| found.push((uid.clone(), i, title.clone(), highlight(&x, &content), x)));
+                section.clear_cache();
+                continue;
+            };
+
+            let (new, count) = replace_matches(&content, &regex, replace, |x, replacement| {
+                if quit || done { return false }
+                if all { return true }
+                log!((logger.vital) Grep("{}", colour_format![cyan(&uid), blue(" › "), none(&title), blue(":"), cyan(&x.line.to_string()), blue(": "), none(&highlight(x, &content))]) as Log);
+                log!((logger.vital) Grep("{}", colour_format![blue("  → "), green(replacement)]) as Log);
+                match ask(&mut logger) {
+                    Answer::Yes => true,
+                    Answer::No => false,
+                    Answer::All => { all = true; true },
+                    Answer::Done => { done = true; false },
+                    Answer::Quit => { quit = true; false },
+                }
+            });
+
+            if count > 0 {
+                log!((logger) Grep("Writing {count} replacements to entry '{uid}', section {i}..."));
+                section.clear_cache();
+                section.content = Some(new);
+                section.store_lazy(logger.hollow());
+                replaced += count;
+            } section.clear_cache();
+        }
+        entry.clear_cache();
+    }
+
+    if replace.is_some() {
+        if replaced == 0 {
+            log!((logger.vital) Grep("No matches of '{pattern}' were replaced") as Inconvenience);
+            return;
+        }
+        archive.bump_itver(logger.hollow());
+        history::record(&archive, "grep-replace", None, logger.hollow());
+        log!((logger.vital) Grep("Successfully replaced {replaced} matches of '{pattern}'") as Log);
+        return;
+    }
+
+    if json::enabled() {
+        return json::print(found.iter()
+            .map(|(uid, section, title, _, x)| json!({ "uid": uid, "section": section, "title": title, "line": x.line, "text": x.text }))
+            .collect());
+    }
+
+    if found.is_empty() {
+        log!((logger.vital) Grep("No matches of '{pattern}' found") as Inconvenience);
+        return;
+    }
+    for (uid, _, title, line, x) in found.iter() {
+        log!((logger.vital) Grep("{}", colour_format![cyan(uid), blue(" › "), none(title), blue(":"), cyan(&x.line.to_string()), blue(": "), none(line)]) as Log);
+    }
+}
@@ -0,0 +1,96 @@
+use lazy_db::*;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, json, list, signature::Signature};
+
+/// A mutating operation performed on the archive
+///
+/// Wipes can't be recorded as they destroy the archive (and its history) along with it
+pub struct Event {
+    /// RFC 3339 timestamp of when the operation happened
+    pub time: String,
+    /// The operation *eg* `commit`
+    pub operation: String,
+    /// The entry or moc the operation affected, if any
+    pub uid: Option<String>,
+    /// The itver of the archive after the operation
+    pub itver: u16,
+    /// Who performed the operation and on which device
+    pub signer: String,
+}
+
+impl Event {
+    fn encode(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}", self.time, self.operation, self.uid.as_deref().unwrap_or(""), self.itver, self.signer)
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        Some(Self {
+            time: parts.next()?.to_string(),
+            operation: parts.next()?.to_string(),
+            uid: Some(parts.next()?.to_string()).filter(|x| !x.is_empty()),
+            itver: parts.next()?.parse().ok()?,
+            signer: parts.next()?.to_string(),
+        })
+    }
+}
+
+fn history_container(archive: &Archive, mut logger: impl Logger) -> LazyContainer {
+    let container = if_err!((logger) [History, err => ("While loading archive history: {err:?}")] retry search_database!((archive.database()) /history));
+    if container.read_data("length").is_err() {
+        if_err!((logger) [History, err => ("While initialising archive history: {err:?}")] retry write_container!((container) length = new_u16(0)));
+    } container
+}
+
+/// Appends a mutating operation to the archive's history
+pub fn record(archive: &Archive, operation: &str, uid: Option<&str>, mut logger: impl Logger) {
+    let itver = if_err!((logger) [History, err => ("While reading archive itver: {err:?}")] retry (|| search_database!((archive.database()) itver)?.collect_u16())());
+    let event = Event {
+        time: chrono::Local::now().to_rfc3339(),
+        operation: operation.to_string(),
+        uid: uid.map(String::from),
+        itver,
+        signer: Signature::current().to_string(),
+    };
+
+    list::push(
+        |file| LazyData::new_string(file, &event.encode()),
+        &history_container(archive, logger.hollow()),
+        logger.hollow(),
+    );
+    crate::webhook::notify(&event, logger);
+}
+
+/// Reads every event in the archive's history, oldest first
+pub fn read(archive: &Archive, logger: impl Logger) -> Vec<Event> {
+    list::read(|x| x.collect_string(), &history_container(archive, logger.hollow()), logger.hollow())
+        .iter()
+        .filter_map(|x| Event::decode(x))
+        .collect()
+}
+
+pub fn history_command(limit: Option<usize>, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let events = read(&archive, logger.hollow());
+    let events = &events[events.len().saturating_sub(limit.unwrap_or(usize::MAX))..];
+
+    if json::enabled() {
+        return json::print(events.iter()
+            .map(|x| json!({ "time": x.time, "operation": x.operation, "uid": x.uid, "itver": x.itver, "signer": x.signer }))
+            .collect());
+    }
+
+    if events.is_empty() {
+        log!((logger.vital) History("No operations recorded in the archive's history") as Inconvenience);
+        return;
+    }
+
+    for x in events {
+        let uid = x.uid.as_ref().map(|x| format!(" `{x}`")).unwrap_or_default();
+        log!((logger.vital) History("{}", colour_format![
+            none(&x.time), blue(" ["), cyan(&x.itver.to_string()), blue("] "),
+            green(&x.operation), none(&uid), blue(" by "), none(&x.signer)
+        ]) as Log);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use soulog::*;
+use crate::{archive::Archive, backlinks, config::Config, entry::{Entry, format_date}, export::ExportOptions, links, moc::{walk_collections, MOC}, sort::sort_uids, transform};
+use crate::theme::{Theme, escape_html};
+
+/// Splits a `[text](href)` link off of the start of text, returning its text, href and the rest
+fn split_link(text: &str) -> Option<(&str, &str, &str)> {
+    let (label, rest) = text.strip_prefix('[')?.split_once("](")?;
+    let (href, rest) = rest.split_once(')')?;
+    (!label.contains('[') && !href.contains(char::is_whitespace)).then_some((label, href, rest))
+}
+
+/// Styles the inline markdown (`**bold**`, `*italics*`, `` `code` `` and `[links](href)`) of escaped text
+fn inline_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let (mut bold, mut italic, mut code) = (false, false, false);
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if !code && rest.starts_with("**") {
+            bold = !bold;
+            result.push_str(if bold { "<strong>" } else { "</strong>" });
+            rest = &rest[2..];
+            continue;
+        }
+        if let Some((label, href, after)) = split_link(rest).filter(|_| !code) {
+            result.push_str(&format!("<a href=\"{href}\">{}</a>", inline_html(label)));
+            rest = after;
+            continue;
+        }
+        match c {
+            '`' => { code = !code; result.push_str(if code { "<code>" } else { "</code>" }) },
+            '*' if !code => { italic = !italic; result.push_str(if italic { "<em>" } else { "</em>" }) },
+            c => result.push(c),
+        } rest = &rest[c.len_utf8()..];
+    }
+
+    // Close anything left open
+    if code { result.push_str("</code>") }
+    if italic { result.push_str("</em>") }
+    if bold { result.push_str("</strong>") }
+    result
+}
+
+/// Renders the markdown of section content (headings, quotes, lists and paragraphs) as html
+pub fn markdown_to_html(text: &str) -> String {
+    let mut result = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut in_list = false;
+    let flush = |paragraph: &mut Vec<String>, result: &mut String| if !paragraph.is_empty() {
+        result.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>\n")));
+        paragraph.clear();
+    };
+
+    for line in text.lines().map(|x| escape_html(x.trim())) {
+        let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* "));
+        if item.is_none() && in_list {
+            result.push_str("</ul>\n");
+            in_list = false;
+        }
+
+        if line.is_empty() {
+            flush(&mut paragraph, &mut result);
+        } else if let Some(item) = item {
+            flush(&mut paragraph, &mut result);
+            if !in_list { result.push_str("<ul>\n"); in_list = true; }
+            result.push_str(&format!("<li>{}</li>\n", inline_html(item)));
+        } else if line.starts_with('#') {
+            flush(&mut paragraph, &mut result);
+            let level = line.chars().take_while(|x| *x == '#').count().min(6);
+            result.push_str(&format!("<h{level}>{}</h{level}>\n", inline_html(line[level..].trim_start_matches('#').trim())));
+        } else if let Some(quote) = line.strip_prefix("&gt;") {
+            flush(&mut paragraph, &mut result);
+            result.push_str(&format!("<blockquote>{}</blockquote>\n", inline_html(quote.trim())));
+        } else {
+            paragraph.push(inline_html(&line));
+        }
+    }
+
+    flush(&mut paragraph, &mut result);
+    if in_list { result.push_str("</ul>\n") }
+    result
+}
+
+/// The file a tag's page is exported to
+pub fn tag_file(tag: &str) -> String {
+    format!("tag-{}.html", tag.replace('/', "--"))
+}
+
+fn tag_links(tags: &[String]) -> String {
+    let links: Vec<String> = tags.iter().map(|x| format!("<a href=\"{}\">#{}</a>", escape_html(&tag_file(x)), escape_html(x))).collect();
+    format!("<p class=\"tags\">{}</p>\n", links.join(" "))
+}
+
+fn write_page(path: &Path, file: &str, html: String, mut logger: impl Logger) {
+    let path = path.join(file);
+    if_err!((logger) [Export, err => ("While writing html page '{}': {err:?}", path.to_string_lossy())] retry std::fs::write(&path, &html));
+}
+
+/// An exported entry as it is linked to from the index, tag and moc pages
+struct Link {
+    uid: String,
+    title: String,
+    date: String,
+}
+
+impl Link {
+    fn html(&self) -> String {
+        format!("<li><span class=\"date\">{}</span> <a href=\"{}.html\">{}</a></li>\n", self.date, escape_html(&self.uid), escape_html(&self.title))
+    }
+}
+
+/// Exports entries and mocs as a static html site with an index page, a page per entry & moc and a page per tag
+pub fn export_html(path: &Path, entries: Vec<Entry>, mut mocs: Vec<MOC>, archive: &Archive, options: &ExportOptions, mut logger: impl Logger) {
+    let theme = Theme::load(options.theme.as_deref().unwrap_or("light"), logger.hollow());
+
+    // Chronological order
+    let uids: Vec<String> = entries.iter().map(|x| x.uid.clone()).collect();
+    let mut by_uid: std::collections::HashMap<String, Entry> = entries.into_iter().map(|x| (x.uid.clone(), x)).collect();
+    let mut entries: Vec<Entry> = sort_uids(&uids, false, logger.hollow()).iter().filter_map(|x| by_uid.remove(x)).collect();
+
+    // The titles of the exported items cross-references link to
+    let titles: std::collections::HashMap<String, String> = entries.iter_mut().map(|x| (x.uid.clone(), x.title(logger.hollow()).clone()))
+        .chain(mocs.iter_mut().map(|x| (x.uid.clone(), x.title(logger.hollow()).clone())))
+        .collect();
+    let backlinks = backlinks::backlink_map(&mut entries, logger.hollow());
+    let backlink_list = |uid: &str| -> String {
+        let items: String = backlinks.get(uid).into_iter().flatten()
+            .filter(|x| titles.contains_key(&x.uid))
+            .map(|x| Link { uid: x.uid.clone(), title: x.title.clone(), date: format_date(&x.date) }.html())
+            .collect();
+        if items.is_empty() { items } else { format!("<h2>Backlinks</h2>\n<ul>\n{items}</ul>\n") }
+    };
+
+    let mut links = Vec::with_capacity(entries.len());
+    let mut tagged: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for mut entry in entries {
+        log!((logger) Export("Exporting entry of uid '{}' as html...", entry.uid));
+        let title = entry.title(logger.hollow()).clone();
+        let date = format_date(entry.date(logger.hollow()));
+        let tags = entry.tags(logger.hollow()).clone();
+        tags.iter().for_each(|x| tagged.entry(x.clone()).or_default().push(links.len()));
+
+        let mut content = format!("<p><a href=\"index.html\">Index</a></p>\n<h1>{}</h1>\n<p class=\"date\">{date}</p>\n{}", escape_html(&title), tag_links(&tags));
+        if !options.force_unlock && entry.is_locked(logger.hollow()) {
+            let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+            content.push_str(&format!("<blockquote><em>This entry is a time capsule sealed until {unlock_date}</em></blockquote>\n"));
+        } else {
+            content.push_str(&format!("<blockquote>{}</blockquote>\n", escape_html(entry.description(logger.hollow()))));
+            let notes = entry.notes(logger.hollow());
+            if !notes.is_empty() {
+                content.push_str("<ul>\n");
+                notes.iter().for_each(|x| content.push_str(&format!("<li>{}</li>\n", escape_html(x))));
+                content.push_str("</ul>\n");
+            }
+            let transforms = &Config::get().export_transforms;
+            entry.sections(logger.hollow()).iter_mut().for_each(|x| {
+                content.push_str(&format!("<h2>{}</h2>\n", escape_html(x.title(logger.hollow()))));
+                let text = transform::apply(transforms, x.content(logger.hollow()), &tags);
+                let text = links::render_cross_references(&text, |uid, label| titles.get(uid).map(|title| format!("[{}]({uid}.html)", label.unwrap_or(title))));
+                content.push_str(&markdown_to_html(&text));
+                x.clear_cache();
+            });
+
+            let items: String = entry.links(logger.hollow()).iter()
+                .filter_map(|uid| titles.get(uid).map(|title| format!("<li><a href=\"{}.html\">{}</a></li>\n", escape_html(uid), escape_html(title))))
+                .collect();
+            if !items.is_empty() { content.push_str(&format!("<h2>Links</h2>\n<ul>\n{items}</ul>\n")) }
+            content.push_str(&backlink_list(&entry.uid));
+        }
+
+        write_page(path, &format!("{}.html", entry.uid), theme.render_page(&title, &content), logger.hollow());
+        links.push(Link { uid: entry.uid.clone(), title, date });
+        entry.clear_cache();
+    }
+
+    // Mocs list the exported entries of each of their collections
+    let mut moc_links = Vec::with_capacity(mocs.len());
+    for mut moc in mocs {
+        log!((logger) Export("Exporting moc of uid '{}' as html...", moc.uid));
+        let title = moc.title(logger.hollow()).clone();
+        let mut content = format!("<p><a href=\"index.html\">Index</a></p>\n<h1>{}</h1>\n{}<blockquote>{}</blockquote>\n", escape_html(&title), tag_links(moc.tags(logger.hollow())), escape_html(moc.description(logger.hollow())));
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+            let level = (at.len() + 1).min(6); // sub-collections go a heading level deeper
+            content.push_str(&format!("<h{level}>{}</h{level}>\n", escape_html(collection.title(logger.hollow()))));
+            if collection.groups_only(logger.hollow()) { return collection.clear_cache() }
+            let uids = collection.search_entries(archive.list_entries(logger.hollow()), logger.hollow());
+            let included: Vec<&Link> = links.iter().filter(|x| uids.contains(&x.uid)).collect();
+            content.push_str("<ul>\n");
+            let included = collection.newest(&included, logger.hollow()).to_vec();
+            collection.arrange(included, |x| &x.uid, logger.hollow()).iter().for_each(|x| content.push_str(&x.html()));
+            content.push_str("</ul>\n");
+            collection.clear_cache();
+        }, logger.hollow());
+        content.push_str(&backlink_list(&moc.uid));
+
+        write_page(path, &format!("{}.html", moc.uid), theme.render_page(&title, &content), logger.hollow());
+        moc_links.push(format!("<li><a href=\"{}.html\">{}</a></li>\n", escape_html(&moc.uid), escape_html(&title)));
+        moc.clear_cache();
+    }
+
+    // Tag pages
+    for (tag, indices) in tagged.iter() {
+        let mut content = format!("<p><a href=\"index.html\">Index</a></p>\n<h1>#{}</h1>\n<ul>\n", escape_html(tag));
+        indices.iter().for_each(|x| content.push_str(&links[*x].html()));
+        content.push_str("</ul>\n");
+        write_page(path, &tag_file(tag), theme.render_page(&format!("#{tag}"), &content), logger.hollow());
+    }
+
+    // Index
+    let mut content = String::from("<h1>Diary</h1>\n");
+    if !moc_links.is_empty() {
+        content.push_str("<h2>Maps of Content</h2>\n<ul>\n");
+        moc_links.iter().for_each(|x| content.push_str(x));
+        content.push_str("</ul>\n");
+    }
+    if !tagged.is_empty() {
+        content.push_str("<h2>Tags</h2>\n");
+        content.push_str(&tag_links(&tagged.keys().cloned().collect::<Vec<_>>()));
+    }
+    content.push_str("<h2>Entries</h2>\n<ul>\n");
+    links.iter().rev().for_each(|x| content.push_str(&x.html()));
+    content.push_str("</ul>\n");
+    write_page(path, "index.html", theme.render_page("Diary", &content), logger.hollow());
+}
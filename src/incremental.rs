@@ -0,0 +1,83 @@
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use lazy_db::*;
+use soulog::*;
+use crate::archive::Archive;
+
+/// A stable (FNV-1a) hash of some text (or other data), used to tell whether an exported file would change
+pub fn fingerprint(data: impl AsRef<[u8]>) -> u64 {
+    data.as_ref().iter().fold(0xcbf29ce484222325, |hash, x| (hash ^ *x as u64).wrapping_mul(0x100000001b3))
+}
+
+/// The files written by an export into a vault and, for incremental exports, the hashes of the files
+/// last exported to its path (kept in the archive so unchanged files can be skipped)
+#[derive(Default)]
+pub struct ExportState {
+    container: Option<LazyContainer>,
+    /// Every file (relative to the export path) exported with the hash of its contents, whether it was written or unchanged
+    pub files: RefCell<Vec<(String, u64)>>,
+    pub written: Cell<usize>,
+    pub skipped: Cell<usize>,
+    /// The exported items that section content can reference
+    pub targets: crate::links::Targets,
+    /// The (wiki)links to the mocs each exported item appears in, by uid
+    pub mocs: std::collections::HashMap<String, Vec<String>>,
+    /// The exported entries that link to each item, by uid
+    pub backlinks: std::collections::HashMap<String, Vec<crate::backlinks::Backlink>>,
+    /// The events counted down to at the end of exported entries (`--events`)
+    pub events: Vec<crate::event::Event>,
+}
+
+impl ExportState {
+    /// Loads the incremental export state of a path (each export path has its own)
+    pub fn load(archive: &Archive, path: &Path, mut logger: impl Logger) -> Self {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let key = format!("{:016x}", fingerprint(path.to_string_lossy().as_bytes()));
+        log!((logger) Export("Loading incremental export state '{key}'..."));
+        let container = if_err!((logger) [Export, err => ("While loading incremental export state: {err:?}")] retry search_database!((archive.database()) /exports/(&key)));
+        Self { container: Some(container), ..Default::default() }
+    }
+
+    /// Checks if the export skips unchanged files
+    #[inline]
+    pub fn is_incremental(&self) -> bool {
+        self.container.is_some()
+    }
+
+    /// Checks if a file (relative to the export path) was last exported with the same contents and still exists
+    pub fn is_unchanged(&self, path: &Path, file: &str, hash: u64) -> bool {
+        let Some(container) = &self.container else { return false };
+        path.join(file).is_file() && container.read_data(Self::key(file)).and_then(|x| x.collect_u64()).is_ok_and(|x| x == hash)
+    }
+
+    /// Records the hash of a file that was just exported
+    pub fn record(&self, file: &str, hash: u64, mut logger: impl Logger) {
+        let Some(container) = &self.container else { return };
+        let key = Self::key(file);
+        if_err!((logger) [Export, err => ("While recording incremental export state of '{file}': {err:?}")] retry write_container!((container) (&key) = new_u64(hash)));
+    }
+
+    /// Files can be in folders of the export, so they are keyed by the hash of their path
+    fn key(file: &str) -> String {
+        format!("{:016x}", fingerprint(file))
+    }
+}
+
+/// Writes an exported file (relative to the export path), skipping it if it is unchanged since the last incremental export
+pub fn write_file(path: &Path, file: &str, data: impl AsRef<[u8]>, state: &ExportState, mut logger: impl Logger) {
+    if let Some(parent) = path.join(file).parent() {
+        if_err!((logger) [Export, err => ("While creating folder '{}': {err:?}", parent.to_string_lossy())] retry std::fs::create_dir_all(parent));
+    }
+    let hash = fingerprint(&data);
+    state.files.borrow_mut().push((file.to_string(), hash));
+
+    if state.is_unchanged(path, file, hash) {
+        log!((logger) Export("Skipping unchanged file '{file}'"));
+        state.skipped.set(state.skipped.get() + 1);
+        return;
+    }
+
+    if_err!((logger) [Export, err => ("While writing exported file '{file}': {err:?}")] retry std::fs::write(path.join(file), &data));
+    state.record(file, hash, logger.hollow());
+    state.written.set(state.written.get() + 1);
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use lazy_db::*;
+use soulog::*;
+use crate::{archive::{Archive, copy_dir}, history, list, read_db_container, sort, with_context};
+
+/// How many of the latest commits the journal keeps (and so how many can be undone), as each record may hold a whole
+/// copy of the item it overwrote
+pub const LIMIT: u16 = 16;
+
+/// Records a commit of an entry or moc into the archive's journal so it can be undone,
+/// keeping a copy of the item it is about to overwrite (if any); the oldest record is dropped once there are `LIMIT`
+pub fn record(archive: &Archive, uid: &str, is_moc: bool, mut logger: impl Logger) {
+    log!((logger) Journal("Recording commit of '{uid}' into the journal..."));
+    let journal = with_context!((logger) [Journal, "journal-read" => ("While loading the journal")] retry search_database!((archive.database()) /journal));
+    let length = match journal.read_data("length") {
+        Ok(x) => with_context!((logger) [Journal, "journal-read" => ("While reading journal length")] {x.collect_u16()} crash),
+        Err(_) => 0,
+    };
+
+    // Drop the oldest records (along with their copies of overwritten items) and move the rest down to make room
+    let length = if length >= LIMIT {
+        let dropped = length - LIMIT + 1;
+        log!((logger) Journal("Dropping the oldest {dropped} record(s) from the journal..."));
+        for i in 0..dropped {
+            with_context!((logger) [Journal, "journal" => ("While removing old journal record")] retry journal.remove(i.to_string()));
+        }
+        for i in dropped..length {
+            let path = |x: u16| journal.path().join(x.to_string());
+            with_context!((logger) [Journal, "journal" => ("While moving journal record")] retry fs::rename(path(i), path(i - dropped)));
+        }
+        LIMIT - 1
+    } else { length };
+
+    let record = with_context!((logger) [Journal, "journal-write" => ("While writing journal record")] retry journal.new_container(length.to_string()));
+    with_context!((logger) [Journal, "journal-write" => ("While writing journal record")] retry write_container!((record) uid = new_string(uid)));
+    with_context!((logger) [Journal, "journal-write" => ("While writing journal record")] retry write_container!((record) is_moc = new_bool(is_moc)));
+
+    let previous = item_path(archive, uid, is_moc);
+    if previous.is_dir() {
+        with_context!((logger) [Journal, "journal" => ("While saving the overwritten item")] retry copy_dir(&previous, record.path().join("previous")));
+    }
+
+    with_context!((logger) [Journal, "journal-write" => ("While writing journal length")] retry write_container!((journal) length = new_u16(length + 1)));
+}
+
+fn item_path(archive: &Archive, uid: &str, is_moc: bool) -> std::path::PathBuf {
+    archive.database().path().join(if is_moc { "mocs" } else { "entries" }).join(uid)
+}
+
+/// Reverses the most recent commit recorded in the journal
+pub fn undo(mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let journal = with_context!((logger) [Undo, "undo-read" => ("While loading the journal")] retry search_database!((archive.database()) /journal));
+    let length = match journal.read_data("length") {
+        Ok(x) => with_context!((logger) [Undo, "undo-read" => ("While reading journal length")] {x.collect_u16()} crash),
+        Err(_) => 0,
+    };
+
+    if length == 0 {
+        log!((logger.vital) Undo("No commits recorded in the journal; nothing to undo") as Inconvenience);
+        return;
+    }
+
+    let idx = (length - 1).to_string();
+    let record = with_context!((logger) [Undo, "undo-read" => ("While reading journal record")] retry journal.read_container(&idx));
+    let uid = read_db_container!(uid from Undo(record) as collect_string with logger);
+    let is_moc = read_db_container!(is_moc from Undo(record) as collect_bool with logger);
+    let kind = if is_moc { "moc" } else { "entry" };
+
+    Archive::backup_before_modification(logger.hollow());
+    log!((logger) Undo("Undoing commit of {kind} '{uid}'..."));
+
+    // Remove the committed item and bring back what it overwrote
+    let path = item_path(&archive, &uid, is_moc);
+    let _ = fs::remove_dir_all(&path);
+    let previous = record.path().join("previous");
+    let restored = previous.is_dir();
+    if restored {
+        log!((logger) Undo("Restoring the {kind} '{uid}' that the commit overwrote..."));
+        with_context!((logger) [Undo, "undo" => ("While restoring overwritten {kind}")] retry copy_dir(&previous, &path));
+    }
+
+    // Fix up the order lists
+    if !is_moc {
+        sort::remove_from_order(&archive, "sorted", &uid, logger.hollow());
+        sort::remove_from_order(&archive, "unsorted", &uid, logger.hollow());
+        if restored {
+            list::push(
+                |file| LazyData::new_string(file, &uid),
+                &with_context!((logger) [Undo, "undo-read" => ("While loading unsorted stack")] retry search_database!((archive.database()) /order/unsorted)),
+                logger.hollow(),
+            );
+        }
+    }
+
+    // Pop the record and bump the itver (never wind it back, as other changes may have been made since the commit)
+    with_context!((logger) [Undo, "undo" => ("While removing journal record")] retry journal.remove(&idx));
+    with_context!((logger) [Undo, "undo-write" => ("While writing journal length")] retry write_container!((journal) length = new_u16(length - 1)));
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, "undo", Some(&uid), logger.hollow());
+
+    if restored {
+        log!((logger.vital) Undo("Successfully undid commit of {kind} '{uid}' and restored its previous version") as Log);
+    } else {
+        log!((logger.vital) Undo("Successfully undid commit of {kind} '{uid}'") as Log);
+    }
+}
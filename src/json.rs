@@ -0,0 +1,13 @@
+use serde_json::Value;
+use crate::cli::JSON;
+
+/// Checks if the cli was asked to output json
+#[inline]
+pub fn enabled() -> bool {
+    unsafe { JSON }
+}
+
+/// Prints a json value as the result of a command
+pub fn print(value: Value) {
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+}
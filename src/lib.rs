@@ -4,7 +4,7 @@ pub mod list;
 pub mod entry;
 pub mod archive;
 pub mod moc;
-pub mod since;
+pub mod date;
 pub mod pull;
 pub mod export;
 pub mod search;
@@ -12,11 +12,73 @@ pub mod sort;
 pub mod scribe;
 pub mod about;
 pub mod uncommit;
+pub mod json;
+pub mod config;
+pub mod signature;
+pub mod tags;
+pub mod tarball;
+pub mod check;
+pub mod trash;
+pub mod redact;
+pub mod journal;
+pub mod history;
+pub mod lock;
+pub mod doctor;
+pub mod stats;
+pub mod diff;
+pub mod calendar;
+pub mod alias;
+pub mod on_this_day;
+pub mod onboard;
+pub mod read;
+pub mod demo;
+pub mod random;
+pub mod theme;
+pub mod html;
+pub mod logseq;
+pub mod membership;
+pub mod dump;
+pub mod provenance;
+pub mod transform;
+pub mod template;
+pub mod grep;
+pub mod incremental;
+pub mod archived;
+pub mod destination;
+pub mod manifest;
+pub mod links;
+pub mod frontmatter;
+pub mod access;
+pub mod markdown;
+pub mod webhook;
+pub mod batch;
+pub mod validate;
+pub mod limits;
+pub mod backlinks;
+pub mod graph;
+pub mod agent;
+pub mod suggest;
+pub mod nudge;
+pub mod shared;
+pub mod cache;
+pub mod signal;
+pub mod locale;
+pub mod event;
+pub mod completions;
+pub mod error;
+pub mod uid;
+pub mod pick;
+pub mod tui;
 
 pub use logger::*;
 pub use scribe::*;
 
 pub fn home_dir() -> std::path::PathBuf {
+    if let Some(path) = std::env::var_os("DIARY_CLI_HOME").filter(|x| !x.is_empty()) {
+        return std::path::PathBuf::from(path);
+    }
+    if let Some(path) = config::configured_home() { return path }
+
     // Linux only; change this if you want to go cross platform
     match std::env::var("HOME") {
         Ok(path) => std::path::Path::new(&path).join(".diary-cli"),
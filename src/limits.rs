@@ -0,0 +1,105 @@
+use std::path::Path;
+use lazy_db::*;
+use soulog::*;
+use crate::{entry::Entry, stats::dir_size, unwrap_opt};
+
+/// The sizes the archive and what is committed into it are warned about at (`[size_limits]`); none of them stop a commit
+pub struct Limits {
+    /// A single attachment
+    pub attachment: Option<u64>,
+    /// A whole entry (or moc), attachments included
+    pub entry: Option<u64>,
+    /// The whole archive
+    pub archive: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            attachment: Some(50_000_000),
+            entry: Some(200_000_000),
+            archive: Some(2_000_000_000),
+        }
+    }
+}
+
+impl Limits {
+    /// Parses the `[size_limits]` table of the config; sizes are bytes or strings like `"50 MB"`, and `0` turns a limit off
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let default = Self::default();
+        let mut limit = |key: &str, default: Option<u64>| match table.get(key) {
+            None => default,
+            Some(toml::Value::Integer(x)) => Some(*x as u64),
+            Some(x) => {
+                let x = unwrap_opt!((x.as_str()) with logger, format: Config("Config's size_limits '{key}' must be a number of bytes or a size like \"50 MB\""));
+                Some(unwrap_opt!((parse_size(x)) with logger, format: Config("Config's size_limits '{key}' has an invalid size '{x}' (like \"500 KB\", \"50 MB\" or \"2 GiB\")")))
+            },
+        }.filter(|x| *x != 0);
+        Self {
+            attachment: limit("attachment", default.attachment),
+            entry: limit("entry", default.entry),
+            archive: limit("archive", default.archive),
+        }
+    }
+}
+
+/// Parses a size like `512`, `500 KB`, `50MB` or `2 GiB` into bytes
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size.find(|x: char| !(x.is_ascii_digit() || x == '.')).unwrap_or(size.len());
+    let number: f64 = size[..split].parse().ok()?;
+    let unit: u64 = match size[split..].trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1_000,
+        "mb" | "m" => 1_000_000,
+        "gb" | "g" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    Some((number * unit as f64) as u64)
+}
+
+/// Formats a number of bytes for humans (`1.5 MiB`)
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 { return format!("{bytes} B") }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    } format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Warns about the attachments and size of a staged entry or moc that are over the configured limits
+pub fn check_item(item: &Path, uid: &str, is_moc: bool, mut logger: impl Logger) {
+    let limits = &crate::config::Config::get().size_limits;
+    if let (Some(limit), false) = (limits.attachment, is_moc) {
+        let container = if_err!((logger) [Limits, err => ("While loading staged entry: {err:?}")] retry LazyContainer::load(item));
+        let mut entry = Entry::load_lazy(uid.to_string(), container);
+        for (i, section) in entry.sections(logger.hollow()).iter_mut().enumerate() {
+            for (j, name) in section.attachments(logger.hollow()).clone().iter().enumerate() {
+                let size = section.attachment_size(j);
+                if size > limit {
+                    log!((logger.vital) Limits("Attachment '{name}' of entry '{uid}' (section {i}) is {}, over the {} limit for attachments", format_size(size), format_size(limit)) as Warning);
+                }
+            }
+        }
+    }
+
+    let size = dir_size(item);
+    if let Some(limit) = limits.entry.filter(|x| size > *x) {
+        log!((logger.vital) Limits("{} '{uid}' is {}, over the {} limit for entries and mocs", if is_moc { "Moc" } else { "Entry" }, format_size(size), format_size(limit)) as Warning);
+    }
+}
+
+/// Warns if the archive has grown over the configured limit
+pub fn check_archive(path: &Path, mut logger: impl Logger) {
+    let Some(limit) = crate::config::Config::get().size_limits.archive else { return };
+    let size = dir_size(path);
+    if size > limit {
+        log!((logger.vital) Limits("The archive is {}, over its {} limit; run `diary-cli stats --largest` to see what takes up the most space", format_size(size), format_size(limit)) as Warning);
+    }
+}
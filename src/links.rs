@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use regex::{Captures, Regex};
+use crate::export::relative_link;
+
+/// How exported markdown links to other files of the vault
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum LinkStyle {
+    /// `[title](path)` links relative to the linking file
+    #[default]
+    Markdown,
+    /// `Obsidian.md` `[[path|title]]` wikilinks from the root of the vault
+    Wiki,
+}
+
+impl LinkStyle {
+    /// Links from a file in the `from` folder of the vault to another file (without its extension)
+    pub fn link(&self, from: &Path, to: &Path, title: &str) -> String {
+        match self {
+            Self::Markdown => format!("[{title}]({})", relative_link(from, to)),
+            Self::Wiki => format!("[[{}|{title}]]", relative_link(Path::new(""), to)),
+        }
+    }
+
+    /// Embeds a file of the vault (with its extension), like an image, into a file in the `from` folder
+    pub fn embed(&self, from: &Path, to: &Path, name: &str) -> String {
+        match self {
+            Self::Markdown => format!("![{name}]({})", relative_link(from, to).replace(' ', "%20")),
+            Self::Wiki => format!("![[{}]]", relative_link(Path::new(""), to)),
+        }
+    }
+}
+
+/// The exported items that can be referenced from section content, by uid, with their title and file (without its extension)
+pub type Targets = HashMap<String, (String, PathBuf)>;
+
+/// Matches `{{uid:some-entry}}` (or `{{uid:some-entry|label}}`) cross-references in section content
+fn cross_reference() -> &'static Regex {
+    static CROSS_REFERENCE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    CROSS_REFERENCE.get_or_init(|| Regex::new(r"\{\{\s*uid:\s*([^}|\s]+)\s*(?:\|([^}]*))?\}\}").unwrap())
+}
+
+/// The uids cross-referenced with `{{uid:...}}` in section content, in order without duplicates
+pub fn cross_references(content: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for x in cross_reference().captures_iter(content) {
+        if !result.iter().any(|uid| uid == &x[1]) { result.push(x[1].to_string()) }
+    } result
+}
+
+/// Replaces the `{{uid:...}}` cross-references in content with what `render` makes of their uid and label;
+/// ones it can't render (like references to items that aren't exported) become their label or uid as plain text
+pub fn render_cross_references(content: &str, mut render: impl FnMut(&str, Option<&str>) -> Option<String>) -> String {
+    cross_reference().replace_all(content, |x: &Captures| {
+        let label = x.get(2).map(|x| x.as_str().trim()).filter(|x| !x.is_empty());
+        render(&x[1], label).unwrap_or_else(|| label.unwrap_or(&x[1]).to_string())
+    }).into_owned()
+}
+
+/// Turns the `{{uid:...}}` cross-references to one uid into plain text (their label or the uid), leaving the others alone
+pub fn unlink_cross_references(content: &str, uid: &str) -> String {
+    cross_reference().replace_all(content, |x: &Captures| match &x[1] == uid {
+        true => x.get(2).map(|x| x.as_str().trim()).filter(|x| !x.is_empty()).unwrap_or(uid).to_string(),
+        false => x[0].to_string(),
+    }).into_owned()
+}
+
+/// Rewrites `{{uid:...}}`, `@uid`, `[[uid]]` and `[[uid|label]]` references to exported items in section content into links;
+/// the latter three are left alone when they don't reference an exported item
+pub fn rewrite_references(content: &str, from: &Path, targets: &Targets, style: LinkStyle) -> String {
+    static REFERENCE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| Regex::new(r"\[\[([^\]|#]+)(?:\|([^\]]+))?\]\]|(^|[^\w@])@(\w[\w-]*(?:[./]\w[\w-]*)*)").unwrap());
+
+    let content = reference.replace_all(content, |x: &Captures| {
+        let (prefix, uid, label) = match (x.get(1), x.get(4)) {
+            (Some(uid), _) => ("", uid.as_str().trim(), x.get(2).map(|x| x.as_str())),
+            (None, Some(uid)) => (x.get(3).map(|x| x.as_str()).unwrap_or(""), uid.as_str(), None),
+            _ => unreachable!(),
+        };
+        match targets.get(uid) {
+            Some((title, file)) => format!("{prefix}{}", style.link(from, file, label.unwrap_or(title))),
+            None => x[0].to_string(),
+        }
+    });
+    render_cross_references(&content, |uid, label| targets.get(uid).map(|(title, file)| style.link(from, file, label.unwrap_or(title))))
+}
@@ -0,0 +1,126 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use soulog::*;
+use toml::Table;
+use crate::unwrap_opt;
+
+/// The names of the months (January first) and days (Monday first) of the languages built in
+const LANGUAGES: &[(&str, [&str; 12], [&str; 7])] = &[
+    ("en", ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"],
+        ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"]),
+    ("de", ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+        ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"]),
+    ("fr", ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+        ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"]),
+    ("es", ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+        ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"]),
+    ("it", ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+        ["lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato", "domenica"]),
+    ("nl", ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+        ["maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag", "zondag"]),
+    ("pt", ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+        ["segunda", "terça", "quarta", "quinta", "sexta", "sábado", "domingo"]),
+    ("sv", ["januari", "februari", "mars", "april", "maj", "juni", "juli", "augusti", "september", "oktober", "november", "december"],
+        ["måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag", "söndag"]),
+];
+
+/// How periodic features (the calendar and weekly streaks) split and name dates (`[calendar]`)
+pub struct Locale {
+    /// The day weeks start on
+    pub week_start: Weekday,
+    /// The names of the months, January first
+    pub months: [String; 12],
+    /// The names of the days, Monday first
+    pub days: [String; 7],
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        let language = system_language().and_then(|x| names(&x)).unwrap_or(LANGUAGES[0]);
+        Self {
+            week_start: Weekday::Mon,
+            months: language.1.map(String::from),
+            days: language.2.map(String::from),
+        }
+    }
+}
+
+/// The language (like `de`) of the system's locale (`LC_ALL`, `LC_TIME` then `LANG`), if it is set
+pub fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"].iter()
+        .filter_map(|x| std::env::var(x).ok())
+        .find(|x| !x.is_empty() && x != "C" && x != "POSIX")
+        .map(|x| x.split(['_', '.', '@']).next().unwrap_or_default().to_lowercase())
+}
+
+/// The month and day names of a built in language
+fn names(language: &str) -> Option<(&'static str, [&'static str; 12], [&'static str; 7])> {
+    LANGUAGES.iter().find(|x| x.0 == language).copied()
+}
+
+/// Parses a day of the week like `monday` or `sun`
+pub fn parse_weekday(day: &str) -> Option<Weekday> {
+    day.trim().parse().ok()
+}
+
+impl Locale {
+    /// Parses the `[calendar]` table of the config
+    pub fn parse(table: &Table, mut logger: impl Logger) -> Self {
+        let mut locale = Self::default();
+        if let Some(x) = table.get("week_start") {
+            let day = unwrap_opt!((x.as_str()) with logger, format: Config("Config's calendar 'week_start' must be a day like \"sunday\""));
+            locale.week_start = unwrap_opt!((parse_weekday(day)) with logger, format: Config("Config's calendar 'week_start' '{day}' isn't a day of the week"));
+        }
+        if let Some(x) = table.get("language") {
+            let language = unwrap_opt!((x.as_str()) with logger, format: Config("Config's calendar 'language' must be a string"));
+            let known: Vec<&str> = LANGUAGES.iter().map(|x| x.0).collect();
+            let (_, months, days) = unwrap_opt!((names(language)) with logger, format: Config("Config's calendar 'language' '{language}' isn't one of {known:?}; name the months and days yourself with 'month_names' and 'day_names'"));
+            locale.months = months.map(String::from);
+            locale.days = days.map(String::from);
+        }
+
+        let mut strings = |key: &str, count: usize| table.get(key).map(|x| {
+            let names: Vec<String> = unwrap_opt!((x.as_array()) with logger, format: Config("Config's calendar '{key}' must be an array of {count} names")).iter()
+                .map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's calendar '{key}' must only contain strings")).to_string())
+                .collect();
+            if names.len() != count {
+                log!((logger.error) Config("Config's calendar '{key}' must have {count} names, not {}", names.len()) as Fatal);
+                return logger.crash();
+            } names
+        });
+        if let Some(x) = strings("month_names", 12) { locale.months = x.try_into().unwrap() }
+        if let Some(x) = strings("day_names", 7) { locale.days = x.try_into().unwrap() }
+        locale
+    }
+
+    /// The name of a month (`1` to `12`)
+    pub fn month(&self, month: u32) -> &str {
+        &self.months[(month as usize).clamp(1, 12) - 1]
+    }
+
+    /// The first three letters of a month's name
+    pub fn short_month(&self, month: u32) -> String {
+        self.month(month).chars().take(3).collect()
+    }
+
+    /// The first three letters of a day's name
+    pub fn short_day(&self, day: Weekday) -> String {
+        self.days[day.num_days_from_monday() as usize].chars().take(3).collect()
+    }
+
+    /// The days of the week in order, starting on `week_start`
+    pub fn week(&self) -> [Weekday; 7] {
+        let mut day = self.week_start;
+        [(); 7].map(|_| { let x = day; day = day.succ(); x })
+    }
+
+    /// The first day of the week a date is in
+    pub fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        week_of(date, self.week_start)
+    }
+}
+
+/// The first day of the week (starting on `week_start`) a date is in
+pub fn week_of(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset = (date.weekday().num_days_from_monday() + 7 - week_start.num_days_from_monday()) % 7;
+    date.checked_sub_days(Days::new(offset as u64)).unwrap_or(date)
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use soulog::*;
+use crate::home_dir;
+
+/// If this process holds the archive lock
+static HELD: AtomicBool = AtomicBool::new(false);
+
+fn lock_path() -> PathBuf {
+    home_dir().join("archive.lock")
+}
+
+/// Checks if the process that created a lock (or pid) file is no longer running
+pub fn is_stale(path: &Path) -> bool {
+    let Some(pid) = fs::read_to_string(path).ok().and_then(|x| x.trim().parse::<u32>().ok()) else { return false };
+    let proc = Path::new("/proc");
+    if proc.is_dir() { return !proc.join(pid.to_string()).exists() }
+    is_dead(pid)
+}
+
+/// Checks if a process is gone on systems without `/proc` (like macOS and the BSDs)
+#[cfg(unix)]
+fn is_dead(pid: u32) -> bool {
+    // Signal 0 only checks for the process; `EPERM` means it exists but belongs to another user
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == -1 && std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn is_dead(_pid: u32) -> bool {
+    false
+}
+
+/// Acquires the advisory lock on the archive for the rest of the process,
+/// waiting for other processes to release it if `--wait` is set
+pub fn acquire(mut logger: impl Logger) {
+    if HELD.load(Ordering::SeqCst) { return }
+    let path = lock_path();
+    let _ = fs::create_dir_all(home_dir());
+    let mut waiting = false;
+
+    loop {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", std::process::id());
+                HELD.store(true, Ordering::SeqCst);
+                return;
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                if is_stale(&path) {
+                    log!((logger.vital) Lock("Removing stale archive lock left by a process that no longer exists") as Inconvenience);
+                    let _ = fs::remove_file(&path);
+                    continue;
+                }
+
+                if !unsafe { crate::cli::WAIT } {
+                    let pid = fs::read_to_string(&path).unwrap_or_default();
+                    log!((logger.error) Lock("The archive is locked by another diary-cli process (pid {}); use `--wait` to wait for it to finish, or remove '{}' if no other process is running", pid.trim(), path.to_string_lossy()) as Fatal);
+                    return logger.crash();
+                }
+
+                if !waiting {
+                    log!((logger.vital) Lock("Waiting for another diary-cli process to release the archive...") as Log);
+                    waiting = true;
+                } std::thread::sleep(Duration::from_millis(100));
+            },
+            Err(err) => {
+                log!((logger.error) Lock("While locking the archive: {err:?}") as Fatal);
+                return logger.crash();
+            },
+        }
+    }
+}
+
+/// Releases the archive lock if this process holds it
+pub fn release() {
+    if HELD.swap(false, Ordering::SeqCst) {
+        let _ = fs::remove_file(lock_path());
+    }
+}
@@ -1,5 +1,30 @@
 use soulog::*;
-use crate::cli::VERBOSE;
+use crate::cli::{VERBOSE, JSON, PRIVATE, PLAIN};
+use crate::redact::redact;
+
+/// Removes the ansi escape codes (colours) from text
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' { result.push(c); continue }
+        if chars.next() != Some('[') { continue }
+        for c in chars.by_ref() {
+            if c.is_ascii_alphabetic() { break }
+        }
+    } result
+}
+
+/// Prints a log to stdout, or to stderr if stdout is reserved for json output
+fn print_log(message: &str) {
+    let message = if unsafe { PRIVATE } { redact(message) } else { message.to_string() };
+    let message = if unsafe { PLAIN } { strip_ansi(&message) } else { message };
+    if unsafe { JSON } {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
 
 pub struct DynamicLogger {
     verbose: Option<Verbose>,
@@ -64,12 +89,14 @@ impl Logger for Verbose {
     fn crash<T>(&mut self) -> T {
         let mut logger = Self::new();
         log!((logger.vital) Diary("if the fatal error occurred during any writing to the archive, the archive may be corrupted! If so, then use `diary-cli rollback` to roll-back to the latest backup (that was made before any modification of the archive") as Warning);
+        crate::error::print_fatal();
+        crate::lock::release();
         std::process::exit(1)
     }
 
     fn verbose(&mut self, log: Log) {
         self.retry_count = 2;
-        println!("{}", colour_format!(blue("["), cyan(log.origin), blue("] "), none(log.message)));
+        print_log(&colour_format!(blue("["), cyan(log.origin), blue("] "), none(log.message)));
     }
 
     fn error(&mut self, log: Log) -> ErrorResponse {
@@ -77,7 +104,8 @@ impl Logger for Verbose {
             LogType::Failure => colour_format![blue("["), red(log.origin), blue("] "), red("Failure"), blue(": "), none(log.message)],
             LogType::Fatal => colour_format![blue("["), red(log.origin), blue("] "), red("Fatal"), blue(": "), none(log.message)],
             _ => panic!("meta error: invalid error log type '{:?}'", log.log_type),
-        }; println!("{message}");
+        }; print_log(&message);
+        if matches!(log.log_type, LogType::Fatal) { crate::error::record_log(&log) }
 
         if ErrorResponse::AskUser.allowed_in(&log) { return ErrorResponse::AskUser };
         if ErrorResponse::Retry.allowed_in(&log) && self.retry_count > 0 {
@@ -97,7 +125,7 @@ impl Logger for Verbose {
             LogType::Result => colour_format![blue("["), green("Result"), blue("] "), green(log.origin), blue(": "), none(log.message)],
             LogType::Log => colour_format!(blue("["), cyan(log.origin), blue("] "), none(log.message)),
             _ => panic!("meta error: invalid error log type '{:?}'", log.log_type),
-        }; println!("{message}");
+        }; print_log(&message);
     }
 }
 
@@ -112,6 +140,8 @@ impl Logger for Quiet {
     fn crash<T>(&mut self) -> T {
         let mut logger = Self::new();
         log!((logger.vital) Diary("The archive may now be corrupted! Use `diary-cli rollback` to roll-back to the latest backup (that was made before any modification of the archive") as Warning);
+        crate::error::print_fatal();
+        crate::lock::release();
         std::process::exit(1)
     }
 
@@ -124,7 +154,8 @@ impl Logger for Quiet {
             LogType::Failure => colour_format![blue("["), red(log.origin), blue("] "), red("Failure"), blue(": "), none(log.message)],
             LogType::Fatal => colour_format![blue("["), red(log.origin), blue("] "), red("Fatal"), blue(": "), none(log.message)],
             _ => panic!("meta error: invalid error log type '{:?}'", log.log_type),
-        }; println!("{message}");
+        }; print_log(&message);
+        if matches!(log.log_type, LogType::Fatal) { crate::error::record_log(&log) }
 
         if ErrorResponse::AskUser.allowed_in(&log) { return ErrorResponse::AskUser };
         if ErrorResponse::Retry.allowed_in(&log) && self.retry_count > 0 {
@@ -144,6 +175,6 @@ impl Logger for Quiet {
             LogType::Result => colour_format![blue("["), green("Result"), blue("] "), green(log.origin), blue(": "), none(log.message)],
             LogType::Log => colour_format!(blue("["), cyan(log.origin), blue("] "), none(log.message)),
             _ => panic!("meta error: invalid error log type '{:?}'", log.log_type),
-        }; println!("{message}");
+        }; print_log(&message);
     }
 }
\ No newline at end of file
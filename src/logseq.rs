@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use soulog::*;
+use crate::{archive::Archive, config::Config, entry::Entry, export::ExportOptions, incremental::{self, ExportState}, links, moc::{walk_collections, MOC}, sort::sort_uids, transform};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The file of a date's journal page (`journals/2023_08_21.md`)
+pub fn journal_file(date: &[u16; 3]) -> String {
+    format!("journals/{:04}_{:02}_{:02}.md", date[2], date[1], date[0])
+}
+
+/// The title Logseq gives a date's journal page by default (`Aug 21st, 2023`), which links to it
+pub fn journal_title(date: &[u16; 3]) -> String {
+    let suffix = match (date[0] % 10, date[0] % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{} {}{suffix}, {}", MONTHS[(date[1] as usize).clamp(1, 12) - 1], date[0], date[2])
+}
+
+/// The file of a page (`pages/<name>.md`); the namespaces of nested names (`a/b`) are split by `___` like Logseq does
+pub fn page_file(name: &str) -> String {
+    format!("pages/{}.md", name.replace('/', "___"))
+}
+
+/// Turns text into child blocks `depth` levels deep; each paragraph (or code block) becomes a block of its own
+pub fn blocks(text: &str, depth: usize) -> String {
+    let indent = "\t".repeat(depth);
+    let mut result = String::new();
+    let mut block: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    let flush = |block: &mut Vec<&str>, result: &mut String| if !block.is_empty() {
+        result.push_str(&format!("{indent}- {}\n", block[0]));
+        block[1..].iter().for_each(|x| result.push_str(&format!("{indent}  {x}\n")));
+        block.clear();
+    };
+
+    for line in text.lines().map(|x| x.trim_end()) {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") { in_code = !in_code }
+        if line.is_empty() && !in_code { flush(&mut block, &mut result) }
+        else { block.push(line) }
+    }
+    flush(&mut block, &mut result);
+    result
+}
+
+/// The `[[page]]` list of a property of a block or page (like `tags::`)
+fn tags_property(tags: &[String]) -> String {
+    tags.iter().map(|x| format!("[[{x}]]")).collect::<Vec<_>>().join(", ")
+}
+
+/// The titles and pages of the exported items, by uid, which cross-references link to
+type Pages = HashMap<String, (String, String)>;
+
+/// Renders an entry as a block of its day's journal page, with its sections, notes and attachments as child blocks
+fn entry_block(path: &Path, entry: &mut Entry, pages: &Pages, options: &ExportOptions, state: &ExportState, mut logger: impl Logger) -> String {
+    let uid = entry.uid.clone();
+    let tags = entry.tags(logger.hollow()).clone();
+    let mut result = format!("- ## {}\n  uid:: {uid}\n", entry.title(logger.hollow()));
+    if !tags.is_empty() { result.push_str(&format!("  tags:: {}\n", tags_property(&tags))) }
+
+    // Logseq shows the backlinks of the pages linked to by itself
+    let links: Vec<String> = entry.links(logger.hollow()).iter().filter_map(|x| pages.get(x).map(|(_, page)| page.clone())).collect();
+    if !links.is_empty() { result.push_str(&format!("  links:: {}\n", tags_property(&links))) }
+
+    // Time capsules only export their title until they unlock
+    if !options.force_unlock && entry.is_locked(logger.hollow()) {
+        let unlock_date = entry.unlock_date(logger.hollow()).unwrap();
+        result.push_str(&format!("\t- *This entry is a time capsule sealed until [[{}]]*\n", journal_title(&unlock_date)));
+        return result;
+    }
+
+    result.push_str(&blocks(entry.description(logger.hollow()), 1));
+    let notes = entry.notes(logger.hollow());
+    if !notes.is_empty() {
+        result.push_str("\t- **Notes**\n");
+        notes.iter().for_each(|x| result.push_str(&blocks(x, 2)));
+    }
+
+    let transforms = &Config::get().export_transforms;
+    entry.sections(logger.hollow()).iter_mut().for_each(|section| {
+        result.push_str(&format!("\t- ### {}\n", section.title(logger.hollow())));
+        let content = transform::apply(transforms, section.content(logger.hollow()), &tags);
+        let content = links::render_cross_references(&content, |uid, label| pages.get(uid).map(|(title, page)| format!("[{}]([[{page}]])", label.unwrap_or(title))));
+        result.push_str(&blocks(&content, 2));
+        section.notes(logger.hollow()).iter().for_each(|x| result.push_str(&blocks(&format!("*{x}*"), 2)));
+
+        // Attachments go into the graph's `assets/` folder
+        for (i, name) in section.attachments(logger.hollow()).clone().iter().enumerate() {
+            log!((logger) Export("Exporting attachment '{name}' of entry '{uid}'..."));
+            let file = format!("assets/{uid}/{name}");
+            incremental::write_file(path, &file, section.attachment(i, logger.hollow()), state, logger.hollow());
+            result.push_str(&format!("\t\t- ![{name}](../{})\n", file.replace(' ', "%20")));
+        }
+        section.clear_cache();
+    });
+    result
+}
+
+/// Exports entries into a Logseq graph: their days' `journals/` pages, a page per tag listing the days tagged with it
+/// and a page per moc listing the days of each of its collections
+pub fn export_logseq(path: &Path, entries: Vec<Entry>, mut mocs: Vec<MOC>, archive: &Archive, options: &ExportOptions, mut logger: impl Logger) {
+    let state = ExportState::default();
+
+    // Chronological order
+    let uids: Vec<String> = entries.iter().map(|x| x.uid.clone()).collect();
+    let mut by_uid: std::collections::HashMap<String, Entry> = entries.into_iter().map(|x| (x.uid.clone(), x)).collect();
+    let mut entries: Vec<Entry> = sort_uids(&uids, false, logger.hollow()).iter().filter_map(|x| by_uid.remove(x)).collect();
+    let pages: Pages = entries.iter_mut().map(|x| (x.uid.clone(), (x.title(logger.hollow()).clone(), journal_title(x.date(logger.hollow())))))
+        .chain(mocs.iter_mut().map(|x| (x.uid.clone(), (x.title(logger.hollow()).clone(), x.uid.clone()))))
+        .collect();
+
+    // Entries of the same day share its journal page
+    let mut journals: BTreeMap<String, String> = BTreeMap::new();
+    let mut links: BTreeMap<String, String> = BTreeMap::new();
+    let mut tagged: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for mut entry in entries {
+        log!((logger) Export("Exporting entry of uid '{}' into its Logseq journal...", entry.uid));
+        let date = *entry.date(logger.hollow());
+        let link = format!("[[{}]] {}", journal_title(&date), entry.title(logger.hollow()));
+        entry.tags(logger.hollow()).iter().for_each(|x| tagged.entry(x.clone()).or_default().push(link.clone()));
+        links.insert(entry.uid.clone(), link);
+
+        let block = entry_block(path, &mut entry, &pages, options, &state, logger.hollow());
+        journals.entry(journal_file(&date)).or_default().push_str(&block);
+        entry.clear_cache();
+    }
+    for (file, text) in journals.iter() {
+        incremental::write_file(path, file, text, &state, logger.hollow());
+    }
+
+    // Tag pages
+    for (tag, items) in tagged.iter() {
+        let text: String = items.iter().map(|x| format!("- {x}\n")).collect();
+        incremental::write_file(path, &page_file(tag), text, &state, logger.hollow());
+    }
+
+    // Moc pages
+    for mut moc in mocs {
+        log!((logger) Export("Exporting moc of uid '{}' as a Logseq page...", moc.uid));
+        let tags = moc.tags(logger.hollow()).clone();
+        let mut text = format!("title:: {}\n", moc.title(logger.hollow()));
+        if !tags.is_empty() { text.push_str(&format!("tags:: {}\n", tags_property(&tags))) }
+        text.push('\n');
+        text.push_str(&blocks(moc.description(logger.hollow()), 0));
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+            // Sub-collections are child blocks of their collection
+            let indent = "\t".repeat(at.len() - 1);
+            text.push_str(&format!("{indent}- {} {}\n", "#".repeat((at.len() + 1).min(6)), collection.title(logger.hollow())));
+            if collection.groups_only(logger.hollow()) { return collection.clear_cache() }
+            let uids = collection.search_entries(archive.list_entries(logger.hollow()), logger.hollow());
+            let included: Vec<String> = sort_uids(&uids, false, logger.hollow()).into_vec().into_iter().filter(|x| links.contains_key(x)).collect();
+            let included = collection.newest(&included, logger.hollow()).to_vec();
+            collection.arrange(included, |x| x, logger.hollow()).iter()
+                .for_each(|x| text.push_str(&format!("{indent}\t- {}\n", links[x])));
+            collection.clear_cache();
+        }, logger.hollow());
+
+        incremental::write_file(path, &page_file(&moc.uid), text, &state, logger.hollow());
+        moc.clear_cache();
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::{Component, Path};
+use soulog::*;
+use clap::ValueEnum;
+use crate::{access::Access, archive::Archive, incremental::fingerprint};
+
+/// The manifest written into exported vaults, recording the files the export wrote
+pub const MANIFEST: &str = "diary-cli-export.toml";
+
+/// The filters an export was made with, recorded so it can be reproduced
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filters {
+    pub tags: Option<Vec<String>>,
+    pub strict: bool,
+    pub include_archived: bool,
+    pub force_unlock: bool,
+    /// Only the items readers with this access can see were exported
+    pub access: Option<Access>,
+}
+
+/// What an export wrote into a vault and what it was exported from
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+    /// The uid of the archive that was exported
+    pub archive: String,
+    /// The itver of the archive when it was exported
+    pub itver: u16,
+    pub filters: Filters,
+    /// Every file (relative to the vault) exported with the hash of its contents
+    /// (manifests from before hashes were recorded only have the files)
+    pub files: Vec<(String, Option<u64>)>,
+}
+
+impl Manifest {
+    /// Reads the manifest of a previous export into a vault (if there is one)
+    pub fn read(path: &Path, mut logger: impl Logger) -> Option<Self> {
+        let file = path.join(MANIFEST);
+        if !file.is_file() { return None }
+
+        log!((logger) Manifest("Reading export manifest '{}'...", file.to_string_lossy()));
+        let text = if_err!((logger) [Manifest, err => ("While reading export manifest: {err:?}")] retry std::fs::read_to_string(&file));
+        let table = match text.parse::<toml::Table>() {
+            Ok(x) => x,
+            Err(err) => {
+                log!((logger.vital) Manifest("Export manifest '{}' is invalid ({}); ignoring it", file.to_string_lossy(), err.message()) as Warning);
+                return None;
+            },
+        };
+
+        let filters = table.get("filters").and_then(|x| x.as_table());
+        let flag = |key: &str| filters.and_then(|x| x.get(key)).and_then(|x| x.as_bool()).unwrap_or(false);
+        let files = match table.get("files") {
+            Some(toml::Value::Table(x)) => x.iter()
+                .map(|(file, hash)| (file.clone(), hash.as_str().and_then(|x| u64::from_str_radix(x, 16).ok())))
+                .collect(),
+            Some(toml::Value::Array(x)) => x.iter().filter_map(|x| x.as_str()).map(|x| (x.to_string(), None)).collect(),
+            _ => Vec::new(),
+        };
+
+        Some(Self {
+            archive: table.get("archive").and_then(|x| x.as_str()).unwrap_or_default().to_string(),
+            itver: table.get("itver").and_then(|x| x.as_integer()).unwrap_or_default() as u16,
+            filters: Filters {
+                tags: filters.and_then(|x| x.get("tags")).and_then(|x| x.as_array())
+                    .map(|x| x.iter().filter_map(|x| x.as_str().map(String::from)).collect()),
+                strict: flag("strict"),
+                include_archived: flag("include_archived"),
+                force_unlock: flag("force_unlock"),
+                access: filters.and_then(|x| x.get("access")).and_then(|x| x.as_str()).and_then(|x| Access::from_str(x, true).ok()),
+            },
+            files,
+        })
+    }
+
+    /// Writes the manifest of an export into a vault
+    pub fn write(&self, path: &Path, mut logger: impl Logger) {
+        let mut filters = toml::Table::new();
+        if let Some(tags) = &self.filters.tags { filters.insert("tags".into(), tags.clone().into()); }
+        filters.insert("strict".into(), self.filters.strict.into());
+        filters.insert("include_archived".into(), self.filters.include_archived.into());
+        filters.insert("force_unlock".into(), self.filters.force_unlock.into());
+        if let Some(access) = self.filters.access.and_then(|x| x.to_possible_value()) { filters.insert("access".into(), access.get_name().into()); }
+
+        let mut table = toml::Table::new();
+        table.insert("diary-cli".into(), env!("CARGO_PKG_VERSION").into());
+        table.insert("archive".into(), self.archive.clone().into());
+        table.insert("itver".into(), (self.itver as i64).into());
+        table.insert("filters".into(), filters.into());
+        table.insert("files".into(), self.files.iter()
+            .map(|(file, hash)| (file.clone(), toml::Value::from(hash.map(|x| format!("{x:016x}")).unwrap_or_default())))
+            .collect::<toml::Table>().into());
+
+        let text = if_err!((logger) [Manifest, err => ("While serialising export manifest: {err:?}")] {toml::to_string(&table)} crash logger.crash());
+        if_err!((logger) [Manifest, err => ("While writing export manifest: {err:?}")] retry std::fs::write(path.join(MANIFEST), &text));
+    }
+
+    /// The files recorded by the manifest
+    pub fn file_names(&self) -> Vec<String> {
+        self.files.iter().map(|(x, _)| x.clone()).collect()
+    }
+}
+
+/// Reads the files recorded by the manifest of a previous export into a vault (none if there isn't one)
+pub fn read_files(path: &Path, logger: impl Logger) -> Vec<String> {
+    Manifest::read(path, logger).map(|x| x.file_names()).unwrap_or_default()
+}
+
+/// Checks if a manifest file stays inside the vault
+fn is_contained(file: &str) -> bool {
+    Path::new(file).components().all(|x| matches!(x, Component::Normal(_)))
+}
+
+/// Deletes the files of a previous export (with the hashes they were exported with) that the current one didn't write,
+/// along with any folders that leaves empty; files edited since they were exported are left alone, as are files it
+/// can't tell (exported before hashes were recorded); returns how many files were deleted
+pub fn prune(path: &Path, previous: &[(String, Option<u64>)], current: &[String], mut logger: impl Logger) -> usize {
+    let mut pruned = 0;
+    for (file, hash) in previous.iter().filter(|(x, _)| !current.contains(x)) {
+        if !is_contained(file) {
+            log!((logger.vital) Manifest("Not pruning '{file}' as it is outside of the vault") as Warning);
+            continue;
+        }
+
+        let full = path.join(file);
+        if !full.is_file() { continue }
+        if hash.is_some_and(|hash| !std::fs::read(&full).is_ok_and(|x| fingerprint(x) == hash)) {
+            log!((logger.vital) Manifest("Not pruning '{file}' as it was edited since it was exported") as Warning);
+            continue;
+        }
+        log!((logger) Manifest("Pruning stale file '{file}'..."));
+        if_err!((logger) [Manifest, err => ("While pruning '{file}': {err:?}")] retry std::fs::remove_file(&full));
+        pruned += 1;
+
+        // Clean up the folders it leaves empty
+        let mut dir = full.parent();
+        while let Some(x) = dir.filter(|x| *x != path) {
+            if std::fs::remove_dir(x).is_err() { break } // not empty
+            dir = x.parent();
+        }
+    } pruned
+}
+
+/// How a vault differs from the manifest of its export
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    /// Exported files that are no longer in the vault
+    pub missing: Vec<String>,
+    /// Exported files whose contents changed since they were exported
+    pub modified: Vec<String>,
+    /// Exported files that can't be checked as the manifest has no hash of them
+    pub unhashed: Vec<String>,
+    /// Files in the vault that weren't exported into it (hidden ones like `.obsidian/` are ignored)
+    pub untracked: Vec<String>,
+}
+
+impl Report {
+    /// Checks if every exported file is still in the vault unchanged
+    #[inline]
+    pub fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Lists the (non-hidden) files in a folder of the vault, relative to the vault
+fn list_files(path: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(read) = std::fs::read_dir(path.join(dir)) else { return };
+    for entry in read.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') { continue }
+        let file = dir.join(&name);
+        if entry.path().is_dir() { list_files(path, &file, files) }
+        else { files.push(file.to_string_lossy().to_string()) }
+    }
+}
+
+/// Compares the files of a vault against the manifest of its export
+pub fn check(path: &Path, manifest: &Manifest) -> Report {
+    let mut report = Report::default();
+    for (file, hash) in manifest.files.iter() {
+        let full = path.join(file);
+        if !full.is_file() { report.missing.push(file.clone()); continue }
+        let Some(hash) = hash else { report.unhashed.push(file.clone()); continue };
+        if !std::fs::read(&full).is_ok_and(|x| fingerprint(x) == *hash) { report.modified.push(file.clone()) }
+    }
+
+    let mut files = Vec::new();
+    list_files(path, Path::new(""), &mut files);
+    report.untracked = files.into_iter()
+        .filter(|x| x != MANIFEST && !manifest.files.iter().any(|(file, _)| file == x))
+        .collect();
+    report.untracked.sort();
+    report
+}
+
+/// Verifies that a vault still matches the manifest of its export, crashing if it doesn't
+pub fn verify(path: &Path, mut logger: impl Logger) {
+    let path_string = path.to_string_lossy();
+    log!((logger) Verify("Verifying export '{path_string}'..."));
+    let Some(manifest) = Manifest::read(path, logger.hollow()) else {
+        log!((logger.error) Verify("'{path_string}' has no export manifest; export into it before verifying it") as Fatal);
+        return logger.crash();
+    };
+
+    let archive = Archive::load(logger.hollow());
+    if manifest.archive != archive.uid().to_string() {
+        log!((logger.vital) Verify("'{path_string}' was exported from a different archive ('{}')", manifest.archive) as Warning);
+    } else if manifest.itver != archive.itver {
+        log!((logger.vital) Verify("The archive has changed since '{path_string}' was exported (itver {} -> {}); re-export to bring it up to date", manifest.itver, archive.itver) as Warning);
+    }
+
+    let report = check(path, &manifest);
+    report.untracked.iter().for_each(|x| log!((logger) Verify("File '{x}' wasn't exported into the vault")));
+    if !report.untracked.is_empty() {
+        log!((logger.vital) Verify("{} files in '{path_string}' weren't exported into it", report.untracked.len()) as Inconvenience);
+    }
+    if !report.unhashed.is_empty() {
+        log!((logger.vital) Verify("{} exported files can't be verified as the manifest predates file hashes; re-export to record them", report.unhashed.len()) as Warning);
+    }
+
+    if !report.is_intact() {
+        report.missing.iter().for_each(|x| log!((logger.vital) Verify("Exported file '{x}' is missing") as Warning));
+        report.modified.iter().for_each(|x| log!((logger.vital) Verify("Exported file '{x}' was modified since it was exported") as Warning));
+        log!((logger.error) Verify("Export '{path_string}' doesn't match its manifest ({} problems found)", report.missing.len() + report.modified.len()) as Fatal);
+        return logger.crash();
+    }
+    log!((logger.vital) Verify("Successfully verified export '{path_string}' ({} files match its manifest)", manifest.files.len() - report.unhashed.len()) as Log);
+}
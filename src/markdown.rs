@@ -0,0 +1,154 @@
+use toml::{Table, Value};
+use soulog::*;
+use crate::entry::{format_date, today};
+
+/// The frontmatter keys a markdown entry can set
+const KEYS: [&str; 9] = ["uid", "title", "description", "date", "unlock_date", "tags", "notes", "links", "template"];
+
+/// Parses a lightweight markdown entry into an entry config: frontmatter sets its `uid`, `title`, `date`, `tags`,
+/// `description`, `notes`, `links`, `unlock_date` and `template`, each `##` heading starts a section, a `#` heading can give the title
+/// and any text before the first section is the description
+pub fn parse(text: &str, name: &str, mut logger: impl Logger) -> Table {
+    log!((logger) Markdown("Parsing markdown entry '{name}'..."));
+    let (frontmatter, body) = split_frontmatter(text);
+    let mut entry = frontmatter.map(|x| parse_frontmatter(x, name, logger.hollow())).unwrap_or_default();
+
+    // Split the body into sections by its `##` headings (ignoring ones in code blocks)
+    let mut leading = Vec::new();
+    let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+    let mut in_code = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") { in_code = !in_code }
+        if !in_code {
+            if let Some(title) = line.strip_prefix("## ") {
+                sections.push((title.trim().to_string(), Vec::new()));
+                continue;
+            }
+            if let Some(title) = line.strip_prefix("# ").filter(|_| sections.is_empty() && !entry.contains_key("title")) {
+                entry.insert("title".into(), title.trim().into());
+                continue;
+            }
+        }
+        match sections.last_mut() {
+            Some((_, lines)) => lines.push(line),
+            None => leading.push(line),
+        }
+    }
+
+    // Text before the first section is the description
+    let leading = block(&leading);
+    if !leading.is_empty() {
+        if entry.contains_key("description") {
+            log!((logger.error) Markdown("Markdown entry '{name}' has both a 'description' in its frontmatter and text before its first '##' section") as Fatal);
+            return logger.crash();
+        } entry.insert("description".into(), leading.trim_end().into());
+    }
+
+    // Defaults
+    if !entry.contains_key("title") {
+        log!((logger.error) Markdown("Markdown entry '{name}' must have a title (a 'title' in its frontmatter or a '# title' heading)") as Fatal);
+        return logger.crash();
+    }
+    let date = match entry.get("date") {
+        Some(Value::Datetime(x)) => x.date.map(|x| [x.day as u16, x.month as u16, x.year]).unwrap_or_else(today),
+        _ => today(),
+    };
+    entry.entry("date").or_insert_with(|| Value::Datetime(format_date(&date).parse().unwrap()));
+    entry.entry("uid").or_insert_with(|| format_date(&date).into());
+    entry.entry("description").or_insert_with(|| "".into());
+    entry.entry("tags").or_insert_with(|| Value::Array(Vec::new()));
+
+    let sections: Vec<Value> = sections.into_iter().map(|(title, lines)| {
+        let mut section = Table::new();
+        section.insert("title".into(), title.into());
+        section.insert("contents".into(), block(&lines).into());
+        section.into()
+    }).collect();
+
+    let mut table = Table::new();
+    table.insert("entry".into(), entry.into());
+    table.insert("section".into(), sections.into());
+    table
+}
+
+/// Splits the `---` delimited frontmatter off of the start of a markdown file
+fn split_frontmatter(text: &str) -> (Option<&str>, &str) {
+    let Some(start) = text.find('\n').filter(|x| text[..*x].trim_end() == "---").map(|x| x + 1) else { return (None, text) };
+    let mut end = start;
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim_end() == "---" { return (Some(&text[start..end]), &text[end + line.len()..]) }
+        end += line.len();
+    } (None, text)
+}
+
+/// Joins lines into a block of text without its surrounding blank lines (ending in a newline unless it is empty)
+fn block(lines: &[&str]) -> String {
+    let text = lines.join("\n");
+    let text = text.trim_matches(|x| x == '\n' || x == '\r');
+    if text.trim().is_empty() { String::new() } else { format!("{text}\n") }
+}
+
+/// Parses a yaml scalar (possibly quoted)
+fn scalar(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(x) = value.strip_prefix(quote).and_then(|x| x.strip_suffix(quote)) { return x.to_string() }
+    } value.to_string()
+}
+
+/// Parses the simple yaml of a markdown entry's frontmatter (`key: value`, `key: [a, b]` and `- item` lists)
+fn parse_frontmatter(frontmatter: &str, name: &str, mut logger: impl Logger) -> Table {
+    let mut pairs: Vec<(String, Value)> = Vec::new();
+    for line in frontmatter.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue }
+
+        // An item of a block list
+        if let Some(item) = trimmed.strip_prefix("- ").or((trimmed == "-").then_some("")) {
+            match pairs.last_mut() {
+                Some((_, Value::Array(x))) => x.push(scalar(item).into()),
+                _ => {
+                    log!((logger.error) Markdown("Markdown entry '{name}'s frontmatter has a list item '{trimmed}' that isn't under a key") as Fatal);
+                    return logger.crash();
+                },
+            } continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            log!((logger.error) Markdown("Markdown entry '{name}'s frontmatter line '{trimmed}' must be a 'key: value' pair") as Fatal);
+            return logger.crash();
+        };
+        let (key, value) = (key.trim().to_string(), value.trim());
+        let value = match value.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            Some(list) => Value::Array(list.split(',').map(scalar).filter(|x| !x.is_empty()).map(Value::from).collect()),
+            None if value.is_empty() => Value::Array(Vec::new()), // the items follow
+            None => scalar(value).into(),
+        };
+        pairs.push((key, value));
+    }
+
+    let mut table = Table::new();
+    for (key, value) in pairs {
+        if !KEYS.contains(&key.as_str()) {
+            log!((logger.vital) Markdown("Ignoring unknown key '{key}' in markdown entry '{name}'s frontmatter (known keys are {KEYS:?})") as Inconvenience);
+            continue;
+        }
+        let value = match (key.as_str(), value) {
+            ("date" | "unlock_date", Value::String(x)) => match x.parse::<toml::value::Datetime>() {
+                Ok(x) if x.date.is_some() => Value::Datetime(x),
+                _ => {
+                    log!((logger.error) Markdown("Markdown entry '{name}'s '{key}' must be a date like 2023-08-21, not '{x}'") as Fatal);
+                    return logger.crash();
+                },
+            },
+            ("tags" | "notes" | "links", Value::String(x)) => Value::Array(vec![x.into()]),
+            ("tags" | "notes" | "links", x) => x,
+            (_, Value::String(x)) => x.into(),
+            _ => {
+                log!((logger.error) Markdown("Markdown entry '{name}'s '{key}' must be a single value, not a list") as Fatal);
+                return logger.crash();
+            },
+        };
+        table.insert(key, value);
+    } table
+}
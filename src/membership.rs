@@ -0,0 +1,51 @@
+use soulog::*;
+use crate::{archive::Archive, moc::walk_collections, search::query_matches};
+
+/// The tags an entry is missing to be included by a collection; `!tag` when it has a tag the collection excludes
+pub fn missing_tags(entry_tags: &[String], include: &[String]) -> Vec<String> {
+    let has = |query: &str| entry_tags.iter().any(|x| query_matches(x, query));
+    include.iter()
+        .filter(|x| match x.strip_prefix('!') {
+            Some(negated) => has(negated),
+            None => !has(x),
+        }).cloned()
+        .collect()
+}
+
+/// Reports which moc collections a just committed entry appears in and which ones it only just misses
+pub fn report(archive: &Archive, uid: String, mut logger: impl Logger) {
+    if !archive.database_exists(format!("entries/{uid}")) { return } // mocs aren't members of collections
+    let Some(mut entry) = archive.get_entry(uid.clone(), logger.hollow()) else { return };
+    let tags = entry.tags(logger.hollow()).clone();
+    let date = *entry.date(logger.hollow());
+    let mut member = false;
+
+    for mut moc in archive.list_mocs(logger.hollow()) {
+        let title = moc.title(logger.hollow()).clone();
+        let hollow = logger.hollow();
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, _| {
+            // Collections that only group sub-collections include nothing themselves
+            // and dated ones nothing outside of their range, whatever its tags
+            if collection.groups_only(logger.hollow()) || !collection.in_range(&date, logger.hollow()) { return collection.clear_cache() }
+            let missing = missing_tags(&tags, &collection.query(logger.hollow()));
+            let name = colour_format![none("moc "), cyan(&title), none(" (collection "), cyan(collection.title(logger.hollow())), none(")")];
+            match missing.as_slice() {
+                [] => {
+                    member = true;
+                    log!((logger.vital) Commit("Entry '{uid}' appears on {name}") as Log);
+                },
+                [tag] => match tag.strip_prefix('!') {
+                    Some(tag) => log!((logger.vital) Commit("Removing tag '{tag}' would put entry '{uid}' on {name}") as Log),
+                    None => log!((logger.vital) Commit("Adding tag '{tag}' would put entry '{uid}' on {name}") as Log),
+                },
+                _ => (),
+            }
+            collection.clear_cache();
+        }, hollow);
+        moc.clear_cache();
+    }
+
+    if !member {
+        log!((logger.vital) Commit("Entry '{uid}' doesn't appear on any moc") as Log);
+    }
+}
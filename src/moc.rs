@@ -1,9 +1,10 @@
 pub mod collection;
+pub mod generate;
 
 pub use collection::*;
 use soulog::*;
 use lazy_db::*;
-use crate::{entry::*, search::Searchable};
+use crate::{archive::Archive, entry::*, search::{Searchable, query_matches}};
 use toml::Table;
 
 // Some ease of life macros
@@ -210,9 +211,20 @@ impl Searchable for MOC {
         self.uid.clone()
     }
 
+    fn container(&self) -> &LazyContainer {
+        &self.container
+    }
+
     fn contains_tag(&mut self, tag: &String, logger: impl Logger) -> bool {
-        let result = self.tags(logger).contains(tag);
+        let result = self.tags(logger).iter().any(|x| query_matches(x, tag));
         self.tags = None;
         result
     }
-}
\ No newline at end of file
+}
+/// Keeps the collection orders of the moc a moc config replaces, if it replaces one (see `keep_orders`)
+pub fn keep_old_orders(table: &mut Table, archive: &Archive, logger: impl Logger) {
+    let Some(uid) = table.get("moc").and_then(|x| x.get("uid")).and_then(|x| x.as_str()).map(String::from) else { return };
+    if !archive.database_exists(format!("mocs/{uid}")) { return }
+    let Some(mut old) = archive.get_moc(uid, logger.hollow()) else { return };
+    if let Some(toml::Value::Array(x)) = table.get_mut("collection") { keep_orders(x, old.collections(logger.hollow()), logger) }
+}
@@ -1,6 +1,6 @@
 use soulog::*;
 use lazy_db::*;
-use crate::entry::*;
+use crate::{entry::*, moc::MOC, search, sort::younger};
 use toml::{Table, Value};
 
 // Some ease of life macros
@@ -26,6 +26,20 @@ pub struct Collection {
     pub title: Option<String>,
     pub notes: Option<Box<[String]>>,
     pub include: Option<Box<[String]>>,
+    /// Tags of the items left out of the collection even if they have the included ones
+    pub exclude: Option<Box<[String]>>,
+    /// The first date of the entries in the collection (day, month then year)
+    pub from: Option<Option<[u16; 3]>>,
+    /// The last date of the entries in the collection (day, month then year)
+    pub to: Option<Option<[u16; 3]>>,
+    /// How many of the newest entries the exported collection lists at most
+    pub limit: Option<Option<u16>>,
+    /// How many items each page of the collection holds when exported
+    pub paginate: Option<Option<u16>>,
+    /// Sub-collections (`[[collection.collection]]`), nested as deeply as needed
+    pub collections: Option<Box<[Collection]>>,
+    /// The uids of the items listed first, in this order, before the rest of the collection's
+    pub order: Option<Box<[String]>>,
 }
 
 impl Collection {
@@ -36,7 +50,46 @@ impl Collection {
         log!((logger) Collection("Reading collection's data..."));
         let title = get!(title at (moc, idx) from table as as_str with logger).to_string();
         get!(raw_notes = notes at (moc, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
-        let raw_include = get!(include at (moc, idx) from table as as_array with logger);
+        let raw_collections = table.get("collection")
+            .map(|x| unwrap_opt!((x.as_array()) with logger, format: Collection("moc '{moc}', collection {idx}'s 'collection' attribute must be a list of tables (`[[collection.collection]]`)")))
+            .cloned()
+            .unwrap_or_default();
+
+        // Collections that only group sub-collections don't need to include anything themselves
+        let raw_include = match raw_collections.is_empty() {
+            true => get!(include at (moc, idx) from table as as_array with logger).clone(),
+            false => { get!(raw_include = include at (moc, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0)); raw_include.clone() },
+        };
+
+        get!(raw_exclude = exclude at (moc, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
+        get!(raw_order = order at (moc, idx) from table as as_array with logger or Vec::<toml::Value>::with_capacity(0));
+        let mut positive = |key: &str| table.get(key).map(|x| {
+            let x = unwrap_opt!((x.as_integer()) with logger, format: Collection("moc '{moc}', collection {idx}'s '{key}' attribute must be an integer"));
+            if x < 1 || x > u16::MAX as i64 {
+                log!((logger.error) Collection("moc '{moc}', collection {idx}'s '{key}' attribute must be between 1 and {}", u16::MAX) as Fatal);
+                return logger.crash();
+            } x as u16
+        });
+        let paginate = positive("paginate");
+        let limit = positive("limit");
+
+        // Get the date range (both ends included)
+        let mut date = |key: &str| table.get(key).map(|x| {
+            let date = match x {
+                Value::Datetime(x) => x.date,
+                Value::String(x) => x.parse::<toml::value::Datetime>().ok().and_then(|x| x.date),
+                _ => None,
+            };
+            let date = unwrap_opt!((date) with logger, format: Collection("moc '{moc}', collection {idx}'s '{key}' attribute must be a date"));
+            [ date.day as u16, date.month as u16, date.year ]
+        });
+        let (from, to) = (date("from"), date("to"));
+        if let (Some(from), Some(to)) = (&from, &to) {
+            if younger(from, to) {
+                log!((logger.error) Collection("moc '{moc}', collection {idx}'s 'from' date must not be after its 'to' date") as Fatal);
+                return logger.crash();
+            }
+        }
 
         // Parse arrays
         unpack_array!(notes from raw_notes with logger by x
@@ -47,12 +100,41 @@ impl Collection {
             => unwrap_opt!((x.as_str()) with logger, format: Collection("All included groups in moc '{moc}', collection '{idx}' must be strings")).to_string()
         );
 
+        unpack_array!(exclude from raw_exclude with logger by x
+            => unwrap_opt!((x.as_str()) with logger, format: Collection("All excluded tags in moc '{moc}', collection '{idx}' must be strings")).to_string()
+        );
+
+        unpack_array!(order from raw_order with logger by x
+            => unwrap_opt!((x.as_str()) with logger, format: Collection("All ordered uids in moc '{moc}', collection '{idx}' must be strings")).to_string()
+        );
+
+        // Sub-collections are written into the `collections` container just like a moc's are
+        let path = format!("{moc}' > '{title}");
+        let collections = if raw_collections.is_empty() { Vec::new() } else {
+            log!((logger) Collection("Parsing the sub-collections of moc '{moc}'s collection {idx}..."));
+            let list = if_err!((logger) [Collection, err => ("While initialising sub-collections: {err:?}")] retry container.new_container("collections"));
+            unpack_array!(collections from raw_collections with logger by (i, x) => {
+                let container = if_err!((logger) [Collection, err => ("While initialising sub-collection {i}: {err:?}")] retry list.new_container(i.to_string()));
+                let table = unwrap_opt!((x.as_table()) with logger, format: Collection("moc '{path}', collection {i} must be a toml table"));
+                Self::new(table, container, &path, i as u8, logger.hollow())
+            });
+            if_err!((logger) [Collection, err => ("While writing sub-collection list length: {err:?}")] retry write_container!((list) length = new_u16(raw_collections.len() as u16)));
+            collections
+        };
+
         log!((logger) Collection("Writing moc '{moc}'s collection {idx} into archive..."));
         let mut this = Self {
             container,
             title: Some(title),
             notes: Some(notes.into_boxed_slice()),
             include: Some(include.into_boxed_slice()),
+            exclude: Some(exclude.into_boxed_slice()),
+            from: Some(from),
+            to: Some(to),
+            limit: Some(limit),
+            paginate: Some(paginate),
+            collections: Some(collections.into_boxed_slice()),
+            order: Some(order.into_boxed_slice()),
         };
 
         this.store_lazy(logger.hollow());
@@ -69,6 +151,21 @@ impl Collection {
         map.insert("title".into(), Value::String(self.title(logger.hollow()).clone()));
         map.insert("notes".into(), self.notes(logger.hollow()).to_vec().into());
         map.insert("include".into(), self.include(logger.hollow()).to_vec().into());
+        let exclude = self.exclude(logger.hollow());
+        if !exclude.is_empty() { map.insert("exclude".into(), exclude.to_vec().into()); }
+        for (key, date) in [("from", *self.from(logger.hollow())), ("to", *self.to(logger.hollow()))] {
+            if let Some(x) = date.and_then(|x| format_date(&x).parse().ok()) { map.insert(key.into(), Value::Datetime(x)); }
+        }
+        if let Some(x) = self.limit(logger.hollow()) {
+            map.insert("limit".into(), (*x as i64).into());
+        }
+        if let Some(x) = self.paginate(logger.hollow()) {
+            map.insert("paginate".into(), (*x as i64).into());
+        }
+        let order = self.order(logger.hollow());
+        if !order.is_empty() { map.insert("order".into(), order.to_vec().into()); }
+        let collections: Vec<Table> = self.collections(logger.hollow()).iter_mut().map(|x| x.pull(logger.hollow())).collect();
+        if !collections.is_empty() { map.insert("collection".into(), collections.into()); }
 
         self.clear_cache();
 
@@ -78,6 +175,10 @@ impl Collection {
     pub fn store_lazy(&self, mut logger: impl Logger) {
         // Only store them if they are accessed (maybe modified)
         if let Some(x) = &self.title { write_db_container!(Collection(self.container) title = new_string(x) with logger); }
+        if let Some(Some(x)) = &self.paginate { write_db_container!(Collection(self.container) paginate = new_u16(*x) with logger); }
+        if let Some(Some(x)) = &self.from { write_db_container!(Collection(self.container) from = new_u16_array(x) with logger); }
+        if let Some(Some(x)) = &self.to { write_db_container!(Collection(self.container) to = new_u16_array(x) with logger); }
+        if let Some(Some(x)) = &self.limit { write_db_container!(Collection(self.container) limit = new_u16(*x) with logger); }
         if let Some(x) = &self.notes {
             list::write(
                 x.as_ref(),
@@ -91,6 +192,22 @@ impl Collection {
                 x.as_ref(),
                 |file, data| LazyData::new_string(file, data),
                 &if_err!((logger) [Collection, err => ("While writing collection's included groups to archive: {:?}", err)] retry self.container.new_container("include")),
+                logger.hollow()
+            );
+        }
+        if let Some(x) = &self.exclude {
+            list::write(
+                x.as_ref(),
+                |file, data| LazyData::new_string(file, data),
+                &if_err!((logger) [Collection, err => ("While writing collection's excluded tags to archive: {:?}", err)] retry self.container.new_container("exclude")),
+                logger.hollow()
+            );
+        }
+        if let Some(x) = &self.order {
+            list::write(
+                x.as_ref(),
+                |file, data| LazyData::new_string(file, data),
+                &if_err!((logger) [Collection, err => ("While writing collection's order to archive: {:?}", err)] retry self.container.new_container("order")),
                 logger
             );
         }
@@ -102,19 +219,95 @@ impl Collection {
             title: None,
             notes: None,
             include: None,
+            exclude: None,
+            from: None,
+            to: None,
+            limit: None,
+            paginate: None,
+            collections: None,
+            order: None,
         }
     }
 
+    /// The tags searched for the items of the collection: the included ones, then the excluded ones as `!tag`s
+    pub fn query(&mut self, logger: impl Logger) -> Vec<String> {
+        let mut query = self.include(logger.hollow()).to_vec();
+        query.extend(self.exclude(logger.hollow()).iter().map(|x| format!("!{x}")));
+        query
+    }
+
+    /// If a date is within the collection's `from`/`to` range (always, if it doesn't have one)
+    pub fn in_range(&mut self, date: &[u16; 3], logger: impl Logger) -> bool {
+        let after = self.from(logger.hollow()).is_none_or(|x| !younger(&x, date));
+        after && self.to(logger).is_none_or(|x| !younger(date, &x))
+    }
+
+    /// If the collection only includes entries of some dates
+    pub fn is_dated(&mut self, logger: impl Logger) -> bool {
+        self.from(logger.hollow()).is_some() || self.to(logger).is_some()
+    }
+
+    /// The uids of the entries in the collection: the ones matching its query, dated within its range
+    pub fn search_entries(&mut self, entries: Vec<Entry>, logger: impl Logger) -> Vec<String> {
+        let entries: Vec<Entry> = entries.into_iter()
+            .filter_map(|mut x| {
+                let keep = self.in_range(x.date(logger.hollow()), logger.hollow());
+                x.clear_cache();
+                keep.then_some(x)
+            }).collect();
+        search::search_strict(&self.query(logger.hollow()), entries, logger)
+    }
+
+    /// The uids of the mocs in the collection; mocs aren't dated, so collections with a date range don't have any
+    pub fn search_mocs(&mut self, mocs: Vec<MOC>, logger: impl Logger) -> Vec<String> {
+        if self.is_dated(logger.hollow()) { return Vec::new() }
+        search::search_strict(&self.query(logger.hollow()), mocs, logger)
+    }
+
+    /// The newest `limit` of a collection's entries (sorted oldest first), or all of them if it has no limit
+    pub fn newest<'a, T>(&mut self, entries: &'a [T], logger: impl Logger) -> &'a [T] {
+        let limit = self.limit(logger).map(|x| x as usize).unwrap_or(usize::MAX);
+        &entries[entries.len().saturating_sub(limit)..]
+    }
+
+    /// Puts the items (of these uids) in the collection's `order` first, in that order, followed by the rest as they were
+    pub fn arrange<T>(&mut self, mut items: Vec<T>, uid: impl Fn(&T) -> &str, logger: impl Logger) -> Vec<T> {
+        let order = self.order(logger);
+        items.sort_by_key(|x| order.iter().position(|y| y == uid(x)).unwrap_or(usize::MAX));
+        items
+    }
+
+    /// If the collection only groups its sub-collections without including anything itself (it has no `include`);
+    /// `include = []` on its own includes everything
+    pub fn groups_only(&mut self, logger: impl Logger) -> bool {
+        let result = self.include(logger.hollow()).is_empty() && !self.collections(logger.hollow()).is_empty();
+        self.collections = None;
+        result
+    }
+
     pub fn clear_cache(&mut self) {
         self.title = None;
         self.notes = None;
         self.include = None;
+        self.exclude = None;
+        self.from = None;
+        self.to = None;
+        self.limit = None;
+        self.paginate = None;
+        self.collections = None;
+        self.order = None;
     }
 
     pub fn fill_cache(&mut self, logger: impl Logger) {
         self.title(logger.hollow());
         self.include(logger.hollow());
         self.notes(logger.hollow());
+        self.exclude(logger.hollow());
+        self.from(logger.hollow());
+        self.to(logger.hollow());
+        self.limit(logger.hollow());
+        self.paginate(logger.hollow());
+        self.order(logger.hollow());
     }
 
     cache_field!(notes(this, logger) -> Box<[String]> {
@@ -129,6 +322,83 @@ impl Collection {
         read_db_container!(title from Collection(this.container) as collect_string with logger)
     });
 
+    cache_field!(from(this, logger) -> Option<[u16; 3]> {
+        read_date(&this.container, "from", logger)
+    });
+
+    cache_field!(to(this, logger) -> Option<[u16; 3]> {
+        read_date(&this.container, "to", logger)
+    });
+
+    cache_field!(limit(this, logger) -> Option<u16> {
+        match this.container.read_data("limit") {
+            Ok(data) => Some(if_err!((logger) [Collection, err => ("While reading collection's limit: {err:?}")] {data.collect_u16()} crash {
+                log!((logger.error) Collection("{err:#?}") as Fatal);
+                logger.crash()
+            })),
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Collection("While reading collection's limit: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    });
+
+    cache_field!(paginate(this, logger) -> Option<u16> {
+        match this.container.read_data("paginate") {
+            Ok(data) => Some(if_err!((logger) [Collection, err => ("While reading collection's pagination: {err:?}")] {data.collect_u16()} crash {
+                log!((logger.error) Collection("{err:#?}") as Fatal);
+                logger.crash()
+            })),
+            Err(LDBError::FileNotFound(_)) => None,
+            Err(err) => {
+                log!((logger.error) Collection("While reading collection's pagination: {err:?}") as Fatal);
+                logger.crash()
+            },
+        }
+    });
+
+    cache_field!(collections(this, logger) -> Box<[Collection]> {
+        // Collections without sub-collections (or from before they were supported) don't have the container
+        if !this.container.path().join("collections").is_dir() { Box::new([]) }
+        else {
+            let container = if_err!((logger) [Collection, err => ("While reading from collection's sub-collections: {err:?}")] retry this.container.child_container("collections"));
+            let length = if_err!((logger) [Collection, err => ("While reading from collection's sub-collections' length: {err:?}")] retry container.read_data("length"));
+            let length = if_err!((logger) [Collection, err => ("While reading from collection's sub-collections' length: {err:?}")] {length.collect_u16()} crash {
+                log!((logger) Collection("{err:#?}") as Fatal);
+                logger.crash()
+            });
+
+            (0..length).map(|i| Self::load_lazy(
+                if_err!((logger) [Collection, err => ("While reading sub-collection {i}: {err:?}")] retry container.child_container(i.to_string()))
+            )).collect()
+        }
+    });
+
+    cache_field!(exclude(this, logger) -> Box<[String]> {
+        // Collections from before excluded tags were supported don't have any
+        if !this.container.path().join("exclude").is_dir() { Box::new([]) }
+        else {
+            list::read(
+                |data| data.collect_string(),
+                &if_err!((logger) [Collection, err => ("While reading from collection's excluded tags: {err:?}")] retry this.container.child_container("exclude")),
+                logger
+            )
+        }
+    });
+
+    cache_field!(order(this, logger) -> Box<[String]> {
+        // Collections that were never ordered by hand (or from before they could be) don't have any
+        if !this.container.path().join("order").is_dir() { Box::new([]) }
+        else {
+            list::read(
+                |data| data.collect_string(),
+                &if_err!((logger) [Collection, err => ("While reading from collection's order: {err:?}")] retry this.container.child_container("order")),
+                logger
+            )
+        }
+    });
+
     cache_field!(include(this, logger) -> Box<[String]> {
         list::read(
             |data| data.collect_string(),
@@ -136,4 +406,54 @@ impl Collection {
             logger
         )
     });
-}
\ No newline at end of file
+}
+
+/// Reads one end of a collection's date range, if it has it
+fn read_date(container: &LazyContainer, key: &str, mut logger: impl Logger) -> Option<[u16; 3]> {
+    match container.read_data(key) {
+        Ok(data) => {
+            let array = if_err!((logger) [Collection, err => ("While reading collection's '{key}' date: {err:?}")] {data.collect_u16_array()} crash {
+                log!((logger.error) Collection("{err:#?}") as Fatal);
+                logger.crash()
+            });
+            Some([array[0], array[1], array[2]])
+        },
+        Err(LDBError::FileNotFound(_)) => None,
+        Err(err) => {
+            log!((logger.error) Collection("While reading collection's '{key}' date: {err:?}") as Fatal);
+            logger.crash()
+        },
+    }
+}
+
+/// Calls `f` on each collection and then, depth first, on its sub-collections along with its position in the tree
+/// (*eg* `[1, 0]` for the first sub-collection of a moc's second collection, so its length is how deeply it is nested)
+pub fn walk_collections(collections: &mut [Collection], f: &mut impl FnMut(&mut Collection, &[usize]), logger: impl Logger) {
+    fn visit(collections: &mut [Collection], at: &mut Vec<usize>, f: &mut impl FnMut(&mut Collection, &[usize]), logger: &impl Logger) {
+        for (i, collection) in collections.iter_mut().enumerate() {
+            at.push(i);
+            f(collection, at);
+            visit(collection.collections(logger.hollow()), at, f, logger);
+            collection.collections = None;
+            at.pop();
+        }
+    }
+    visit(collections, &mut Vec::new(), f, &logger)
+}
+
+/// Keeps the `order` of the old collections (matched by title) of a moc being re-committed in the tables of its new
+/// ones, unless they set their own (`order = []` clears it)
+pub fn keep_orders(tables: &mut [Value], old: &mut [Collection], mut logger: impl Logger) {
+    for table in tables.iter_mut().filter_map(|x| x.as_table_mut()) {
+        let title = table.get("title").and_then(|x| x.as_str()).unwrap_or_default().to_string();
+        let Some(i) = old.iter_mut().position(|x| *x.title(logger.hollow()) == title) else { continue };
+        let old = &mut old[i];
+        if !table.contains_key("order") && !old.order(logger.hollow()).is_empty() {
+            log!((logger) Collection("Keeping the order of collection '{title}'"));
+            table.insert("order".into(), old.order(logger.hollow()).to_vec().into());
+        }
+        if let Some(Value::Array(collections)) = table.get_mut("collection") {
+            keep_orders(collections, old.collections(logger.hollow()), logger.hollow());
+        }
+    }
+}
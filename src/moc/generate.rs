@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use soulog::*;
+use toml::{Table, Value};
+use crate::{archive::Archive, archived, entry::format_date, search::tag_matches};
+
+/// How a generated moc splits the entries of its tag into collections
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// A collection per sub-tag (`travel/japan`, `travel/peru`), plus one for the entries with none of them
+    #[default]
+    Subtag,
+    /// A collection per year the tag was used in
+    Year,
+}
+
+/// The collections of a moc of the entries (their tags and dates) with a tag; empty if there's nothing to group
+pub fn collections(tag: &str, by: GroupBy, entries: &[(Vec<String>, [u16; 3])]) -> Vec<Table> {
+    let tag = tag.trim_end_matches('/');
+    let entries: Vec<&(Vec<String>, [u16; 3])> = entries.iter().filter(|(tags, _)| tags.iter().any(|x| tag_matches(x, tag))).collect();
+    let collection = |title: String, include: &str| {
+        let mut table = Table::new();
+        table.insert("title".into(), Value::String(title));
+        table.insert("include".into(), vec![include.to_string()].into());
+        table
+    };
+
+    match by {
+        GroupBy::Subtag => {
+            // Entries of deeper tags (`travel/japan/tokyo`) are grouped under the direct sub-tag they are in
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            let mut untagged = false;
+            for (tags, _) in entries.iter() {
+                let mut children: Vec<String> = tags.iter()
+                    .filter_map(|x| x.strip_prefix(tag)?.strip_prefix('/')?.split('/').next().map(|x| format!("{tag}/{x}")))
+                    .collect();
+                children.sort();
+                children.dedup();
+                untagged |= children.is_empty();
+                children.into_iter().for_each(|x| *counts.entry(x).or_default() += 1);
+            }
+            if counts.is_empty() { return Vec::new() }
+
+            // The most used first
+            let mut children: Vec<(String, usize)> = counts.into_iter().collect();
+            children.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            let mut result: Vec<Table> = children.iter()
+                .map(|(child, _)| collection(child[tag.len() + 1..].to_string(), child))
+                .collect();
+            if untagged {
+                let mut other = collection(String::from("Other"), tag);
+                other.insert("exclude".into(), children.into_iter().map(|x| x.0).collect::<Vec<_>>().into());
+                result.push(other);
+            } result
+        },
+        GroupBy::Year => {
+            let mut years: Vec<u16> = entries.iter().map(|(_, date)| date[2]).collect();
+            years.sort();
+            years.dedup();
+            years.into_iter().map(|year| {
+                let mut table = collection(year.to_string(), tag);
+                for (key, date) in [("from", [1, 1, year]), ("to", [31, 12, year])] {
+                    table.insert(key.into(), Value::Datetime(format_date(&date).parse().unwrap()));
+                } table
+            }).collect()
+        },
+    }
+}
+
+/// Builds a moc config of the entries with a tag, split into collections by its sub-tags or years
+pub fn generate(tag: &str, by: GroupBy, uid: &str, title: &str, archive: &Archive, mut logger: impl Logger) -> Table {
+    log!((logger) Generate("Reading the tags and dates of the entries tagged '{tag}'..."));
+    let entries: Vec<(Vec<String>, [u16; 3])> = archived::visible(archive.list_entries(logger.hollow()), false, logger.hollow()).into_iter()
+        .map(|mut x| (x.tags(logger.hollow()).to_vec(), *x.date(logger.hollow())))
+        .collect();
+
+    let collections = collections(tag, by, &entries);
+    if collections.is_empty() {
+        if entries.iter().any(|(tags, _)| tags.iter().any(|x| tag_matches(x, tag))) {
+            log!((logger.error) Generate("No entries have sub-tags of '{tag}' (like '{tag}/...'); try `--by year`") as Fatal);
+        } else {
+            log!((logger.error) Generate("No entries are tagged '{tag}'") as Fatal);
+        } return logger.crash();
+    }
+
+    let by_name = match by { GroupBy::Subtag => "sub-tag", GroupBy::Year => "year" };
+    let mut moc = Table::new();
+    moc.insert("uid".into(), Value::String(uid.to_string()));
+    moc.insert("title".into(), Value::String(title.to_string()));
+    moc.insert("description".into(), Value::String(format!("The entries tagged '{tag}' by {by_name}")));
+    moc.insert("notes".into(), Value::Array(Vec::new()));
+    moc.insert("tags".into(), Value::Array(Vec::new()));
+
+    let mut table = Table::new();
+    table.insert("is-moc".into(), Value::Boolean(true));
+    table.insert("moc".into(), Value::Table(moc));
+    table.insert("collection".into(), collections.into_iter().map(Value::Table).collect::<Vec<_>>().into());
+    table
+}
+
+/// Generates a moc of the entries with a tag and commits it, or only prints its config with `print`
+pub fn generate_command(tag: String, by: GroupBy, uid: Option<String>, title: Option<String>, print: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let uid = uid.unwrap_or_else(|| format!("{}-moc", tag.trim_matches('/').replace('/', "-")));
+    let title = title.unwrap_or_else(|| format!("All about {}", tag.trim_matches('/')));
+    let table = generate(&tag, by, &uid, &title, &archive, logger.hollow());
+    let count = table["collection"].as_array().map(|x| x.len()).unwrap_or(0);
+
+    if print {
+        return println!("{}", toml::to_string_pretty(&table).unwrap());
+    }
+
+    log!((logger) Generate("Committing the generated moc '{uid}' with {count} collections..."));
+    archive.commit_table(table, Path::new("<generated>"), logger.hollow());
+    log!((logger.vital) Generate("Generated moc '{uid}' with {count} collections; pull it with `diary-cli pull -m {uid}` to edit it") as Log);
+}
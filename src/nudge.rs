@@ -0,0 +1,91 @@
+use soulog::*;
+use chrono::{Datelike, Days, NaiveDate};
+use crate::{archive::Archive, config::Config, entry::Entry, unwrap_opt};
+
+/// Gentle warnings about committed entries that don't stick to the user's journaling habits (`[nudges]`); they never
+/// stop a commit and are all off unless set
+#[derive(Default)]
+pub struct Nudges {
+    /// Entries with fewer words than this in their description and sections
+    pub min_words: Option<usize>,
+    /// Entries without any tags
+    pub untagged: bool,
+    /// Entries with the same title as an entry of the day before
+    pub repeated_title: bool,
+    /// Entries whose description is just their title
+    pub description_is_title: bool,
+}
+
+impl Nudges {
+    /// Parses the `[nudges]` table of the config
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let min_words = table.get("min_words")
+            .map(|x| unwrap_opt!((x.as_integer().filter(|x| *x > 0)) with logger, format: Config("Config's nudges 'min_words' must be a positive number")) as usize);
+        let mut flag = |key: &str| table.get(key)
+            .map(|x| unwrap_opt!((x.as_bool()) with logger, format: Config("Config's nudges '{key}' must be a boolean")))
+            .unwrap_or(false);
+        Self {
+            min_words,
+            untagged: flag("untagged"),
+            repeated_title: flag("repeated_title"),
+            description_is_title: flag("description_is_title"),
+        }
+    }
+}
+
+/// Counts the words of some text
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().filter(|x| x.chars().any(char::is_alphanumeric)).count()
+}
+
+/// The date before an entry's date (day, month then year)
+pub fn day_before(date: &[u16; 3]) -> Option<[u16; 3]> {
+    let date = NaiveDate::from_ymd_opt(date[2] as i32, date[1] as u32, date[0] as u32)?.checked_sub_days(Days::new(1))?;
+    Some([date.day() as u16, date.month() as u16, date.year() as u16])
+}
+
+/// What an entry could do better by the configured nudges; `yesterday` is the titles of the entries of the day before
+pub fn nudges(config: &Nudges, entry: &mut Entry, yesterday: &[String], logger: impl Logger) -> Vec<String> {
+    let mut result = Vec::new();
+    let title = entry.title(logger.hollow()).clone();
+
+    if let Some(min) = config.min_words {
+        let mut words = word_count(entry.description(logger.hollow()));
+        entry.sections(logger.hollow()).iter_mut().for_each(|x| {
+            words += word_count(x.content(logger.hollow()));
+            x.clear_cache();
+        });
+        if words < min { result.push(format!("is only {words} words long; you wanted to write at least {min}")) }
+    }
+    if config.untagged && entry.tags(logger.hollow()).is_empty() {
+        result.push("has no tags, so no moc collection or tag search will find it".to_string());
+    }
+    if config.repeated_title && yesterday.iter().any(|x| x.trim().eq_ignore_ascii_case(title.trim())) {
+        result.push(format!("has the same title as an entry of the day before ('{title}')"));
+    }
+    if config.description_is_title && entry.description(logger.hollow()).trim().eq_ignore_ascii_case(title.trim()) {
+        result.push("has a description that only repeats its title".to_string());
+    }
+    result
+}
+
+/// Nudges about a just staged entry (by the config's `[nudges]`)
+pub fn check(entry: &mut Entry, archive: &Archive, mut logger: impl Logger) {
+    let config = &Config::get().nudges;
+    if config.min_words.is_none() && !config.untagged && !config.repeated_title && !config.description_is_title { return }
+
+    // Only look through the archive when there is a title to compare against
+    let mut yesterday = Vec::new();
+    if let Some(before) = config.repeated_title.then(|| day_before(entry.date(logger.hollow()))).flatten() {
+        for mut other in archive.list_entries(logger.hollow()) {
+            if other.uid != entry.uid && *other.date(logger.hollow()) == before {
+                yesterday.push(other.title(logger.hollow()).clone());
+            } other.clear_cache();
+        }
+    }
+
+    let uid = entry.uid.clone();
+    for nudge in nudges(config, entry, &yesterday, logger.hollow()) {
+        log!((logger.vital) Nudge("Entry '{uid}' {nudge}") as Inconvenience);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, entry::{Entry, format_date, today}, json};
+
+/// Entry uids and dates indexed by `(month, day)`, newest year first
+pub type DayIndex = HashMap<(u16, u16), Vec<(String, [u16; 3])>>;
+
+/// Indexes entries by their `(month, day)` so entries from the same day of different years can be found
+pub fn index_by_day(dates: impl IntoIterator<Item = (String, [u16; 3])>) -> DayIndex {
+    let mut index = DayIndex::new();
+    dates.into_iter().for_each(|(uid, date)| index.entry((date[1], date[0])).or_default().push((uid, date)));
+    index.values_mut().for_each(|x| x.sort_by_key(|(_, date)| std::cmp::Reverse(date[2])));
+    index
+}
+
+/// Parses a `M-D` date *eg* `8-21`
+fn parse_month_day(date: &str) -> Option<(u16, u16)> {
+    let (month, day) = date.split_once('-')?;
+    let (month, day) = (month.trim().parse().ok()?, day.trim().parse().ok()?);
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((month, day))
+}
+
+pub fn on_this_day(date: Option<String>, read: bool, force_unlock: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let today = today();
+    let (month, day) = match &date {
+        Some(x) => match parse_month_day(x) {
+            Some(x) => x,
+            None => {
+                log!((logger.error) OnThisDay("Date '{x}' must be a month and day like `8-21`") as Fatal);
+                return logger.crash();
+            },
+        },
+        None => (today[1], today[0]),
+    };
+
+    log!((logger) OnThisDay("Finding entries written on {month}-{day} of previous years..."));
+    let index = index_by_day(archive.list_entries(logger.hollow())
+        .into_iter()
+        .map(|mut x| { let date = *x.date(logger.hollow()); (x.uid, date) }));
+    let found: Vec<&(String, [u16; 3])> = index.get(&(month, day))
+        .map(|x| x.iter().filter(|(_, date)| date[2] < today[2]).collect())
+        .unwrap_or_default();
+
+    let mut entries: Vec<Entry> = found.iter().filter_map(|(uid, _)| archive.get_entry(uid.clone(), logger.hollow())).collect();
+
+    if json::enabled() {
+        return json::print(entries.iter_mut().map(|x| {
+            let locked = !force_unlock && x.is_locked(logger.hollow());
+            let mut item = json!({ "uid": x.uid, "date": format_date(x.date(logger.hollow())), "title": x.title(logger.hollow()), "locked": locked });
+            if !locked {
+                item["description"] = json!(x.description(logger.hollow()));
+                if read {
+                    item["sections"] = x.sections(logger.hollow()).iter_mut()
+                        .map(|x| json!({ "title": x.title(logger.hollow()), "content": x.content(logger.hollow()) }))
+                        .collect();
+                }
+            } item
+        }).collect());
+    }
+
+    if entries.is_empty() {
+        log!((logger.vital) OnThisDay("No entries written on {month}-{day} of previous years") as Inconvenience);
+        return;
+    }
+
+    for entry in entries.iter_mut() {
+        let date = *entry.date(logger.hollow());
+        let years = today[2].saturating_sub(date[2]);
+        let title = entry.title(logger.hollow()).clone();
+        log!((logger.vital) OnThisDay("{}", colour_format![
+            cyan(&format_date(&date)), blue(&format!(" ({years} years ago) `")), none(&entry.uid), blue("` "), green(&title)
+        ]) as Log);
+
+        if !force_unlock && entry.is_locked(logger.hollow()) {
+            log!((logger.vital) OnThisDay("  *time capsule; use `--force-unlock` to see it*") as Log);
+            continue;
+        }
+
+        log!((logger.vital) OnThisDay("  {}", entry.description(logger.hollow())) as Log);
+        if read {
+            entry.sections(logger.hollow()).iter_mut().for_each(|section| {
+                log!((logger.vital) OnThisDay("  ## {}", section.title(logger.hollow())) as Log);
+                section.content(logger.hollow()).lines().for_each(|x| log!((logger.vital) OnThisDay("  {x}") as Log));
+            });
+        }
+    }
+}
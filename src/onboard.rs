@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+use soulog::*;
+use toml::Table;
+use crate::{archive::Archive, config::Config, entry::{format_date, today}, home_dir};
+
+/// Checks if diary-cli has never been set up (there is no archive and no config)
+pub fn is_first_run() -> bool {
+    let home = home_dir();
+    !home.join("archive").exists() && !home.join("config.toml").exists()
+}
+
+/// Asks the user a question, returning the default if they don't answer
+fn ask(prompt: &str, default: &str, logger: &mut impl Logger) -> String {
+    let answer = logger.ask("Setup", &format!("{prompt} [{default}]"));
+    let answer = answer.trim();
+    if answer.is_empty() { default.to_string() } else { answer.to_string() }
+}
+
+fn confirm(prompt: &str, logger: &mut impl Logger) -> bool {
+    ask(&format!("{prompt} (y/n)"), "y", logger).to_lowercase().starts_with('y')
+}
+
+/// A sample entry showing off the entry format
+pub fn sample_entry() -> String {
+    let today = today();
+    format!(r#"[entry]
+uid = "{date}"
+title = "My first entry"
+description = "Getting started with diary-cli"
+tags = ["diary-cli"]
+notes = ["entries are written as toml and committed into the archive"]
+date = {date}
+
+[[section]]
+title = "Today"
+contents = """
+This is my first entry! Edit this file and run `diary-cli commit` on it to update it in the archive.
+"""
+"#, date = format_date(&today))
+}
+
+/// Guides a new user through setting up their config, archive and first entry
+pub fn wizard(mut logger: impl Logger) {
+    log!((logger.vital) Setup("Welcome to diary-cli! Let's get you set up (press enter to accept the defaults)") as Log);
+
+    // Archive location
+    let default_home = home_dir();
+    let home = PathBuf::from(ask("Where should diary-cli keep your archive and config?", &default_home.to_string_lossy(), &mut logger));
+    if home != default_home {
+        log!((logger.vital) Setup("Add `export DIARY_CLI_HOME=\"{}\"` to your shell's profile so diary-cli can find it", home.to_string_lossy()) as Inconvenience);
+        std::env::set_var("DIARY_CLI_HOME", &home);
+    }
+
+    log!((logger.vital) Setup("Archives aren't encrypted; keep them (and their backups) somewhere only you can read") as Inconvenience);
+
+    // Config
+    let config = Config::get();
+    let mut table = Table::new();
+    table.insert("author".into(), ask("Who should your commits be signed by?", &config.author(), &mut logger).into());
+    table.insert("device".into(), ask("What is this device called?", &config.device(), &mut logger).into());
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| String::from("nano"));
+    table.insert("editor".into(), ask("Which editor do you write entries in?", &editor, &mut logger).into());
+
+    let config_path = home.join("config.toml");
+    if_err!((logger) [Setup, err => ("While creating '{}': {err:?}", home.to_string_lossy())] retry std::fs::create_dir_all(&home));
+    if_err!((logger) [Setup, err => ("While writing config '{}': {err:?}", config_path.to_string_lossy())] retry std::fs::write(&config_path, table.to_string()));
+    log!((logger.vital) Setup("Saved your config to '{}'", config_path.to_string_lossy()) as Log);
+
+    let archive = Archive::init(logger.hollow());
+
+    // Sample entry
+    if !confirm("Create a sample entry to see how entries are written?", &mut logger) { return finish(&mut logger) }
+    let path = Path::new(&ask("Where should the sample entry be saved?", "first-entry.toml", &mut logger)).to_path_buf();
+    if_err!((logger) [Setup, err => ("While writing sample entry: {err:?}")] retry std::fs::write(&path, sample_entry()));
+    log!((logger.vital) Setup("Wrote a sample entry to '{}'", path.to_string_lossy()) as Log);
+
+    if confirm("Commit the sample entry into your archive?", &mut logger) {
+        archive.commit(&path, logger.hollow());
+    } finish(&mut logger)
+}
+
+fn finish(logger: &mut impl Logger) {
+    log!((logger.vital) Setup("You're all set! Run `diary-cli --help` to see everything diary-cli can do") as Log);
+}
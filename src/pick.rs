@@ -0,0 +1,146 @@
+use std::io::{BufRead, IsTerminal, Write};
+use soulog::*;
+use crate::{about, archive::Archive, archived, json, pull, read, uncommit};
+
+/// How many of the best matches are shown at once
+const SHOWN: usize = 10;
+
+/// What is done with the entry or moc that's picked
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum PickAction {
+    /// Shows its about-info
+    #[default]
+    About,
+    /// Reads it in the terminal (entries only)
+    Read,
+    /// Pulls its config into the current directory
+    Pull,
+    /// Moves it to the trash
+    Remove,
+}
+
+/// An entry or moc that can be picked
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub uid: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub is_moc: bool,
+}
+
+impl Candidate {
+    /// What the query is matched against
+    fn haystack(&self) -> String {
+        format!("{} {} {}", self.uid, self.title, self.tags.join(" ")).to_lowercase()
+    }
+}
+
+/// How well a query matches some text, if every word of it does: a word's characters have to appear in order within a
+/// single word of the text, with runs of them and ones at the start of it scoring higher
+pub fn score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase();
+    let words: Vec<Vec<char>> = text.split_whitespace().map(|x| x.chars().collect()).collect();
+    query.to_lowercase().split_whitespace()
+        .map(|x| words.iter().filter_map(|y| word_score(x, y)).max())
+        .sum()
+}
+
+fn word_score(query: &str, word: &[char]) -> Option<usize> {
+    let (mut score, mut at, mut previous) = (0, 0, None);
+    for x in query.chars() {
+        let found = at + word[at..].iter().position(|y| *y == x)?;
+        score += 1;
+        if previous.is_some_and(|y| y + 1 == found) { score += 2 }
+        if found == 0 || !word[found - 1].is_alphanumeric() { score += 3 }
+        previous = Some(found);
+        at = found + 1;
+    } Some(score)
+}
+
+/// The candidates a query matches, best first (all of them, in order, for an empty query)
+pub fn rank<'a>(query: &str, candidates: &'a [Candidate]) -> Vec<&'a Candidate> {
+    let mut ranked: Vec<(usize, &Candidate)> = candidates.iter()
+        .filter_map(|x| Some((score(query, &x.haystack())?, x)))
+        .collect();
+    ranked.sort_by_key(|x| std::cmp::Reverse(x.0));
+    ranked.into_iter().map(|(_, x)| x).collect()
+}
+
+/// The entries (and mocs, unless only entries can be picked) that aren't archived, newest first
+fn candidates(archive: &Archive, entries_only: bool, logger: impl Logger) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = archived::visible(archive.list_entries(logger.hollow()), false, logger.hollow())
+        .into_iter()
+        .map(|mut x| Candidate { title: x.title(logger.hollow()).to_string(), tags: x.tags(logger.hollow()).to_vec(), uid: x.uid, is_moc: false })
+        .collect();
+    candidates.sort_by(|a, b| b.uid.cmp(&a.uid));
+    if !entries_only {
+        candidates.extend(archived::visible(archive.list_mocs(logger.hollow()), false, logger.hollow())
+            .into_iter()
+            .map(|mut x| Candidate { title: x.title(logger.hollow()).to_string(), tags: x.tags(logger.hollow()).to_vec(), uid: x.uid, is_moc: true }));
+    } candidates
+}
+
+fn show(matches: &[&Candidate], mut logger: impl Logger) {
+    for (i, x) in matches.iter().take(SHOWN).enumerate() {
+        let kind = if x.is_moc { " (moc)" } else { "" };
+        log!((logger.vital) Pick("{}{}{}", colour_format![cyan(&format!("{:>2}", i + 1)), blue(". "), green(&x.uid), blue(kind), blue(": ")], x.title, colour_format![blue(" ["), none(&x.tags.join(", ")), blue("]")]) as Log);
+    }
+    if matches.len() > SHOWN {
+        log!((logger.vital) Pick("...and {} more; type more to narrow them down", matches.len() - SHOWN) as Log);
+    }
+}
+
+/// Lets the user narrow the candidates down by typing queries until they pick one by its number (or the best match with
+/// an empty line); `None` if they quit
+fn choose(mut query: String, candidates: &[Candidate], mut logger: impl Logger) -> Option<Candidate> {
+    loop {
+        let matches = rank(&query, candidates);
+        if matches.is_empty() {
+            log!((logger.vital) Pick("Nothing matches '{query}'") as Inconvenience);
+        } else {
+            show(&matches, logger.hollow());
+        }
+
+        print!("Pick [number, enter for the first, text to search, q to quit] {}> ", if query.is_empty() { String::new() } else { format!("({query}) ") });
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 { return None } // end of input
+        match line.trim() {
+            "q" => return None,
+            "" if !matches.is_empty() => return Some(matches[0].clone()),
+            "" => (),
+            x => match x.parse::<usize>() {
+                Ok(i) if (1..=matches.len().min(SHOWN)).contains(&i) => return Some(matches[i - 1].clone()),
+                _ => query = x.to_string(),
+            },
+        }
+    }
+}
+
+/// Picks an entry or moc interactively by searching their uids, titles and tags, then runs an action on it
+pub fn pick(query: Option<String>, action: PickAction, mut logger: impl Logger) {
+    if json::enabled() || !std::io::stdin().is_terminal() {
+        log!((logger.error) Pick("Picking is interactive, so it needs a terminal (and can't be used with `--json`)") as Fatal);
+        return logger.crash();
+    }
+
+    let archive = Archive::load(logger.hollow());
+    let candidates = candidates(&archive, action == PickAction::Read, logger.hollow());
+    if candidates.is_empty() {
+        log!((logger.error) Pick("There's nothing in the archive to pick from") as Fatal);
+        return logger.crash();
+    }
+
+    let Some(picked) = choose(query.unwrap_or_default(), &candidates, logger.hollow()) else {
+        return log!((logger.vital) Pick("Picked nothing; doing nothing") as Log);
+    };
+    drop(archive);
+    match action {
+        PickAction::About => about::about(picked.is_moc, picked.uid, false, false, logger),
+        PickAction::Read => read::read(picked.uid, false, logger),
+        PickAction::Pull => {
+            pull::pull(std::path::PathBuf::from("."), String::from("config.toml"), picked.is_moc, picked.uid, false, false, logger)
+        },
+        PickAction::Remove => uncommit::uncommmit(picked.uid, picked.is_moc, false, logger),
+    }
+}
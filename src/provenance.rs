@@ -0,0 +1,43 @@
+use lazy_db::*;
+use soulog::*;
+
+/// Where and when an entry or moc was committed from
+pub struct Provenance {
+    /// The absolute path of the config file it was committed from
+    pub source: String,
+    /// When it was committed (RFC 3339)
+    pub committed_at: String,
+}
+
+impl Provenance {
+    /// The provenance of committing a config file now
+    pub fn current(source: &std::path::Path) -> Self {
+        Self {
+            source: source.to_string_lossy().to_string(),
+            committed_at: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        }
+    }
+
+    /// Writes the provenance into an entry or moc container
+    pub fn store(&self, container: &LazyContainer, mut logger: impl Logger) {
+        if_err!((logger) [Provenance, err => ("While writing commit provenance: {err:?}")] retry write_container!((container) source = new_string(&self.source)));
+        if_err!((logger) [Provenance, err => ("While writing commit provenance: {err:?}")] retry write_container!((container) committed_at = new_string(&self.committed_at)));
+    }
+
+    /// Reads the provenance of an entry or moc container; items committed before provenance was recorded have none
+    pub fn load(container: &LazyContainer, mut logger: impl Logger) -> Option<Self> {
+        let source = match container.read_data("source") {
+            Ok(x) => x,
+            Err(LDBError::FileNotFound(_)) => return None,
+            Err(err) => {
+                log!((logger.error) Provenance("While reading commit provenance: {err:?}") as Fatal);
+                return logger.crash();
+            },
+        };
+        let source = if_err!((logger) [Provenance, err => ("While reading commit provenance: {err:?}")] {source.collect_string()} crash logger.crash());
+        let committed_at = if_err!((logger) [Provenance, err => ("While reading commit provenance: {err:?}")] retry container.read_data("committed_at"));
+        let committed_at = if_err!((logger) [Provenance, err => ("While reading commit provenance: {err:?}")] {committed_at.collect_string()} crash logger.crash());
+
+        Some(Self { source, committed_at })
+    }
+}
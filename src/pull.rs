@@ -1,9 +1,10 @@
 use soulog::*;
 use std::{path::PathBuf, fs};
-use crate::{archive::Archive, unwrap_opt};
+use crate::{archive::Archive, entry::format_date, unwrap_opt};
 
-pub fn pull(path: PathBuf, file_name: String, is_moc: bool, uid: String, one_file: bool, mut logger: impl Logger) {
+pub fn pull(path: PathBuf, file_name: String, is_moc: bool, uid: String, one_file: bool, force_unlock: bool, mut logger: impl Logger) {
     let archive = Archive::load(logger.hollow());
+    let uid = archive.resolve_uid(uid, is_moc, logger.hollow());
 
     if_err!((logger) {fs::create_dir_all(&path)} else(err) {
         log!((logger.vital) Pull("While initialising path '{}': {err:?}; ignoring error...", path.to_string_lossy()) as Inconvenience) 
@@ -14,17 +15,23 @@ pub fn pull(path: PathBuf, file_name: String, is_moc: bool, uid: String, one_fil
         pull_moc(archive, path, file_name, uid, logger.hollow());
     } else {
         log!((logger) Pull("Pulling entry with uid '{uid}' from archive..."));
-        pull_entry(archive, path, file_name, uid, one_file, logger.hollow());
+        pull_entry(archive, path, file_name, uid, one_file, force_unlock, logger.hollow());
     }
 
     log!((logger.vital) Pull("Successfully pulled config file from archive") as Log);
 }
 
-fn pull_entry(archive: Archive, path: PathBuf, file_name: String, uid: String, one_file: bool, mut logger: impl Logger) {
+fn pull_entry(archive: Archive, path: PathBuf, file_name: String, uid: String, one_file: bool, force_unlock: bool, mut logger: impl Logger) {
     let error_msg = format!("Entry of uid '{uid}' not found in archive");
     let mut entry = unwrap_opt!((archive.get_entry(uid, logger.hollow())) with logger, format: Pull("{error_msg}"));
     std::mem::drop(error_msg);
 
+    if !force_unlock && entry.is_locked(logger.hollow()) {
+        let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+        log!((logger.error) Pull("Entry '{}' is a time capsule that unlocks on {unlock_date}; use `--force-unlock` to pull it anyway", entry.uid) as Fatal);
+        return logger.crash();
+    }
+
     let map = entry.pull(&path, one_file, logger.hollow());
     let contents = if_err!((logger) [Pull, err => ("While encoding entry toml: {err:?}")] retry toml::to_string_pretty(&map));
     
@@ -0,0 +1,32 @@
+use soulog::*;
+use crate::{about, archive::{Archive, random_id}, read, search};
+
+/// Shows a random entry, optionally one matching a tag filter
+pub fn random(tags: Option<Vec<String>>, strict: bool, full: bool, force_unlock: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Random("Picking a random entry..."));
+
+    // Time capsules that haven't unlocked aren't worth surfacing
+    let entries: Vec<_> = archive.list_entries(logger.hollow())
+        .into_iter()
+        .filter_map(|mut x| (force_unlock || !x.is_locked(logger.hollow())).then_some(x))
+        .collect();
+    let uids = match tags {
+        Some(tags) if strict => search::search_strict(&tags, entries, logger.hollow()),
+        Some(tags) => search::search(&tags, entries, logger.hollow()),
+        None => entries.into_iter().map(|x| x.uid).collect(),
+    };
+
+    if uids.is_empty() {
+        log!((logger.error) Random("No entries to pick from") as Fatal);
+        return logger.crash();
+    }
+
+    let uid = uids[(random_id() % uids.len() as u64) as usize].clone();
+    drop(archive);
+    if full {
+        read::read(uid, force_unlock, logger)
+    } else {
+        about::about(false, uid, force_unlock, false, logger)
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, cli::PLAIN, entry::{format_date, Entry}, json, unwrap_opt};
+
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Styles the inline markdown (`**bold**` and `` `code` ``) of a line
+fn render_inline(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let (mut bold, mut code) = (false, false);
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if !code && rest.starts_with("**") {
+            bold = !bold;
+            result.push_str(if bold { BOLD } else { "\x1b[22m" });
+            rest = &rest[2..];
+            continue;
+        }
+        if c == '`' {
+            code = !code;
+            result.push_str(if code { "\x1b[36m" } else { "\x1b[39m" });
+        } else { result.push(c) }
+        rest = &rest[c.len_utf8()..];
+    } result
+}
+
+/// Renders markdown for the terminal; headings, bold, code, quotes and lists are styled unless `plain`
+pub fn render_markdown(text: &str, plain: bool) -> String {
+    if plain { return text.lines().map(|x| format!("{x}\n")).collect() }
+    text.lines().map(|line| {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            format!("{BOLD}\x1b[32m{}{RESET}\n", heading.trim_start_matches('#').trim())
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            format!("{indent}\x1b[34m│{RESET} \x1b[3m{}{RESET}\n", render_inline(quote.trim_start()))
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            format!("{indent}\x1b[34m•{RESET} {}{RESET}\n", render_inline(item))
+        } else {
+            format!("{}{RESET}\n", render_inline(line))
+        }
+    }).collect()
+}
+
+/// Shows text through the user's pager when writing to a terminal
+fn page(text: &str) {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        let pager = std::env::var("PAGER").unwrap_or_else(|_| String::from("less -R"));
+        let mut parts = pager.split_whitespace();
+        if let Some(program) = parts.next() {
+            if let Ok(mut child) = Command::new(program).args(parts).stdin(Stdio::piped()).spawn() {
+                if let Some(mut stdin) = child.stdin.take() { let _ = stdin.write_all(text.as_bytes()); }
+                let _ = child.wait();
+                return;
+            }
+        }
+    } let _ = stdout.write_all(text.as_bytes());
+}
+
+pub fn read(uid: String, force_unlock: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let uid = archive.resolve_uid(uid, false, logger.hollow());
+    let mut entry = unwrap_opt!((archive.get_entry(uid.clone(), logger.hollow())) with logger, format: Read("Entry of uid '{uid}' not found in archive"));
+
+    if !force_unlock && entry.is_locked(logger.hollow()) {
+        let unlock_date = format_date(&entry.unlock_date(logger.hollow()).unwrap());
+        log!((logger.error) Read("Entry '{uid}' is a time capsule that unlocks on {unlock_date}; use `--force-unlock` to read it anyway") as Fatal);
+        return logger.crash();
+    }
+
+    let date = format_date(entry.date(logger.hollow()));
+    if json::enabled() {
+        return json::print(json!({
+            "uid": entry.uid,
+            "date": date,
+            "title": entry.title(logger.hollow()),
+            "description": entry.description(logger.hollow()),
+            "notes": entry.notes(logger.hollow()),
+            "sections": entry.sections(logger.hollow()).iter_mut()
+                .map(|x| json!({ "title": x.title(logger.hollow()), "notes": x.notes(logger.hollow()), "content": x.content(logger.hollow()) }))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    page(&render_markdown(&markdown(&mut entry, logger), unsafe { PLAIN }));
+}
+
+/// Assembles an entry as markdown: its title, date, description and notes, then its sections
+pub fn markdown(entry: &mut Entry, logger: impl Logger) -> String {
+    let date = format_date(entry.date(logger.hollow()));
+    let title = entry.title(logger.hollow()).clone();
+    let mut markdown = format!("# {title}\n{date}\n> {}\n", entry.description(logger.hollow()));
+    entry.notes(logger.hollow()).iter().for_each(|x| markdown.push_str(&format!("- {x}\n")));
+    entry.sections(logger.hollow()).iter_mut().for_each(|section| {
+        markdown.push_str(&format!("\n## {}\n", section.title(logger.hollow())));
+        section.notes(logger.hollow()).iter().for_each(|x| markdown.push_str(&format!("- {x}\n")));
+        markdown.push_str(section.content(logger.hollow()));
+        markdown.push('\n');
+    });
+    markdown
+}
@@ -0,0 +1,59 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Quoted text longer than this is assumed to be diary content and is hashed
+const MAX_QUOTED: usize = 40;
+/// Logs longer than this are truncated
+const MAX_LENGTH: usize = 400;
+
+/// Hashes text into a short stable identifier, so redacted logs can still be correlated
+pub fn hash(text: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// Redacts a log message for privacy mode; paths are made relative to the working or home directory, long quoted snippets are hashed and long messages are truncated
+pub fn redact(message: &str) -> String {
+    let mut message = message.to_string();
+    let cwd = std::env::current_dir().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+    let home = std::env::var("HOME").unwrap_or_default();
+
+    // Relativise the most specific directory first
+    let mut dirs = [(cwd, "."), (home, "~")];
+    dirs.sort_by_key(|(x, _)| std::cmp::Reverse(x.len()));
+    for (dir, replacement) in dirs.iter().filter(|(x, _)| x.len() > 1) {
+        message = message.replace(dir.as_str(), replacement);
+    }
+
+    let message = redact_quotes(&message);
+    match message.char_indices().nth(MAX_LENGTH) {
+        Some((i, _)) => format!("{}… (truncated)", &message[..i]),
+        None => message,
+    }
+}
+
+fn redact_quotes(message: &str) -> String {
+    let chars: Vec<char> = message.chars().collect();
+    let mut result = String::with_capacity(message.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let opens = (c == '\'' || c == '"') && (i == 0 || matches!(chars[i - 1], ' ' | '(' | '[' | '{' | ':' | '='));
+        let close = opens.then(|| (i + 1..chars.len()).find(|&j| {
+            chars[j] == c && chars.get(j + 1).map(|x| !x.is_alphanumeric()).unwrap_or(true)
+        })).flatten();
+
+        match close {
+            Some(j) if j - i - 1 > MAX_QUOTED => {
+                let inner: String = chars[i + 1..j].iter().collect();
+                result.push(c);
+                result.push_str(&format!("<redacted {} chars #{}>", j - i - 1, hash(&inner)));
+                result.push(c);
+                i = j + 1;
+            },
+            _ => { result.push(c); i += 1 },
+        }
+    } result
+}
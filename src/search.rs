@@ -1,13 +1,72 @@
 use soulog::*;
-use crate::{archive::Archive, entry::Entry, moc::MOC, sort};
+use serde_json::json;
+use crate::{archive::Archive, archived, date::Span, entry::{Entry, format_date}, moc::MOC, sort, json, stats::to_naive};
 
 pub trait Searchable {
     fn get_uid(&self) -> String;
+    fn container(&self) -> &lazy_db::LazyContainer;
     #[allow(clippy::ptr_arg)]
     fn contains_tag(&mut self, tag: &String, logger: impl Logger) -> bool;
 }
 
+/// Checks if a tag matches a (possibly hierarchical) query tag; `project/rust` matches `project/rust/diary-cli`
+pub fn tag_matches(tag: &str, query: &str) -> bool {
+    let query = query.trim_end_matches('/');
+    match tag.strip_prefix(query) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Checks if a tag matches a filter query, which may be a glob (`proj*`) or a hierarchical tag
+pub fn query_matches(tag: &str, query: &str) -> bool {
+    if !query.contains(['*', '?']) { return tag_matches(tag, query) }
+
+    // A glob matching a parent tag matches its children too
+    let query = query.trim_end_matches('/');
+    let mut parent = tag;
+    loop {
+        if glob_matches(parent, query) { return true }
+        match parent.rsplit_once('/') {
+            Some((x, _)) => parent = x,
+            None => return false,
+        }
+    }
+}
+
+/// Matches text against a glob where `*` matches any characters and `?` matches one
+fn glob_matches(text: &str, glob: &str) -> bool {
+    let (text, glob): (Vec<char>, Vec<char>) = (text.chars().collect(), glob.chars().collect());
+    let (mut t, mut g) = (0, 0);
+    let mut star: Option<(usize, usize)> = None; // position of the last `*` in the glob & text
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => { star = Some((g, t)); g += 1; },
+            Some(x) if *x == '?' || *x == text[t] => { t += 1; g += 1; },
+            _ => match star {
+                Some((sg, st)) => { g = sg + 1; t = st + 1; star = Some((sg, st + 1)); },
+                None => return false,
+            },
+        }
+    } glob[g..].iter().all(|x| *x == '*')
+}
+
+/// Splits a filter into the tags to search for and the negated (`!tag`) tags to exclude
+fn split_negated(tags: &[String]) -> (Vec<&String>, Vec<String>) {
+    let negated = tags.iter().filter_map(|x| x.strip_prefix('!')).map(String::from).collect();
+    (tags.iter().filter(|x| !x.starts_with('!')).collect(), negated)
+}
+
+/// Checks if a set of tags matches any tags of a filter while matching none of its negated (`!tag`) tags
+pub fn filter_matches(item_tags: &[String], filter: &[String]) -> bool {
+    let (tags, negated) = split_negated(filter);
+    let has = |query: &str| item_tags.iter().any(|x| query_matches(x, query));
+    !negated.iter().any(|x| has(x)) && (tags.is_empty() || tags.iter().any(|x| has(x)))
+}
+
 pub fn search_strict(tags: &[String], items: Vec<impl Searchable>, logger: impl Logger) -> Vec<String> {
+    let (tags, negated) = split_negated(tags);
     let mut result = Vec::new();
     for mut item in items.into_iter() {
         let mut all_tags_present = true;
@@ -16,7 +75,7 @@ pub fn search_strict(tags: &[String], items: Vec<impl Searchable>, logger: impl
                 all_tags_present = false;
                 break;
             }
-        } if all_tags_present {
+        } if all_tags_present && !negated.iter().any(|x| item.contains_tag(x, logger.hollow())) {
             result.push(item.get_uid());
         }
     }
@@ -25,9 +84,16 @@ pub fn search_strict(tags: &[String], items: Vec<impl Searchable>, logger: impl
 }
 
 pub fn search<T: Searchable>(tags: &[String], items: Vec<T>, logger: impl Logger) -> Vec<String> {
+    let (tags, negated) = split_negated(tags);
     let mut result = Vec::new();
     for mut item in items.into_iter() {
-        for tag in tags {
+        if negated.iter().any(|x| item.contains_tag(x, logger.hollow())) { continue }
+        if tags.is_empty() {
+            result.push(item.get_uid());
+            continue;
+        }
+
+        for tag in tags.iter() {
             if item.contains_tag(tag, logger.hollow()) {
                 result.push(item.get_uid());
                 break;
@@ -37,32 +103,61 @@ pub fn search<T: Searchable>(tags: &[String], items: Vec<T>, logger: impl Logger
     result
 }
 
-pub fn list_command(strict: bool, show_mocs: bool, show_entries: bool, filter: Option<Vec<String>>, mut logger: impl Logger) {
+/// Leaves out the entries dated before the `horizon` (counted back from today), saying how many were left out
+pub fn within_horizon(entries: Vec<Entry>, horizon: Option<Span>, mut logger: impl Logger) -> Vec<Entry> {
+    let Some(start) = horizon.and_then(|x| x.before(chrono::Local::now().date_naive())) else { return entries };
+    let count = entries.len();
+    let entries: Vec<Entry> = entries.into_iter()
+        .filter_map(|mut x| {
+            let date = to_naive(x.date(logger.hollow()));
+            x.clear_cache();
+            date.is_none_or(|date| date >= start).then_some(x)
+        })
+        .collect();
+
+    let hidden = count - entries.len();
+    if hidden > 0 && !json::enabled() {
+        log!((logger.vital) List("Leaving out {hidden} entries from before the last {} (`--all` lists every entry)", horizon.unwrap()) as Inconvenience);
+    } entries
+}
+
+/// Lists the tags, entries and mocs of the archive, or the ones with some tags (`filter` is the tags and if they are
+/// searched for strictly), leaving out entries older than the `horizon`; `sorted` instead lists the entries
+/// chronologically (newest first if it is `Some(true)`)
+pub fn list_command(show_mocs: bool, show_entries: bool, filter: Option<(Vec<String>, bool)>, include_archived: bool, horizon: Option<Span>, sorted: Option<bool>, mut logger: impl Logger) {
     let archive = Archive::load(logger.hollow());
 
     // Get entries and mocs
     sort::sort(logger.hollow());
-    let mut entries: Vec<_> = sort::read_sorted(&archive, logger.hollow())
+    let entries: Vec<_> = sort::read_sorted(&archive, logger.hollow())
         .into_vec()
         .into_iter()
         .map(|x| archive.get_entry(x, logger.hollow()).unwrap())
         .collect();
+    let mut entries = within_horizon(archived::visible(entries, include_archived, logger.hollow()), horizon, logger.hollow());
 
-    let mut mocs = archive.list_mocs(logger.hollow());
+    let mut mocs = archived::visible(archive.list_mocs(logger.hollow()), include_archived, logger.hollow());
+
+    if let (None, Some(reverse)) = (&filter, sorted) {
+        return list_chronology(&archive, entries.into_iter().map(|x| x.uid).collect(), reverse, logger);
+    }
 
-    let filter = match filter {
+    let (filter, strict) = match filter {
         Some(x) => x,
         None => {
             log!((logger) List("Listing selected items..."));
 
-            let tags = get_unique_tags(&mut entries, &mut mocs, logger.hollow());
-
-            log!((logger.vital) tags("{tags:#?}") as Result);
-            std::mem::drop(tags);
+            let mut tags: Vec<String> = get_unique_tags(&mut entries, &mut mocs, logger.hollow()).into_iter().cloned().collect();
+            tags.sort();
 
             let entry_uids: Vec<String> = entries.into_iter().map(|e| e.uid).collect();
             let moc_uids: Vec<String> = mocs.into_iter().map(|m| m.uid).collect();
 
+            if json::enabled() {
+                return print_json(&tags, show_entries.then_some(&entry_uids), show_mocs.then_some(&moc_uids));
+            }
+
+            log!((logger.vital) tags("{tags:#?}") as Result);
             if show_entries { log!((logger.vital) entries("{entry_uids:#?}") as Result) }
             if show_mocs { log!((logger.vital) mocs("{moc_uids:#?}") as Result) }
             return;
@@ -82,13 +177,46 @@ pub fn list_command(strict: bool, show_mocs: bool, show_entries: bool, filter: O
         moc_uids = search(&filter, mocs, logger.hollow());
     }
 
+    if let Some(reverse) = sorted { return list_chronology(&archive, entry_uids, reverse, logger) }
     log!((logger) List("Listing found entries and mocs..."));
 
+    if json::enabled() {
+        return print_json(&filter, show_entries.then_some(&entry_uids), show_mocs.then_some(&moc_uids));
+    }
+
     log!((logger.vital) tags("{filter:?}") as Result);
     if show_entries { log!((logger.vital) entries("{entry_uids:?}") as Result) }
     if show_mocs { log!((logger.vital) mocs("{moc_uids:?}") as Result) }
 }
 
+/// Lists entries (already in chronological order) with their dates and titles, newest first with `reverse`
+pub fn list_chronology(archive: &Archive, mut uids: Vec<String>, reverse: bool, mut logger: impl Logger) {
+    if reverse { uids.reverse() }
+    let entries: Vec<(String, String, String)> = uids.into_iter().filter_map(|uid| {
+        let mut entry = archive.get_entry(uid.clone(), logger.hollow())?;
+        Some((format_date(entry.date(logger.hollow())), uid, entry.title(logger.hollow()).clone()))
+    }).collect();
+
+    if json::enabled() {
+        return json::print(json!({
+            "chronology": entries.iter().map(|(date, uid, title)| json!({ "uid": uid, "date": date, "title": title })).collect::<Vec<_>>(),
+        }));
+    }
+    if entries.is_empty() {
+        log!((logger.vital) List("No entries to list") as Inconvenience);
+    }
+    for (date, uid, title) in entries {
+        log!((logger.vital) List("{date}  {uid}  {title}") as Log);
+    }
+}
+
+fn print_json(tags: &[String], entry_uids: Option<&Vec<String>>, moc_uids: Option<&Vec<String>>) {
+    let mut result = json!({ "tags": tags });
+    if let Some(x) = entry_uids { result["entries"] = json!(x) }
+    if let Some(x) = moc_uids { result["mocs"] = json!(x) }
+    json::print(result);
+}
+
 use std::collections::HashSet;
 fn get_unique_tags<'a>(entries: &'a mut [Entry], mocs: &'a mut [MOC], logger: impl Logger) -> HashSet<&'a String> {
     let mut tags = HashSet::new();
@@ -0,0 +1,161 @@
+use std::ops::Deref;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, atomic::{AtomicU64, Ordering}};
+use soulog::*;
+use crate::{archive::Archive, archived, cache::Lru, config::Config, entry::{format_date, today}};
+
+/// A section of a cached entry
+pub struct CachedSection {
+    pub title: String,
+    pub content: String,
+    pub notes: Box<[String]>,
+}
+
+/// An entry read fully from the archive, so it can be viewed again without reading it from disk
+pub struct CachedEntry {
+    pub uid: String,
+    pub title: String,
+    pub description: String,
+    /// Date goes from `day` to `month` then to `year`
+    pub date: [u16; 3],
+    pub unlock_date: Option<[u16; 3]>,
+    pub tags: Box<[String]>,
+    pub notes: Box<[String]>,
+    pub links: Box<[String]>,
+    pub sections: Box<[CachedSection]>,
+    pub archived: bool,
+}
+
+impl CachedEntry {
+    /// If it is a time capsule that hasn't unlocked yet
+    pub fn is_locked(&self) -> bool {
+        self.unlock_date.is_some_and(|x| crate::sort::younger(&x, &today()))
+    }
+
+    /// Assembles the entry as markdown, the same way `read::markdown` does
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!("# {}\n{}\n> {}\n", self.title, format_date(&self.date), self.description);
+        self.notes.iter().for_each(|x| markdown.push_str(&format!("- {x}\n")));
+        self.sections.iter().for_each(|section| {
+            markdown.push_str(&format!("\n## {}\n", section.title));
+            section.notes.iter().for_each(|x| markdown.push_str(&format!("- {x}\n")));
+            markdown.push_str(&section.content);
+            markdown.push('\n');
+        });
+        markdown
+    }
+}
+
+/// A loaded archive that can be cloned into threads and read from at the same time, without loading it again for each
+/// of them; the entries read through it are kept in a cache of the most recently used ones (`entry_cache` in
+/// config.toml), which is dropped whenever the archive is modified
+#[derive(Clone)]
+pub struct SharedArchive {
+    archive: Arc<Archive>,
+    entries: Arc<RwLock<Lru<String, Arc<CachedEntry>>>>,
+    /// The commit sequence of the archive the cached entries were read at
+    sequence: Arc<AtomicU64>,
+}
+
+impl Deref for SharedArchive {
+    type Target = Archive;
+
+    fn deref(&self) -> &Archive {
+        &self.archive
+    }
+}
+
+impl SharedArchive {
+    pub fn new(archive: Archive) -> Self {
+        Self::with_capacity(archive, Config::get().entry_cache())
+    }
+
+    /// Shares an archive, caching at most `capacity` entries
+    pub fn with_capacity(archive: Archive, capacity: usize) -> Self {
+        Self {
+            sequence: Arc::new(AtomicU64::new(archive.sequence)),
+            archive: Arc::new(archive),
+            entries: Arc::new(RwLock::new(Lru::new(capacity))),
+        }
+    }
+
+    /// Reads the cache; a reader that panicked can't have left it half written, so a poisoned lock is still fine to use
+    fn cache(&self) -> RwLockReadGuard<'_, Lru<String, Arc<CachedEntry>>> {
+        self.entries.read().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Modifies the cache, waiting for the readers of it to finish
+    fn cache_mut(&self) -> RwLockWriteGuard<'_, Lru<String, Arc<CachedEntry>>> {
+        self.entries.write().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Drops the cached entries if the archive has been modified since they were read (by this process or another)
+    pub fn refresh(&self, logger: impl Logger) {
+        let sequence = self.archive.current_sequence(logger);
+        if self.sequence.swap(sequence, Ordering::AcqRel) != sequence { self.cache_mut().clear() }
+    }
+
+    /// An entry, read from the archive unless it is cached; `None` if there's no such entry
+    pub fn entry(&self, uid: &str, logger: impl Logger) -> Option<Arc<CachedEntry>> {
+        self.refresh(logger.hollow());
+        if let Some(x) = self.cache().get(&uid.to_string()) { return Some(x) }
+        if !self.archive.database_exists(format!("entries/{uid}")) { return None }
+
+        // Read without holding the lock so other readers aren't kept waiting on the disk
+        let mut entry = self.archive.get_entry(uid.to_string(), logger.hollow())?;
+        let sections = entry.sections(logger.hollow()).iter_mut().map(|x| {
+            let section = CachedSection {
+                title: x.title(logger.hollow()).clone(),
+                content: x.content(logger.hollow()).clone(),
+                notes: x.notes(logger.hollow()).clone(),
+            };
+            x.clear_cache();
+            section
+        }).collect();
+        let cached = Arc::new(CachedEntry {
+            uid: uid.to_string(),
+            title: entry.title(logger.hollow()).clone(),
+            description: entry.description(logger.hollow()).clone(),
+            date: *entry.date(logger.hollow()),
+            unlock_date: *entry.unlock_date(logger.hollow()),
+            tags: entry.tags(logger.hollow()).clone(),
+            notes: entry.notes(logger.hollow()).clone(),
+            links: entry.links(logger.hollow()).clone(),
+            sections,
+            archived: archived::is_archived(&entry.container, logger.hollow()),
+        });
+        entry.clear_cache();
+
+        // Another reader may have cached it in the meantime; theirs is kept so everyone shares the same one
+        Some(self.cache_mut().insert(uid.to_string(), cached))
+    }
+
+    /// Every entry in the archive (only the most recently used of which stay cached)
+    pub fn entries(&self, logger: impl Logger) -> Vec<Arc<CachedEntry>> {
+        self.archive.uids(false, logger.hollow()).into_iter()
+            .filter_map(|x| self.entry(&x, logger.hollow()))
+            .collect()
+    }
+
+    /// Commits an entry or moc config through the shared archive, dropping the old cached version of it
+    pub fn commit_table(&self, table: toml::Table, config: &std::path::Path, logger: impl Logger) -> String {
+        let uid = self.archive.commit_table(table, config, logger.hollow());
+        self.invalidate(&uid);
+        self.refresh(logger);
+        uid
+    }
+
+    /// Forgets the cached version of an entry that has changed
+    pub fn invalidate(&self, uid: &str) {
+        self.cache_mut().remove(&uid.to_string());
+    }
+
+    /// Forgets every cached entry
+    pub fn clear_cache(&self) {
+        self.cache_mut().clear();
+    }
+
+    /// How many entries are cached
+    pub fn cached(&self) -> usize {
+        self.cache().len()
+    }
+}
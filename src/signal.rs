@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, atomic::{AtomicI32, Ordering}};
+use soulog::*;
+use crate::{lock, DynamicLogger};
+
+/// The last interrupting signal received (`SIGINT` or `SIGTERM`), `0` if none
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+/// The write end of the pipe the signal handler wakes the watcher thread through
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+/// How many critical sections are running, the temporary files and directories to remove and what else to clean up
+/// when interrupted
+static STATE: Mutex<State> = Mutex::new(State { critical: 0, temporary: Vec::new(), cleanups: Vec::new() });
+static INSTALL: Once = Once::new();
+
+struct State {
+    critical: usize,
+    temporary: Vec<PathBuf>,
+    cleanups: Vec<fn()>,
+}
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|x| x.into_inner())
+}
+
+/// Defers interrupts until it is dropped, so writes to the archive that must happen together aren't cut off halfway
+pub struct Critical(());
+
+impl Drop for Critical {
+    fn drop(&mut self) {
+        let mut state = state();
+        state.critical -= 1;
+        if state.critical == 0 && interrupted() { shutdown(state) }
+    }
+}
+
+/// Starts a critical section that SIGINT and SIGTERM wait for the end of
+pub fn critical() -> Critical {
+    state().critical += 1;
+    Critical(())
+}
+
+/// If the process has been asked to stop
+pub fn interrupted() -> bool {
+    SIGNAL.load(Ordering::SeqCst) != 0
+}
+
+/// Removes a temporary file or directory if the process is interrupted before it is cleaned up
+pub fn remove_on_interrupt(path: impl Into<PathBuf>) {
+    state().temporary.push(path.into());
+}
+
+/// Stops removing a temporary file or directory on interrupt, once it is cleaned up or kept
+pub fn keep(path: &Path) {
+    state().temporary.retain(|x| x != path);
+}
+
+/// Runs a cleanup (like giving the terminal back) if the process is interrupted
+pub fn on_interrupt(cleanup: fn()) {
+    state().cleanups.push(cleanup);
+}
+
+/// Cleans up after the process and exits it the way an interrupted process does
+fn shutdown(mut state: std::sync::MutexGuard<'_, State>) -> ! {
+    let mut logger = DynamicLogger::new();
+    let signal = SIGNAL.load(Ordering::SeqCst);
+    state.cleanups.drain(..).for_each(|x| x());
+    log!((logger.vital) Signal("Interrupted; cleaning up before exiting") as Warning);
+    for path in state.temporary.drain(..) {
+        let _ = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+    }
+    lock::release();
+    std::process::exit(128 + signal)
+}
+
+#[cfg(unix)]
+extern "C" fn handle(signal: libc::c_int) {
+    // Only async-signal-safe calls here; the watcher thread does the rest
+    SIGNAL.store(signal, Ordering::SeqCst);
+    let byte = 1u8;
+    unsafe { libc::write(PIPE.load(Ordering::SeqCst), &byte as *const u8 as *const libc::c_void, 1) };
+}
+
+/// Handles SIGINT and SIGTERM for the rest of the process: outside of critical sections it exits straight away, inside
+/// of them once they end, either way removing temporary files and releasing the archive lock first
+pub fn install() {
+    #[cfg(unix)]
+    INSTALL.call_once(|| {
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 { return }
+        PIPE.store(fds[1], Ordering::SeqCst);
+
+        std::thread::spawn(move || loop {
+            let mut byte = 0u8;
+            match unsafe { libc::read(fds[0], &mut byte as *mut u8 as *mut libc::c_void, 1) } {
+                1.. => (),
+                // Only an interrupted read is worth another try; the pipe closing or any other error would happen again
+                // straight away, forever
+                -1 if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted => continue,
+                _ => return,
+            }
+            let state = state();
+            if state.critical == 0 { shutdown(state) }
+            let mut logger = DynamicLogger::new();
+            log!((logger.vital) Signal("Interrupted; finishing what is being written to the archive first") as Inconvenience);
+        });
+
+        unsafe {
+            libc::signal(libc::SIGINT, handle as *const () as libc::sighandler_t);
+            libc::signal(libc::SIGTERM, handle as *const () as libc::sighandler_t);
+        }
+    });
+}
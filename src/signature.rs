@@ -0,0 +1,49 @@
+use lazy_db::*;
+use soulog::*;
+use crate::config::Config;
+
+/// The author and device that committed an entry or moc
+pub struct Signature {
+    pub author: String,
+    pub device: String,
+}
+
+impl Signature {
+    /// Creates a signature from the configured author and device
+    pub fn current() -> Self {
+        let config = Config::get();
+        Self {
+            author: config.author(),
+            device: config.device(),
+        }
+    }
+
+    /// Writes the signature into an entry or moc container
+    pub fn store(&self, container: &LazyContainer, mut logger: impl Logger) {
+        if_err!((logger) [Signature, err => ("While writing commit signature: {err:?}")] retry write_container!((container) author = new_string(&self.author)));
+        if_err!((logger) [Signature, err => ("While writing commit signature: {err:?}")] retry write_container!((container) device = new_string(&self.device)));
+    }
+
+    /// Reads the signature of an entry or moc container; items committed before signatures existed have none
+    pub fn load(container: &LazyContainer, mut logger: impl Logger) -> Option<Self> {
+        let author = match container.read_data("author") {
+            Ok(x) => x,
+            Err(LDBError::FileNotFound(_)) => return None,
+            Err(err) => {
+                log!((logger.error) Signature("While reading commit signature: {err:?}") as Fatal);
+                return logger.crash();
+            },
+        };
+        let author = if_err!((logger) [Signature, err => ("While reading commit signature: {err:?}")] {author.collect_string()} crash logger.crash());
+        let device = if_err!((logger) [Signature, err => ("While reading commit signature: {err:?}")] retry container.read_data("device"));
+        let device = if_err!((logger) [Signature, err => ("While reading commit signature: {err:?}")] {device.collect_string()} crash logger.crash());
+
+        Some(Self { author, device })
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.author, self.device)
+    }
+}
@@ -1,6 +1,7 @@
 use soulog::*;
 use lazy_db::*;
-use crate::{list, archive::Archive};
+use chrono::{DateTime, FixedOffset};
+use crate::{list, archive::Archive, history, provenance::Provenance, search, signal, unwrap_opt};
 
 pub fn younger(this: &[u16; 3], other: &[u16; 3]) -> bool {
     let this_date = this[2] as u32 * 10000 + this[1] as u32 * 100 + this[0] as u32;
@@ -8,47 +9,65 @@ pub fn younger(this: &[u16; 3], other: &[u16; 3]) -> bool {
     this_date > other_date
 }
 
-pub fn sort(mut logger: impl Logger) {
-    // load archive
-    let archive = Archive::load(logger.hollow());
+/// Removes a uid from an order list (`sorted` or `unsorted`)
+pub fn remove_from_order(archive: &Archive, order: &str, uid: &str, mut logger: impl Logger) {
+    let container = if_err!((logger) [Sort, err => ("While loading order list: {err:?}")] retry search_database!((archive.database()) /order/(order)));
+    let items: Vec<String> = list::read(|x| x.collect_string(), &container, logger.hollow())
+        .into_vec()
+        .into_iter()
+        .filter(|x| x != uid)
+        .collect();
+    list::write(&items, |file, x| LazyData::new_string(file, x), &container, logger.hollow());
+}
+
+/// Orders uids (with their entries' dates and when they were committed) chronologically; entries of the same day
+/// are in the order they were committed, with the ones committed before commit times were recorded first, and
+/// otherwise keep the order they were given in
+pub fn chronological(mut items: Vec<(String, [u16; 3], Option<DateTime<FixedOffset>>)>) -> Vec<String> {
+    // A stable sort, so equal dates and commit times fall back to the given order
+    items.sort_by(|a, b| {
+        let (this, other) = (&a.1, &b.1);
+        let date = (this[2], this[1], this[0]).cmp(&(other[2], other[1], other[0]));
+        date.then_with(|| a.2.cmp(&b.2))
+    });
+    items.into_iter().map(|x| x.0).collect()
+}
 
+/// Sorts the unsorted entries into the sorted list; with `force` the whole list is sorted again even if there are
+/// none, for lists sorted before same-day entries were ordered by commit time
+fn sort_archive(archive: &Archive, force: bool, mut logger: impl Logger) {
     let unsorted = list::read(
         |x| x.collect_string(),
         &if_err!((logger) [Sort, err => ("While reading unsorted stack length: {err:?}")] retry search_database!((archive.database()) /order/unsorted)),
         logger.hollow(),
     );
 
-    if unsorted.len() == 0 {
+    if unsorted.is_empty() && !force {
         log!((logger.verbose) Sort("No unsorted items on unsorted stack; doing nothing") as Inconvenience);
         return;
     }
 
-    let mut sorted = list::read(
-        |x| x.collect_string(),
-        &if_err!((logger) [Sort, err => ("While reading sorted list length: {err:?}")] retry search_database!((archive.database()) /order/sorted)),
-        logger.hollow(),
-    ).into_vec();
+    let sorted = read_sorted(archive, logger.hollow()).into_vec();
 
     log!((logger) Sort("Sorting unsorted entries..."));
-    // main sorting code
-    for usort_uid in unsorted.into_vec() {
-        let usort_date = *archive.get_entry(usort_uid.clone(), logger.hollow()).unwrap().date(logger.hollow());
-        
-        sorted.retain(|item| item != &usort_uid); // remove duplicates
-
-        let mut is_oldest = true;
-        for i in (0..sorted.len()).rev() {
-            let sort_date = *archive.get_entry(sorted[i].clone(), logger.hollow()).unwrap().date(logger.hollow());
-
-            if younger(&usort_date, &sort_date) {
-                sorted.insert(i + 1, usort_uid.clone()); // replace the one before
-                is_oldest = false;
-                break;
-            }
-        } if is_oldest {
-            sorted.insert(0, usort_uid);
-        }
+    // Recommitted entries are on both lists; they keep their new date (and commit time) but only appear once
+    let mut uids: Vec<String> = Vec::new();
+    for uid in sorted.into_iter().chain(unsorted.into_vec()) {
+        if !uids.contains(&uid) { uids.push(uid) }
     }
+    let items = uids.into_iter().filter_map(|uid| {
+        // Leaves uids of removed entries out, for `diary-cli check` to report
+        if !archive.database_exists(format!("entries/{uid}")) {
+            log!((logger.vital) Sort("Skipping entry '{uid}' as it is no longer in the archive") as Warning);
+            return None;
+        }
+        let mut entry = archive.get_entry(uid.clone(), logger.hollow())?;
+        let committed_at = Provenance::load(&entry.container, logger.hollow())
+            .and_then(|x| DateTime::parse_from_rfc3339(&x.committed_at).ok());
+        Some((uid, *entry.date(logger.hollow()), committed_at))
+    }).collect();
+    let sorted = chronological(items);
+    let _critical = signal::critical(); // The sorted and unsorted lists are written together
 
     // Store updates
     log!((logger) Sort("Sorted list length: {}", sorted.len()));
@@ -66,10 +85,51 @@ pub fn sort(mut logger: impl Logger) {
         logger.hollow()
     );
 
+    history::record(archive, "sort", None, logger.hollow());
     log!((logger.vital) Sort("Successfully sorted entries") as Log);
 }
 
-pub fn sort_uids(uids: &[String], logger: impl Logger) -> Box<[String]> {
+/// Puts a just committed entry into its place in the sorted list, after every entry of its day (as it is the latest
+/// committed of them)
+pub fn insert_sorted(archive: &Archive, uid: &str, mut logger: impl Logger) {
+    log!((logger) Sort("Putting entry '{uid}' into its place in the sorted list..."));
+    let date = *unwrap_opt!((archive.get_entry(uid.to_string(), logger.hollow())) with logger, format: Sort("Entry '{uid}' doesn't exist")).date(logger.hollow());
+    let mut sorted: Vec<String> = read_sorted(archive, logger.hollow()).into_vec().into_iter().filter(|x| x != uid).collect();
+
+    // New entries are usually of recent days, so only the end of the list is read
+    let mut index = sorted.len();
+    while index > 0 {
+        // Leaves uids of removed entries for `diary-cli check` to clean up
+        if !archive.database_exists(format!("entries/{}", sorted[index - 1])) { index -= 1; continue }
+        let other = archive.get_entry(sorted[index - 1].clone(), logger.hollow()).map(|mut x| *x.date(logger.hollow()));
+        if other.is_none_or(|x| !younger(&x, &date)) { break }
+        index -= 1;
+    }
+    sorted.insert(index, uid.to_string());
+    let _critical = signal::critical();
+
+    list::write(
+        sorted.as_ref(),
+        |file, x| LazyData::new_string(file, x),
+        &if_err!((logger) [Sort, err => ("While initing sorted list: {err:?}")] retry search_database!((archive.database()) /order/sorted)),
+        logger.hollow(),
+    );
+}
+
+pub fn sort(logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    sort_archive(&archive, false, logger);
+}
+
+/// Sorts the unsorted entries of the archive (every entry again with `full`), optionally listing the resulting chronology
+pub fn sort_command(list: bool, reverse: bool, full: bool, logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    sort_archive(&archive, full, logger.hollow());
+    if list { search::list_chronology(&archive, read_sorted(&archive, logger.hollow()).into_vec(), reverse, logger) }
+}
+
+/// The uids given in chronological order (newest first with `reverse`), sorting the archive first if it needs it
+pub fn sort_uids(uids: &[String], reverse: bool, logger: impl Logger) -> Box<[String]> {
     // load archive & sort if sorting is needed
     let archive = Archive::load(logger.hollow());
     sort(logger.hollow());
@@ -77,7 +137,9 @@ pub fn sort_uids(uids: &[String], logger: impl Logger) -> Box<[String]> {
     let sorted = read_sorted(&archive, logger.hollow()).into_vec();
     
     // remove unspecified
-    sorted.into_iter().filter(|x| uids.contains(x)).collect()
+    let mut sorted: Box<[String]> = sorted.into_iter().filter(|x| uids.contains(x)).collect();
+    if reverse { sorted.reverse() }
+    sorted
 }
 
 pub fn read_sorted(archive: &Archive, mut logger: impl Logger) -> Box<[String]> {
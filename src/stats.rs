@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, config::Config, entry::today, event, json, limits::format_size, locale::week_of, search::filter_matches, signature::Signature, tags::tag_usage};
+
+/// How many of the most used tags are shown
+const TOP_TAGS: usize = 10;
+/// What entries committed before signatures existed are counted under
+const UNSIGNED: &str = "unknown";
+/// How many of the soonest events are counted down to
+const UPCOMING: usize = 5;
+/// The width of the longest bar of a trend
+const TREND_WIDTH: usize = 40;
+
+/// Counts the words of a piece of text
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// Converts an archive date (`[day, month, year]`) into a calendar date
+pub fn to_naive(date: &[u16; 3]) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::from_ymd_opt(date[2] as i32, date[1] as u32, date[0] as u32)
+}
+
+/// The number of the day (or week, if it is given the day weeks start on) a date falls in, so consecutive periods have
+/// consecutive numbers
+fn period(date: chrono::NaiveDate, week_start: Option<chrono::Weekday>) -> i32 {
+    use chrono::Datelike;
+    match week_start {
+        Some(x) => week_of(date, x).num_days_from_ce().div_euclid(7),
+        None => date.num_days_from_ce(),
+    }
+}
+
+/// The current and longest runs of consecutive days (or weeks starting on `week_start`) that have at least one entry;
+/// the current streak is still alive if the last entry was in the previous period
+pub fn streaks(dates: &[[u16; 3]], today: &[u16; 3], week_start: Option<chrono::Weekday>) -> (usize, usize) {
+    let mut periods: Vec<i32> = dates.iter().filter_map(to_naive).map(|x| period(x, week_start)).collect();
+    periods.sort();
+    periods.dedup();
+
+    let (mut longest, mut current) = (0, 0);
+    for (i, x) in periods.iter().enumerate() {
+        current = match i.checked_sub(1).map(|i| periods[i]) {
+            Some(previous) if previous + 1 == *x => current + 1,
+            _ => 1,
+        }; longest = longest.max(current);
+    }
+
+    let today = to_naive(today).map(|x| period(x, week_start));
+    let alive = matches!((periods.last(), today), (Some(last), Some(today)) if today - last <= 1 && today >= *last);
+    (if alive { current } else { 0 }, longest)
+}
+
+/// The longest run of consecutive days that have at least one entry
+pub fn longest_streak(dates: &[[u16; 3]]) -> usize {
+    streaks(dates, &today(), None).1
+}
+
+/// The total size of the files in a directory
+pub fn dir_size(path: &Path) -> u64 {
+    std::fs::read_dir(path).map(|x| x.filter_map(|x| x.ok())
+        .map(|x| match x.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&x.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        }).sum()
+    ).unwrap_or(0)
+}
+
+/// How many entries were committed from each device and by each author
+#[derive(Debug, Default, PartialEq)]
+pub struct Signatures {
+    pub devices: BTreeMap<String, usize>,
+    pub authors: BTreeMap<String, usize>,
+}
+
+/// Counts the entries of the archive by the device and author that committed them (`unknown` for entries committed
+/// before signatures existed)
+pub fn signatures(archive: &Archive, logger: impl Logger) -> Signatures {
+    let mut signatures = Signatures::default();
+    for entry in archive.list_entries(logger.hollow()) {
+        let (device, author) = match Signature::load(&entry.container, logger.hollow()) {
+            Some(x) => (x.device, x.author),
+            None => (UNSIGNED.to_string(), UNSIGNED.to_string()),
+        };
+        *signatures.devices.entry(device).or_default() += 1;
+        *signatures.authors.entry(author).or_default() += 1;
+    } signatures
+}
+
+pub fn stats_command(mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Stats("Gathering archive statistics..."));
+
+    let mut dates = Vec::new();
+    let mut per_month: BTreeMap<(u16, u16), usize> = BTreeMap::new();
+    let mut words = 0;
+    let entries = archive.list_entries(logger.hollow());
+    let entry_count = entries.len();
+    for mut entry in entries {
+        let date = *entry.date(logger.hollow());
+        dates.push(date);
+        *per_month.entry((date[2], date[1])).or_default() += 1;
+        entry.sections(logger.hollow()).iter_mut().for_each(|x| {
+            words += word_count(x.content(logger.hollow()));
+            x.clear_cache();
+        }); entry.clear_cache();
+    }
+
+    let moc_count = archive.list_mocs(logger.hollow()).len();
+    let mut per_year: BTreeMap<u16, usize> = BTreeMap::new();
+    per_month.iter().for_each(|((year, _), count)| *per_year.entry(*year).or_default() += count);
+    let average = if entry_count == 0 { 0.0 } else { words as f64 / entry_count as f64 };
+    let streak = longest_streak(&dates);
+    let size = dir_size(archive.database().path());
+    let usage = tag_usage(&archive, logger.hollow());
+    let top_tags = &usage[..usage.len().min(TOP_TAGS)];
+    let signatures = signatures(&archive, logger.hollow());
+    let today = chrono::Local::now().date_naive();
+    let events: Vec<event::Event> = event::read(&archive, logger.hollow()).into_iter()
+        .filter(|x| x.date >= today)
+        .take(UPCOMING)
+        .collect();
+
+    if json::enabled() {
+        return json::print(json!({
+            "entries": entry_count,
+            "mocs": moc_count,
+            "per_year": per_year.iter().map(|(year, count)| (year.to_string(), json!(count))).collect::<serde_json::Map<_, _>>(),
+            "per_month": per_month.iter().map(|((year, month), count)| (format!("{year}-{month:02}"), json!(count))).collect::<serde_json::Map<_, _>>(),
+            "words": words,
+            "average_words": average,
+            "longest_streak": streak,
+            "devices": signatures.devices,
+            "authors": signatures.authors,
+            "top_tags": top_tags.iter().map(|x| json!({ "tag": x.tag, "count": x.count })).collect::<Vec<_>>(),
+            "size": size,
+            "upcoming_events": events.iter().map(|x| x.to_json(today)).collect::<Vec<_>>(),
+        }));
+    }
+
+    log!((logger.vital) entries("{entry_count}") as Result);
+    log!((logger.vital) mocs("{moc_count}") as Result);
+    log!((logger.vital) words("{words} ({average:.1} per entry)") as Result);
+    log!((logger.vital) streak("{streak} days") as Result);
+    log!((logger.vital) size("{}", format_size(size)) as Result);
+
+    for (year, count) in per_year.iter() {
+        let months: Vec<String> = per_month.iter()
+            .filter(|((x, _), _)| x == year)
+            .map(|((_, month), count)| format!("{month:02}: {count}"))
+            .collect();
+        log!((logger.vital) Stats("{}", colour_format![cyan(&year.to_string()), blue(" ("), none(&count.to_string()), blue(") "), none(&months.join(", "))]) as Log);
+    }
+
+    for (kind, counts) in [("devices", &signatures.devices), ("authors", &signatures.authors)] {
+        if counts.is_empty() { continue }
+        let counts: Vec<String> = counts.iter().map(|(x, count)| format!("{x}: {count}")).collect();
+        log!((logger.vital) Stats("{}", colour_format![cyan(kind), blue(" "), none(&counts.join(", "))]) as Log);
+    }
+
+    for x in top_tags.iter() {
+        log!((logger.vital) Stats("{}", colour_format![green(&x.tag), blue(" ("), cyan(&x.count.to_string()), blue(")")]) as Log);
+    }
+
+    events.iter().for_each(|x| event::log_event(x, today, logger.hollow()));
+}
+
+/// Counts the entries of each month (`(year, month)`) from the first date to the last, including empty months
+pub fn monthly_trend(dates: &[[u16; 3]]) -> Vec<((u16, u16), usize)> {
+    let mut counts: BTreeMap<(u16, u16), usize> = BTreeMap::new();
+    dates.iter().for_each(|x| *counts.entry((x[2], x[1])).or_default() += 1);
+    let (Some(first), Some(last)) = (counts.keys().next().copied(), counts.keys().last().copied()) else { return Vec::new() };
+
+    let mut result = Vec::new();
+    let mut month = first;
+    while month <= last {
+        result.push((month, counts.get(&month).copied().unwrap_or(0)));
+        month = if month.1 == 12 { (month.0 + 1, 1) } else { (month.0, month.1 + 1) };
+    } result
+}
+
+pub fn trend_command(tag: String, csv: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Stats("Counting the entries tagged '{tag}' each month..."));
+    let filter = [tag.clone()];
+    let dates: Vec<[u16; 3]> = archive.list_entries(logger.hollow())
+        .into_iter()
+        .filter_map(|mut x| filter_matches(x.tags(logger.hollow()), &filter).then(|| *x.date(logger.hollow())))
+        .collect();
+    let trend = monthly_trend(&dates);
+
+    if json::enabled() {
+        return json::print(trend.iter().map(|((year, month), count)| (format!("{year}-{month:02}"), json!(count))).collect::<serde_json::Map<_, _>>().into());
+    }
+
+    if csv {
+        println!("month,count");
+        trend.iter().for_each(|((year, month), count)| println!("{year}-{month:02},{count}"));
+        return;
+    }
+
+    if trend.is_empty() {
+        log!((logger.vital) Stats("No entries are tagged '{tag}'") as Inconvenience);
+        return;
+    }
+
+    let max = trend.iter().map(|(_, x)| *x).max().unwrap_or(1).max(1);
+    for ((year, month), count) in trend.iter() {
+        let bar = "█".repeat((count * TREND_WIDTH).div_ceil(max));
+        log!((logger.vital) Stats("{}", colour_format![cyan(&format!("{year}-{month:02}")), none(" "), green(&bar), none(&format!(" {count}"))]) as Log);
+    }
+}
+
+/// The entries and mocs (`(uid, is_moc, size)`) of an archive, largest first
+pub fn item_sizes(archive: &Archive) -> Vec<(String, bool, u64)> {
+    let mut sizes: Vec<(String, bool, u64)> = [("entries", false), ("mocs", true)].iter()
+        .flat_map(|(kind, is_moc)| std::fs::read_dir(archive.database().path().join(kind)).into_iter().flatten().flatten()
+            .filter(|x| x.path().is_dir())
+            .map(|x| (x.file_name().to_string_lossy().to_string(), *is_moc, dir_size(&x.path())))
+        ).collect();
+    sizes.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    sizes
+}
+
+/// Shows the largest entries, mocs and attachments of the archive, so runaway growth can be found
+pub fn largest_command(count: usize, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Stats("Measuring the entries and mocs of the archive..."));
+    let total = dir_size(archive.database().path());
+    let mut items = item_sizes(&archive);
+    items.truncate(count);
+
+    let mut attachments: Vec<(String, String, u64)> = Vec::new();
+    for mut entry in archive.list_entries(logger.hollow()) {
+        let uid = entry.uid.clone();
+        entry.sections(logger.hollow()).iter_mut().for_each(|section| {
+            for (i, name) in section.attachments(logger.hollow()).clone().iter().enumerate() {
+                attachments.push((uid.clone(), name.clone(), section.attachment_size(i)));
+            } section.clear_cache();
+        }); entry.clear_cache();
+    }
+    attachments.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    attachments.truncate(count);
+
+    if json::enabled() {
+        return json::print(json!({
+            "size": total,
+            "items": items.iter().map(|(uid, is_moc, size)| json!({ "uid": uid, "kind": if *is_moc { "moc" } else { "entry" }, "size": size })).collect::<Vec<_>>(),
+            "attachments": attachments.iter().map(|(uid, name, size)| json!({ "uid": uid, "name": name, "size": size })).collect::<Vec<_>>(),
+        }));
+    }
+
+    log!((logger.vital) size("{}", format_size(total)) as Result);
+    for (uid, is_moc, size) in items.iter() {
+        let share = if total == 0 { 0.0 } else { *size as f64 * 100.0 / total as f64 };
+        log!((logger.vital) Stats("{}", colour_format![cyan(&format_size(*size)), blue(" ("), none(&format!("{share:.1}%")), blue(") "), none(if *is_moc { "moc " } else { "entry " }), green(uid)]) as Log);
+    }
+    for (uid, name, size) in attachments.iter() {
+        log!((logger.vital) Stats("{}", colour_format![cyan(&format_size(*size)), none(" attachment "), green(name), none(" of "), green(uid)]) as Log);
+    }
+}
+
+pub fn streak_command(weekly: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Streak("Reading the dates of every entry..."));
+    let dates: Vec<[u16; 3]> = archive.list_entries(logger.hollow())
+        .into_iter()
+        .map(|mut x| *x.date(logger.hollow()))
+        .collect();
+
+    let (current, longest) = streaks(&dates, &today(), weekly.then_some(Config::get().calendar.week_start));
+    let unit = if weekly { "weeks" } else { "days" };
+
+    if json::enabled() {
+        return json::print(json!({ "current": current, "longest": longest, "unit": unit }));
+    }
+
+    log!((logger.vital) current("{current} {unit}") as Result);
+    log!((logger.vital) longest("{longest} {unit}") as Result);
+    if current > 0 && current == longest {
+        log!((logger.vital) Streak("You're on your longest streak yet; keep it going!") as Log);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::Path;
+use soulog::*;
+use toml::{Table, Value};
+use crate::{archive::Archive, config::Config, entry::resolve_path, json, unwrap_opt};
+
+/// Words too common to say anything about what an entry is about
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "was", "were", "with", "that", "this", "have", "had", "has", "but", "not", "are", "you", "your",
+    "our", "they", "them", "then", "than", "from", "about", "after", "before", "into", "just", "very", "what", "when",
+    "where", "which", "who", "will", "would", "could", "should", "there", "their", "been", "being", "all", "any", "some",
+    "out", "its", "it's", "i'm", "did", "got", "get", "too", "also", "again", "over", "only", "because", "while",
+];
+
+/// The tags suggested when committing an entry (`[tag_suggestions]`)
+pub struct Suggestions {
+    /// If entries committed from a terminal are offered tag suggestions at all
+    pub enabled: bool,
+    /// How many tags are suggested at most
+    pub count: usize,
+}
+
+impl Default for Suggestions {
+    fn default() -> Self {
+        Self { enabled: true, count: 5 }
+    }
+}
+
+impl Suggestions {
+    /// Parses the `[tag_suggestions]` table of the config
+    pub fn parse(table: &Table, mut logger: impl Logger) -> Self {
+        let default = Self::default();
+        let enabled = table.get("enabled")
+            .map(|x| unwrap_opt!((x.as_bool()) with logger, format: Config("Config's tag_suggestions 'enabled' must be a boolean")))
+            .unwrap_or(default.enabled);
+        let count = table.get("count")
+            .map(|x| unwrap_opt!((x.as_integer().filter(|x| *x > 0)) with logger, format: Config("Config's tag_suggestions 'count' must be a positive number")) as usize)
+            .unwrap_or(default.count);
+        Self { enabled, count }
+    }
+}
+
+/// Splits text into lowercase words worth comparing, leaving out short and stop words
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|x: char| !(x.is_alphanumeric() || x == '\''))
+        .map(|x| x.trim_matches('\'').to_lowercase())
+        .filter(|x| x.chars().count() >= 3 && !STOP_WORDS.contains(&x.as_str()) && !x.chars().all(|x| x.is_ascii_digit()))
+        .collect()
+}
+
+/// The words of every entry by the tags they were given, for weighing up which tags fit new text
+#[derive(Default)]
+pub struct TagIndex {
+    /// How often each word appears in the entries of each tag
+    tags: HashMap<String, HashMap<String, usize>>,
+    /// How many entries each word appears in
+    documents: HashMap<String, usize>,
+    entries: usize,
+}
+
+impl TagIndex {
+    /// Adds an entry's text and tags to the index
+    pub fn add(&mut self, tags: &[String], text: &str) {
+        let words = tokenize(text);
+        self.entries += 1;
+        let mut seen: Vec<&String> = Vec::new();
+        for word in words.iter() {
+            if !seen.contains(&word) {
+                *self.documents.entry(word.clone()).or_default() += 1;
+                seen.push(word);
+            }
+        }
+        for tag in tags {
+            let counts = self.tags.entry(tag.clone()).or_default();
+            words.iter().for_each(|x| *counts.entry(x.clone()).or_default() += 1);
+        }
+    }
+
+    /// How rare a word is across the indexed entries (its inverse document frequency)
+    fn idf(&self, word: &str) -> f64 {
+        let documents = self.documents.get(word).copied().unwrap_or(0);
+        ((self.entries as f64 + 1.0) / (documents as f64 + 1.0)).ln() + 1.0
+    }
+
+    /// The tf-idf weights of a bag of words
+    fn weights(&self, counts: &HashMap<String, usize>) -> HashMap<String, f64> {
+        let total: usize = counts.values().sum();
+        counts.iter().map(|(word, count)| (word.clone(), *count as f64 / total.max(1) as f64 * self.idf(word))).collect()
+    }
+
+    /// The tags (other than `existing`) that best fit some text, best first, with how well they fit (`0` to `1`,
+    /// or more for tags the text mentions by name)
+    pub fn suggest(&self, text: &str, existing: &[String], count: usize) -> Vec<(String, f64)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        tokenize(text).into_iter().for_each(|x| *counts.entry(x).or_default() += 1);
+        if counts.is_empty() { return Vec::new() }
+        let weights = self.weights(&counts);
+        let norm = weights.values().map(|x| x * x).sum::<f64>().sqrt();
+
+        let mut scores: Vec<(String, f64)> = self.tags.iter()
+            .filter(|(tag, _)| !existing.contains(tag))
+            .map(|(tag, tag_counts)| {
+                // Cosine similarity of the text and everything written under the tag
+                let tag_weights = self.weights(tag_counts);
+                let tag_norm = tag_weights.values().map(|x| x * x).sum::<f64>().sqrt();
+                let dot: f64 = weights.iter().filter_map(|(word, x)| tag_weights.get(word).map(|y| x * y)).sum();
+                let mut score = if norm > 0.0 && tag_norm > 0.0 { dot / (norm * tag_norm) } else { 0.0 };
+
+                // Text that names a tag (or the last part of a nested one) is very likely about it
+                let name = tag.rsplit('/').next().unwrap_or(tag).to_lowercase();
+                if counts.contains_key(&name) { score += 1.0 }
+                (tag.clone(), score)
+            })
+            .filter(|(_, score)| *score >= 0.1)
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores.truncate(count);
+        scores
+    }
+}
+
+/// Indexes the text and tags of every entry in the archive
+pub fn index_archive(archive: &Archive, logger: impl Logger) -> TagIndex {
+    let mut index = TagIndex::default();
+    for mut entry in archive.list_entries(logger.hollow()) {
+        let mut text = format!("{}\n", entry.title(logger.hollow()));
+        text.push_str(&format!("{}\n", entry.description(logger.hollow())));
+        text.push_str(&entry.notes(logger.hollow()).join("\n"));
+        text.push('\n');
+        entry.sections(logger.hollow()).iter_mut().for_each(|x| {
+            text.push_str(x.content(logger.hollow()));
+            x.clear_cache();
+        });
+        index.add(entry.tags(logger.hollow()), &text);
+        entry.clear_cache();
+    }
+    index
+}
+
+/// The text of an entry config that hasn't been committed yet: its title, description, notes and sections' contents
+fn config_text(table: &Table, config: &Path) -> String {
+    let entry = table.get("entry").and_then(Value::as_table);
+    let string = |key: &str| entry.and_then(|x| x.get(key)).and_then(Value::as_str).unwrap_or_default().to_string();
+    let mut text = format!("{}\n{}\n", string("title"), string("description"));
+    entry.and_then(|x| x.get("notes")).and_then(Value::as_array).into_iter().flatten()
+        .filter_map(Value::as_str)
+        .for_each(|x| { text.push_str(x); text.push('\n') });
+
+    for section in table.get("section").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_table) {
+        match (section.get("contents").or(section.get("content")).and_then(Value::as_str), section.get("path").and_then(Value::as_str)) {
+            (Some(contents), _) => text.push_str(contents),
+            (None, Some(path)) => text.push_str(&std::fs::read_to_string(resolve_path(config, Path::new(path))).unwrap_or_default()),
+            _ => (),
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Offers tags that fit an entry config about to be committed from a terminal, adding the ones the user accepts
+pub fn review(mut table: Table, config: &Path, archive: &Archive, mut logger: impl Logger) -> Table {
+    let settings = &Config::get().tag_suggestions;
+    let is_moc = table.get("is-moc").and_then(Value::as_bool).unwrap_or(false);
+    if !settings.enabled || is_moc || json::enabled() || !std::io::stdin().is_terminal() { return table }
+
+    let existing: Vec<String> = table.get("entry").and_then(|x| x.get("tags")).and_then(Value::as_array).into_iter().flatten()
+        .filter_map(|x| x.as_str().map(String::from))
+        .collect();
+    log!((logger) Commit("Looking for tags to suggest..."));
+    let suggestions: Vec<String> = index_archive(archive, logger.hollow())
+        .suggest(&config_text(&table, config), &existing, settings.count)
+        .into_iter().map(|(tag, _)| tag)
+        .collect();
+    if suggestions.is_empty() { return table }
+
+    let answer = logger.ask("Commit", &format!("Add suggested tags {}? (y/n/edit)", suggestions.join(", ")));
+    let added: Vec<String> = match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => suggestions,
+        "e" | "edit" => logger.ask("Commit", "Tags to add (separated by spaces or commas)")
+            .split(|x: char| x == ',' || x.is_whitespace())
+            .filter(|x| !x.is_empty() && !existing.iter().any(|y| y == x))
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    };
+    if added.is_empty() { return table }
+
+    log!((logger.vital) Commit("Adding tags {}", added.join(", ")) as Log);
+    let tags = table.get_mut("entry").and_then(|x| x.as_table_mut()).map(|x| x.entry("tags").or_insert(Value::Array(Vec::new())));
+    if let Some(Value::Array(tags)) = tags { tags.extend(added.into_iter().map(Value::String)) }
+    table
+}
@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, HashMap};
+use soulog::*;
+use serde_json::json;
+use crate::{archive::Archive, history, entry::format_date, json, moc::walk_collections, search::tag_matches, sort::younger};
+
+/// How often a tag is used and when it was last used by an entry
+pub struct TagUsage {
+    pub tag: String,
+    pub count: usize,
+    pub latest: Option<[u16; 3]>,
+}
+
+/// Aggregates every tag in the archive, sorted by most used
+pub fn tag_usage(archive: &Archive, logger: impl Logger) -> Vec<TagUsage> {
+    let mut usage: HashMap<String, TagUsage> = HashMap::new();
+    let mut record = |tag: &String, date: Option<[u16; 3]>| {
+        let item = usage.entry(tag.clone()).or_insert_with(|| TagUsage { tag: tag.clone(), count: 0, latest: None });
+        item.count += 1;
+        if let Some(date) = date {
+            if item.latest.map(|x| younger(&date, &x)).unwrap_or(true) { item.latest = Some(date) }
+        }
+    };
+
+    for mut entry in archive.list_entries(logger.hollow()) {
+        let date = *entry.date(logger.hollow());
+        entry.tags(logger.hollow()).iter().for_each(|x| record(x, Some(date)));
+    }
+
+    for mut moc in archive.list_mocs(logger.hollow()) {
+        moc.tags(logger.hollow()).iter().for_each(|x| record(x, None));
+    }
+
+    let mut usage: Vec<TagUsage> = usage.into_values().collect();
+    usage.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    usage
+}
+
+/// A node in the hierarchy of nested tags (`project/rust/diary-cli`)
+#[derive(Default)]
+pub struct TagNode {
+    pub count: usize,
+    pub children: BTreeMap<String, TagNode>,
+}
+
+/// Arranges tag usage into a tree of nested tags, where each node counts the usage of its children too
+pub fn tag_tree(usage: &[TagUsage]) -> TagNode {
+    let mut root = TagNode::default();
+    for x in usage.iter() {
+        let mut node = &mut root;
+        for part in x.tag.split('/').filter(|x| !x.is_empty()) {
+            node = node.children.entry(part.to_string()).or_default();
+            node.count += x.count;
+        }
+    } root
+}
+
+fn tree_json(node: &TagNode) -> serde_json::Value {
+    node.children.iter()
+        .map(|(name, x)| (name.clone(), json!({ "count": x.count, "children": tree_json(x) })))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn log_tree(node: &TagNode, depth: usize, logger: &mut impl Logger) {
+    for (name, x) in node.children.iter() {
+        log!((logger.vital) Tags("{}{}", "  ".repeat(depth), colour_format![none(name), blue(" ("), cyan(&x.count.to_string()), blue(")")]) as Log);
+        log_tree(x, depth + 1, logger);
+    }
+}
+
+pub fn tags_command(tree: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    log!((logger) Tags("Counting tag usage across entries and mocs..."));
+    let usage = tag_usage(&archive, logger.hollow());
+
+    if tree {
+        let tree = tag_tree(&usage);
+        if json::enabled() { return json::print(tree_json(&tree)) }
+        return log_tree(&tree, 0, &mut logger);
+    }
+
+    if json::enabled() {
+        return json::print(usage.iter()
+            .map(|x| json!({ "tag": x.tag, "count": x.count, "latest": x.latest.map(|x| format_date(&x)) }))
+            .collect());
+    }
+
+    if usage.is_empty() {
+        log!((logger.vital) Tags("No tags found in the archive") as Inconvenience);
+        return;
+    }
+
+    let width = usage.iter().map(|x| x.tag.len()).max().unwrap_or(0).max(3);
+    log!((logger.vital) Tags("{}", colour_format![green(&format!("{:<width$}  {:>5}  latest", "tag", "count"))]) as Log);
+    for x in usage.iter() {
+        let latest = x.latest.map(|x| format_date(&x)).unwrap_or_else(|| String::from("-"));
+        log!((logger.vital) Tags("{:<width$}  {:>5}  {latest}", x.tag, x.count) as Log);
+    }
+}
+
+/// Replaces every tag in `from` (and their nested tags) with `into`, removing any duplicates that creates
+pub fn replace_tags(tags: &[String], from: &[String], into: &str) -> Option<Box<[String]>> {
+    let rename = |tag: &String| from.iter()
+        .find(|x| tag_matches(tag, x))
+        .map(|x| format!("{into}{}", &tag[x.trim_end_matches('/').len()..]));
+    if !tags.iter().any(|x| rename(x).is_some()) { return None }
+
+    let mut result: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().map(|x| rename(x).unwrap_or_else(|| x.clone())) {
+        if !result.contains(&tag) { result.push(tag) }
+    } Some(result.into_boxed_slice())
+}
+
+/// Renames (or merges) tags across every entry, moc and collection in the archive
+pub fn rename_tags(from: Vec<String>, into: String, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    Archive::backup_before_modification(logger.hollow());
+    log!((logger) Tag("Renaming tags {from:?} to '{into}'..."));
+    let mut changed = 0usize;
+
+    for mut entry in archive.list_entries(logger.hollow()) {
+        let tags = replace_tags(entry.tags(logger.hollow()), &from, &into);
+        entry.clear_cache();
+        if let Some(tags) = tags {
+            log!((logger) Tag("Updating tags of entry '{}'...", entry.uid));
+            entry.tags = Some(tags);
+            entry.store_lazy(logger.hollow());
+            changed += 1;
+        }
+    }
+
+    for mut moc in archive.list_mocs(logger.hollow()) {
+        let tags = replace_tags(moc.tags(logger.hollow()), &from, &into);
+        moc.tags = None;
+        if let Some(tags) = tags {
+            log!((logger) Tag("Updating tags of moc '{}'...", moc.uid));
+            moc.tags = Some(tags);
+            moc.store_lazy(logger.hollow());
+            changed += 1;
+        }
+
+        let uid = moc.uid.clone();
+        let hollow = logger.hollow();
+        walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+            let include = replace_tags(collection.include(logger.hollow()), &from, &into);
+            let exclude = replace_tags(collection.exclude(logger.hollow()), &from, &into);
+            collection.clear_cache();
+            if include.is_some() || exclude.is_some() {
+                log!((logger) Tag("Updating included and excluded tags of moc '{uid}', collection {}...", at.iter().map(usize::to_string).collect::<Vec<_>>().join(".")));
+                collection.include = include;
+                collection.exclude = exclude;
+                collection.store_lazy(logger.hollow());
+                changed += 1;
+            }
+        }, hollow);
+    }
+
+    if changed == 0 {
+        log!((logger.vital) Tag("No items use tags {from:?}; doing nothing") as Inconvenience);
+        return;
+    }
+
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, "tag-rename", None, logger.hollow());
+    log!((logger.vital) Tag("Successfully renamed tags {from:?} to '{into}' in {changed} items") as Log);
+}
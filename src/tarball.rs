@@ -0,0 +1,55 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Checks if a backup path is a plain (`.tar`) or compressed (`.tar.zst`) tarball rather than an `.ldb` file
+pub fn is_tarball(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar") || name.ends_with(".tar.zst")
+}
+
+/// Writes an archive directory into a standard tarball under `archive/`, along with a `manifest.toml`
+pub fn write(archive: &Path, out_path: &Path, compress: bool, manifest: &str) -> io::Result<()> {
+    let file = File::create(out_path)?;
+    let mut file = if compress {
+        // Finished by hand, as finishing it on drop would throw away any error writing the end of the stream
+        build(zstd::Encoder::new(file, 0)?, archive, manifest)?.finish()?
+    } else {
+        build(file, archive, manifest)?
+    };
+    file.flush()
+}
+
+/// Writes the tarball into a writer, returning it once the tarball is complete
+fn build<W: Write>(writer: W, archive: &Path, manifest: &str) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, "manifest.toml", manifest.as_bytes())?;
+    builder.append_dir_all("archive", archive)?;
+    builder.into_inner()
+}
+
+/// Unpacks the archive directory of a tarball written by `write` into `out_path`
+pub fn unpack(path: &Path, out_path: &Path) -> io::Result<()> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if path.to_string_lossy().ends_with(".zst") {
+        Box::new(zstd::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+
+    let tmp = out_path.with_extension("unpack");
+    let _ = fs::remove_dir_all(&tmp); // cleanup of any failed previous unpacks
+    tar::Archive::new(reader).unpack(&tmp)?;
+
+    let archive = tmp.join("archive");
+    if !archive.is_dir() {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "tarball doesn't contain an `archive` directory"));
+    }
+    fs::rename(archive, out_path)?;
+    fs::remove_dir_all(tmp)
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::path::Path;
+use soulog::*;
+
+/// The default layout of an exported entry
+pub const DEFAULT_ENTRY: &str = "{{frontmatter}}# {{title}}\n---\n**Description:** {{description}}\n\n{{notes}}---\n{{sections}}{{links}}{{backlinks}}";
+/// The default layout of an exported time capsule that hasn't unlocked yet
+pub const DEFAULT_CAPSULE: &str = "{{frontmatter}}# {{title}}\n---\n> *This entry is a time capsule sealed until {{unlock_date}}*\n";
+/// The default layout of an exported moc
+pub const DEFAULT_MOC: &str = "{{frontmatter}}# {{title}}\n---\n**Description:** {{description}}\n\n{{notes}}---\n{{collections}}{{backlinks}}";
+/// The default layout of a page of a collection exported into its own file (`--split-collections`)
+pub const DEFAULT_COLLECTION: &str = "{{frontmatter}}# {{title}}\n*Collection of {{moc_link}}*\n{{navigation}}---\n{{notes}}{{items}}";
+
+/// The templates the markdown exporter renders entries, mocs and collections with
+pub struct Templates {
+    pub entry: String,
+    pub capsule: String,
+    pub moc: String,
+    pub collection: String,
+    /// The other templates of the directory by name (`letter.md` as `letter`), which entries can be rendered with instead
+    /// of `entry`
+    pub named: HashMap<String, String>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            entry: DEFAULT_ENTRY.to_string(),
+            capsule: DEFAULT_CAPSULE.to_string(),
+            moc: DEFAULT_MOC.to_string(),
+            collection: DEFAULT_COLLECTION.to_string(),
+            named: HashMap::new(),
+        }
+    }
+}
+
+impl Templates {
+    /// Loads `entry.md`, `capsule.md`, `moc.md` and `collection.md` from a template directory, using the default
+    /// template for any that are missing, along with any other `.md` templates in it
+    pub fn load(dir: &Path, mut logger: impl Logger) -> Self {
+        if !dir.is_dir() {
+            log!((logger.error) Template("Template directory '{}' doesn't exist", dir.to_string_lossy()) as Fatal);
+            return logger.crash();
+        }
+
+        let mut named = HashMap::new();
+        let files = if_err!((logger) [Template, err => ("While reading template directory '{}': {err:?}", dir.to_string_lossy())] retry std::fs::read_dir(dir));
+        for path in files.flatten().map(|x| x.path()).filter(|x| x.extension().is_some_and(|x| x == "md")) {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            if ["entry", "capsule", "moc", "collection"].contains(&name.as_str()) { continue }
+            log!((logger) Template("Found entry template '{name}'"));
+            let text = if_err!((logger) [Template, err => ("While reading '{}': {err:?}", path.to_string_lossy())] retry std::fs::read_to_string(&path));
+            named.insert(name, text);
+        }
+
+        let mut read = |name: &str, default: &str| {
+            let path = dir.join(name);
+            if !path.is_file() { return default.to_string() }
+            log!((logger) Template("Using custom template '{}'", path.to_string_lossy()));
+            if_err!((logger) [Template, err => ("While reading '{}': {err:?}", path.to_string_lossy())] retry std::fs::read_to_string(&path))
+        };
+
+        Self {
+            entry: read("entry.md", DEFAULT_ENTRY),
+            capsule: read("capsule.md", DEFAULT_CAPSULE),
+            moc: read("moc.md", DEFAULT_MOC),
+            collection: read("collection.md", DEFAULT_COLLECTION),
+            named,
+        }
+    }
+
+    /// The template of an entry rendered with the template `name` (if it has one); falls back to `entry` if the
+    /// directory doesn't have it
+    pub fn entry(&self, name: Option<&str>, uid: &str, mut logger: impl Logger) -> &str {
+        let Some(name) = name else { return &self.entry };
+        match self.named.get(name) {
+            Some(x) => x,
+            None => {
+                log!((logger.vital) Template("Entry '{uid}' uses template '{name}', but there's no '{name}.md' in the template directory (set with `--template`); using the default entry template") as Inconvenience);
+                &self.entry
+            },
+        }
+    }
+}
+
+/// Renders a template, replacing `{{name}}` with the `escape`d value of a variable and `{{{name}}}` with its raw value;
+/// unknown variables render as nothing
+pub fn substitute(template: &str, vars: &[(&str, &str)], escape: impl Fn(&str) -> String) -> String {
+    let lookup = |name: &str| vars.iter().find(|(x, _)| *x == name.trim()).map(|(_, x)| *x).unwrap_or("");
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let raw = rest[start..].starts_with("{{{");
+        let (open, close) = if raw { ("{{{", "}}}") } else { ("{{", "}}") };
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(close) else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+
+        let value = lookup(&after[..end]);
+        if raw { result.push_str(value) } else { result.push_str(&escape(value)) }
+        rest = &after[end + close.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Renders a markdown template, where nothing is escaped
+#[inline]
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    substitute(template, vars, str::to_string)
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+use soulog::*;
+use crate::{home_dir, template::substitute};
+
+const DEFAULT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>{{{style}}}</style>
+</head>
+<body>
+<main>
+{{{content}}}
+</main>
+</body>
+</html>
+"#;
+
+const LIGHT_STYLE: &str = "body { font-family: sans-serif; line-height: 1.6; margin: 0 auto; max-width: 48em; padding: 1em; color: #222; background: #fff; }
+a { color: #2a6e3f; }
+blockquote { border-left: 0.25em solid #8a8; color: #555; margin-left: 0; padding-left: 1em; }
+.tags, .date { color: #6a6; }
+";
+
+const DARK_STYLE: &str = "body { font-family: sans-serif; line-height: 1.6; margin: 0 auto; max-width: 48em; padding: 1em; color: #ddd; background: #1b1d1e; }
+a { color: #7fd08f; }
+blockquote { border-left: 0.25em solid #4a6; color: #aaa; margin-left: 0; padding-left: 1em; }
+.tags, .date { color: #8c8; }
+";
+
+/// The built-in themes' css by name
+pub const THEMES: &[(&str, &str)] = &[("light", LIGHT_STYLE), ("dark", DARK_STYLE)];
+
+/// The templates and css the html exporter renders with
+pub struct Theme {
+    /// Wraps every exported page; has access to `title`, `style` and `content`
+    pub page: String,
+    pub style: String,
+}
+
+/// The directory users put their own html templates and css overrides in
+#[inline]
+pub fn theme_dir() -> PathBuf {
+    home_dir().join("templates").join("html")
+}
+
+impl Theme {
+    /// Loads the user's theme from the templates directory on top of a built-in theme;
+    /// `style.css` is appended to the built-in css so it only needs to override what it changes
+    pub fn load(name: &str, mut logger: impl Logger) -> Self {
+        let Some((_, builtin)) = THEMES.iter().find(|(x, _)| *x == name) else {
+            let names: Vec<&str> = THEMES.iter().map(|(x, _)| *x).collect();
+            log!((logger.error) Theme("Unknown theme '{name}'; the built-in themes are {names:?}") as Fatal);
+            return logger.crash();
+        };
+
+        let dir = theme_dir();
+        let mut read = |name: &str| {
+            let path = dir.join(name);
+            if !path.is_file() { return None }
+            log!((logger) Theme("Using custom html template '{}'", path.to_string_lossy()));
+            Some(if_err!((logger) [Theme, err => ("While reading '{}': {err:?}", path.to_string_lossy())] retry std::fs::read_to_string(&path)))
+        };
+
+        let page = read("page.html").unwrap_or_else(|| DEFAULT_PAGE.to_string());
+        let style = match read("style.css") {
+            Some(x) => format!("{builtin}{x}"),
+            None => builtin.to_string(),
+        };
+        Self { page, style }
+    }
+
+    /// Renders a page of the theme
+    pub fn render_page(&self, title: &str, content: &str) -> String {
+        render(&self.page, &[("title", title), ("style", &self.style), ("content", content)])
+    }
+}
+
+/// Escapes text so it can be placed inside html
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a template, replacing `{{name}}` with the escaped value of a variable and `{{{name}}}` with its raw value;
+/// unknown variables render as nothing
+#[inline]
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    substitute(template, vars, escape_html)
+}
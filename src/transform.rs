@@ -0,0 +1,41 @@
+use regex::Regex;
+use soulog::*;
+use crate::{search::filter_matches, unwrap_opt};
+
+/// A regex replacement applied to section content at export time
+pub struct Transform {
+    pub pattern: Regex,
+    /// The replacement, which may refer to capture groups (`$1`, `${name}`)
+    pub replace: String,
+    /// Only applies to items with these tags (`!tag` excludes a tag); applies to everything if empty
+    pub tags: Vec<String>,
+}
+
+impl Transform {
+    /// Parses a `[[export_transform]]` table of the config
+    pub fn parse(table: &toml::Table, idx: usize, mut logger: impl Logger) -> Self {
+        let pattern = unwrap_opt!((table.get("pattern")) with logger, format: Config("Config's export_transform {idx} must have a 'pattern' attribute"));
+        let pattern = unwrap_opt!((pattern.as_str()) with logger, format: Config("Config's export_transform {idx} 'pattern' attribute must be a string"));
+        let pattern = if_err!((logger) [Config, err => ("Invalid export_transform {idx} pattern: {err}")] {Regex::new(pattern)} crash {
+            log!((logger.error) Config("Config's export_transform {idx} has an invalid pattern: {err}") as Fatal);
+            logger.crash()
+        });
+        let replace = table.get("replace")
+            .map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's export_transform {idx} 'replace' attribute must be a string")))
+            .unwrap_or("")
+            .to_string();
+        let tags = table.get("tags")
+            .map(|x| unwrap_opt!((x.as_array()) with logger, format: Config("Config's export_transform {idx} 'tags' attribute must be an array")))
+            .map(|x| x.iter().map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's export_transform {idx} 'tags' must only contain strings")).to_string()).collect())
+            .unwrap_or_default();
+
+        Self { pattern, replace, tags }
+    }
+}
+
+/// Runs content through the transforms in order, skipping those scoped to tags the item doesn't have
+pub fn apply(transforms: &[Transform], content: &str, tags: &[String]) -> String {
+    transforms.iter()
+        .filter(|x| x.tags.is_empty() || filter_matches(tags, &x.tags))
+        .fold(content.to_string(), |content, x| x.pattern.replace_all(&content, x.replace.as_str()).into_owned())
+}
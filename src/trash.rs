@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use lazy_db::*;
+use soulog::*;
+use serde_json::json;
+use crate::{archive::{Archive, copy_dir}, config::Config, history, home_dir, json, limits::{format_size, parse_size}, list, signal, unwrap_opt, with_context};
+
+/// An entry or moc that was removed into the trash
+pub struct TrashItem {
+    pub uid: String,
+    pub is_moc: bool,
+    pub path: PathBuf,
+    pub removed: SystemTime,
+    pub size: u64,
+}
+
+impl TrashItem {
+    pub fn kind(&self) -> &'static str {
+        if self.is_moc { "moc" } else { "entry" }
+    }
+}
+
+/// How long and how much of what is removed the trash keeps (`[trash]`); it keeps everything by default
+#[derive(Default)]
+pub struct Retention {
+    /// Items removed longer ago than this expire
+    pub max_age: Option<Duration>,
+    /// The oldest items expire until the trash is at most this many bytes
+    pub max_size: Option<u64>,
+}
+
+impl Retention {
+    /// Parses the `[trash]` table of the config (`max_age` in days and `max_size` in bytes or like `"500 MB"`)
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let max_age = table.get("max_age").map(|x| {
+            let days = unwrap_opt!((x.as_integer().filter(|x| *x > 0)) with logger, format: Config("Config's trash 'max_age' must be a positive number of days"));
+            Duration::from_secs(days as u64 * 24 * 60 * 60)
+        });
+        let max_size = table.get("max_size").map(|x| match x {
+            toml::Value::Integer(x) => *x as u64,
+            x => {
+                let size = x.as_str().and_then(parse_size);
+                unwrap_opt!((size) with logger, format: Config("Config's trash 'max_size' must be a number of bytes or a size like \"500 MB\""))
+            },
+        });
+        Self { max_age, max_size }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.max_age.is_some() || self.max_size.is_some()
+    }
+
+    /// The items (most recently removed first, like `list_items`) that have expired at `now`: the ones that are too old
+    /// and the oldest ones that don't fit in the size cap
+    pub fn expired<'a>(&self, items: &'a [TrashItem], now: SystemTime) -> Vec<&'a TrashItem> {
+        let mut kept = 0;
+        items.iter().filter(|x| {
+            let too_old = self.max_age.is_some_and(|age| now.duration_since(x.removed).unwrap_or_default() > age);
+            if too_old { return true }
+            kept += x.size;
+            self.max_size.is_some_and(|size| kept > size)
+        }).collect()
+    }
+}
+
+#[inline]
+pub fn trash_dir() -> PathBuf {
+    home_dir().join("trash")
+}
+
+fn trash_file(uid: &str, is_moc: bool) -> PathBuf {
+    trash_dir().join(format!("{}.{uid}.ldb", if is_moc { "moc" } else { "entry" }))
+}
+
+/// Compiles an entry or moc's container into the trash
+pub fn store(path: &Path, uid: &str, is_moc: bool, mut logger: impl Logger) {
+    log!((logger) Trash("Moving '{uid}' into the trash..."));
+    let out = trash_file(uid, is_moc);
+    if out.is_file() {
+        log!((logger.vital) Trash("Replacing the older copy of '{uid}' already in the trash") as Inconvenience);
+        let _ = fs::remove_file(&out);
+    }
+
+    let tmp = trash_dir().join("tmp");
+    let _ = fs::remove_dir_all(&tmp); // cleanup of any failed previous removals
+    signal::remove_on_interrupt(&tmp);
+    let database = with_context!((logger) [Trash, "trash" => ("While initialising trash item")] retry LazyDB::init(&tmp));
+    with_context!((logger) [Trash, "trash" => ("While copying '{uid}' into the trash")] retry copy_dir(path, tmp.join("item")));
+    with_context!((logger) [Trash, "trash" => ("While compiling trash item")] retry database.compile(&out));
+    let _ = fs::remove_dir_all(&tmp); // cleanup
+    signal::keep(&tmp);
+}
+
+/// Lists the items in the trash, most recently removed first
+pub fn list_items(logger: impl Logger) -> Vec<TrashItem> {
+    let path = trash_dir();
+    if !path.is_dir() { return Vec::new() }
+
+    let dir = with_context!((logger) [Trash, "trash-read" => ("While reading the trash")] retry fs::read_dir(&path));
+    let mut items: Vec<TrashItem> = dir.filter_map(|x| x.ok())
+        .filter_map(|x| {
+            let name = x.file_name().to_string_lossy().to_string();
+            let (kind, uid) = name.strip_suffix(".ldb")?.split_once('.')?;
+            let metadata = x.metadata().ok()?;
+            Some(TrashItem {
+                uid: uid.to_string(),
+                is_moc: kind == "moc",
+                path: x.path(),
+                removed: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                size: metadata.len(),
+            })
+        }).collect();
+    items.sort_by_key(|x| std::cmp::Reverse(x.removed));
+    items
+}
+
+pub fn list_command(mut logger: impl Logger) {
+    expire_on_load(logger.hollow());
+    let items = list_items(logger.hollow());
+
+    if json::enabled() {
+        return json::print(items.iter()
+            .map(|x| json!({
+                "uid": x.uid,
+                "kind": x.kind(),
+                "removed": chrono::DateTime::<chrono::Local>::from(x.removed).to_rfc3339(),
+                "size": x.size,
+            })).collect());
+    }
+
+    if items.is_empty() {
+        log!((logger.vital) Trash("The trash is empty") as Inconvenience);
+        return;
+    }
+
+    for x in items.iter() {
+        let removed = chrono::DateTime::<chrono::Local>::from(x.removed).format("%Y-%m-%d %H:%M");
+        log!((logger.vital) Trash("{}", colour_format![cyan(x.kind()), blue(" `"), none(&x.uid), blue("` "), none(&format!("removed {removed}"))]) as Log);
+    }
+}
+
+/// Permanently deletes the items of the trash that have expired, returning how many were
+pub fn expire(retention: &Retention, mut logger: impl Logger) -> usize {
+    let items = list_items(logger.hollow());
+    let expired = retention.expired(&items, SystemTime::now());
+    for x in expired.iter() {
+        log!((logger) Trash("Deleting expired {} '{}' ({}) from the trash...", x.kind(), x.uid, format_size(x.size)));
+        if_err!((logger) {fs::remove_file(&x.path)} else(err) {
+            log!((logger.vital) Trash("While deleting expired '{}' from the trash: {err:?}; ignoring error...", x.uid) as Inconvenience)
+        });
+    } expired.len()
+}
+
+/// Expires the trash as configured (at most once per run), whenever the archive or the trash is loaded
+pub fn expire_on_load(logger: impl Logger) {
+    static EXPIRED: std::sync::Once = std::sync::Once::new();
+    let retention = &Config::get().trash;
+    if !retention.is_set() || !trash_dir().is_dir() { return }
+    EXPIRED.call_once(|| { expire(retention, logger); });
+}
+
+pub fn prune_command(mut logger: impl Logger) {
+    let retention = &Config::get().trash;
+    if !retention.is_set() {
+        log!((logger.vital) Trash("No trash retention is configured (`max_age` or `max_size` in config.toml's `[trash]`); keeping everything") as Inconvenience);
+        return;
+    }
+    let pruned = expire(retention, logger.hollow());
+    log!((logger.vital) Trash("Deleted {pruned} expired items from the trash") as Log);
+}
+
+/// Restores an entry or moc from the trash back into the archive
+pub fn restore(uid: String, is_moc: bool, mut logger: impl Logger) {
+    let archive = Archive::load(logger.hollow());
+    let path = trash_file(&uid, is_moc);
+    let kind = if is_moc { "moc" } else { "entry" };
+
+    if !path.is_file() {
+        log!((logger.error) Trash("No {kind} of uid '{uid}' in the trash") as Fatal);
+        return logger.crash();
+    }
+
+    let to = archive.database().path().join(if is_moc { "mocs" } else { "entries" }).join(&uid);
+    if to.is_dir() {
+        log!((logger.error) Trash("A {kind} of uid '{uid}' already exists in the archive; remove it before restoring") as Fatal);
+        return logger.crash();
+    }
+
+    log!((logger) Trash("Restoring {kind} of uid '{uid}' from the trash..."));
+    let tmp = trash_dir().join("tmp");
+    let _ = fs::remove_dir_all(&tmp); // cleanup of any failed previous restores
+    with_context!((logger) [Trash, "trash" => ("While decompiling trash item")] retry LazyDB::decompile(&path, &tmp));
+    with_context!((logger) [Trash, "trash" => ("While restoring '{uid}' from the trash")] retry copy_dir(tmp.join("item"), &to));
+    let _ = fs::remove_dir_all(&tmp); // cleanup
+
+    if !is_moc {
+        list::push(
+            |file| LazyData::new_string(file, &uid),
+            &with_context!((logger) [Trash, "trash-read" => ("While loading unsorted stack")] retry search_database!((archive.database()) /order/unsorted)),
+            logger.hollow(),
+        );
+    }
+
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, "trash-restore", Some(&uid), logger.hollow());
+    if_err!((logger) {fs::remove_file(&path)} else(err) {
+        log!((logger.vital) Trash("While removing restored item from the trash: {err:?}; ignoring error...") as Inconvenience)
+    });
+    log!((logger.vital) Trash("Successfully restored {kind} of uid '{uid}' from the trash") as Log);
+}
@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use soulog::*;
+use crate::{archive::Archive, config::Config, entry::format_date, home_dir, json, moc::walk_collections, search::filter_matches, shared::SharedArchive, signal, sort::younger};
+
+/// An entry or moc listed in the tui
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub uid: String,
+    pub title: String,
+    /// The date of an entry (day, month then year); mocs aren't dated
+    pub date: Option<[u16; 3]>,
+    pub tags: Vec<String>,
+    pub is_moc: bool,
+}
+
+/// How the items of the tui are sorted
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    Title,
+}
+
+impl Sort {
+    fn next(self) -> Self {
+        match self {
+            Self::Newest => Self::Oldest,
+            Self::Oldest => Self::Title,
+            Self::Title => Self::Newest,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Newest => "newest first",
+            Self::Oldest => "oldest first",
+            Self::Title => "by title",
+        }
+    }
+}
+
+/// Which kinds of items the tui lists
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Kinds {
+    #[default]
+    All,
+    Entries,
+    Mocs,
+}
+
+impl Kinds {
+    fn next(self) -> Self {
+        match self {
+            Self::All => Self::Entries,
+            Self::Entries => Self::Mocs,
+            Self::Mocs => Self::All,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::All => "entries & mocs",
+            Self::Entries => "entries",
+            Self::Mocs => "mocs",
+        }
+    }
+}
+
+/// The indices of the items of some kinds matching a tag filter (like `list -f`), sorted; mocs come after the entries
+/// when sorting by date, as they don't have one
+pub fn shown(items: &[Item], filter: &[String], sort: Sort, kinds: Kinds) -> Vec<usize> {
+    let mut shown: Vec<usize> = (0..items.len())
+        .filter(|x| match kinds { Kinds::All => true, Kinds::Entries => !items[*x].is_moc, Kinds::Mocs => items[*x].is_moc })
+        .filter(|x| filter_matches(&items[*x].tags, filter))
+        .collect();
+    shown.sort_by(|a, b| {
+        let (a, b) = (&items[*a], &items[*b]);
+        let by_title = || a.title.to_lowercase().cmp(&b.title.to_lowercase()).then_with(|| a.uid.cmp(&b.uid));
+        match (sort, a.date, b.date) {
+            (Sort::Title, ..) | (_, None, None) => by_title(),
+            (_, Some(_), None) => std::cmp::Ordering::Less,
+            (_, None, Some(_)) => std::cmp::Ordering::Greater,
+            (Sort::Newest, Some(x), Some(y)) => order(&y, &x).then_with(|| b.uid.cmp(&a.uid)),
+            (Sort::Oldest, Some(x), Some(y)) => order(&x, &y).then_with(|| a.uid.cmp(&b.uid)),
+        }
+    });
+    shown
+}
+
+fn order(a: &[u16; 3], b: &[u16; 3]) -> std::cmp::Ordering {
+    if younger(a, b) { std::cmp::Ordering::Greater }
+    else if younger(b, a) { std::cmp::Ordering::Less }
+    else { std::cmp::Ordering::Equal }
+}
+
+/// Styles markdown for the preview pane; headings, quotes and lists like `read` does
+pub fn styled(markdown: &str) -> Text<'static> {
+    markdown.lines().map(|line| {
+        let trimmed = line.trim_start();
+        let indent = line[..line.len() - trimmed.len()].to_string();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            Line::styled(heading.trim_start_matches('#').trim().to_string(), Style::new().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            Line::from(vec![Span::raw(indent), Span::styled("│ ", Style::new().fg(Color::Blue)), Span::styled(quote.trim_start().to_string(), Style::new().add_modifier(Modifier::ITALIC))])
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            Line::from(vec![Span::raw(indent), Span::styled("• ", Style::new().fg(Color::Blue)), Span::raw(item.to_string())])
+        } else {
+            Line::raw(line.to_string())
+        }
+    }).collect::<Vec<_>>().into()
+}
+
+/// What the bottom line of the tui is being used for
+enum Input {
+    /// Typing a tag filter
+    Filter(String),
+    /// Typing the path to export into
+    Export(String),
+    /// Confirming the removal of an item
+    Remove,
+}
+
+struct App {
+    /// Kept loaded for as long as the tui runs; commands it runs modify the archive on disk, which drops what it cached
+    archive: SharedArchive,
+    items: Vec<Item>,
+    shown: Vec<usize>,
+    state: ListState,
+    filter: Vec<String>,
+    sort: Sort,
+    kinds: Kinds,
+    input: Option<Input>,
+    /// What happened last, shown on the bottom line
+    status: String,
+    /// The previews of the items shown so far, by uid
+    previews: HashMap<String, Text<'static>>,
+    scroll: u16,
+}
+
+impl App {
+    fn new(logger: impl Logger) -> Self {
+        let archive = SharedArchive::new(archive(logger.hollow()));
+        let mut this = Self {
+            items: items(&archive, logger),
+            archive,
+            shown: Vec::new(),
+            state: ListState::default(),
+            filter: Vec::new(),
+            sort: Sort::default(),
+            kinds: Kinds::default(),
+            input: None,
+            status: String::new(),
+            previews: HashMap::new(),
+            scroll: 0,
+        };
+        this.refresh();
+        this
+    }
+
+    /// Re-reads the items after a command may have changed the archive, keeping the selection where it can
+    fn reload(&mut self, logger: impl Logger) {
+        let selected = self.selected().map(|x| x.uid.clone());
+        self.archive.refresh(logger.hollow());
+        self.items = items(&self.archive, logger);
+        self.previews.clear();
+        self.refresh();
+        if let Some(i) = selected.and_then(|x| self.shown.iter().position(|y| self.items[*y].uid == x)) {
+            self.state.select(Some(i));
+        }
+    }
+
+    /// Re-filters and re-sorts the items, selecting the first
+    fn refresh(&mut self) {
+        self.shown = shown(&self.items, &self.filter, self.sort, self.kinds);
+        self.state.select((!self.shown.is_empty()).then_some(0));
+        self.scroll = 0;
+    }
+
+    fn selected(&self) -> Option<&Item> {
+        self.state.selected().and_then(|x| self.shown.get(x)).map(|x| &self.items[*x])
+    }
+
+    fn select(&mut self, offset: isize) {
+        if self.shown.is_empty() { return }
+        let at = self.state.selected().unwrap_or(0) as isize + offset;
+        self.state.select(Some(at.clamp(0, self.shown.len() as isize - 1) as usize));
+        self.scroll = 0;
+    }
+
+    fn preview(&mut self, logger: impl Logger) -> Text<'static> {
+        let Some(item) = self.selected().cloned() else { return Text::raw("Nothing matches the filter") };
+        self.previews.entry(item.uid.clone())
+            .or_insert_with(|| styled(&preview(&self.archive, &item, logger)))
+            .clone()
+    }
+}
+
+/// Loads the archive without locking it, so other diary-cli commands (including the ones the tui runs) can use it
+fn archive(mut logger: impl Logger) -> Archive {
+    let path = home_dir().join("archive");
+    if !path.is_dir() {
+        log!((logger.error) Tui("There's no archive to browse; run `diary-cli init` to make one") as Fatal);
+        return logger.crash();
+    }
+    Archive::load_dir(path, logger)
+}
+
+/// The entries are read through the archive's cache, so the previews of the ones read most recently don't have to be
+/// read from disk again
+fn items(archive: &SharedArchive, logger: impl Logger) -> Vec<Item> {
+    let entries = archive.entries(logger.hollow()).into_iter().map(|x| Item {
+        uid: x.uid.clone(),
+        title: x.title.clone(),
+        date: Some(x.date),
+        tags: x.tags.to_vec(),
+        is_moc: false,
+    });
+    let mocs = archive.list_mocs(logger.hollow()).into_iter().map(|mut x| Item {
+        title: x.title(logger.hollow()).clone(),
+        date: None,
+        tags: x.tags(logger.hollow()).to_vec(),
+        uid: x.uid,
+        is_moc: true,
+    });
+    entries.chain(mocs).collect()
+}
+
+/// The markdown shown for an item: the whole entry (only the title and unlock date of time capsules), or a moc with the
+/// titles and tags of its collections
+fn preview(archive: &SharedArchive, item: &Item, logger: impl Logger) -> String {
+    if !item.is_moc {
+        let Some(entry) = archive.entry(&item.uid, logger.hollow()) else { return format!("# {}\n> No longer in the archive\n", item.title) };
+        if entry.is_locked() {
+            let unlock_date = format_date(&entry.unlock_date.unwrap());
+            return format!("# {}\n{}\n> A time capsule that unlocks on {unlock_date}\n", item.title, format_date(&entry.date));
+        }
+        return entry.markdown();
+    }
+
+    if !archive.database_exists(format!("mocs/{}", item.uid)) { return format!("# {}\n> No longer in the archive\n", item.title) }
+    let Some(mut moc) = archive.get_moc(item.uid.clone(), logger.hollow()) else { return String::new() };
+    let mut markdown = format!("# {}\n> {}\n", item.title, moc.description(logger.hollow()));
+    moc.notes(logger.hollow()).iter().for_each(|x| markdown.push_str(&format!("- {x}\n")));
+    walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+        let query = collection.query(logger.hollow());
+        markdown.push_str(&format!("\n{} {}\n", "#".repeat(at.len() + 1), collection.title(logger.hollow())));
+        if !query.is_empty() { markdown.push_str(&format!("- includes `{}`\n", query.join(" "))) }
+        collection.clear_cache();
+    }, logger.hollow());
+    markdown
+}
+
+fn draw(frame: &mut Frame, app: &mut App, logger: impl Logger) {
+    let [main, bottom] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(35), Constraint::Min(0)]).areas(main);
+
+    let items: Vec<ListItem> = app.shown.iter().map(|x| {
+        let item = &app.items[*x];
+        let date = item.date.map(|x| format!("{} ", format_date(&x))).unwrap_or_else(|| String::from("moc "));
+        ListItem::new(Line::from(vec![Span::styled(date, Style::new().fg(Color::Blue)), Span::raw(item.title.clone())]))
+    }).collect();
+    let filter = if app.filter.is_empty() { String::new() } else { format!(", tagged {}", app.filter.join(" ")) };
+    let list = List::new(items)
+        .block(Block::bordered().title(format!(" {} ({}{filter}) ", app.kinds.name(), app.sort.name())))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, left, &mut app.state);
+
+    let title = app.selected().map(|x| format!(" {} ", x.uid)).unwrap_or_default();
+    let preview = Paragraph::new(app.preview(logger))
+        .block(Block::bordered().title(title))
+        .wrap(Wrap { trim: false })
+        .scroll((app.scroll, 0));
+    frame.render_widget(preview, right);
+
+    let line = match &app.input {
+        Some(Input::Filter(x)) => format!("Filter by tags (`!tag` excludes, `proj*` globs): {x}_"),
+        Some(Input::Export(x)) if Config::get().default_dest.is_some() => format!("Export into (empty for `default_dest`): {x}_"),
+        Some(Input::Export(x)) => format!("Export into: {x}_"),
+        Some(Input::Remove) => format!("Move '{}' to the trash? [y/n]", app.selected().map(|x| x.uid.as_str()).unwrap_or_default()),
+        None if !app.status.is_empty() => app.status.clone(),
+        None => String::from("↑↓ move  / filter  s sort  tab kinds  e edit  x export  d remove  pgup/pgdn scroll  q quit"),
+    };
+    frame.render_widget(Paragraph::new(line).style(Style::new().fg(Color::Cyan)), bottom);
+}
+
+/// Leaves the tui so commands and the editor can use the terminal
+fn suspend() {
+    ratatui::restore();
+    println!();
+}
+
+/// Goes back into the tui after it was suspended
+fn resume(terminal: &mut DefaultTerminal) {
+    let _ = ratatui::crossterm::terminal::enable_raw_mode();
+    let _ = ratatui::crossterm::execute!(std::io::stdout(), ratatui::crossterm::terminal::EnterAlternateScreen);
+    let _ = terminal.clear();
+}
+
+/// Runs a diary-cli command while the tui is suspended; true if it succeeded
+fn run(args: &[&str], mut logger: impl Logger) -> bool {
+    match std::env::current_exe().and_then(|x| Command::new(x).arg("--wait").args(args).status()) {
+        Ok(status) => status.success(),
+        Err(err) => {
+            log!((logger.error) Tui("While running `diary-cli {}`: {err:?}", args.join(" ")) as Warning);
+            false
+        },
+    }
+}
+
+/// Waits for enter, so what a command printed can be read before going back to the tui
+fn pause() {
+    print!("\nPress enter to go back to the tui...");
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stdin().lock().read_line(&mut String::new());
+}
+
+/// Pulls an item into a temporary folder, opens it in the editor and commits it back over the old one if it changed
+/// (while the tui is suspended)
+fn edit(item: &Item, logger: impl Logger) -> String {
+    let dir = std::env::temp_dir().join(format!("diary-cli-edit-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_string = dir.to_string_lossy().to_string();
+    let mut pull = vec!["pull", "-1", "-p", &dir_string, "-f", "config.toml", &item.uid];
+    if item.is_moc { pull.push("-m") }
+    if !run(&pull, logger.hollow()) {
+        pause();
+        return format!("Couldn't pull '{}'", item.uid);
+    }
+
+    let config = dir.join("config.toml");
+    let before = std::fs::read_to_string(&config).unwrap_or_default();
+    let opened = open_editor(&config);
+    let result = match std::fs::read_to_string(&config) {
+        _ if !opened => String::from("Couldn't open the editor; set `editor` in config.toml or `$EDITOR`"),
+        Ok(after) if after == before => format!("'{}' didn't change; nothing was committed", item.uid),
+        Ok(_) => {
+            let committed = run(&["commit", "--overwrite", &config.to_string_lossy()], logger.hollow());
+            pause();
+            if committed { format!("Committed the changes to '{}'", item.uid) }
+            else { format!("Committing '{}' failed; the archive was left as it was", item.uid) }
+        },
+        Err(_) => format!("The config of '{}' went missing", item.uid),
+    };
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Opens a file in the editor: `editor` in config.toml, then `$EDITOR`, then nano
+fn open_editor(path: &Path) -> bool {
+    let editor = Config::get().editor.clone()
+        .or_else(|| std::env::var("EDITOR").ok().filter(|x| !x.trim().is_empty()))
+        .unwrap_or_else(|| String::from("nano"));
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else { return false };
+    Command::new(program).args(parts).arg(path).status().is_ok_and(|x| x.success())
+}
+
+/// Handles a key press; false once the tui should quit
+fn handle(key: KeyCode, app: &mut App, terminal: &mut DefaultTerminal, logger: impl Logger) -> bool {
+    app.status.clear();
+    match (app.input.take(), key) {
+        (Some(Input::Filter(mut x)), KeyCode::Char(c)) => { x.push(c); app.input = Some(Input::Filter(x)) },
+        (Some(Input::Export(mut x)), KeyCode::Char(c)) => { x.push(c); app.input = Some(Input::Export(x)) },
+        (Some(Input::Filter(mut x)), KeyCode::Backspace) => { x.pop(); app.input = Some(Input::Filter(x)) },
+        (Some(Input::Export(mut x)), KeyCode::Backspace) => { x.pop(); app.input = Some(Input::Export(x)) },
+        (Some(Input::Filter(x)), KeyCode::Enter) => {
+            app.filter = x.split_whitespace().map(String::from).collect();
+            app.refresh();
+        },
+        (Some(Input::Export(x)), KeyCode::Enter) if !x.trim().is_empty() || Config::get().default_dest.is_some() => {
+            let mut args = vec!["export"];
+            if !x.trim().is_empty() { args.push(x.trim()) }
+            // `-s` makes export match any of the tags, like the tui's filter does
+            if !app.filter.is_empty() { args.extend(["-s", "-t"]); args.extend(app.filter.iter().map(String::as_str)) }
+            suspend();
+            app.status = if run(&args, logger.hollow()) { String::from("Exported the archive") } else { String::from("Exporting failed") };
+            pause();
+            resume(terminal);
+        },
+        (Some(Input::Remove), KeyCode::Char('y')) => if let Some(item) = app.selected().cloned() {
+            let mut args = vec!["remove", item.uid.as_str()];
+            if item.is_moc { args.push("-m") }
+            suspend();
+            app.status = if run(&args, logger.hollow()) { format!("Moved '{}' to the trash", item.uid) } else { format!("Couldn't remove '{}'", item.uid) };
+            pause();
+            app.reload(logger);
+            resume(terminal);
+        },
+        (Some(_), _) => (), // anything else cancels the input
+        (None, KeyCode::Char('q') | KeyCode::Esc) => return false,
+        (None, KeyCode::Down | KeyCode::Char('j')) => app.select(1),
+        (None, KeyCode::Up | KeyCode::Char('k')) => app.select(-1),
+        (None, KeyCode::Home | KeyCode::Char('g')) => app.select(isize::MIN / 2),
+        (None, KeyCode::End | KeyCode::Char('G')) => app.select(isize::MAX / 2),
+        (None, KeyCode::PageDown) => app.scroll = app.scroll.saturating_add(10),
+        (None, KeyCode::PageUp) => app.scroll = app.scroll.saturating_sub(10),
+        (None, KeyCode::Char('/') | KeyCode::Char('f')) => app.input = Some(Input::Filter(app.filter.join(" "))),
+        (None, KeyCode::Char('s')) => { app.sort = app.sort.next(); app.refresh() },
+        (None, KeyCode::Tab) => { app.kinds = app.kinds.next(); app.refresh() },
+        (None, KeyCode::Char('x')) => app.input = Some(Input::Export(String::new())),
+        (None, KeyCode::Char('d')) if app.selected().is_some() => app.input = Some(Input::Remove),
+        (None, KeyCode::Char('e')) => if let Some(item) = app.selected().cloned() {
+            suspend();
+            app.status = edit(&item, logger.hollow());
+            app.reload(logger);
+            resume(terminal);
+        },
+        _ => (),
+    } true
+}
+
+/// Wraps the logger of the tui, giving the terminal back before a fatal error is shown or the process crashes, so it
+/// isn't left in raw mode on the alternate screen
+struct TuiLogger<L: Logger>(L);
+
+impl<L: Logger> Logger for TuiLogger<L> {
+    fn new() -> Self { Self(L::new()) }
+    fn hollow(&self) -> Self { Self(self.0.hollow()) }
+
+    fn crash<T>(&mut self) -> T {
+        ratatui::restore();
+        self.0.crash()
+    }
+
+    fn error(&mut self, log: Log) -> ErrorResponse {
+        if matches!(log.log_type, LogType::Fatal) { ratatui::restore() }
+        self.0.error(log)
+    }
+
+    fn vital(&mut self, log: Log) { self.0.vital(log) }
+    fn verbose(&mut self, log: Log) { self.0.verbose(log) }
+    fn ask(&self, origin: &str, prompt: &str) -> String { self.0.ask(origin, prompt) }
+}
+
+/// Browses the archive in a terminal ui: the entries and mocs on the left, the selected one on the right
+pub fn tui(mut logger: impl Logger) {
+    if json::enabled() || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        log!((logger.error) Tui("The tui needs a terminal (and can't be used with `--json`)") as Fatal);
+        return logger.crash();
+    }
+
+    let mut logger = TuiLogger(logger);
+    let mut app = App::new(logger.hollow());
+    // Panics are already handled by ratatui; crashes by the logger and interrupts here
+    let mut terminal = ratatui::init();
+    signal::on_interrupt(ratatui::restore);
+    loop {
+        if let Err(err) = terminal.draw(|frame| draw(frame, &mut app, logger.hollow())) {
+            log!((logger.error) Tui("While drawing the tui: {err:?}") as Fatal);
+            return logger.crash();
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => if !handle(key.code, &mut app, &mut terminal, logger.hollow()) { break },
+            Ok(_) => (),
+            Err(err) => {
+                log!((logger.error) Tui("While reading input: {err:?}") as Fatal);
+                return logger.crash();
+            },
+        }
+    }
+    ratatui::restore();
+}
@@ -0,0 +1,61 @@
+/// How many uids are suggested at most when one doesn't match anything
+const SUGGESTIONS: usize = 3;
+
+/// What a uid (or the start of one) given on the command line matches in the archive
+#[derive(Debug, PartialEq)]
+pub enum Match<'a> {
+    /// The uid itself, or the only uid that starts with it
+    Found(&'a str),
+    /// Every uid that starts with it, when there are more than one
+    Ambiguous(Vec<&'a str>),
+    /// Nothing; with the closest uids as suggestions
+    Missing(Vec<&'a str>),
+}
+
+/// Finds the uid a uid or uid prefix refers to out of the uids of the archive
+pub fn find<'a>(uid: &str, uids: &'a [String]) -> Match<'a> {
+    if let Some(x) = uids.iter().find(|x| *x == uid) { return Match::Found(x) }
+
+    let mut prefixed: Vec<&str> = uids.iter().filter(|x| x.starts_with(uid)).map(|x| x.as_str()).collect();
+    prefixed.sort();
+    match prefixed.len() {
+        0 => Match::Missing(suggest(uid, uids)),
+        1 => Match::Found(prefixed[0]),
+        _ => Match::Ambiguous(prefixed),
+    }
+}
+
+/// The uids close enough to a uid to be a typo of it, closest first
+pub fn suggest<'a>(uid: &str, uids: &'a [String]) -> Vec<&'a str> {
+    let max = (uid.chars().count() / 3).max(2);
+    let mut close: Vec<(usize, &str)> = uids.iter()
+        .map(|x| (edit_distance(uid, x), x.as_str()))
+        .filter(|(distance, _)| *distance <= max)
+        .collect();
+    close.sort();
+    close.into_iter().take(SUGGESTIONS).map(|(_, x)| x).collect()
+}
+
+/// The levenshtein distance between two strings: how many characters have to be inserted, removed or replaced to turn
+/// one into the other
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, y) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + (x != *y) as usize)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        } previous = current;
+    } previous[b.len()]
+}
+
+/// Formats uids as a list like `` `a`, `b` or `c` ``
+pub fn or_list(uids: &[&str]) -> String {
+    let quoted: Vec<String> = uids.iter().map(|x| format!("`{x}`")).collect();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+        _ => quoted.join(""),
+    }
+}
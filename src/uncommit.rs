@@ -1,11 +1,12 @@
 use std::fs;
 use lazy_db::*;
 use soulog::*;
-use crate::{archive::Archive, home_dir, list, sort};
+use crate::{archive::Archive, history, list, signal, sort, trash};
 
-pub fn uncommmit(uid: String, is_moc: bool, mut logger: impl Logger) {
+pub fn uncommmit(uid: String, is_moc: bool, purge: bool, mut logger: impl Logger) {
     let archive = Archive::load(logger.hollow());
-    
+    let uid = archive.resolve_uid(uid, is_moc, logger.hollow());
+
     let path = if is_moc {
         archive.database().path().join("mocs").join(&uid)
     } else {
@@ -14,7 +15,6 @@ pub fn uncommmit(uid: String, is_moc: bool, mut logger: impl Logger) {
     
     // Check if path exists
     if !path.is_dir() {
-        println!("{path:?}");
         if is_moc {
             log!((logger.error) Remove("moc of uid '{uid}' doesn't exist") as Fatal);
         } else {
@@ -22,38 +22,44 @@ pub fn uncommmit(uid: String, is_moc: bool, mut logger: impl Logger) {
         } return logger.crash();
     }
 
-    // Confirm with the user about the action
-    let expected = "mhm, yep, I do wanna remove this entry/moc permanently";
-    log!((logger.vital) Remove("To confirm with removing an entry/moc of uid '{uid}' PERMANENTLY enter the phrase below (without quotes):") as Log);
-    if_err!((logger) [Remove, err => ("Entered phrase incorrect, please retry")] retry {
-        log!((logger.vital) Remove("\"{expected}\"") as Log);
-        let input = logger.ask("Remove", "Enter the phrase");
-        if &input[0..input.len() - 1] != expected { Err(()) }
-        else { Ok(()) }
-    });
+    if purge {
+        // Confirm with the user about the action
+        let expected = "mhm, yep, I do wanna remove this entry/moc permanently";
+        log!((logger.vital) Remove("To confirm with removing an entry/moc of uid '{uid}' PERMANENTLY enter the phrase below (without quotes):") as Log);
+        if_err!((logger) [Remove, err => ("Entered phrase incorrect, please retry")] retry {
+            log!((logger.vital) Remove("\"{expected}\"") as Log);
+            let input = logger.ask("Remove", "Enter the phrase");
+            if &input[0..input.len() - 1] != expected { Err(()) }
+            else { Ok(()) }
+        });
+    }
     
     // Backup archive before modification
     log!((logger) Remove("Backing up archive before removal, if you want to revert back, run `diary-cli rollback -f`"));
-    let _ = std::fs::remove_file(home_dir().join("backup.ldb")); // Clean up
-    Archive::backup(home_dir().join("backup.ldb"), logger.hollow());
+    Archive::backup_before_modification(logger.hollow());
 
     log!((logger) Remove("Removing entry/moc of uid '{uid}'..."));
 
     // Remove the entry/moc
     sort::sort(logger.hollow());
+    let _critical = signal::critical();
+    if !purge { trash::store(&path, &uid, is_moc, logger.hollow()) }
     if_err!((logger) [Remove, err => ("While removing entry/moc from archive: {err:?}")] retry fs::remove_dir_all(&path));
 
     // Update order lists
-    if is_moc { return; }
-
-    let sorted_container = if_err!((logger) [Remove, err => ("While loading sorted list: {err:?}")] retry search_database!((archive.database()) /order/sorted));
-    let sorted: Box<[String]> = sort::read_sorted(&archive, logger.hollow()).into_vec().into_iter().filter(|x| *x != uid).collect();
+    if !is_moc {
+        let sorted_container = if_err!((logger) [Remove, err => ("While loading sorted list: {err:?}")] retry search_database!((archive.database()) /order/sorted));
+        let sorted: Box<[String]> = sort::read_sorted(&archive, logger.hollow()).into_vec().into_iter().filter(|x| *x != uid).collect();
 
-    list::write(&sorted, |f, x| LazyData::new_string(f, x), &sorted_container, logger.hollow());
+        list::write(&sorted, |f, x| LazyData::new_string(f, x), &sorted_container, logger.hollow());
+    }
 
-    // Update itver
-    log!((logger) Commit("Updating archive itver..."));
-    if_err!((logger) [Commit, err => ("While update archive itver: {err:?}")] retry write_database!((archive.database()) itver = new_u16(archive.itver + 1)));
+    archive.bump_itver(logger.hollow());
+    history::record(&archive, if purge { "purge" } else { "remove" }, Some(&uid), logger.hollow());
 
-    log!((logger.vital) Remove("Successfully removed entry/moc of uid '{uid}'") as Log)
-}
\ No newline at end of file
+    if purge {
+        log!((logger.vital) Remove("Successfully removed entry/moc of uid '{uid}' permanently") as Log)
+    } else {
+        log!((logger.vital) Remove("Successfully moved entry/moc of uid '{uid}' to the trash; run `diary-cli trash restore` to bring it back") as Log)
+    }
+}
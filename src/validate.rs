@@ -0,0 +1,432 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use toml::{Spanned, Table, Value};
+use soulog::*;
+use crate::{archive::Archive, batch, entry::resolve_path, links};
+
+/// A problem with an attribute of a config, at its key path (like `section[1].title`)
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+    /// The line and column of the attribute in the config file, when it was read from one
+    pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() { write!(f, "{}", self.message) }
+        else { write!(f, "{}: {}", self.key, self.message) }
+    }
+}
+
+/// Everything wrong with a config; it can only be committed without errors, while warnings are just worth knowing
+#[derive(Default)]
+pub struct Report {
+    /// The uid of the entry or moc, if it has one
+    pub uid: Option<String>,
+    pub is_moc: bool,
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn error(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(Problem { key: key.into(), message: message.into(), location: None });
+    }
+
+    fn warning(&mut self, key: impl Into<String>, message: impl Into<String>) {
+        self.warnings.push(Problem { key: key.into(), message: message.into(), location: None });
+    }
+
+    /// Finds where in the text of the config each of the problems is
+    pub fn locate(&mut self, text: &str) {
+        for problem in self.errors.iter_mut().chain(self.warnings.iter_mut()) {
+            problem.location = locate(text, &problem.key);
+        }
+    }
+
+    /// A required string attribute
+    fn string<'a>(&mut self, table: &'a Table, key: &str, at: &str) -> Option<&'a str> {
+        match table.get(key) {
+            Some(Value::String(x)) => Some(x),
+            Some(_) => { self.error(path(at, key), "must be a string"); None },
+            None => { self.error(path(at, key), "is missing"); None },
+        }
+    }
+
+    /// An optional (or with `required`, required) list of strings attribute
+    fn strings(&mut self, table: &Table, key: &str, at: &str, required: bool) -> Vec<String> {
+        let array = match table.get(key) {
+            Some(Value::Array(x)) => x,
+            Some(_) => { self.error(path(at, key), "must be a list of strings"); return Vec::new() },
+            None if required => { self.error(path(at, key), "is missing"); return Vec::new() },
+            None => return Vec::new(),
+        };
+        let mut result = Vec::with_capacity(array.len());
+        for (i, x) in array.iter().enumerate() {
+            match x.as_str() {
+                Some(x) => result.push(x.to_string()),
+                None => self.error(format!("{}[{i}]", path(at, key)), "must be a string"),
+            }
+        } result
+    }
+
+    /// A date attribute (a toml date, or a string of one with `strings`), returning it if it is valid
+    fn date(&mut self, table: &Table, key: &str, at: &str, strings: bool) -> Option<toml::value::Date> {
+        let date = match table.get(key) {
+            Some(Value::Datetime(x)) => x.date,
+            Some(Value::String(x)) if strings => x.parse::<toml::value::Datetime>().ok().and_then(|x| x.date),
+            Some(_) => None,
+            None => return None,
+        };
+        if date.is_none() { self.error(path(at, key), "must be a date like 2023-08-21") }
+        date
+    }
+
+    /// A list of tables attribute
+    fn tables<'a>(&mut self, table: &'a Table, key: &str, at: &str) -> Vec<(String, &'a Table)> {
+        let key = path(at, key);
+        let header = key.split('.').map(|x| x.split('[').next().unwrap_or(x)).collect::<Vec<_>>().join(".");
+        let array = match table.get(key.rsplit('.').next().unwrap_or(&key)) {
+            Some(Value::Array(x)) => x,
+            Some(_) => { self.error(&key, format!("must be a list of tables (`[[{header}]]`)")); return Vec::new() },
+            None => { self.error(&key, format!("is missing (add a `[[{header}]]`)")); return Vec::new() },
+        };
+        let mut result = Vec::with_capacity(array.len());
+        for (i, x) in array.iter().enumerate() {
+            let at = format!("{key}[{i}]");
+            match x.as_table() {
+                Some(x) => result.push((at, x)),
+                None => self.error(at, "must be a table"),
+            }
+        } result
+    }
+}
+
+fn path(at: &str, key: &str) -> String {
+    if at.is_empty() { key.to_string() } else { format!("{at}.{key}") }
+}
+
+/// Validates a parsed entry or moc config (written at `config`) fully without writing anything,
+/// checking its uid against the archive when there is one
+pub fn validate(table: &Table, config: &Path, archive: Option<&Archive>) -> Report {
+    let mut report = Report::default();
+    report.is_moc = match table.get("is-moc") {
+        Some(Value::Boolean(x)) => *x,
+        Some(_) => { report.error("is-moc", "must be a boolean"); false },
+        None => false,
+    };
+    let kind = if report.is_moc { "moc" } else { "entry" };
+
+    // Metadata
+    let mut links = Vec::new();
+    match table.get(kind) {
+        Some(Value::Table(meta)) => {
+            report.uid = report.string(meta, "uid", kind).map(String::from);
+            report.string(meta, "title", kind);
+            report.string(meta, "description", kind);
+            report.strings(meta, "notes", kind, false);
+            report.strings(meta, "tags", kind, true);
+            if !report.is_moc {
+                links = report.strings(meta, "links", kind, false);
+                match meta.get("date") {
+                    Some(_) => { report.date(meta, "date", kind, false); },
+                    None => report.error(path(kind, "date"), "is missing"),
+                }
+                report.date(meta, "unlock_date", kind, true);
+                if meta.get("template").is_some_and(|x| !x.is_str()) { report.error(path(kind, "template"), "must be the name of a template (a string)") }
+            }
+        },
+        Some(_) => report.error(kind, format!("must be a table (`[{kind}]`)")),
+        None => report.error(kind, format!("is missing (add an `[{kind}]` table)")),
+    }
+
+    if report.is_moc { validate_collections(table, "", &mut report) }
+    else { validate_sections(table, config, archive, &mut report) }
+
+    // Links to items that don't exist (yet)
+    if let Some(archive) = archive {
+        let uid = report.uid.clone();
+        for (i, link) in links.iter().enumerate().filter(|(_, x)| Some(*x) != uid.as_ref()) {
+            if !archive.database_exists(format!("entries/{link}")) && !archive.database_exists(format!("mocs/{link}")) {
+                report.warning(format!("entry.links[{i}]"), format!("links to '{link}', but there's no entry or moc of uid '{link}' in the archive"));
+            }
+        }
+    }
+
+    // Re-committing a uid only replaces what is in the archive with `--overwrite`
+    if let (Some(archive), Some(uid)) = (archive, &report.uid) {
+        let kind = if report.is_moc { "mocs" } else { "entries" };
+        let key = path(if report.is_moc { "moc" } else { "entry" }, "uid");
+        if archive.database_exists(format!("{kind}/{uid}")) {
+            match unsafe { crate::cli::OVERWRITE } {
+                true => report.warning(key, format!("'{uid}' is already in the archive; committing will replace it")),
+                false => report.error(key, format!("'{uid}' is already in the archive; commit with `--overwrite` to replace it")),
+            }
+        }
+    }
+    report
+}
+
+fn validate_sections(table: &Table, config: &Path, archive: Option<&Archive>, report: &mut Report) {
+    let mut names = Vec::new();
+    let mut references = Vec::new();
+    for (at, section) in report.tables(table, "section", "") {
+        report.string(section, "title", &at);
+        report.strings(section, "notes", &at, false);
+
+        // Contents
+        let source = match section.get("path") {
+            Some(Value::String(x)) => {
+                let resolved = resolve_path(config, Path::new(x));
+                if !resolved.is_file() { report.error(path(&at, "path"), format!("'{x}' isn't a file (relative paths are relative to the entry's config file or `--base-dir`)")) }
+                std::fs::read_to_string(&resolved).ok()
+            },
+            Some(_) => { report.error(path(&at, "path"), "must be a string"); None },
+            None => None,
+        };
+        if section.contains_key("contents") && section.contains_key("content") {
+            report.error(&at, "can only have one of 'contents' or 'content'");
+        }
+        let key = if section.contains_key("contents") { "contents" } else { "content" };
+        let inline = match section.get(key) {
+            Some(Value::String(x)) => Some(x.clone()),
+            Some(_) => { report.error(path(&at, key), "must be a string"); None },
+            None => None,
+        };
+        match (section.contains_key("path"), section.contains_key(key)) {
+            (false, false) => report.error(&at, "must have its contents inline ('contents') or in a file ('path')"),
+            (true, true) => report.warning(&at, "has both a 'path' and inline contents; the path is used"),
+            _ => (),
+        }
+        for reference in source.or(inline).map(|x| links::cross_references(&x)).unwrap_or_default() {
+            if !references.contains(&reference) { references.push(reference) }
+        }
+
+        // Attachments
+        for (i, attachment) in report.strings(section, "attachments", &at, false).iter().enumerate() {
+            let resolved = resolve_path(config, Path::new(attachment));
+            let key = format!("{at}.attachments[{i}]");
+            if !resolved.is_file() { report.error(&key, format!("'{attachment}' isn't a file (relative paths are relative to the entry's config file or `--base-dir`)")) }
+            let name = Path::new(attachment).file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+            if names.contains(&name) { report.error(key, format!("there's already an attachment named '{name}' in the entry")) }
+            else { names.push(name) }
+        }
+    }
+
+    // Cross-references
+    let Some(archive) = archive else { return };
+    let uid = report.uid.clone();
+    for reference in references.iter().filter(|x| Some(*x) != uid.as_ref()) {
+        if !archive.database_exists(format!("entries/{reference}")) && !archive.database_exists(format!("mocs/{reference}")) {
+            report.warning("section", format!("cross-references '{{{{uid:{reference}}}}}', but there's no entry or moc of uid '{reference}' in the archive"));
+        }
+    }
+}
+
+/// Validates the collections of a moc, or the sub-collections of a collection `at` some path
+fn validate_collections(table: &Table, at: &str, report: &mut Report) {
+    for (at, collection) in report.tables(table, "collection", at) {
+        let nested = collection.contains_key("collection");
+        report.string(collection, "title", &at);
+        report.strings(collection, "notes", &at, false);
+        report.strings(collection, "include", &at, !nested); // collections can just group sub-collections
+        report.strings(collection, "exclude", &at, false);
+        report.strings(collection, "order", &at, false);
+        let from = report.date(collection, "from", &at, true);
+        let to = report.date(collection, "to", &at, true);
+        if let (Some(from), Some(to)) = (from, to) {
+            if (from.year, from.month, from.day) > (to.year, to.month, to.day) { report.error(path(&at, "from"), "must not be after 'to'") }
+        }
+        for key in ["paginate", "limit"] {
+            match collection.get(key) {
+                Some(Value::Integer(x)) if (1..=u16::MAX as i64).contains(x) => (),
+                Some(Value::Integer(_)) => report.error(path(&at, key), format!("must be between 1 and {}", u16::MAX)),
+                Some(_) => report.error(path(&at, key), "must be an integer"),
+                None => (),
+            }
+        }
+        if nested { validate_collections(collection, &at, report) }
+    }
+}
+
+/// Reads and validates a config file, reporting a file that is missing or isn't valid toml as its only error
+pub fn validate_file(file: &Path, archive: Option<&Archive>) -> Report {
+    let mut report = Report::default();
+    let text = match std::fs::read_to_string(file) {
+        Ok(x) => x,
+        Err(err) => { report.error("", format!("couldn't be read: {err}")); return report },
+    };
+    match text.parse::<Table>() {
+        Ok(table) => {
+            let mut report = validate(&table, file, archive);
+            report.locate(&text);
+            report
+        },
+        Err(err) => {
+            report.error("", format!("isn't valid toml: {}", err.message()));
+            report.errors[0].location = err.span().map(|x| position(&text, x.start));
+            report
+        },
+    }
+}
+
+/// Where a problem is in a config (`entry.toml:3:9`), so editors can jump to it
+fn at(name: &str, problem: &Problem) -> String {
+    match problem.location {
+        Some((line, column)) => format!("{name}:{line}:{column}"),
+        None => name.to_string(),
+    }
+}
+
+/// Logs the problems of a config's report, returning if it is valid
+pub fn log_report(name: &str, report: &Report, mut logger: impl Logger) -> bool {
+    report.warnings.iter().for_each(|x| log!((logger.vital) Validate("{}: {x}", at(name, x)) as Warning));
+    report.errors.iter().for_each(|x| log!((logger.vital) Validate("{}: {x}", at(name, x)) as Inconvenience));
+    if report.is_valid() { log!((logger.vital) Validate("'{name}' is valid") as Log) }
+    report.is_valid()
+}
+
+/// Validates a config before it is committed, logging every error in it (located in its file if it is a toml one)
+/// before crashing instead of only the first one parsing it would crash on
+pub fn check_commit(table: &Table, config: &Path, archive: &Archive, mut logger: impl Logger) {
+    let mut report = validate(table, config, Some(archive));
+    if report.is_valid() { return }
+
+    if config.extension().is_some_and(|x| x == "toml") {
+        if let Ok(text) = std::fs::read_to_string(config) { report.locate(&text) }
+    }
+    let name = config.to_string_lossy();
+    report.errors.iter().for_each(|x| log!((logger.vital) Commit("{}: {x}", at(&name, x)) as Inconvenience));
+    log!((logger.error) Commit("Config '{name}' has {} problems (listed above), so it wasn't committed", report.errors.len()) as Fatal);
+    logger.crash()
+}
+
+/// Validates a markdown entry (from a file or stdin) without committing it
+pub fn validate_markdown(file: Option<&Path>, mut logger: impl Logger) {
+    let (table, source) = Archive::read_markdown(file, logger.hollow());
+    let archive = crate::home_dir().join("archive").is_dir().then(|| Archive::load(logger.hollow()));
+    let report = validate(&table, &source, archive.as_ref());
+    if log_report(&source.to_string_lossy(), &report, logger.hollow()) { return }
+    log!((logger.error) Validate("Markdown entry is invalid; nothing was committed") as Fatal);
+    logger.crash()
+}
+
+/// Validates entry and moc configs (and directories of them) without committing anything, reporting every problem
+/// of each at once; fails if any of them couldn't be committed
+pub fn validate_paths(paths: Vec<String>, mut logger: impl Logger) {
+    let files: Vec<PathBuf> = batch::discover(&paths);
+    if files.is_empty() {
+        log!((logger.vital) Validate("No configs found to validate in {paths:?}") as Inconvenience);
+        return;
+    }
+    let archive = crate::home_dir().join("archive").is_dir().then(|| Archive::load(logger.hollow()));
+
+    let mut invalid = 0;
+    let mut uids: Vec<(String, &Path)> = Vec::new();
+    for file in files.iter() {
+        log!((logger) Validate("Validating '{}'...", file.to_string_lossy()));
+        let mut report = validate_file(file, archive.as_ref());
+
+        // The same uid twice in one run would have the later config replace the earlier
+        if let Some(uid) = &report.uid {
+            let kind = if report.is_moc { "moc" } else { "entry" };
+            match uids.iter().find(|(x, _)| x == uid) {
+                Some((_, other)) => report.error(path(kind, "uid"), format!("'{uid}' is also the uid of '{}'", other.to_string_lossy())),
+                None => uids.push((uid.clone(), file)),
+            }
+        }
+
+        if !log_report(&file.to_string_lossy(), &report, logger.hollow()) { invalid += 1 }
+    }
+
+    if invalid > 0 {
+        log!((logger.error) Validate("{invalid} of {} configs are invalid; nothing was committed", files.len()) as Fatal);
+        return logger.crash();
+    }
+    log!((logger.vital) Validate("All {} configs are valid; nothing was committed", files.len()) as Log);
+}
+
+/// A toml value with the spans of its values, to find where in a config a key path is
+enum Node {
+    Table(Vec<(String, Spanned<Node>)>),
+    Array(Vec<Spanned<Node>>),
+    Value,
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a toml value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Node, E> { Ok(Node::Value) }
+    fn visit_i64<E>(self, _: i64) -> Result<Node, E> { Ok(Node::Value) }
+    fn visit_u64<E>(self, _: u64) -> Result<Node, E> { Ok(Node::Value) }
+    fn visit_f64<E>(self, _: f64) -> Result<Node, E> { Ok(Node::Value) }
+    fn visit_str<E>(self, _: &str) -> Result<Node, E> { Ok(Node::Value) }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut result = Vec::new();
+        while let Some(x) = seq.next_element()? { result.push(x) }
+        Ok(Node::Array(result))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut result = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            // Datetimes are deserialized as a map of a private key that can't be spanned
+            if key.starts_with("$__toml_private") {
+                map.next_value::<IgnoredAny>()?;
+                return Ok(Node::Value);
+            } result.push((key, map.next_value()?));
+        } Ok(Node::Table(result))
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+/// Finds the line and column (from 1) in a config of the value at a key path (like `section[1].path`);
+/// when it is missing, where the closest of its parents is instead
+pub fn locate(text: &str, key: &str) -> Option<(usize, usize)> {
+    let mut node = &toml::from_str::<Node>(text).ok()?;
+    let mut span = None;
+    for segment in key.split('.').filter(|x| !x.is_empty()) {
+        let (name, indices) = segment.split_once('[').map(|(x, rest)| (x, Some(rest))).unwrap_or((segment, None));
+        let mut next = match node {
+            Node::Table(x) => x.iter().find(|(key, _)| key == name).map(|(_, x)| x),
+            _ => None,
+        };
+        for index in indices.into_iter().flat_map(|x| x.split('[')).filter_map(|x| x.trim_end_matches(']').parse::<usize>().ok()) {
+            next = match next.map(|x| x.get_ref()) {
+                Some(Node::Array(x)) => x.get(index),
+                _ => None,
+            };
+        }
+        let Some(next) = next else { break };
+        span = Some(next.span());
+        node = next.get_ref();
+    }
+
+    Some(position(text, span?.start))
+}
+
+/// The line and column (from 1) of a byte offset in text
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
@@ -0,0 +1,96 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use serde_json::json;
+use soulog::*;
+use crate::{history::Event, unwrap_opt};
+
+/// How long a webhook gets to accept and answer an event before it is given up on
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The webhooks of the config (`[webhooks]`) that are sent the archive's mutations
+#[derive(Default)]
+pub struct Webhooks {
+    /// The `http://` urls events are posted to
+    pub urls: Vec<String>,
+    /// Only these operations (like `commit`, `remove` or `sort`) are posted; all of them if empty
+    pub events: Vec<String>,
+}
+
+impl Webhooks {
+    /// Parses the `[webhooks]` table of the config
+    pub fn parse(table: &toml::Table, mut logger: impl Logger) -> Self {
+        let mut list = |key: &str| table.get(key)
+            .map(|x| unwrap_opt!((x.as_array()) with logger, format: Config("Config's webhooks '{key}' attribute must be a list of strings")))
+            .map(|x| x.iter().map(|x| unwrap_opt!((x.as_str()) with logger, format: Config("Config's webhooks '{key}' must only contain strings")).to_string()).collect())
+            .unwrap_or_default();
+        Self { urls: list("urls"), events: list("events") }
+    }
+}
+
+/// The json body an event is posted as
+pub fn payload(event: &Event) -> String {
+    json!({
+        "event": event.operation,
+        "uid": event.uid,
+        "itver": event.itver,
+        "time": event.time,
+        "signer": event.signer,
+    }).to_string()
+}
+
+/// Splits an `http://host[:port][/path]` url into its host, port and path
+pub fn parse_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(x) => (&rest[..x], &rest[x..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() { return None }
+    Some((host.to_string(), port, path.to_string()))
+}
+
+/// Posts a json body to an `http://` url, returning the status code it answered with
+pub fn post(url: &str, body: &str) -> Result<u16, String> {
+    let (host, port, path) = parse_url(url).ok_or_else(|| String::from("only http:// urls are supported"))?;
+    let address = (host.as_str(), port).to_socket_addrs().map_err(|x| x.to_string())?
+        .next().ok_or_else(|| format!("couldn't resolve '{host}'"))?;
+
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|x| x.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|x| x.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|x| x.to_string())?;
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: diary-cli/{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        env!("CARGO_PKG_VERSION"),
+        body.len(),
+    );
+    stream.write_all(request.as_bytes()).map_err(|x| x.to_string())?;
+
+    // Only the status line matters
+    let mut response = Vec::new();
+    let _ = stream.take(1024).read_to_end(&mut response);
+    let response = String::from_utf8_lossy(&response);
+    response.split_whitespace().nth(1)
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(|| String::from("it didn't answer with an http response"))
+}
+
+/// Posts a mutation of the archive to the configured webhooks; failures are only warned about
+pub fn notify(event: &Event, mut logger: impl Logger) {
+    let webhooks = &crate::config::Config::get().webhooks;
+    if !webhooks.events.is_empty() && !webhooks.events.contains(&event.operation) { return }
+
+    let body = payload(event);
+    for url in webhooks.urls.iter() {
+        log!((logger) Webhook("Posting '{}' event to webhook '{url}'...", event.operation));
+        match post(url, &body) {
+            Ok(status) if (200..300).contains(&status) => (),
+            Ok(status) => log!((logger.vital) Webhook("Webhook '{url}' answered the '{}' event with status {status}", event.operation) as Warning),
+            Err(err) => log!((logger.vital) Webhook("Couldn't post the '{}' event to webhook '{url}': {err}", event.operation) as Warning),
+        }
+    }
+}
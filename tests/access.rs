@@ -0,0 +1,23 @@
+use diary_cli::access::{Access, AccessRules};
+use soulog::*;
+
+#[test]
+fn access_rules() {
+    let table: toml::Table = r#"
+public = ["blog", "travel/*"]
+authenticated = ["friends"]
+private = ["blog/drafts"]
+"#.parse().unwrap();
+    let rules = AccessRules::parse(&table, sbl::PanicLogger::new());
+    let tags = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(rules.access(&tags(&["blog/rust"])), Access::Public);
+    assert_eq!(rules.access(&tags(&["travel/japan/tokyo"])), Access::Public);
+    assert_eq!(rules.access(&tags(&["blog", "friends"])), Access::Authenticated);
+    assert_eq!(rules.access(&tags(&["blog/drafts/wip"])), Access::Private); // the most restrictive rule wins
+    assert_eq!(rules.access(&tags(&["health"])), Access::Private); // untagged items stay private
+    assert!(Access::Public < Access::Authenticated && Access::Authenticated < Access::Private);
+
+    let open = AccessRules::parse(&"default = 'public'".parse().unwrap(), sbl::PanicLogger::new());
+    assert_eq!(open.access(&tags(&["health"])), Access::Public);
+}
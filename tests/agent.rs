@@ -0,0 +1,28 @@
+mod isol;
+use isol::*;
+use std::time::Duration;
+use soulog::*;
+use diary_cli::agent::{list_backups, AgentConfig};
+
+#[test]
+fn parse_agent_config() {
+    let table: toml::Table = toml::from_str("backup_every = 24\nwatch = \"/tmp/inbox\"").unwrap();
+    let config = AgentConfig::parse(&table, sbl::PanicLogger::new());
+    assert_eq!(config.backup_every, Some(Duration::from_secs(24 * 60 * 60)));
+    assert_eq!(config.watch.as_deref(), Some(std::path::Path::new("/tmp/inbox")));
+    assert_eq!(config.keep_backups, 7);
+    assert_eq!(config.interval, Duration::from_secs(60));
+    assert!(AgentConfig::parse(&toml::Table::new(), sbl::PanicLogger::new()).backup_every.is_none());
+}
+
+#[test]
+fn list_scheduled_backups() {
+    let env = new_env();
+    let dir = env.get_path().join("backups");
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["backup-2024-01-02_000000.tar.zst", "backup.ldb", "backup-2023-12-31_235959.tar.zst", "notes.txt"] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    let names: Vec<String> = list_backups(&dir).iter().map(|x| x.file_name().unwrap().to_string_lossy().to_string()).collect();
+    assert_eq!(names, ["backup-2023-12-31_235959.tar.zst", "backup-2024-01-02_000000.tar.zst"]);
+}
@@ -0,0 +1,27 @@
+use std::collections::HashMap;
+use diary_cli::alias::{expand, split_args};
+
+fn args(x: &[&str]) -> Vec<String> {
+    x.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn split_quoted_args() {
+    assert_eq!(split_args("export 'my vault' -t  public \"\""), args(&["export", "my vault", "-t", "public", ""]));
+}
+
+#[test]
+fn expand_aliases() {
+    let aliases = HashMap::from([
+        (String::from("exp"), String::from("export out -t public")),
+        (String::from("pub"), String::from("exp --strict")),
+        (String::from("list"), String::from("sort")),
+        (String::from("loop"), String::from("loop")),
+    ]);
+    let builtins = args(&["export", "list"]);
+
+    assert_eq!(expand(args(&["diary-cli", "--json", "exp", "-s"]), &aliases, &builtins), args(&["diary-cli", "--json", "export", "out", "-t", "public", "-s"]));
+    assert_eq!(expand(args(&["diary-cli", "pub"]), &aliases, &builtins), args(&["diary-cli", "export", "out", "-t", "public", "--strict"]));
+    assert_eq!(expand(args(&["diary-cli", "list"]), &aliases, &builtins), args(&["diary-cli", "list"]));
+    assert_eq!(expand(args(&["diary-cli", "loop"]), &aliases, &builtins), args(&["diary-cli", "loop"]));
+}
@@ -0,0 +1,19 @@
+mod isol;
+
+use diary_cli::archived::{is_archived, set_archived};
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+
+#[test]
+fn archived_flag() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("entry")).unwrap();
+
+    assert!(!is_archived(&container, logger.hollow()));
+    set_archived(&container, true, logger.hollow());
+    assert!(is_archived(&container, logger.hollow()));
+    set_archived(&container, false, logger.hollow());
+    assert!(!is_archived(&container, logger));
+}
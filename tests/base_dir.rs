@@ -0,0 +1,12 @@
+use std::path::{Path, PathBuf};
+use diary_cli::{cli::BASE_DIR, entry::resolve_path};
+
+#[test]
+fn base_dir_override() {
+    let config = Path::new("someday/config.toml");
+    assert_eq!(resolve_path(config, Path::new("section.txt")), PathBuf::from("someday/section.txt"));
+
+    BASE_DIR.set(PathBuf::from("notes")).unwrap();
+    assert_eq!(resolve_path(config, Path::new("section.txt")), PathBuf::from("notes/section.txt"));
+    assert_eq!(resolve_path(config, Path::new("/abs/section.txt")), PathBuf::from("/abs/section.txt"));
+}
@@ -0,0 +1,20 @@
+mod isol;
+use isol::*;
+use diary_cli::batch::discover;
+
+#[test]
+fn discover_configs() {
+    let env = new_env();
+    let root = env.get_path();
+    std::fs::create_dir_all(root.join("entries/2023/.drafts")).unwrap();
+    for file in ["entries/b.toml", "entries/a.toml", "entries/notes.md", "entries/2023/c.toml", "entries/2023/.drafts/d.toml", "e.toml"] {
+        std::fs::write(root.join(file), "").unwrap();
+    }
+
+    let paths = [root.join("e.toml"), root.join("entries"), root.join("missing.toml")]
+        .map(|x| x.to_string_lossy().to_string());
+    let found = discover(&paths);
+    let expected = ["e.toml", "entries/2023/c.toml", "entries/a.toml", "entries/b.toml", "missing.toml"]
+        .map(|x| root.join(x));
+    assert_eq!(found, expected);
+}
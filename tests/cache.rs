@@ -0,0 +1,29 @@
+use diary_cli::cache::Lru;
+
+#[test]
+fn least_recently_used_evicted() {
+    let mut cache = Lru::new(2);
+    cache.insert("a", 1);
+    cache.insert("b", 2);
+    assert_eq!(cache.get(&"a"), Some(1));
+
+    // `b` hasn't been used since `a` was read, so it makes room for `c`
+    cache.insert("c", 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&"b"), None);
+    assert_eq!(cache.get(&"a"), Some(1));
+    assert_eq!(cache.get(&"c"), Some(3));
+
+    assert_eq!(cache.remove(&"a"), Some(1));
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn existing_value_kept() {
+    let mut cache = Lru::new(0);
+    assert_eq!(cache.capacity(), 1);
+    assert_eq!(cache.insert("a", 1), 1);
+    assert_eq!(cache.insert("a", 2), 1);
+    assert_eq!(cache.get(&"a"), Some(1));
+}
@@ -0,0 +1,16 @@
+use diary_cli::calendar::{day_counts, level};
+
+#[test]
+fn heatmap_levels() {
+    assert_eq!(level(0, 10), 0);
+    assert_eq!(level(1, 10), 1);
+    assert_eq!(level(10, 10), 4);
+    assert_eq!(level(3, 3), 3);
+}
+
+#[test]
+fn counts_only_the_year() {
+    let counts = day_counts(&[[1, 1, 2024], [1, 1, 2024], [2, 1, 2024], [1, 1, 2023]], 2024);
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts.values().sum::<usize>(), 3);
+}
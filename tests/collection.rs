@@ -0,0 +1,143 @@
+mod isol;
+
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+use diary_cli::moc::{walk_collections, MOC};
+use toml::Table;
+
+#[test]
+fn nested_collections() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("mocs")).unwrap();
+    let toml = "
+        is-moc = true
+        [moc]
+        uid = 'nested'
+        title = 'Nested'
+        description = ''
+        tags = []
+
+        [[collection]]
+        title = 'Work'
+
+        [[collection.collection]]
+        title = 'Meetings'
+        include = [ 'work/meetings' ]
+        from = 2023-01-01
+        to = '2023-12-31'
+
+        [[collection.collection]]
+        title = 'Projects'
+        include = [ 'work/projects' ]
+        notes = [ 'the big ones' ]
+
+        [[collection.collection.collection]]
+        title = 'Ideas'
+        include = [ 'work/projects', 'ideas' ]
+
+        [[collection]]
+        title = 'Everything'
+        include = []
+        exclude = [ 'private', 'draft' ]
+        limit = 2
+    ".parse::<Table>().unwrap();
+
+    let mut moc = MOC::new(toml.clone(), "nested.toml", container, logger.hollow());
+    let mut titles = Vec::new();
+    walk_collections(moc.collections(logger.hollow()), &mut |collection, at| {
+        let groups_only = collection.groups_only(logger.hollow());
+        titles.push((collection.title(logger.hollow()).clone(), at.to_vec(), groups_only));
+    }, logger.hollow());
+    assert_eq!(titles, [
+        ("Work".to_string(), vec![0], true),
+        ("Meetings".to_string(), vec![0, 0], false),
+        ("Projects".to_string(), vec![0, 1], false),
+        ("Ideas".to_string(), vec![0, 1, 0], false),
+        ("Everything".to_string(), vec![1], false),
+    ]);
+
+    // Excluded tags are searched for as negated ones and the limit keeps the newest entries
+    let everything = &mut moc.collections(logger.hollow())[1];
+    assert_eq!(everything.query(logger.hollow()), ["!private", "!draft"]);
+    assert_eq!(everything.newest(&[1, 2, 3], logger.hollow()), [2, 3]);
+    assert_eq!(moc.collections(logger.hollow())[0].newest(&[1, 2, 3], logger.hollow()), [1, 2, 3]);
+
+    // Dated collections only include entries within their range (both ends included)
+    let meetings = &mut moc.collections(logger.hollow())[0].collections(logger.hollow())[0];
+    assert!(meetings.is_dated(logger.hollow()));
+    assert!(meetings.in_range(&[1, 1, 2023], logger.hollow()));
+    assert!(meetings.in_range(&[31, 12, 2023], logger.hollow()));
+    assert!(!meetings.in_range(&[31, 12, 2022], logger.hollow()));
+    assert!(!meetings.in_range(&[1, 1, 2024], logger.hollow()));
+    assert!(moc.collections(logger.hollow())[1].in_range(&[1, 1, 1970], logger.hollow()));
+
+    // Pulling it back gives the same tree of collections
+    moc.clear_cache();
+    let pulled = moc.pull(logger.hollow());
+    let collections = pulled["collection"].as_array().unwrap();
+    let projects = &collections[0]["collection"][1];
+    assert_eq!(projects["notes"].as_array().unwrap().len(), 1);
+    assert_eq!(projects["collection"][0]["title"].as_str(), Some("Ideas"));
+    assert!(collections[1].get("collection").is_none());
+    assert_eq!(collections[1]["limit"].as_integer(), Some(2));
+    assert!(collections[0].get("exclude").is_none());
+    let meetings = &collections[0]["collection"][0];
+    assert_eq!(meetings["from"].as_datetime().map(|x| x.to_string()), Some("2023-01-01".to_string()));
+    assert_eq!(meetings["to"].as_datetime().map(|x| x.to_string()), Some("2023-12-31".to_string()));
+    assert!(collections[1].get("from").is_none());
+}
+
+#[test]
+fn collection_order() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("mocs")).unwrap();
+    let toml = "
+        is-moc = true
+        [moc]
+        uid = 'ordered'
+        title = 'Ordered'
+        description = ''
+        tags = []
+
+        [[collection]]
+        title = 'Trips'
+        include = [ 'travel' ]
+        order = [ 'japan', 'peru' ]
+
+        [[collection.collection]]
+        title = 'Walks'
+        include = [ 'walking' ]
+        order = [ 'coast' ]
+    ".parse::<Table>().unwrap();
+
+    // Ordered items come first, in order, and the rest keep theirs
+    let mut moc = MOC::new(toml, "ordered.toml", container, logger.hollow());
+    let trips = &mut moc.collections(logger.hollow())[0];
+    let uids: Vec<String> = ["iceland", "peru", "norway", "japan"].iter().map(|x| x.to_string()).collect();
+    assert_eq!(trips.arrange(uids, |x| x, logger.hollow()), ["japan", "peru", "iceland", "norway"]);
+
+    moc.clear_cache();
+    let pulled = moc.pull(logger.hollow());
+    assert_eq!(pulled["collection"][0]["order"].as_array().unwrap().len(), 2);
+
+    // Re-committing without an order keeps the old one, unless it's cleared with `order = []`
+    let mut tables: Vec<toml::Value> = "
+        [[collection]]
+        title = 'Trips'
+        include = [ 'travel' ]
+        [[collection.collection]]
+        title = 'Walks'
+        include = [ 'walking' ]
+        [[collection]]
+        title = 'New'
+        include = []
+        order = []
+    ".parse::<Table>().unwrap()["collection"].as_array().unwrap().clone();
+    diary_cli::moc::keep_orders(&mut tables, moc.collections(logger.hollow()), logger.hollow());
+    assert_eq!(tables[0]["order"].as_array().unwrap()[1].as_str(), Some("peru"));
+    assert_eq!(tables[0]["collection"][0]["order"].as_array().unwrap()[0].as_str(), Some("coast"));
+    assert!(tables[1]["order"].as_array().unwrap().is_empty());
+}
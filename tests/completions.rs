@@ -0,0 +1,11 @@
+use clap::CommandFactory;
+use diary_cli::{cli::Cli, completions::Targets};
+
+#[test]
+fn find_uid_and_tag_arguments() {
+    let targets = Targets::find(&Cli::command());
+    assert!(targets.uids.iter().any(|x| x == "about"));
+    assert!(!targets.uids.iter().any(|x| x.starts_with("trash")));
+    assert!(targets.tags.iter().any(|x| x == "tag/rename"));
+    assert!(targets.tag_options.iter().any(|(top, _, short, long)| top == "list" && *short == Some('f') && long.as_deref() == Some("filter")));
+}
@@ -0,0 +1,27 @@
+use diary_cli::{archive::with_default_tags, config::*};
+use soulog::*;
+
+#[test]
+fn archive_config_overrides_user_config() {
+    let user: toml::Table = "editor = 'vim'\ndefault_tags = ['diary']\nkeep_backups = 3\ncolour = 'never'".parse().unwrap();
+    let archive: toml::Table = "editor = 'nano'\ndefault_format = 'html'".parse().unwrap();
+    let config = Config::parse(&merge(user, archive), sbl::PanicLogger::new());
+    assert_eq!(config.editor.as_deref(), Some("nano"));
+    assert_eq!(config.default_tags, ["diary"]);
+    assert_eq!(config.keep_backups(), 3);
+    assert_eq!(config.colour, Some(false));
+    assert!(config.default_format.is_some());
+
+    let config = Config::parse(&toml::Table::new(), sbl::PanicLogger::new());
+    assert_eq!(config.keep_backups(), 1);
+    assert_eq!(config.colour, None);
+}
+
+#[test]
+fn default_tags_are_added_once() {
+    let table: toml::Table = "[entry]\ntags = ['diary', 'rain']\n[moc]\ntags = []".parse().unwrap();
+    let table = with_default_tags(table, &["diary".to_string(), "2024".to_string()]);
+    let tags: Vec<&str> = table["entry"]["tags"].as_array().unwrap().iter().map(|x| x.as_str().unwrap()).collect();
+    assert_eq!(tags, ["diary", "rain", "2024"]);
+    assert!(table["moc"]["tags"].as_array().unwrap().is_empty());
+}
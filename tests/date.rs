@@ -0,0 +1,47 @@
+use chrono::NaiveDate;
+use diary_cli::date::*;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn days_across_leap_years() {
+    assert_eq!(days_between(date(2020, 1, 1), date(2024, 1, 1)), 1461);
+    assert_eq!(days_between(date(2024, 3, 1), date(2024, 2, 28)), -2);
+    // 2100 isn't a leap year, unlike 2000
+    assert_eq!(days_between(date(2100, 2, 28), date(2100, 3, 1)), 1);
+    assert_eq!(days_between(date(2000, 2, 28), date(2000, 3, 1)), 2);
+}
+
+#[test]
+fn days_ago() {
+    assert_eq!(days_before(date(2024, 3, 1), 1), Some(date(2024, 2, 29)));
+    assert_eq!(days_before(date(2024, 2, 29), -366), Some(date(2025, 3, 1)));
+}
+
+#[test]
+fn iso_weeks() {
+    assert_eq!(iso_week(date(2024, 2, 29)), "2024-W09");
+    // The first days of a year can be in the last week of the one before
+    assert_eq!(iso_week(date(2021, 1, 3)), "2020-W53");
+}
+
+#[test]
+fn parse_dates() {
+    assert_eq!(parse_date("2024-02-29"), Some(date(2024, 2, 29)));
+    assert_eq!(parse_date("2023-02-29"), None);
+    assert!(parse_date("today").is_some());
+}
+
+#[test]
+fn spans_go_back_in_time() {
+    assert_eq!(Span::parse("1y"), Some(Span::Years(1)));
+    assert_eq!(Span::parse("2W"), Some(Span::Weeks(2)));
+    assert_eq!(Span::parse("y"), None);
+    assert_eq!(Span::parse("3x"), None);
+    assert_eq!(Span::Years(1).before(date(2024, 3, 31)), Some(date(2023, 3, 31)));
+    assert_eq!(Span::Months(1).before(date(2024, 3, 31)), Some(date(2024, 2, 29)));
+    assert_eq!(Span::Weeks(2).before(date(2024, 3, 31)), Some(date(2024, 3, 17)));
+    assert_eq!(Span::Days(30).to_string(), "30d");
+}
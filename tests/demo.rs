@@ -0,0 +1,14 @@
+use diary_cli::demo::demo_entry;
+use chrono::NaiveDate;
+
+#[test]
+fn demo_entries_are_valid_and_reproducible() {
+    let date = NaiveDate::from_ymd_opt(2023, 8, 21).unwrap();
+    let entry = demo_entry("2023-08-21", date, 42);
+    assert_eq!(entry, demo_entry("2023-08-21", date, 42));
+
+    let table = entry.parse::<toml::Table>().unwrap();
+    assert_eq!(table["entry"]["uid"].as_str(), Some("2023-08-21"));
+    assert!(table["entry"]["date"].as_datetime().is_some());
+    assert!(!table["section"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,21 @@
+use diary_cli::{destination::Destination, export::{ExportFormat, ExportLayout}};
+use soulog::*;
+
+#[test]
+fn parse_destinations() {
+    let table: toml::Table = r#"
+path = "/tmp/vault"
+format = "HTML"
+layout = "tag"
+tags = ["public"]
+incremental = true
+"#.parse().unwrap();
+
+    let dest = Destination::parse("blog", &table, sbl::PanicLogger::new());
+    assert_eq!(dest.path, "/tmp/vault");
+    assert!(dest.format == Some(ExportFormat::Html));
+    assert!(dest.layout == Some(ExportLayout::Tag));
+    assert_eq!(dest.tags, Some(vec![String::from("public")]));
+    assert!(dest.incremental && !dest.strict && !dest.split_collections);
+    assert_eq!(dest.theme, None);
+}
@@ -0,0 +1,37 @@
+use diary_cli::dump::{content_hash, json_to_toml, toml_to_json};
+
+#[test]
+fn toml_json_round_trip() {
+    let config: toml::Table = r#"
+[entry]
+uid = "e1"
+date = 2023-08-21
+unlock_date = 2030-01-01
+tags = ["a", "b"]
+notes = []
+
+[[section]]
+title = "S1"
+contents = "text"
+"#.parse().unwrap();
+
+    let json = toml_to_json(&toml::Value::Table(config.clone()));
+    assert_eq!(json["entry"]["date"], "2023-08-21");
+    assert_eq!(json_to_toml(&json), Some(toml::Value::Table(config)));
+}
+
+#[test]
+fn json_nulls_are_dropped() {
+    let json = serde_json::json!({ "title": "x", "signature": null });
+    let toml = json_to_toml(&json).unwrap();
+    assert_eq!(toml.as_table().unwrap().len(), 1);
+}
+
+#[test]
+fn content_hash_ignores_uid_and_metadata() {
+    let item = serde_json::json!({ "entry": { "uid": "a", "date": "2023-08-21", "title": "x" }, "section": [], "archived": false });
+    let copy = serde_json::json!({ "entry": { "title": "x", "uid": "b", "date": "2023-08-21" }, "section": [], "signature": { "author": "me" } });
+    let changed = serde_json::json!({ "entry": { "uid": "a", "date": "2023-08-22", "title": "x" }, "section": [] });
+    assert_eq!(content_hash(&item), content_hash(&copy));
+    assert_ne!(content_hash(&item), content_hash(&changed));
+}
@@ -17,9 +17,10 @@ fn isol_entry() {
     fs::write(&example_path, "example content of a file").unwrap();
     let toml = format!("
         [entry]
+        uid = 'example-entry'
         title = 'Example Entry Title'
         description = 'Example Entry Description'
-        groups = [ '2023', 'entry', 'term1' ]
+        tags = [ '2023', 'entry', 'term1' ]
         notes = [ 'entry-note1', 'entry-note2', 'entry-note3', 'entry-note4' ]
         date = 2023-08-21
 
@@ -0,0 +1,39 @@
+use diary_cli::error::*;
+use soulog::*;
+use diary_cli::DynamicLogger;
+
+#[test]
+fn context_keeps_the_cause_chain() {
+    let read: std::io::Result<()> = Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"));
+    let error = read.context("import-read", "Import", || String::from("Couldn't read 'dump.json'")).unwrap_err();
+    let outer = Error::new("import", "Import", "Import failed").with_source(error);
+
+    assert_eq!(outer.causes(), ["Couldn't read 'dump.json'", "no such file"]);
+    let json = outer.to_json();
+    assert_eq!(json["code"], "import");
+    assert_eq!(json["message"], "Import failed");
+    assert_eq!(json["causes"][1], "no such file");
+}
+
+#[test]
+fn ok_results_have_no_context() {
+    let parsed: Result<u8> = "7".parse::<u8>().context("parse", "Test", || unreachable!());
+    assert_eq!(parsed.unwrap(), 7);
+    assert!(Error::new("fatal", "Test", "Oops").causes().is_empty());
+}
+
+#[test]
+fn summary_and_retry() {
+    let read: std::io::Result<()> = Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"));
+    let error = read.context("archive-read", "Archive", || String::from("While loading archive uid")).unwrap_err();
+    assert_eq!(error.summary(), "While loading archive uid: no such file");
+    assert_eq!(Error::new("fatal", "Test", "Oops").summary(), "Oops");
+
+    // Failures are retried until they succeed
+    let mut attempts = 0;
+    let value = retry(|| {
+        attempts += 1;
+        if attempts < 2 { Err(Error::new("test", "Test", "Not yet")) } else { Ok(attempts) }
+    }, DynamicLogger::new());
+    assert_eq!(value, 2);
+}
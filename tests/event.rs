@@ -0,0 +1,27 @@
+use chrono::NaiveDate;
+use diary_cli::event::*;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn countdowns() {
+    let trip = Event { title: "trip to Japan".into(), date: date(2025, 4, 1) };
+    assert_eq!(trip.countdown(date(2025, 3, 20)), "in 12 days");
+    assert_eq!(trip.countdown(date(2025, 3, 31)), "tomorrow");
+    assert_eq!(trip.countdown(date(2025, 4, 1)), "today");
+    assert_eq!(trip.countdown(date(2025, 4, 4)), "3 days ago");
+}
+
+#[test]
+fn upcoming_events_of_an_entry() {
+    let events = [
+        Event { title: "exam".into(), date: date(2025, 2, 27) },
+        Event { title: "trip to Japan".into(), date: date(2025, 4, 1) },
+        Event { title: "birthday".into(), date: date(2025, 6, 1) },
+    ];
+    assert_eq!(upcoming(&events, date(2025, 3, 2), HORIZON), [&events[1]]);
+    assert_eq!(upcoming_section(&events, date(2025, 3, 20)), "\n## Upcoming\n- trip to Japan (in 12 days, 2025-04-01)\n");
+    assert_eq!(upcoming_section(&events, date(2024, 1, 1)), "");
+}
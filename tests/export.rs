@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+use diary_cli::export::{heading_anchor, relative_link, tag_index, ExportLayout};
+
+#[test]
+fn heading_anchors() {
+    assert_eq!(heading_anchor("First (2023-08-21)"), "first-2023-08-21");
+    assert_eq!(heading_anchor(" Hello, World! "), "hello-world");
+    assert_eq!(heading_anchor("snake_case and-dash"), "snake_case-and-dash");
+}
+
+#[test]
+fn layout_folders() {
+    let tags = [String::from("/project/rust/"), String::from("diary")];
+    assert_eq!(ExportLayout::Flat.item_dir(Some(&[21, 8, 2023]), &tags), PathBuf::new());
+    assert_eq!(ExportLayout::Date.item_dir(Some(&[21, 8, 2023]), &tags), PathBuf::from("2023/08"));
+    assert_eq!(ExportLayout::Date.item_dir(None, &tags), PathBuf::new());
+    assert_eq!(ExportLayout::Tag.item_dir(None, &tags), PathBuf::from("tags/project/rust"));
+    assert_eq!(ExportLayout::Tag.item_dir(None, &[]), PathBuf::from("tags/untagged"));
+}
+
+#[test]
+fn relative_links() {
+    assert_eq!(relative_link(Path::new(""), Path::new("e1")), "e1");
+    assert_eq!(relative_link(Path::new(""), Path::new("2023/08/e1")), "2023/08/e1");
+    assert_eq!(relative_link(Path::new("tags/a"), Path::new("tags/b/e1")), "../b/e1");
+    assert_eq!(relative_link(Path::new("tags/a"), Path::new("e1")), "../../e1");
+}
+
+#[test]
+fn tag_pages_index() {
+    let tags = [
+        vec![String::from("travel/japan"), String::from("food")],
+        vec![String::from("travel/"), String::from("travel/peru")],
+        vec![],
+    ];
+    let index = tag_index([("a", tags[0].as_slice()), ("b", tags[1].as_slice()), ("c", tags[2].as_slice())]);
+
+    // Parents of nested tags list the entries of their sub-tags, once each and in order
+    assert_eq!(index.keys().collect::<Vec<_>>(), ["food", "travel", "travel/japan", "travel/peru"]);
+    assert_eq!(index["travel"], ["a", "b"]);
+    assert_eq!(index["travel/peru"], ["b"]);
+}
@@ -0,0 +1,30 @@
+use diary_cli::frontmatter::Frontmatter;
+use soulog::*;
+
+#[test]
+fn default_frontmatter() {
+    let frontmatter = Frontmatter::default();
+    assert_eq!(frontmatter.render(&[String::from("a")], "Title", Some(&[1, 8, 2023]), &[]), "---\ntags:\n  - a\ndate: 2023-8-1\n---\n");
+    assert_eq!(frontmatter.render(&[], "Title", None, &[]), "---\n---\n");
+    assert_eq!(
+        frontmatter.render(&[], "Title", None, &[String::from("[[moc-family|Family]]")]),
+        "---\nmocs:\n  - \"[[moc-family|Family]]\"\n---\n",
+    );
+}
+
+#[test]
+fn configured_frontmatter() {
+    let table: toml::Table = r#"
+date_format = "%Y/%m/%d"
+aliases = true
+[extra]
+publish = true
+authors = ["me", "you"]
+"#.parse().unwrap();
+
+    let frontmatter = Frontmatter::parse(&table, sbl::PanicLogger::new());
+    assert_eq!(
+        frontmatter.render(&[], "A: title", Some(&[1, 8, 2023]), &[]),
+        "---\naliases:\n  - \"A: title\"\ndate: 2023/08/01\nauthors:\n  - \"me\"\n  - \"you\"\npublish: true\n---\n",
+    );
+}
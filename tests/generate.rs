@@ -0,0 +1,37 @@
+use diary_cli::moc::generate::{collections, GroupBy};
+
+fn entry(tags: &[&str], year: u16) -> (Vec<String>, [u16; 3]) {
+    (tags.iter().map(|x| x.to_string()).collect(), [1, 6, year])
+}
+
+#[test]
+fn collections_by_subtag() {
+    let entries = [
+        entry(&["travel/japan/tokyo", "travel/japan", "food"], 2023),
+        entry(&["travel/peru"], 2023),
+        entry(&["travel/japan"], 2024),
+        entry(&["travel"], 2024),
+        entry(&["work"], 2024),
+    ];
+
+    // The most used sub-tags first, then the entries with none of them
+    let travel = collections("travel", GroupBy::Subtag, &entries);
+    let titles: Vec<&str> = travel.iter().map(|x| x["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["japan", "peru", "Other"]);
+    assert_eq!(travel[0]["include"].as_array().unwrap()[0].as_str(), Some("travel/japan"));
+    assert_eq!(travel[2]["include"].as_array().unwrap()[0].as_str(), Some("travel"));
+    assert_eq!(travel[2]["exclude"].as_array().unwrap().len(), 2);
+
+    assert!(collections("work", GroupBy::Subtag, &entries).is_empty());
+    assert!(collections("nope", GroupBy::Subtag, &entries).is_empty());
+}
+
+#[test]
+fn collections_by_year() {
+    let entries = [entry(&["travel/japan"], 2024), entry(&["travel"], 2023), entry(&["work"], 2022)];
+    let travel = collections("travel", GroupBy::Year, &entries);
+    let titles: Vec<&str> = travel.iter().map(|x| x["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["2023", "2024"]);
+    assert_eq!(travel[0]["from"].as_datetime().map(|x| x.to_string()), Some("2023-01-01".to_string()));
+    assert_eq!(travel[0]["to"].as_datetime().map(|x| x.to_string()), Some("2023-12-31".to_string()));
+}
@@ -0,0 +1,38 @@
+use diary_cli::graph::{EdgeKind, Graph, NodeKind};
+
+fn graph() -> Graph {
+    let mut graph = Graph::default();
+    let entry = graph.node(NodeKind::Entry, "day-1", "A \"good\" day");
+    let moc = graph.node(NodeKind::Moc, "days", "Days & <nights>");
+    let tag = graph.node(NodeKind::Tag, "life", "#life");
+    graph.node(NodeKind::Tag, "life", "#life");
+    graph.edge(&entry, &tag, EdgeKind::Tagged);
+    graph.edge(&moc, &entry, EdgeKind::Includes);
+    graph.edge(&moc, &entry, EdgeKind::Includes);
+    graph
+}
+
+#[test]
+fn dedup_nodes_and_edges() {
+    let graph = graph();
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(graph.edges.len(), 2);
+    assert_eq!(graph.nodes[0].id, "entry:day-1");
+}
+
+#[test]
+fn render_dot() {
+    let dot = graph().to_dot();
+    assert!(dot.starts_with("digraph diary {\n"));
+    assert!(dot.contains("\"entry:day-1\" [label=\"A \\\"good\\\" day\", kind=entry, shape=box];"));
+    assert!(dot.contains("\"moc:days\" -> \"entry:day-1\" [kind=includes, style=solid];"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn render_graphml() {
+    let graphml = graph().to_graphml();
+    assert!(graphml.contains("<node id=\"moc:days\"><data key=\"label\">Days &amp; &lt;nights&gt;</data><data key=\"kind\">moc</data></node>"));
+    assert!(graphml.contains("<edge source=\"entry:day-1\" target=\"tag:life\"><data key=\"relation\">tagged</data></edge>"));
+    assert!(graphml.ends_with("</graphml>\n"));
+}
@@ -0,0 +1,23 @@
+use diary_cli::grep::{find_matches, replace_matches};
+use regex::Regex;
+
+#[test]
+fn find_matches_with_lines() {
+    let regex = Regex::new("te?a").unwrap();
+    let matches = find_matches("coffee\nsome tea\nand ta", &regex);
+    assert_eq!(matches.iter().map(|x| (x.line, x.text.as_str())).collect::<Vec<_>>(), vec![(2, "some tea"), (3, "and ta")]);
+    assert_eq!(matches[0].range, 12..15);
+}
+
+#[test]
+fn replace_selected_matches() {
+    let regex = Regex::new(r"(\w+)ed").unwrap();
+    let mut asked = Vec::new();
+    let (result, count) = replace_matches("walked, talked and jumped", &regex, "${1}s", |x, replacement| {
+        asked.push(replacement.to_string());
+        x.range.start != 8
+    });
+    assert_eq!(result, "walks, talked and jumps");
+    assert_eq!(count, 2);
+    assert_eq!(asked, vec!["walks", "talks", "jumps"]);
+}
@@ -0,0 +1,17 @@
+use diary_cli::html::{markdown_to_html, tag_file};
+
+#[test]
+fn render_markdown_as_html() {
+    assert_eq!(
+        markdown_to_html("# Title\nsome **bold** <b>\ntext\n\n- a *b*\n- `c`\n> quote"),
+        "<h1>Title</h1>\n<p>some <strong>bold</strong> &lt;b&gt;<br>\ntext</p>\n<ul>\n<li>a <em>b</em></li>\n<li><code>c</code></li>\n</ul>\n<blockquote>quote</blockquote>\n",
+    );
+    assert_eq!(markdown_to_html("unclosed **bold"), "<p>unclosed <strong>bold</strong></p>\n");
+    assert_eq!(markdown_to_html("see [**e1**](e1.html) `[x](y)` [no link]"), "<p>see <a href=\"e1.html\"><strong>e1</strong></a> <code>[x](y)</code> [no link]</p>\n");
+}
+
+#[test]
+fn tag_files() {
+    assert_eq!(tag_file("travel"), "tag-travel.html");
+    assert_eq!(tag_file("work/meetings"), "tag-work--meetings.html");
+}
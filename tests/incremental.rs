@@ -0,0 +1,8 @@
+use diary_cli::incremental::fingerprint;
+
+#[test]
+fn stable_fingerprints() {
+    assert_eq!(fingerprint(""), 0xcbf29ce484222325);
+    assert_eq!(fingerprint("a"), 0xaf63dc4c8601ec8c);
+    assert_ne!(fingerprint("# Entry\n"), fingerprint("# Entry \n"));
+}
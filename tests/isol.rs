@@ -43,4 +43,14 @@ impl Drop for TmpPath {
     fn drop(&mut self) {
         fs::remove_dir_all(&self.0).unwrap();
     }
-}
\ No newline at end of file
+}
+/// A temporary diary-cli home for the archive commands of a test binary to use (config included); the home is set for
+/// the whole process, so only one test of a binary should use it
+#[allow(dead_code)]
+pub fn new_home() -> TmpPath {
+    let tmp = new_env();
+    let path = fs::canonicalize(tmp.get_path()).unwrap();
+    std::env::set_var("DIARY_CLI_HOME", &path);
+    std::env::set_var("XDG_CONFIG_HOME", path.join("config"));
+    tmp
+}
@@ -0,0 +1,58 @@
+mod isol;
+
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+use diary_cli::{archive::Archive, journal, uncommit, DynamicLogger};
+
+#[test]
+fn journal_limit() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let path = tmp.get_path().join("archive");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) uid = new_u64(1)).unwrap();
+    write_database!((&database) itver = new_u16(0)).unwrap();
+    let archive = Archive::load_dir(path, logger.hollow());
+
+    // Only the latest records are kept, the oldest ones moved out of the way
+    let commits = journal::LIMIT + 4;
+    (0..commits).for_each(|i| journal::record(&archive, &format!("entry-{i}"), false, logger.hollow()));
+    let journal = search_database!((archive.database()) /journal).unwrap();
+    assert_eq!(journal.read_data("length").unwrap().collect_u16().unwrap(), journal::LIMIT);
+    assert_eq!(journal.read_container("0").unwrap().read_data("uid").unwrap().collect_string().unwrap(), "entry-4");
+    let last = (journal::LIMIT - 1).to_string();
+    assert_eq!(journal.read_container(&last).unwrap().read_data("uid").unwrap().collect_string().unwrap(), format!("entry-{}", commits - 1));
+    assert!(journal.read_container(journal::LIMIT.to_string()).is_err());
+}
+
+#[test]
+fn undo_bumps_itver() {
+    let home = new_home();
+    let logger = DynamicLogger::new();
+    let config = home.get_path().join("entry.toml");
+    let table = "
+        [entry]
+        uid = 'entry'
+        title = 'Entry'
+        description = ''
+        tags = []
+        date = 2023-08-21
+
+        [[section]]
+        title = 'Section'
+        content = 'Content'
+    ".parse::<toml::Table>().unwrap();
+    Archive::init(logger.hollow());
+    let itver = || Archive::load(logger.hollow()).itver;
+
+    // Undoing a commit that other changes came after doesn't take the itver back to before them
+    let mut itvers = vec![itver()];
+    Archive::load(logger.hollow()).commit_table(table, &config, logger.hollow());
+    itvers.push(itver());
+    uncommit::uncommmit(String::from("entry"), false, false, logger.hollow());
+    itvers.push(itver());
+    journal::undo(logger.hollow());
+    itvers.push(itver());
+    assert!(itvers.windows(2).all(|x| x[0] < x[1]), "{itvers:?}");
+}
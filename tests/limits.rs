@@ -0,0 +1,18 @@
+use diary_cli::limits::{format_size, parse_size};
+
+#[test]
+fn parse_sizes() {
+    assert_eq!(parse_size("512"), Some(512));
+    assert_eq!(parse_size("50 MB"), Some(50_000_000));
+    assert_eq!(parse_size("1.5kb"), Some(1_500));
+    assert_eq!(parse_size("2 GiB"), Some(2 << 30));
+    assert_eq!(parse_size("lots"), None);
+    assert_eq!(parse_size("5 parsecs"), None);
+}
+
+#[test]
+fn format_sizes() {
+    assert_eq!(format_size(1000), "1000 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(3 << 30), "3.0 GiB");
+}
@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+use diary_cli::links::{cross_references, render_cross_references, rewrite_references, unlink_cross_references, LinkStyle, Targets};
+
+fn targets() -> Targets {
+    let mut targets = Targets::new();
+    targets.insert(String::from("e1"), (String::from("First"), PathBuf::from("2023/08/e1")));
+    targets.insert(String::from("m1"), (String::from("Moc"), PathBuf::from("m1")));
+    targets
+}
+
+#[test]
+fn link_styles() {
+    assert_eq!(LinkStyle::Markdown.link(Path::new("tags/a"), Path::new("e1"), "T"), "[T](../../e1)");
+    assert_eq!(LinkStyle::Wiki.link(Path::new("tags/a"), Path::new("2023/08/e1"), "T"), "[[2023/08/e1|T]]");
+}
+
+#[test]
+fn embed_styles() {
+    assert_eq!(LinkStyle::Markdown.embed(Path::new("2023/08"), Path::new("assets/e1/my photo.jpg"), "my photo.jpg"), "![my photo.jpg](../../assets/e1/my%20photo.jpg)");
+    assert_eq!(LinkStyle::Wiki.embed(Path::new("2023/08"), Path::new("assets/e1/scan.pdf"), "scan.pdf"), "![[assets/e1/scan.pdf]]");
+}
+
+#[test]
+fn rewrite_entry_references() {
+    let content = "see @e1, [[m1]] and [[e1|that]]\n@e1 me@e1.com @nope [[nope]]";
+    assert_eq!(
+        rewrite_references(content, Path::new(""), &targets(), LinkStyle::Markdown),
+        "see [First](2023/08/e1), [Moc](m1) and [that](2023/08/e1)\n[First](2023/08/e1) me@e1.com @nope [[nope]]",
+    );
+    assert_eq!(
+        rewrite_references("@e1 and [[m1]]", Path::new("2023/08"), &targets(), LinkStyle::Wiki),
+        "[[2023/08/e1|First]] and [[m1|Moc]]",
+    );
+}
+
+#[test]
+fn uid_cross_references() {
+    let content = "see {{uid:e1}}, {{ uid: m1 | the moc }} and {{uid:e1|again}}\n{{uid:nope}} {{uid:nope|gone}}";
+    assert_eq!(cross_references(content), ["e1", "m1", "nope"]);
+    assert_eq!(
+        rewrite_references(content, Path::new(""), &targets(), LinkStyle::Markdown),
+        "see [First](2023/08/e1), [the moc](m1) and [again](2023/08/e1)\nnope gone",
+    );
+    assert_eq!(render_cross_references("{{uid:e1}}", |uid, _| Some(format!("<{uid}>"))), "<e1>");
+}
+
+#[test]
+fn unlink_broken_references() {
+    let content = "see {{uid:e1}}, {{uid:nope}} and {{ uid: nope | gone }}";
+    assert_eq!(unlink_cross_references(content, "nope"), "see {{uid:e1}}, nope and gone");
+}
@@ -0,0 +1,27 @@
+use chrono::{NaiveDate, Weekday};
+use diary_cli::locale::*;
+use soulog::*;
+
+#[test]
+fn weeks_start_on_the_configured_day() {
+    let date = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(); // a wednesday
+    assert_eq!(week_of(date, Weekday::Mon), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    assert_eq!(week_of(date, Weekday::Sun), NaiveDate::from_ymd_opt(2023, 12, 31).unwrap());
+    assert_eq!(week_of(date, Weekday::Wed), date);
+}
+
+#[test]
+fn parse_calendar_config() {
+    let table: toml::Table = "week_start = 'sunday'\nlanguage = 'de'".parse().unwrap();
+    let locale = Locale::parse(&table, sbl::PanicLogger::new());
+    assert_eq!(locale.week()[0], Weekday::Sun);
+    assert_eq!(locale.week()[6], Weekday::Sat);
+    assert_eq!(locale.month(3), "März");
+    assert_eq!(locale.short_day(Weekday::Sun), "Son");
+
+    // Names given by hand replace the language's
+    let table: toml::Table = "day_names = ['L', 'M', 'X', 'J', 'V', 'S', 'D']".parse().unwrap();
+    let locale = Locale::parse(&table, sbl::PanicLogger::new());
+    assert_eq!(locale.week_start, Weekday::Mon);
+    assert_eq!(locale.short_day(Weekday::Wed), "X");
+}
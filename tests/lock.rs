@@ -0,0 +1,26 @@
+mod isol;
+
+use std::fs;
+use std::process::Command;
+use isol::*;
+use diary_cli::lock;
+
+#[test]
+fn stale_lock() {
+    let tmp = new_env();
+    let path = tmp.get_path().join("archive.lock");
+
+    // A lock held by a running process is not stale
+    fs::write(&path, std::process::id().to_string()).unwrap();
+    assert!(!lock::is_stale(&path));
+
+    // ...but one left behind by a process that has exited is
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    fs::write(&path, child.id().to_string()).unwrap();
+    assert!(lock::is_stale(&path));
+
+    // An unreadable lock is never treated as stale
+    fs::write(&path, "not a pid").unwrap();
+    assert!(!lock::is_stale(&path));
+}
@@ -0,0 +1,19 @@
+use diary_cli::logseq::{blocks, journal_file, journal_title, page_file};
+
+#[test]
+fn journal_pages() {
+    assert_eq!(journal_file(&[21, 8, 2023]), "journals/2023_08_21.md");
+    assert_eq!(journal_title(&[21, 8, 2023]), "Aug 21st, 2023");
+    assert_eq!(journal_title(&[2, 1, 2024]), "Jan 2nd, 2024");
+    assert_eq!(journal_title(&[13, 12, 2024]), "Dec 13th, 2024");
+    assert_eq!(journal_title(&[23, 3, 2025]), "Mar 23rd, 2025");
+    assert_eq!(page_file("health/running"), "pages/health___running.md");
+}
+
+#[test]
+fn content_blocks() {
+    let text = "Caught the train.\nIt was late.\n\n```\nfn main() {\n\n}\n```\nLast one";
+    let expected = "\t- Caught the train.\n\t  It was late.\n\t- ```\n\t  fn main() {\n\t  \n\t  }\n\t  ```\n\t  Last one\n";
+    assert_eq!(blocks(text, 1), expected);
+    assert_eq!(blocks("", 0), "");
+}
@@ -0,0 +1,70 @@
+mod isol;
+
+use diary_cli::manifest::{check, prune, read_files, Filters, Manifest};
+use soulog::*;
+use isol::*;
+
+#[test]
+fn prune_stale_files() {
+    use diary_cli::incremental::fingerprint;
+
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let vault = tmp.get_path().join("vault");
+    std::fs::create_dir_all(vault.join("2023/08")).unwrap();
+    for file in ["2023/08/e1.md", "e2.md", "e3.md", "e4.md", "mine.md"] { std::fs::write(vault.join(file), "").unwrap() }
+    std::fs::write(vault.join("e3.md"), "edited in the vault").unwrap();
+    std::fs::write(tmp.get_path().join("outside.md"), "").unwrap();
+
+    let previous: Vec<String> = ["../outside.md", "2023/08/e1.md", "e2.md", "e3.md"].into_iter().map(String::from).collect();
+    let mut manifest = Manifest { files: previous.iter().map(|x| (x.clone(), Some(fingerprint("")))).collect(), ..Default::default() };
+    manifest.files.push((String::from("e4.md"), None));
+    manifest.write(&vault, logger.hollow());
+    assert_eq!(read_files(&vault, logger.hollow())[..4], previous);
+
+    // Edited files are kept, and files without a hash can't have been edited as far as it can tell
+    assert_eq!(prune(&vault, &manifest.files, &[String::from("e2.md")], logger), 2);
+    assert!(!vault.join("2023").exists() && !vault.join("e4.md").exists());
+    assert!(vault.join("e2.md").exists() && vault.join("e3.md").exists() && vault.join("mine.md").exists());
+    assert!(tmp.get_path().join("outside.md").exists());
+}
+
+#[test]
+fn manifest_roundtrip() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let manifest = Manifest {
+        archive: String::from("1234"),
+        itver: 7,
+        filters: Filters { tags: Some(vec![String::from("work")]), strict: true, include_archived: false, force_unlock: true, access: Some(diary_cli::access::Access::Public) },
+        files: vec![(String::from("2023/08/e1.md"), Some(u64::MAX)), (String::from("e2.md"), Some(1))],
+    };
+    manifest.write(tmp.get_path(), logger.hollow());
+    assert_eq!(Manifest::read(tmp.get_path(), logger), Some(manifest));
+}
+
+#[test]
+fn verify_vault() {
+    use diary_cli::incremental::fingerprint;
+
+    let tmp = new_env();
+    let vault = tmp.get_path();
+    std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+    for file in ["e1.md", "e2.md", "e3.md", "mine.md", ".obsidian/app.json"] { std::fs::write(vault.join(file), file).unwrap() }
+    let manifest = Manifest {
+        files: ["e1.md", "e2.md", "e4.md"].into_iter().map(|x| (x.to_string(), Some(fingerprint(x)))).chain([(String::from("e3.md"), None)]).collect(),
+        ..Default::default()
+    };
+    assert!(!check(vault, &manifest).is_intact());
+
+    std::fs::write(vault.join("e2.md"), "changed").unwrap();
+    let report = check(vault, &manifest);
+    assert_eq!(report.missing, vec!["e4.md"]);
+    assert_eq!(report.modified, vec!["e2.md"]);
+    assert_eq!(report.unhashed, vec!["e3.md"]);
+    assert_eq!(report.untracked, vec!["mine.md"]);
+
+    std::fs::write(vault.join("e2.md"), "e2.md").unwrap();
+    std::fs::write(vault.join("e4.md"), "e4.md").unwrap();
+    assert!(check(vault, &manifest).is_intact());
+}
@@ -0,0 +1,67 @@
+use diary_cli::markdown::parse;
+use soulog::*;
+
+#[test]
+fn markdown_entry() {
+    let text = "---
+uid: trip
+date: 2023-08-21
+tags: [travel, 'family']
+notes:
+  - packed light
+---
+# A Trip
+
+Went away for the weekend.
+
+## Morning
+Caught the train.
+```
+## not a section
+```
+
+## Evening
+Dinner by the sea.
+";
+    let table = parse(text, "trip.md", sbl::PanicLogger::new());
+    let expected: toml::Table = r#"
+[entry]
+uid = "trip"
+title = "A Trip"
+description = "Went away for the weekend."
+date = 2023-08-21
+tags = ["travel", "family"]
+notes = ["packed light"]
+
+[[section]]
+title = "Morning"
+contents = "Caught the train.\n```\n## not a section\n```\n"
+
+[[section]]
+title = "Evening"
+contents = "Dinner by the sea.\n"
+"#.parse().unwrap();
+    assert_eq!(table, expected);
+}
+
+#[test]
+fn markdown_entry_defaults() {
+    let table = parse("---\ntitle: Quick\ndate: 2024-01-02\n---\n## Thoughts\nshort", "<stdin>", sbl::PanicLogger::new());
+    let entry = table["entry"].as_table().unwrap();
+    assert_eq!(entry["uid"].as_str(), Some("2024-01-02"));
+    assert_eq!(entry["description"].as_str(), Some(""));
+    assert_eq!(entry["tags"].as_array().map(|x| x.len()), Some(0));
+    assert_eq!(table["section"][0]["contents"].as_str(), Some("short\n"));
+}
+
+#[test]
+fn markdown_links() {
+    let text = "---\ntitle: Linked\ndate: 2023-08-21\nlinks: [trip, moc-travel]\n---\n";
+    let table = parse(text, "linked.md", sbl::PanicLogger::new());
+    let links = table["entry"]["links"].as_array().unwrap();
+    assert_eq!(links, &vec![toml::Value::from("trip"), toml::Value::from("moc-travel")]);
+
+    let text = "---\ntitle: Linked\nlinks: trip\n---\n";
+    let table = parse(text, "linked.md", sbl::PanicLogger::new());
+    assert_eq!(table["entry"]["links"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,12 @@
+use diary_cli::membership::missing_tags;
+
+fn tags(x: &[&str]) -> Vec<String> { x.iter().map(|x| x.to_string()).collect() }
+
+#[test]
+fn missing_collection_tags() {
+    let entry = tags(&["travel/japan", "food"]);
+    assert!(missing_tags(&entry, &tags(&["travel", "food"])).is_empty());
+    assert_eq!(missing_tags(&entry, &tags(&["travel", "family"])), tags(&["family"]));
+    assert_eq!(missing_tags(&entry, &tags(&["trav*", "!food"])), tags(&["!food"]));
+    assert!(missing_tags(&entry, &[]).is_empty());
+}
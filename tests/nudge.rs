@@ -0,0 +1,50 @@
+mod isol;
+
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+use diary_cli::{entry::Entry, nudge::{day_before, nudges, word_count, Nudges}};
+use toml::Table;
+
+#[test]
+fn count_words() {
+    assert_eq!(word_count("Went for a run - then coffee."), 6);
+    assert_eq!(word_count("  \n "), 0);
+    assert_eq!(day_before(&[1, 3, 2024]), Some([29, 2, 2024]));
+    assert_eq!(day_before(&[1, 1, 2024]), Some([31, 12, 2023]));
+    assert_eq!(day_before(&[31, 2, 2024]), None);
+}
+
+#[test]
+fn entry_nudges() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("Entry")).unwrap();
+    let toml = "
+        [entry]
+        uid = 'short'
+        title = 'Rainy day'
+        description = 'rainy day'
+        tags = []
+        date = 2023-08-21
+
+        [[section]]
+        title = 'Morning'
+        content = 'Stayed in and read.'
+    ".parse::<Table>().unwrap();
+    let mut entry = Entry::new(toml, "short.toml", container, logger.hollow());
+
+    // Nothing is nudged about unless it is configured
+    assert!(nudges(&Nudges::default(), &mut entry, &["Rainy day".to_string()], logger.hollow()).is_empty());
+
+    let config = Nudges { min_words: Some(10), untagged: true, repeated_title: true, description_is_title: true };
+    assert_eq!(nudges(&config, &mut entry, &["RAINY DAY".to_string()], logger.hollow()), [
+        "is only 6 words long; you wanted to write at least 10",
+        "has no tags, so no moc collection or tag search will find it",
+        "has the same title as an entry of the day before ('Rainy day')",
+        "has a description that only repeats its title",
+    ]);
+
+    let config = Nudges { min_words: Some(6), ..config };
+    assert_eq!(nudges(&config, &mut entry, &["Sunny day".to_string()], logger.hollow()).len(), 2);
+}
@@ -0,0 +1,14 @@
+use diary_cli::on_this_day::index_by_day;
+
+#[test]
+fn index_entries_by_day() {
+    let index = index_by_day([
+        (String::from("a"), [21, 8, 2021]),
+        (String::from("b"), [21, 8, 2023]),
+        (String::from("c"), [22, 8, 2023]),
+    ]);
+    let uids: Vec<&str> = index[&(8, 21)].iter().map(|(x, _)| x.as_str()).collect();
+    assert_eq!(uids, ["b", "a"]);
+    assert_eq!(index[&(8, 22)].len(), 1);
+    assert!(!index.contains_key(&(8, 23)));
+}
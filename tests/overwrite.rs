@@ -0,0 +1,43 @@
+mod isol;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use soulog::*;
+use isol::*;
+use diary_cli::{archive::Archive, DynamicLogger};
+
+fn config(sections: &[&str]) -> toml::Table {
+    let mut toml = String::from("
+        [entry]
+        uid = 'entry'
+        title = 'Entry'
+        description = ''
+        tags = []
+        date = 2023-08-21
+    ");
+    sections.iter().for_each(|x| toml.push_str(&format!("\n[[section]]\ntitle = '{x}'\ncontent = 'Content of {x}'\n")));
+    toml.parse().unwrap()
+}
+
+#[test]
+fn overwrite_replaces_whole_entry() {
+    let home = new_home();
+    let logger = DynamicLogger::new();
+    let path = home.get_path().join("entry.toml");
+    Archive::init(logger.hollow());
+    let archive = Archive::load(logger.hollow());
+    archive.commit_item(config(&["A", "B"]), &path, logger.hollow());
+
+    // Committing a uid that is already in the archive is refused without `--overwrite`
+    let refused = catch_unwind(AssertUnwindSafe(|| archive.commit_item(config(&["A"]), &path, sbl::PanicLogger::new())));
+    assert!(refused.is_err());
+    let sections = |logger: DynamicLogger| -> Vec<String> {
+        let mut entry = archive.get_entry(String::from("entry"), logger.hollow()).unwrap();
+        entry.sections(logger.hollow()).iter_mut().map(|x| x.title(logger.hollow()).clone()).collect()
+    };
+    assert_eq!(sections(logger.hollow()), ["A", "B"]);
+
+    // ...and with it, none of the old entry's sections are kept
+    unsafe { diary_cli::cli::OVERWRITE = true };
+    archive.commit_item(config(&["A"]), &path, logger.hollow());
+    assert_eq!(sections(logger.hollow()), ["A"]);
+}
@@ -0,0 +1,26 @@
+use diary_cli::pick::*;
+
+fn candidate(uid: &str, title: &str, tags: &[&str]) -> Candidate {
+    Candidate { uid: uid.to_string(), title: title.to_string(), tags: tags.iter().map(|x| x.to_string()).collect(), is_moc: false }
+}
+
+#[test]
+fn every_word_has_to_match_within_a_word() {
+    assert!(score("jpn trip", "2024-trip-japan a week in japan travel").is_some());
+    assert!(score("kyoto", "2024-trip-japan a week in japan travel").is_none());
+    assert!(score("trip", "the river is pretty").is_none());
+    assert_eq!(score("", "anything"), Some(0));
+}
+
+#[test]
+fn closer_matches_rank_first() {
+    let candidates = [
+        candidate("2024-03-01", "Walking in the rain", &["weather"]),
+        candidate("2024-03-02", "A long walk", &["health/walking"]),
+        candidate("2024-03-03", "Work", &["work"]),
+    ];
+    let ranked: Vec<&str> = rank("walk", &candidates).iter().map(|x| x.uid.as_str()).collect();
+    assert_eq!(ranked, ["2024-03-01", "2024-03-02"]);
+    assert_eq!(rank("", &candidates).len(), 3);
+    assert_eq!(rank("rain weather", &candidates)[0].uid, "2024-03-01");
+}
@@ -0,0 +1,7 @@
+use diary_cli::logger::strip_ansi;
+
+#[test]
+fn strip_colours() {
+    assert_eq!(strip_ansi("\x1b[34m[\x1b[36mSort\x1b[34m] \x1b[0mSorted entries\x1b[0m"), "[Sort] Sorted entries");
+    assert_eq!(strip_ansi("no colours"), "no colours");
+}
@@ -0,0 +1,9 @@
+use diary_cli::{logger::strip_ansi, read::render_markdown};
+
+#[test]
+fn render_markdown_for_terminal() {
+    let rendered = render_markdown("## Title\n> a **bold** quote\n  - item with `code`\nplain", false);
+    assert_eq!(strip_ansi(&rendered), "Title\n│ a bold quote\n  • item with code\nplain\n");
+    assert!(rendered.contains("\x1b[1mbold"));
+    assert_eq!(render_markdown("## Title\n- item", true), "## Title\n- item\n");
+}
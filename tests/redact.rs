@@ -0,0 +1,17 @@
+use diary_cli::redact::{redact, hash};
+
+#[test]
+fn redact_long_quotes() {
+    let content = "a very long and very private piece of diary content that shouldn't be logged";
+    let redacted = redact(&format!("While parsing '{content}': invalid"));
+    assert!(!redacted.contains("private"));
+    assert!(redacted.contains(&hash(content)));
+    assert_eq!(redact("Entry 'uid's 'title' attribute"), "Entry 'uid's 'title' attribute");
+}
+
+#[test]
+fn redact_truncates() {
+    let redacted = redact(&"x".repeat(1000));
+    assert!(redacted.len() < 500);
+    assert!(redacted.ends_with("(truncated)"));
+}
@@ -0,0 +1,27 @@
+mod isol;
+
+use soulog::*;
+use isol::*;
+use diary_cli::{archive::{copy_dir, Archive, Relation}, DynamicLogger};
+
+#[test]
+fn backup_relation() {
+    let home = new_home();
+    let logger = DynamicLogger::new();
+    let archive = Archive::init(logger.hollow());
+    let backup = home.get_path().join("backup");
+    copy_dir(archive.database().path(), &backup).unwrap();
+    let load = |path: &std::path::Path| Archive::load_dir(path.to_path_buf(), logger.hollow());
+    assert_eq!(archive.relation(&load(&backup), logger.hollow()), Relation::Same);
+
+    // Once the archive is modified, the backup is behind it
+    archive.bump_itver(logger.hollow());
+    let archive = load(archive.database().path());
+    assert_eq!(archive.relation(&load(&backup), logger.hollow()), Relation::Older);
+    assert_eq!(load(&backup).relation(&archive, logger.hollow()), Relation::Newer);
+
+    // ...and once the backup is modified as well, neither is behind the other
+    load(&backup).bump_itver(logger.hollow());
+    assert_eq!(archive.relation(&load(&backup), logger.hollow()), Relation::Diverged);
+    assert_eq!(load(&backup).relation(&archive, logger.hollow()), Relation::Diverged);
+}
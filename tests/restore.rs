@@ -0,0 +1,39 @@
+mod isol;
+
+use soulog::*;
+use isol::*;
+use diary_cli::{archive::Archive, home_dir, uncommit, DynamicLogger};
+
+fn config(uid: &str) -> toml::Table {
+    format!("
+        [entry]
+        uid = '{uid}'
+        title = 'Entry {uid}'
+        description = ''
+        tags = []
+        date = 2023-08-21
+
+        [[section]]
+        title = 'Section'
+        content = 'Content of {uid}'
+    ").parse().unwrap()
+}
+
+#[test]
+fn selective_restore() {
+    let home = new_home();
+    let logger = DynamicLogger::new();
+    let backup = home.get_path().join("two.ldb");
+    Archive::init(logger.hollow());
+    let archive = Archive::load(logger.hollow());
+    for uid in ["one", "two"] { archive.commit_item(config(uid), &home.get_path().join(format!("{uid}.toml")), logger.hollow()); }
+    Archive::backup(&backup, logger.hollow());
+    for uid in ["one", "two"] { uncommit::uncommmit(String::from(uid), false, false, logger.hollow()) }
+
+    // Only the selected entry is brought back, and the unpacked backup doesn't outlive the restore
+    Archive::load_selective(&backup, Some(vec![String::from("one")]), None, logger.hollow());
+    let archive = Archive::load(logger.hollow());
+    assert!(archive.database_exists("entries/one"));
+    assert!(!archive.database_exists("entries/two"));
+    assert!(!home_dir().join("new").exists());
+}
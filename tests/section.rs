@@ -5,6 +5,7 @@ use lazy_db::*;
 use isol::*;
 use diary_cli::entry::Section;
 use std::fs;
+use std::path::Path;
 use toml::Table;
 
 #[test]
@@ -40,4 +41,67 @@ fn isol_section() {
     assert_eq!(title, "Example Title");
     assert_eq!(content, "example content of a file");
     assert_eq!(notes, &vec![String::from("note1"), String::from("note2"), String::from("note3")].into_boxed_slice());
-}
\ No newline at end of file
+}
+#[test]
+fn relative_section_paths() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("Section")).unwrap();
+    fs::create_dir_all(tmp.get_path().join("text")).unwrap();
+    fs::write(tmp.get_path().join("text/real.txt"), "relative content").unwrap();
+    std::os::unix::fs::symlink("text/real.txt", tmp.get_path().join("link.txt")).unwrap();
+    let config = tmp.get_path().join("entry.toml").to_string_lossy().to_string();
+
+    // Relative to the config file, not the working directory; symlinks record their target
+    let toml = "title = 'Relative'\npath = 'link.txt'";
+    let mut section = Section::new(&toml.parse::<Table>().unwrap(), container, &config, 0, logger.hollow());
+    section.clear_cache();
+
+    assert_eq!(section.content(logger.hollow()), "relative content");
+    let source = section.source(logger.hollow()).clone().unwrap();
+    assert_eq!(Path::new(&source), tmp.get_path().join("text/real.txt").canonicalize().unwrap());
+}
+
+#[test]
+fn section_attachments() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let container = LazyContainer::init(tmp.get_path().join("Section")).unwrap();
+    fs::create_dir_all(tmp.get_path().join("media")).unwrap();
+    fs::write(tmp.get_path().join("media/photo.jpg"), [0xff, 0xd8, 0x00, 0xff]).unwrap();
+    fs::write(tmp.get_path().join("scan.pdf"), "%PDF").unwrap();
+    let config = tmp.get_path().join("entry.toml").to_string_lossy().to_string();
+
+    let toml = "title = 'Attached'\ncontents = ''\nattachments = ['media/photo.jpg', 'scan.pdf']";
+    let mut section = Section::new(&toml.parse::<Table>().unwrap(), container, &config, 0, logger.hollow());
+    section.clear_cache();
+    assert_eq!(section.attachments(logger.hollow()).as_ref(), [String::from("photo.jpg"), String::from("scan.pdf")]);
+    assert_eq!(section.attachment(0, logger.hollow()).as_ref(), [0xff, 0xd8, 0x00, 0xff]);
+
+    // Pulled back out into the attachments folder
+    let out = tmp.get_path().join("pulled");
+    let table = section.pull(0, &out, Path::new("attachments/e1"), true, logger.hollow());
+    let attachments: Vec<&str> = table["attachments"].as_array().unwrap().iter().map(|x| x.as_str().unwrap()).collect();
+    assert_eq!(attachments, ["attachments/e1/photo.jpg", "attachments/e1/scan.pdf"]);
+    assert_eq!(fs::read(out.join("attachments/e1/scan.pdf")).unwrap(), b"%PDF");
+}
+
+#[test]
+fn inline_section_content() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let config = tmp.get_path().join("entry.toml").to_string_lossy().to_string();
+
+    for key in ["contents", "content"] {
+        let container = LazyContainer::init(tmp.get_path().join(key)).unwrap();
+        let toml = format!("title = 'Inline'\n{key} = \"\"\"\nwritten right\nin the config\n\"\"\"");
+        let mut section = Section::new(&toml.parse::<Table>().unwrap(), container, &config, 0, logger.hollow());
+        section.clear_cache();
+        assert_eq!(section.content(logger.hollow()), "written right\nin the config\n");
+
+        // Pulled back into a single file, the content stays inline
+        let table = section.pull(0, tmp.get_path(), Path::new("attachments"), true, logger.hollow());
+        assert_eq!(table["contents"].as_str(), Some("written right\nin the config\n"));
+        assert!(table.get("path").is_none());
+    }
+}
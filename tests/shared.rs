@@ -0,0 +1,97 @@
+mod isol;
+
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+use diary_cli::{archive::Archive, entry::Entry, shared::SharedArchive};
+use toml::Table;
+
+fn archive_with_entries(tmp: &TmpPath, count: usize) -> Archive {
+    let logger = sbl::PanicLogger::new();
+    let path = tmp.get_path().join("archive");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) uid = new_u64(1)).unwrap();
+    write_database!((&database) itver = new_u16(0)).unwrap();
+    for i in 0..count {
+        let toml = format!("
+            [entry]
+            uid = 'entry-{i}'
+            title = 'Entry {i}'
+            description = ''
+            tags = [ 'tag-{i}' ]
+            date = 2023-08-2{i}
+
+            [[section]]
+            title = 'Section'
+            content = 'Content'
+        ");
+        Entry::new(toml.parse::<Table>().unwrap(), "entry.toml", search_database!((&database) /entries).unwrap(), logger.hollow());
+    }
+
+    Archive::load_dir(path, logger.hollow())
+}
+
+#[test]
+fn concurrent_reads() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let shared = SharedArchive::new(archive_with_entries(&tmp, 4));
+    let titles: Vec<Vec<String>> = std::thread::scope(|scope| {
+        let readers: Vec<_> = (0..4).map(|_| {
+            let shared = shared.clone();
+            scope.spawn(move || {
+                let logger = sbl::PanicLogger::new();
+                let mut titles: Vec<String> = shared.entries(logger.hollow()).iter().map(|x| x.title.clone()).collect();
+                titles.sort();
+                titles
+            })
+        }).collect();
+        readers.into_iter().map(|x| x.join().unwrap()).collect()
+    });
+
+    // Every reader sees the same entries, which were only cached once
+    assert!(titles.iter().all(|x| *x == ["Entry 0", "Entry 1", "Entry 2", "Entry 3"]));
+    assert_eq!(shared.cached(), 4);
+    assert_eq!(shared.entry("entry-2", logger.hollow()).unwrap().date, [22, 8, 2023]);
+    assert!(shared.entry("missing", logger.hollow()).is_none());
+
+    shared.invalidate("entry-2");
+    assert_eq!(shared.cached(), 3);
+    shared.clear_cache();
+    assert_eq!(shared.cached(), 0);
+}
+
+#[test]
+fn bounded_cache() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let shared = SharedArchive::with_capacity(archive_with_entries(&tmp, 4), 2);
+
+    // Only the two most recently read entries stay cached, and they are the same ones when read again
+    assert_eq!(shared.entries(logger.hollow()).len(), 4);
+    assert_eq!(shared.cached(), 2);
+    let first = shared.entry("entry-0", logger.hollow()).unwrap();
+    assert!(std::sync::Arc::ptr_eq(&first, &shared.entry("entry-0", logger.hollow()).unwrap()));
+    assert_eq!(shared.cached(), 2);
+
+    // Any modification of the archive drops everything read before it
+    shared.advance_commit(logger.hollow());
+    let again = shared.entry("entry-0", logger.hollow()).unwrap();
+    assert!(!std::sync::Arc::ptr_eq(&first, &again));
+    assert_eq!(shared.cached(), 1);
+    assert_eq!(again.sections[0].content, "Content");
+}
+
+#[test]
+fn cached_markdown() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let archive = archive_with_entries(&tmp, 1);
+    let mut entry = archive.get_entry(String::from("entry-0"), logger.hollow()).unwrap();
+    let expected = diary_cli::read::markdown(&mut entry, logger.hollow());
+
+    // A cached entry reads the same as the one in the archive
+    let cached = SharedArchive::new(archive).entry("entry-0", logger.hollow()).unwrap();
+    assert_eq!(cached.markdown(), expected);
+    assert!(!cached.is_locked());
+}
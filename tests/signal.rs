@@ -0,0 +1,39 @@
+use diary_cli::signal;
+use std::process::Command;
+
+/// Run in a child process by `interrupt_waits_for_critical`, as it is interrupted
+#[test]
+fn interrupted_child() {
+    let Some(dir) = std::env::var_os("DIARY_CLI_SIGNAL_TEST") else { return };
+    let dir = std::path::PathBuf::from(dir);
+    signal::install();
+    std::fs::create_dir_all(dir.join("tmp")).unwrap();
+    signal::remove_on_interrupt(dir.join("tmp"));
+
+    let critical = signal::critical();
+    unsafe { libc::raise(libc::SIGINT) };
+    // Give the watcher thread time to (not) exit
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(signal::interrupted());
+    std::fs::write(dir.join("finished"), "").unwrap();
+    drop(critical);
+    unreachable!("the process should exit once the critical section ends");
+}
+
+#[test]
+fn interrupt_waits_for_critical() {
+    let dir = std::env::temp_dir().join(format!("diary-cli-signal-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "interrupted_child", "--nocapture", "--test-threads=1"])
+        .env("DIARY_CLI_SIGNAL_TEST", &dir)
+        .env("DIARY_CLI_HOME", &dir)
+        .status()
+        .unwrap();
+
+    // It finished the critical section, then cleaned up and exited as interrupted
+    assert_eq!(status.code(), Some(130));
+    assert!(dir.join("finished").is_file());
+    assert!(!dir.join("tmp").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
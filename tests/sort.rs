@@ -1,8 +1,63 @@
-use diary_cli::sort::younger;
+mod isol;
+
+use soulog::*;
+use lazy_db::*;
+use isol::*;
+use diary_cli::{archive::Archive, entry::Entry, list, sort::{insert_sorted, read_sorted, younger}};
+use toml::Table;
 
 #[test]
 fn sort_is_younger() {
     let date1 = [15, 8, 2023];
     let date2 = [30, 8, 2023];
     assert!(younger(&date2, &date1))
-}
\ No newline at end of file
+}
+
+#[test]
+fn same_day_by_commit_time() {
+    use diary_cli::sort::chronological;
+    let time = |x: &str| Some(chrono::DateTime::parse_from_rfc3339(x).unwrap());
+    let items = vec![
+        (String::from("late"), [2, 1, 2024], time("2024-01-02T22:00:00+00:00")),
+        (String::from("early"), [2, 1, 2024], time("2024-01-02T08:00:00+00:00")),
+        (String::from("legacy"), [2, 1, 2024], None),
+        (String::from("older"), [1, 1, 2024], time("2024-02-01T08:00:00+00:00")),
+        (String::from("legacy-2"), [2, 1, 2024], None),
+    ];
+
+    // Dates first, then commit times, then the order they were given in
+    assert_eq!(chronological(items), ["older", "legacy", "legacy-2", "early", "late"]);
+}
+
+#[test]
+fn insert_into_place() {
+    let tmp = new_env();
+    let logger = sbl::PanicLogger::new();
+    let path = tmp.get_path().join("archive");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) uid = new_u64(1)).unwrap();
+    write_database!((&database) itver = new_u16(0)).unwrap();
+    for (uid, date) in [("first", "2024-01-01"), ("third", "2024-01-03"), ("second", "2024-01-02"), ("third-again", "2024-01-03")] {
+        let toml = format!("
+            [entry]
+            uid = '{uid}'
+            title = '{uid}'
+            description = ''
+            tags = []
+            date = {date}
+
+            [[section]]
+            title = 'Section'
+            content = 'Content'
+        ");
+        Entry::new(toml.parse::<Table>().unwrap(), "entry.toml", search_database!((&database) /entries).unwrap(), logger.hollow());
+    }
+    let sorted = search_database!((&database) /order/sorted).unwrap();
+    list::write(&[String::from("first"), String::from("third")], |file, x| LazyData::new_string(file, x), &sorted, logger.hollow());
+
+    // Entries of a day already in the list go after the ones committed before them
+    let archive = Archive::load_dir(path, logger.hollow());
+    insert_sorted(&archive, "second", logger.hollow());
+    insert_sorted(&archive, "third-again", logger.hollow());
+    assert_eq!(&*read_sorted(&archive, logger.hollow()), ["first", "second", "third", "third-again"]);
+}
@@ -0,0 +1,78 @@
+mod isol;
+
+use chrono::Weekday;
+use diary_cli::stats::{longest_streak, monthly_trend, streaks, word_count};
+
+#[test]
+fn longest_streaks() {
+    assert_eq!(longest_streak(&[]), 0);
+    assert_eq!(longest_streak(&[[31, 12, 2023], [1, 1, 2024], [2, 1, 2024], [2, 1, 2024], [5, 1, 2024]]), 3);
+    assert_eq!(longest_streak(&[[1, 3, 2024], [28, 2, 2024], [29, 2, 2024]]), 3);
+}
+
+#[test]
+fn word_counts() {
+    assert_eq!(word_count("  hello,\n world  again "), 3);
+    assert_eq!(word_count(""), 0);
+}
+
+#[test]
+fn current_streaks() {
+    let dates = [[1, 1, 2024], [2, 1, 2024], [4, 1, 2024], [5, 1, 2024], [6, 1, 2024]];
+    assert_eq!(streaks(&dates, &[6, 1, 2024], None), (3, 3));
+    assert_eq!(streaks(&dates, &[7, 1, 2024], None), (3, 3));
+    assert_eq!(streaks(&dates, &[8, 1, 2024], None), (0, 3));
+
+    // 2024-01-01 is a monday, so these are three consecutive weeks
+    let dates = [[1, 1, 2024], [14, 1, 2024], [15, 1, 2024]];
+    assert_eq!(streaks(&dates, &[21, 1, 2024], Some(Weekday::Mon)), (3, 3));
+    assert_eq!(streaks(&dates, &[29, 1, 2024], Some(Weekday::Mon)), (0, 3));
+
+    // Weeks starting on sundays put the 14th in the same week as the 15th, leaving a week without entries after the 1st
+    assert_eq!(streaks(&dates, &[21, 1, 2024], Some(Weekday::Sun)), (1, 1));
+}
+
+#[test]
+fn monthly_trends() {
+    assert!(monthly_trend(&[]).is_empty());
+    let trend = monthly_trend(&[[3, 11, 2022], [9, 2, 2023], [1, 11, 2022], [28, 2, 2023]]);
+    assert_eq!(trend, vec![((2022, 11), 2), ((2022, 12), 0), ((2023, 1), 0), ((2023, 2), 2)]);
+}
+
+#[test]
+fn signature_counts() {
+    use soulog::*;
+    use lazy_db::*;
+    use diary_cli::{archive::Archive, entry::Entry, signature::Signature, stats::signatures};
+
+    let tmp = isol::new_env();
+    let logger = sbl::PanicLogger::new();
+    let path = tmp.get_path().join("archive");
+    let database = LazyDB::init(&path).unwrap();
+    write_database!((&database) uid = new_u64(1)).unwrap();
+    write_database!((&database) itver = new_u16(0)).unwrap();
+    for (i, signer) in [Some(("ann", "laptop")), Some(("ann", "phone")), Some(("bob", "laptop")), None].into_iter().enumerate() {
+        let toml = format!("
+            [entry]
+            uid = 'entry-{i}'
+            title = 'Entry {i}'
+            description = ''
+            tags = []
+            date = 2023-08-2{i}
+
+            [[section]]
+            title = 'Section'
+            content = 'Content'
+        ");
+        let entry = Entry::new(toml.parse::<toml::Table>().unwrap(), "entry.toml", search_database!((&database) /entries).unwrap(), logger.hollow());
+        if let Some((author, device)) = signer {
+            Signature { author: author.to_string(), device: device.to_string() }.store(&entry.container, logger.hollow());
+        }
+    }
+
+    // Entries committed before signatures existed are counted as unknown
+    let counts = signatures(&Archive::load_dir(path, logger.hollow()), logger.hollow());
+    let count = |x: &[(&str, usize)]| x.iter().map(|(x, count)| (x.to_string(), *count)).collect();
+    assert_eq!(counts.devices, count(&[("laptop", 2), ("phone", 1), ("unknown", 1)]));
+    assert_eq!(counts.authors, count(&[("ann", 2), ("bob", 1), ("unknown", 1)]));
+}
@@ -0,0 +1,35 @@
+use diary_cli::suggest::{tokenize, TagIndex};
+
+fn strings(x: &[&str]) -> Vec<String> {
+    x.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn tokenize_words() {
+    assert_eq!(tokenize("The run, AND a 10 km race by the River!"), strings(&["run", "race", "river"]));
+    assert_eq!(tokenize("it's 2023 and I'm at it"), Vec::<String>::new());
+}
+
+#[test]
+fn suggest_tags() {
+    let mut index = TagIndex::default();
+    index.add(&strings(&["health/running"]), "Went for a morning run along the river, legs sore after the run");
+    index.add(&strings(&["health/running", "friends"]), "Ran a race with friends by the river");
+    index.add(&strings(&["work"]), "Long meeting about the quarterly report, then emails");
+    index.add(&strings(&["cooking"]), "Baked bread and made soup for dinner");
+
+    // The closest fit comes first and unrelated tags aren't suggested
+    let tags: Vec<String> = index.suggest("An easy run by the river before breakfast", &[], 5).into_iter().map(|x| x.0).collect();
+    assert_eq!(tags.first().map(String::as_str), Some("health/running"));
+    assert!(!tags.contains(&"cooking".to_string()));
+
+    // Tags the entry already has are left out
+    let tags: Vec<String> = index.suggest("An easy run by the river", &strings(&["health/running"]), 5).into_iter().map(|x| x.0).collect();
+    assert!(!tags.contains(&"health/running".to_string()));
+
+    // Naming a tag puts it first
+    let tags: Vec<String> = index.suggest("Ran by the river, then work all afternoon", &[], 1).into_iter().map(|x| x.0).collect();
+    assert_eq!(tags, strings(&["work"]));
+
+    assert!(index.suggest("", &[], 5).is_empty());
+}
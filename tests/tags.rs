@@ -0,0 +1,33 @@
+use diary_cli::search::{filter_matches, query_matches, tag_matches};
+use diary_cli::tags::replace_tags;
+
+#[test]
+fn hierarchical_tag_matches() {
+    assert!(tag_matches("project/rust/diary-cli", "project/rust"));
+    assert!(tag_matches("project/rust", "project/rust/"));
+    assert!(tag_matches("project", "project"));
+    assert!(!tag_matches("project/rusty", "project/rust"));
+    assert!(!tag_matches("project", "project/rust"));
+}
+
+#[test]
+fn rename_nested_tags() {
+    let tags = [String::from("project/rust"), String::from("code"), String::from("project")];
+    let renamed = replace_tags(&tags, &[String::from("project")], "work").unwrap();
+    assert_eq!(renamed.as_ref(), ["work/rust", "code", "work"]);
+    assert!(replace_tags(&tags, &[String::from("missing")], "work").is_none());
+}
+
+#[test]
+fn glob_and_negated_filters() {
+    assert!(query_matches("project", "proj*"));
+    assert!(query_matches("project/rust", "proj*"));
+    assert!(query_matches("project/rust", "*/rust"));
+    assert!(query_matches("day", "d?y"));
+    assert!(!query_matches("work", "proj*"));
+
+    let tags = [String::from("project/rust"), String::from("work")];
+    assert!(filter_matches(&tags, &[String::from("proj*")]));
+    assert!(!filter_matches(&tags, &[String::from("proj*"), String::from("!work")]));
+    assert!(filter_matches(&tags, &[String::from("!personal")]));
+}
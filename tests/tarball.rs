@@ -0,0 +1,24 @@
+mod isol;
+
+use std::fs;
+use isol::*;
+use diary_cli::tarball;
+
+#[test]
+fn round_trip() {
+    let tmp = new_env();
+    let archive = tmp.get_path().join("archive");
+    fs::create_dir_all(archive.join("entries")).unwrap();
+    fs::write(archive.join("entries/note"), "some content").unwrap();
+
+    // Both kinds of tarball unpack back into the same archive
+    for name in ["backup.tar", "backup.tar.zst"] {
+        let path = tmp.get_path().join(name);
+        tarball::write(&archive, &path, name.ends_with(".zst"), "version = 1\n").unwrap();
+        assert!(tarball::is_tarball(&path));
+
+        let out = tmp.get_path().join(format!("{name}.out"));
+        tarball::unpack(&path, &out).unwrap();
+        assert_eq!(fs::read_to_string(out.join("entries/note")).unwrap(), "some content");
+    }
+}
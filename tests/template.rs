@@ -0,0 +1,37 @@
+mod isol;
+
+use soulog::*;
+use isol::*;
+use diary_cli::template::{render, Templates};
+
+#[test]
+fn render_markdown_templates() {
+    let vars = [("title", "<Hello> & bye"), ("notes", "- a\n")];
+    assert_eq!(render("# {{title}}\n{{notes}}", &vars), "# <Hello> & bye\n- a\n");
+    assert_eq!(render("{{ title }}|{{unknown}}|", &vars), "<Hello> & bye||");
+}
+
+#[test]
+fn default_templates() {
+    let templates = Templates::default();
+    let vars = [("frontmatter", "---\n---\n"), ("title", "Title"), ("description", "Desc"), ("notes", ""), ("sections", "### S\n> text\n")];
+    assert_eq!(render(&templates.entry, &vars), "---\n---\n# Title\n---\n**Description:** Desc\n\n---\n### S\n> text\n");
+    assert_eq!(render(&templates.capsule, &[("title", "T"), ("unlock_date", "2030-01-01")]), "# T\n---\n> *This entry is a time capsule sealed until 2030-01-01*\n");
+}
+
+#[test]
+fn named_templates() {
+    let env = new_env();
+    let dir = env.get_path().to_path_buf();
+    std::fs::write(dir.join("letter.md"), "Dear diary,\n{{sections}}").unwrap();
+    std::fs::write(dir.join("moc.md"), "# {{title}}").unwrap();
+
+    let logger = sbl::PanicLogger::new();
+    let templates = Templates::load(&dir, logger.hollow());
+
+    // Only the templates that aren't the built-in ones are named ones entries can use
+    assert_eq!(templates.named.len(), 1);
+    assert_eq!(templates.entry(Some("letter"), "uid", logger.hollow()), "Dear diary,\n{{sections}}");
+    assert_eq!(templates.entry(None, "uid", logger.hollow()), templates.entry);
+    assert_eq!(templates.moc, "# {{title}}");
+}
@@ -0,0 +1,9 @@
+use diary_cli::theme::render;
+
+#[test]
+fn render_templates() {
+    let vars = [("title", "<Hello> & bye"), ("content", "<p>hi</p>")];
+    assert_eq!(render("<h1>{{title}}</h1>{{{content}}}", &vars), "<h1>&lt;Hello&gt; &amp; bye</h1><p>hi</p>");
+    assert_eq!(render("{{ title }}|{{unknown}}|", &vars), "&lt;Hello&gt; &amp; bye||");
+    assert_eq!(render("unclosed {{title", &vars), "unclosed {{title");
+}
@@ -0,0 +1,21 @@
+use diary_cli::transform::{apply, Transform};
+use regex::Regex;
+
+fn transform(pattern: &str, replace: &str, tags: &[&str]) -> Transform {
+    Transform { pattern: Regex::new(pattern).unwrap(), replace: replace.to_string(), tags: tags.iter().map(|x| x.to_string()).collect() }
+}
+
+#[test]
+fn transform_pipelines() {
+    let pipeline = [
+        transform(r"\bbc\b", "because", &[]),
+        transform(r"==(.+?)==", "**$1**", &[]),
+        transform(r"\[\[secret\]\].*", "", &["public"]),
+        transform("because", "since", &["!casual"]),
+    ];
+    let tags = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(apply(&pipeline, "I left bc ==tired==", &tags(&["casual"])), "I left because **tired**");
+    assert_eq!(apply(&pipeline, "bc [[secret]] stuff", &tags(&["public/blog"])), "since ");
+    assert_eq!(apply(&[], "untouched", &[]), "untouched");
+}
@@ -0,0 +1,57 @@
+mod isol;
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use soulog::*;
+use isol::*;
+use diary_cli::{archive::Archive, trash::{self, Retention, TrashItem}, uncommit, DynamicLogger};
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn item(uid: &str, days_ago: u64, size: u64, now: SystemTime) -> TrashItem {
+    TrashItem { uid: uid.to_string(), is_moc: false, path: PathBuf::from(uid), removed: now - Duration::from_secs(days_ago * DAY), size }
+}
+
+#[test]
+fn expire_trash() {
+    let now = SystemTime::now();
+    let items = [item("new", 1, 300, now), item("mid", 30, 300, now), item("old", 100, 300, now)];
+    let uids = |retention: Retention| retention.expired(&items, now).iter().map(|x| x.uid.clone()).collect::<Vec<_>>();
+
+    assert!(uids(Retention::default()).is_empty());
+    assert_eq!(uids(Retention { max_age: Some(Duration::from_secs(90 * DAY)), max_size: None }), ["old"]);
+    assert_eq!(uids(Retention { max_age: None, max_size: Some(500) }), ["mid", "old"]);
+    assert_eq!(uids(Retention { max_age: Some(Duration::from_secs(90 * DAY)), max_size: Some(600) }), ["old"]);
+}
+
+#[test]
+fn store_and_restore() {
+    let home = new_home();
+    let logger = DynamicLogger::new();
+    Archive::init(logger.hollow());
+    let config = "
+        [entry]
+        uid = 'entry'
+        title = 'Entry'
+        description = ''
+        tags = []
+        date = 2023-08-21
+
+        [[section]]
+        title = 'Section'
+        content = 'Content'
+    ".parse().unwrap();
+    Archive::load(logger.hollow()).commit_item(config, &home.get_path().join("entry.toml"), logger.hollow());
+
+    // Removing an entry stores it in the trash...
+    uncommit::uncommmit(String::from("entry"), false, false, logger.hollow());
+    assert!(!Archive::load(logger.hollow()).database_exists("entries/entry"));
+    assert_eq!(trash::list_items(logger.hollow()).iter().map(|x| x.uid.clone()).collect::<Vec<_>>(), ["entry"]);
+
+    // ...and restoring it brings it back intact, emptying the trash
+    trash::restore(String::from("entry"), false, logger.hollow());
+    let archive = Archive::load(logger.hollow());
+    let mut entry = archive.get_entry(String::from("entry"), logger.hollow()).unwrap();
+    assert_eq!(entry.title(logger.hollow()), "Entry");
+    assert!(trash::list_items(logger.hollow()).is_empty());
+}
@@ -0,0 +1,43 @@
+use diary_cli::tui::*;
+
+fn item(uid: &str, title: &str, date: Option<[u16; 3]>, tags: &[&str]) -> Item {
+    Item { uid: uid.to_string(), title: title.to_string(), date, tags: tags.iter().map(|x| x.to_string()).collect(), is_moc: date.is_none() }
+}
+
+fn items() -> Vec<Item> {
+    vec![
+        item("2024-03-02", "Beach day", Some([2, 3, 2024]), &["travel"]),
+        item("trips", "Trips", None, &["travel", "moc"]),
+        item("2023-12-09", "Work", Some([9, 12, 2023]), &["work"]),
+        item("2025-01-21", "Another trip", Some([21, 1, 2025]), &["travel", "family"]),
+    ]
+}
+
+fn uids(items: &[Item], shown: Vec<usize>) -> Vec<&str> {
+    shown.into_iter().map(|x| items[x].uid.as_str()).collect()
+}
+
+#[test]
+fn sorts_with_mocs_after_entries() {
+    let items = items();
+    assert_eq!(uids(&items, shown(&items, &[], Sort::Newest, Kinds::All)), ["2025-01-21", "2024-03-02", "2023-12-09", "trips"]);
+    assert_eq!(uids(&items, shown(&items, &[], Sort::Oldest, Kinds::All)), ["2023-12-09", "2024-03-02", "2025-01-21", "trips"]);
+    assert_eq!(uids(&items, shown(&items, &[], Sort::Title, Kinds::All)), ["2025-01-21", "2024-03-02", "trips", "2023-12-09"]);
+}
+
+#[test]
+fn filters_by_tags_and_kinds() {
+    let items = items();
+    let filter = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(uids(&items, shown(&items, &filter(&["travel"]), Sort::Newest, Kinds::Entries)), ["2025-01-21", "2024-03-02"]);
+    assert_eq!(uids(&items, shown(&items, &filter(&["travel", "!family"]), Sort::Newest, Kinds::All)), ["2024-03-02", "trips"]);
+    assert_eq!(uids(&items, shown(&items, &[], Sort::Newest, Kinds::Mocs)), ["trips"]);
+    assert!(shown(&items, &filter(&["nothing"]), Sort::Newest, Kinds::All).is_empty());
+}
+
+#[test]
+fn styles_markdown_lines() {
+    let text = styled("# Title\n> quoted\n- item\nplain");
+    let lines: Vec<String> = text.lines.iter().map(|x| x.spans.iter().map(|x| x.content.as_ref()).collect()).collect();
+    assert_eq!(lines, ["Title", "│ quoted", "• item", "plain"]);
+}
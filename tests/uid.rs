@@ -0,0 +1,24 @@
+use diary_cli::uid::*;
+
+fn uids() -> Vec<String> {
+    ["2024-trip-japan", "2024-trip-japan-day-2", "2024-trip-norway", "birthday"].iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn uids_match_exactly_or_by_unique_prefix() {
+    let uids = uids();
+    assert_eq!(find("2024-trip-japan", &uids), Match::Found("2024-trip-japan"));
+    assert_eq!(find("2024-trip-n", &uids), Match::Found("2024-trip-norway"));
+    assert_eq!(find("birth", &uids), Match::Found("birthday"));
+    assert_eq!(find("2024-trip-j", &uids), Match::Ambiguous(vec!["2024-trip-japan", "2024-trip-japan-day-2"]));
+}
+
+#[test]
+fn missing_uids_suggest_the_closest() {
+    let uids = uids();
+    assert_eq!(find("2024-trip-japn", &uids), Match::Missing(vec!["2024-trip-japan"]));
+    assert_eq!(find("brithday", &uids), Match::Missing(vec!["birthday"]));
+    assert_eq!(find("holiday", &uids), Match::Missing(vec![]));
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(or_list(&["a", "b", "c"]), "`a`, `b` or `c`");
+}
@@ -0,0 +1,126 @@
+mod isol;
+use isol::*;
+use diary_cli::validate::{locate, validate, validate_file};
+
+#[test]
+fn report_every_problem() {
+    let env = new_env();
+    let config = env.get_path().join("entry.toml");
+    let table: toml::Table = r#"
+[entry]
+uid = "e1"
+title = 4
+tags = ["a", 2]
+date = "yesterday"
+
+[[section]]
+title = "One"
+contents = "a"
+content = "b"
+attachments = ["missing.jpg"]
+
+[[section]]
+path = "missing.txt"
+"#.parse().unwrap();
+
+    let report = validate(&table, &config, None);
+    let errors: Vec<String> = report.errors.iter().map(|x| x.to_string()).collect();
+    assert_eq!(report.uid.as_deref(), Some("e1"));
+    assert_eq!(errors, [
+        "entry.title: must be a string",
+        "entry.description: is missing",
+        "entry.tags[1]: must be a string",
+        "entry.date: must be a date like 2023-08-21",
+        "section[0]: can only have one of 'contents' or 'content'",
+        "section[0].attachments[0]: 'missing.jpg' isn't a file (relative paths are relative to the entry's config file or `--base-dir`)",
+        "section[1].title: is missing",
+        "section[1].path: 'missing.txt' isn't a file (relative paths are relative to the entry's config file or `--base-dir`)",
+    ]);
+}
+
+#[test]
+fn valid_moc() {
+    let env = new_env();
+    let config = env.get_path().join("moc.toml");
+    std::fs::write(&config, r#"
+is-moc = true
+[moc]
+uid = "m1"
+title = "Moc"
+description = ""
+tags = []
+
+[[collection]]
+title = "All"
+include = ["a"]
+paginate = 0
+"#).unwrap();
+
+    let report = validate_file(&config, None);
+    assert!(report.is_moc);
+    assert_eq!(report.errors.iter().map(|x| x.to_string()).collect::<Vec<_>>(), ["collection[0].paginate: must be between 1 and 65535"]);
+
+    std::fs::write(&config, "not = [toml").unwrap();
+    assert!(!validate_file(&config, None).is_valid());
+}
+
+#[test]
+fn locate_problems() {
+    let text = "[entry]\nuid = \"e1\"\ntitle = 4\ntags = [\"a\", 2]\n\n[[section]]\ntitle = \"One\"\n\n[[section]]\npath = \"x\"\n";
+    assert_eq!(locate(text, "entry.title"), Some((3, 9)));
+    assert_eq!(locate(text, "entry.tags[1]"), Some((4, 14)));
+    assert_eq!(locate(text, "section[1].path"), Some((10, 8)));
+    assert_eq!(locate(text, "section[1].title"), Some((9, 1))); // missing, so where its section is
+    assert_eq!(locate(text, "moc"), None);
+
+    let env = new_env();
+    let config = env.get_path().join("entry.toml");
+    std::fs::write(&config, text).unwrap();
+    let report = validate_file(&config, None);
+    let title = report.errors.iter().find(|x| x.key == "entry.title").unwrap();
+    assert_eq!(title.location, Some((3, 9)));
+}
+
+#[test]
+fn nested_collections() {
+    let env = new_env();
+    let config = env.get_path().join("moc.toml");
+    let table: toml::Table = r#"
+is-moc = true
+[moc]
+uid = "m1"
+title = "Moc"
+description = ""
+tags = []
+
+[[collection]]
+title = "Groups"
+
+[[collection.collection]]
+title = "Inner"
+
+[[collection.collection]]
+title = "Other"
+include = ["b"]
+from = "soon"
+collection = 4
+
+[[collection]]
+title = "Leaf"
+exclude = "b"
+from = 2023-12-31
+to = "2023-01-01"
+limit = 0
+"#.parse().unwrap();
+
+    let report = validate(&table, &config, None);
+    assert_eq!(report.errors.iter().map(|x| x.to_string()).collect::<Vec<_>>(), [
+        "collection[0].collection[0].include: is missing",
+        "collection[0].collection[1].from: must be a date like 2023-08-21",
+        "collection[0].collection[1].collection: must be a list of tables (`[[collection.collection.collection]]`)",
+        "collection[1].include: is missing",
+        "collection[1].exclude: must be a list of strings",
+        "collection[1].from: must not be after 'to'",
+        "collection[1].limit: must be between 1 and 65535",
+    ]);
+}
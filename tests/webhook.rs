@@ -0,0 +1,45 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use diary_cli::history::Event;
+use diary_cli::webhook::{parse_url, payload, post};
+
+#[test]
+fn webhook_urls() {
+    assert_eq!(parse_url("http://localhost:4000/rebuild"), Some((String::from("localhost"), 4000, String::from("/rebuild"))));
+    assert_eq!(parse_url("http://example.com"), Some((String::from("example.com"), 80, String::from("/"))));
+    assert_eq!(parse_url("https://example.com/hook"), None);
+    assert_eq!(parse_url("http://:80/"), None);
+}
+
+#[test]
+fn post_event() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" { break }
+            if let Some(x) = line.strip_prefix("Content-Length: ") { length = x.trim().parse().unwrap() }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut().write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        (request_line, String::from_utf8(body).unwrap())
+    });
+
+    let event = Event { time: String::from("2023-08-21T10:00:00+00:00"), operation: String::from("commit"), uid: Some(String::from("e1")), itver: 3, signer: String::from("me@pc") };
+    assert_eq!(post(&url, &payload(&event)), Ok(204));
+
+    let (request_line, body) = server.join().unwrap();
+    assert_eq!(request_line, "POST /hook HTTP/1.1\r\n");
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["event"], "commit");
+    assert_eq!(body["uid"], "e1");
+    assert_eq!(body["itver"], 3);
+}